@@ -1,13 +1,24 @@
 use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
 use less_oxide::{compile, CompileOptions};
 
-struct Case {
+struct Case<'a> {
     name: &'static str,
-    source: &'static str,
+    source: &'a str,
     minify: bool,
 }
 
+/// 大文件样本的最少行数，用于衡量解析器在长输入上的扫描开销。
+const LARGE_FILE_LINES: usize = 100_000;
+
+/// 重复 `benchmark.less` 直到超过 [`LARGE_FILE_LINES`] 行。
+fn large_file_source() -> String {
+    let unit = include_str!("../fixtures/benchmark.less");
+    let repeat = LARGE_FILE_LINES.div_ceil(unit.lines().count());
+    unit.repeat(repeat)
+}
+
 fn compile_benchmarks(c: &mut Criterion) {
+    let large_file = large_file_source();
     let cases = [
         Case {
             name: "baseline_pretty",
@@ -49,6 +60,16 @@ fn compile_benchmarks(c: &mut Criterion) {
             source: include_str!("../fixtures/arithmetic.less"),
             minify: true,
         },
+        Case {
+            name: "large_file_pretty",
+            source: &large_file,
+            minify: false,
+        },
+        Case {
+            name: "large_file_minified",
+            source: &large_file,
+            minify: true,
+        },
     ];
 
     for case in cases {
@@ -56,7 +77,7 @@ fn compile_benchmarks(c: &mut Criterion) {
     }
 }
 
-fn bench_case(c: &mut Criterion, case: &Case) {
+fn bench_case(c: &mut Criterion, case: &Case<'_>) {
     let mut group = c.benchmark_group(format!("less_compile/{}", case.name));
     group.throughput(Throughput::Bytes(case.source.len() as u64));
 
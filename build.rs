@@ -3,8 +3,26 @@ fn main() {
     if std::env::var_os("CARGO_FEATURE_NODE").is_some() {
         napi_build::setup();
     }
+    // 启用 capi 特性时根据 src/capi.rs 在 OUT_DIR 中生成 C 头文件，由测试与提交的 include/less_oxide.h 比对。
+    #[cfg(feature = "capi")]
+    generate_header();
     println!("cargo:rerun-if-changed=src/lib.rs");
     println!("cargo:rerun-if-changed=src/parser.rs");
     println!("cargo:rerun-if-changed=src/evaluator.rs");
     println!("cargo:rerun-if-changed=src/serializer.rs");
 }
+
+#[cfg(feature = "capi")]
+fn generate_header() {
+    let crate_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+    let out_dir = std::env::var("OUT_DIR").unwrap();
+    let config = cbindgen::Config::from_file(format!("{crate_dir}/cbindgen.toml")).unwrap();
+    cbindgen::Builder::new()
+        .with_config(config)
+        .with_src(format!("{crate_dir}/src/capi.rs"))
+        .generate()
+        .expect("无法生成 C 头文件")
+        .write_to_file(format!("{out_dir}/less_oxide.h"));
+    println!("cargo:rerun-if-changed=src/capi.rs");
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+}
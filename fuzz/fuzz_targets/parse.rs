@@ -0,0 +1,15 @@
+//! 任意字节输入下解析器不得 panic 或死循环：`cargo +nightly fuzz run parse`。
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(source) = std::str::from_utf8(data) else {
+        return;
+    };
+    let strict = less_oxide::parse(source);
+    let (_, errors) = less_oxide::parse_lenient(source);
+    // 容错解析只在严格解析失败的位置跳过语句。
+    assert_eq!(strict.is_err(), !errors.is_empty());
+});
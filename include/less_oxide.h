@@ -0,0 +1,74 @@
+/* less_oxide C ABI，由 cbindgen 根据 src/capi.rs 生成，请勿手动修改。 */
+
+#ifndef LESS_OXIDE_H
+#define LESS_OXIDE_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// 编译结果；成功时 `error` 为 `NULL`，失败时只有 `error` 非空。
+typedef struct LessOxideResult {
+  // 生成的 CSS。
+  char *css;
+  // source map JSON，未开启 `source_map` 时为 `NULL`。
+  char *map;
+  // 通过 `@import` 读取的文件路径数组，共 `imports_len` 项。
+  char **imports;
+  // `imports` 的长度。
+  size_t imports_len;
+  // 错误信息（含位置），成功时为 `NULL`。
+  char *error;
+} LessOxideResult;
+
+// 编译选项；传入 `NULL` 时全部取默认值。字符串字段可为 `NULL`，须为 UTF-8。
+typedef struct LessOxideOptions {
+  // 是否压缩输出。
+  bool minify;
+  // 源文件路径，用于解析相对 `@import`；`less_oxide_compile_file` 忽略此项。
+  const char *filename;
+  // 额外的 `@import` 检索目录数组，共 `include_paths_len` 项。
+  const char *const *include_paths;
+  // `include_paths` 的长度。
+  size_t include_paths_len;
+  // 输出格式：`expanded` / `compact` / `compressed`，`NULL` 时由 `minify` 决定。
+  const char *output_style;
+  // 是否生成 source map，结果见 [`LessOxideResult::map`]。
+  bool source_map;
+} LessOxideOptions;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// 编译 LESS 源码，返回的结果须用 [`less_oxide_result_free`] 释放，不会返回 `NULL`。
+//
+// # Safety
+//
+// `source` 必须是以 NUL 结尾的有效字符串；`options` 为 `NULL` 或指向有效的
+// [`LessOxideOptions`]，其中的指针在调用期间有效。
+struct LessOxideResult *less_oxide_compile(const char *source,
+                                           const struct LessOxideOptions *options);
+
+// 编译 LESS 文件，`@import` 相对于文件所在目录解析；释放规则同 [`less_oxide_compile`]。
+//
+// # Safety
+//
+// `path` 必须是以 NUL 结尾的有效字符串；`options` 的要求同 [`less_oxide_compile`]。
+struct LessOxideResult *less_oxide_compile_file(const char *path,
+                                                const struct LessOxideOptions *options);
+
+// 释放编译结果及其中的全部字符串；传入 `NULL` 时什么也不做。
+//
+// # Safety
+//
+// `result` 必须为 `NULL` 或由本库返回且尚未释放的指针，释放后不得再访问。
+void less_oxide_result_free(struct LessOxideResult *result);
+
+#ifdef __cplusplus
+} // extern "C"
+#endif // __cplusplus
+
+#endif /* LESS_OXIDE_H */
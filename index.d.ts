@@ -9,6 +9,186 @@ export interface JsCompileOptions {
   minify?: boolean
   /** 源文件路径，用于解析 @import。 */
   filename?: string
+  /** 额外的 @import 检索目录，排在 `filename` 所在目录之后。 */
+  includePaths?: Array<string>
+  /** 算术模式：`always`（默认）、`parens-division` 或 `strict`。 */
+  math?: string
+  /** 不同单位无法换算的加减（如 `1px + 2em`）是否报错；默认与 lessc 一致沿用左侧单位。 */
+  strictUnits?: boolean
+  /** 未定义的变量是否按原文输出并给出 `undefined-variable` 警告，默认报错。 */
+  allowUndefinedVariables?: boolean
+  /** 同一文件被多次 @import 时是否重复展开，默认只展开一次。 */
+  multipleImports?: boolean
+  /** mixin 递归展开的最大深度。 */
+  maxMixinDepth?: number
+  /** @import 嵌套的最大层数，默认 64。 */
+  maxImportDepth?: number
+  /** 求值产生的选择器与声明文本累计允许的最大字节数，超出时报错；编译不可信输入时建议设置。 */
+  maxOutputSize?: number
+  /** 父选择器展开后单条规则允许的最大选择器数，默认 4096。 */
+  maxSelectors?: number
+  /** 是否生成 source map（规则级映射），结果见 `compileLessWithMetadata` 返回值的 `map`。 */
+  sourceMap?: boolean
+  /** 把 source map 以 base64 data URI 嵌入 CSS 末尾的注释，此时结果中的 `map` 为 `null`；隐含开启 `sourceMap`。 */
+  sourceMapFileInline?: boolean
+  /** 写入 `sourceMappingURL` 注释的映射文件地址，缺省时不写注释。 */
+  sourceMapURL?: string
+  /** 映射中的 `sourceRoot`。 */
+  sourceMapRootpath?: string
+  /** 在映射的 `sourcesContent` 中嵌入源文件内容。 */
+  outputSourceFiles?: boolean
+  /** 插入到源码之前的全局变量，如 `{ 'primary-color': '#1890ff' }`。 */
+  globalVars?: Record<string, string>
+  /** 追加到源码之后、覆盖同名定义的变量，用于主题定制。 */
+  modifyVars?: Record<string, string>
+  /** 在非压缩输出中保留块注释；以 `/*!` 开头的版权注释总会保留。 */
+  keepComments?: boolean
+  /** 在 `--custom` 属性的值中替换 `@{var}` 插值，默认原样输出。 */
+  customPropertyInterpolation?: boolean
+  /** 保留没有任何声明的空规则（如 `.a {}`），默认省略。 */
+  keepEmptyRules?: boolean
+  /** 输出格式：`expanded`（与 lessc 一致）、`compact` 或 `compressed`；缺省时由 `minify` 决定。 */
+  outputStyle?: 'expanded' | 'compact' | 'compressed'
+  /** 合并相邻的同选择器规则，并去掉完全相同的重复声明（保留最后一条）。 */
+  mergeRules?: boolean
+  /** 需要补全厂商前缀的目标浏览器，如 `['safari 12', 'ie 11']`；支持 flexbox、`user-select`、`backdrop-filter` 等常见属性。 */
+  autoprefix?: string[]
+  /** 给类名加上哈希后缀（CSS Modules），映射见 `compileLessWithMetadata` 结果的 `classNames`；`:global(...)` 中的类名不变。 */
+  cssModules?: boolean
+  /** 计算类名哈希时入口文件路径相对的根目录，缺省为进程的当前工作目录；换目录或机器构建时类名不变。 */
+  cssModulesRoot?: string
+  /** 参与类名哈希的附加字符串，可用于区分不同项目；编译源码字符串时类名只由原类名与该值决定。 */
+  cssModulesSalt?: string
+  /** 被导入文件中相对 `url()` 的改写方式：`off`（默认）、`local`（只改写 `./`、`../` 开头的路径）或 `all`。 */
+  rewriteUrls?: string
+  /** 设为 `comments` 时在每条规则前输出标明源文件与行号的注释（仅非压缩输出）。 */
+  dumpLineNumbers?: 'comments'
+  /** 收集编译统计，结果见 `compileLessWithMetadata` 返回值的 `stats`。 */
+  collectStats?: boolean
+  /**
+   * 自定义 @import 解析：`(path, from) => { contents, resolvedPath } | null`，
+   * 返回空值时按 `filename`/`includePaths` 继续查找；抛出异常时编译失败。
+   */
+  resolveImport?: (path: string, from: string | null) => JsImportResult | null | undefined
 }
-/** 暴露给 Node.js 的异步编译函数。 */
+/** `resolveImport` 回调的返回值。 */
+export interface JsImportResult {
+  /** 导入文件的源码。 */
+  contents: string
+  /** 用于去重与依赖追踪的路径，缺省时使用原始导入路径。 */
+  resolvedPath?: string
+  /** 非空时以该信息中止编译；`index.js` 把回调中抛出的异常转换为此项。 */
+  error?: string
+}
+/** Node.js 侧的编译结果对象，字段与 less.js `render` 的结果保持一致。 */
+export interface JsCompileOutput {
+  /** 生成的 CSS 文本。 */
+  css: string
+  /** source map JSON，未生成时为 `null`。 */
+  map?: string
+  /** 通过 `@import` 读取的全部文件路径。 */
+  imports: Array<string>
+  /** 开启 `cssModules` 时原类名到改写后类名的映射。 */
+  classNames: Record<string, string>
+  /** 全部顶层变量的最终值与声明位置，按首次声明的顺序排列。 */
+  variables: Array<JsVariableInfo>
+  /** 求值中产生的警告，可直接交给 `console.warn` 输出。 */
+  warnings: Array<JsWarning>
+  /** 开启 `collectStats` 时的编译统计。 */
+  stats?: JsCompileStats
+}
+/** 编译统计，耗时以毫秒为单位。 */
+export interface JsCompileStats {
+  /** 输出的规则数。 */
+  rules: number
+  /** 输出的声明数。 */
+  declarations: number
+  /** 全部规则的选择器数之和。 */
+  selectors: number
+  /** 源码中规则的最大嵌套层数。 */
+  maxNestingDepth: number
+  /** 展开 mixin 的总次数。 */
+  mixinExpansions: number
+  /** 入口与各导入文件的解析耗时。 */
+  files: Array<JsFileStats>
+  /** 求值及后处理的耗时。 */
+  evaluateTime: number
+}
+/** 单个文件的解析耗时。 */
+export interface JsFileStats {
+  /** 文件路径；直接编译字符串时入口为 `null`。 */
+  path?: string
+  /** 解析耗时（毫秒）。 */
+  parseTime: number
+}
+/** `compileMany` 的一个入口。 */
+export interface JsCompileEntry {
+  /** 入口文件路径，`@import` 相对于它所在目录解析。 */
+  path: string
+  /** 该入口的编译选项。 */
+  options?: JsCompileOptions
+}
+/** `compileMany` 中单个入口的结果，`result` 与 `error` 恰有一个非空。 */
+export interface JsCompileManyResult {
+  /** 入口文件路径，与传入时一致。 */
+  path: string
+  /** 编译成功时的结构化结果，与 `compileLessWithMetadata` 相同。 */
+  result?: JsCompileOutput
+  /** 编译失败时的错误信息。 */
+  error?: string
+}
+/** `renderFile` 的结果：CSS 与 source map 已写入磁盘，只返回路径与依赖信息。 */
+export interface JsRenderFileOutput {
+  /** 写入的 source map 文件路径；未生成外部映射时为 `null`。 */
+  mapFile?: string
+  /** 通过 `@import` 读取的全部文件路径。 */
+  imports: Array<string>
+  /** 求值中产生的警告。 */
+  warnings: Array<JsWarning>
+}
+/** 编译警告，如单位不兼容的运算或未知函数。 */
+export interface JsWarning {
+  /** 警告类别，如 `unit-mismatch`、`unknown-function`、`deprecated`。 */
+  code: string
+  /** 不含位置的警告描述。 */
+  message: string
+  /** 所在的文件；直接编译字符串或无法定位时为 `null`。 */
+  file?: string
+  /** 所在的行号，从 1 开始；无法定位时为 `null`。 */
+  line?: number
+  /** 所在的列号，从 1 开始；无法定位时为 `null`。 */
+  column?: number
+}
+/** 顶层变量的求值结果。 */
+export interface JsVariableInfo {
+  /** 不含 `@` 的变量名。 */
+  name: string
+  /** 求值后的值。 */
+  value: string
+  /** 声明所在的文件；直接编译字符串时为 `null`。 */
+  file?: string
+  /** 声明所在的行号，从 1 开始。 */
+  line: number
+  /** 声明所在的列号，从 1 开始。 */
+  column: number
+}
+/** 暴露给 Node.js 的同步编译函数。 */
 export declare function compileLess(source: string, options?: JsCompileOptions | undefined | null): string
+/** 异步编译 LESS 源码，返回 Promise。 */
+export declare function compileLessAsync(source: string, options?: JsCompileOptions | undefined | null): Promise<string>
+/** 异步编译 LESS 文件，`@import` 相对于文件所在目录解析，返回 Promise。 */
+export declare function compileFileAsync(path: string, options?: JsCompileOptions | undefined | null): Promise<string>
+/**
+ * 并行编译多个文件，返回与 `entries` 顺序一致的结果数组的 Promise；
+ * 单个入口失败只体现在该项的 `error` 中，不会使整个 Promise 失败。
+ */
+export declare function compileMany(entries: Array<JsCompileEntry>): Promise<Array<JsCompileManyResult>>
+/** 解析 LESS 源码并返回 AST 的 JSON 字符串。 */
+export declare function parseToJson(source: string): string
+/** 编译并返回 `{ css, map, imports, classNames, variables, warnings, stats }` 结构化结果。 */
+export declare function compileLessWithMetadata(source: string, options?: JsCompileOptions | undefined | null): JsCompileOutput
+/**
+ * 编译 `input` 并把 CSS（以及开启 `sourceMap` 时的 `<output>.map`）直接写入磁盘，
+ * 不在 JS 与 Rust 之间传递大段 CSS 文本，适合在 JS 中实现类似 CLI 的构建。
+ */
+export declare function renderFile(input: string, output: string, options?: JsCompileOptions | undefined | null): JsRenderFileOutput
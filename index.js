@@ -15,10 +15,42 @@ try {
   }
 }
 
+/**
+ * 编译选项：minify、filename、includePaths、math、strictUnits、allowUndefinedVariables、multipleImports、maxMixinDepth、maxImportDepth、maxOutputSize、maxSelectors、sourceMap、sourceMapFileInline、sourceMapURL、sourceMapRootpath、outputSourceFiles、globalVars、modifyVars、keepComments、customPropertyInterpolation、keepEmptyRules、outputStyle、mergeRules、autoprefix、cssModules、cssModulesRoot、cssModulesSalt、rewriteUrls、dumpLineNumbers、collectStats、resolveImport。
+ * @typedef {import('./index').JsCompileOptions} JsCompileOptions
+ */
+
+/**
+ * 包装 `resolveImport`：异步编译经 threadsafe function 调用回调，其中抛出的异常或无法识别的返回值
+ * 无法传回 Rust，这里改为返回 `{ error }`，由编译器作为编译错误报告。
+ * @param {JsCompileOptions} options 编译配置
+ * @returns {JsCompileOptions} 替换了 `resolveImport` 的配置
+ */
+function guardResolveImport(options) {
+  if (!options || typeof options.resolveImport !== 'function') {
+    return options;
+  }
+  const { resolveImport } = options;
+  return {
+    ...options,
+    resolveImport(path, from) {
+      try {
+        const result = resolveImport(path, from);
+        if (result != null && (typeof result !== 'object' || typeof result.contents !== 'string')) {
+          return { contents: '', error: 'resolveImport 必须返回 { contents, resolvedPath } 或 null' };
+        }
+        return result;
+      } catch (err) {
+        return { contents: '', error: err instanceof Error ? err.message : String(err) };
+      }
+    },
+  };
+}
+
 /**
  * 编译 LESS 字符串为 CSS。
  * @param {string} source LESS 源码
- * @param {{ minify?: boolean }} [options] 编译配置
+ * @param {JsCompileOptions} [options] 编译配置
  * @returns {string} CSS 结果
  */
 function compileLess(source, options = {}) {
@@ -28,7 +60,92 @@ function compileLess(source, options = {}) {
   return nativeBinding.compileLess(source, options);
 }
 
+/**
+ * 编译 LESS 字符串，同时返回 `@import` 依赖列表，便于监听模式做失效判断。
+ * @param {string} source LESS 源码
+ * @param {JsCompileOptions} [options] 编译配置
+ * @returns {{ css: string, map: string | null, imports: string[], warnings: import('./index').JsWarning[] }} CSS、source map、被导入的文件路径与编译警告
+ */
+function compileLessWithMetadata(source, options = {}) {
+  if (typeof source !== 'string') {
+    throw new TypeError('source 必须是字符串');
+  }
+  return nativeBinding.compileLessWithMetadata(source, options);
+}
+
+/**
+ * 在工作线程中编译 LESS 字符串，不阻塞事件循环。
+ * @param {string} source LESS 源码
+ * @param {JsCompileOptions} [options] 编译配置
+ * @returns {Promise<string>} CSS 结果
+ */
+function compileLessAsync(source, options = {}) {
+  if (typeof source !== 'string') {
+    return Promise.reject(new TypeError('source 必须是字符串'));
+  }
+  return nativeBinding.compileLessAsync(source, guardResolveImport(options));
+}
+
+/**
+ * 在工作线程中编译 LESS 文件。
+ * @param {string} path LESS 文件路径
+ * @param {JsCompileOptions} [options] 编译配置
+ * @returns {Promise<string>} CSS 结果
+ */
+function compileFileAsync(path, options = {}) {
+  if (typeof path !== 'string') {
+    return Promise.reject(new TypeError('path 必须是字符串'));
+  }
+  return nativeBinding.compileFileAsync(path, guardResolveImport(options));
+}
+
+/**
+ * 在工作线程中并行编译多个 LESS 文件，适合一次构建数百个入口的 monorepo。
+ * @param {{ path: string, options?: JsCompileOptions }[]} entries 入口文件及各自的编译配置
+ * @returns {Promise<import('./index').JsCompileManyResult[]>} 与 entries 顺序一致的结果，失败的入口带有 error
+ */
+function compileMany(entries) {
+  if (!Array.isArray(entries) || entries.some((entry) => !entry || typeof entry.path !== 'string')) {
+    return Promise.reject(new TypeError('entries 必须是 { path, options } 对象数组'));
+  }
+  return nativeBinding.compileMany(
+    entries.map(({ path, options = {} }) => ({ path, options: guardResolveImport(options) })),
+  );
+}
+
+/**
+ * 编译 LESS 文件并把 CSS（以及开启 sourceMap 时的 `.map`）直接写入 `output`，只返回依赖信息。
+ * @param {string} input LESS 文件路径
+ * @param {string} output CSS 输出路径，所在目录不存在时自动创建
+ * @param {JsCompileOptions} [options] 编译配置
+ * @returns {import('./index').JsRenderFileOutput} 写入的 source map 路径、被导入的文件路径与编译警告
+ */
+function renderFile(input, output, options = {}) {
+  if (typeof input !== 'string' || typeof output !== 'string') {
+    throw new TypeError('input 与 output 必须是字符串');
+  }
+  return nativeBinding.renderFile(input, output, options);
+}
+
+/**
+ * 解析 LESS 源码（不求值、不展开 `@import`），返回 AST 的 JSON 字符串。
+ * @param {string} source LESS 源码
+ * @returns {string} AST JSON
+ */
+function parseToJson(source) {
+  if (typeof source !== 'string') {
+    throw new TypeError('source 必须是字符串');
+  }
+  return nativeBinding.parseToJson(source);
+}
+
 module.exports = {
   compileLess,
+  compileLessAsync,
+  compileFileAsync,
+  compileMany,
+  compileLessWithMetadata,
+  renderFile,
+  parseToJson,
   compile: compileLess,
 };
@@ -1,57 +1,198 @@
 use std::fmt::{self, Display};
+use std::path::Path;
+use std::sync::Arc;
 
 /// 表示一份完整的 LESS 样式表。
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Stylesheet {
     pub statements: Vec<Statement>,
 }
 
+/// 节点在源码中的位置：起止字节偏移、起点的行列号（从 1 开始）与所属文件。
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub column: usize,
+    /// 节点来自的文件；直接编译字符串时为 `None`。
+    pub file: Option<Arc<Path>>,
+}
+
 /// 树中的顶层语句。
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Statement {
     Import(ImportStatement),
+    Plugin(PluginStatement),
+    /// `@charset "UTF-8";` 中的编码字符串（含引号）。
+    Charset(String),
+    BodylessAtRule(BodylessAtRule),
     AtRule(AtRule),
     RuleSet(RuleSet),
     Variable(VariableDeclaration),
     MixinDefinition(MixinDefinition),
     MixinCall(MixinCall),
+    DetachedCall(DetachedCall),
+    Each(EachCall),
+    /// `@import (reference)` 引入的语句：只登记变量与 mixin，规则本身不输出。
+    Reference(Vec<Statement>),
+    /// `@import (inline)` 引入的文件原文，不解析、原样输出。
+    RawCss(String),
+    Comment(Comment),
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct VariableDeclaration {
     pub name: String,
-    pub value: Value,
+    pub value: VariableInit,
+    pub span: Span,
 }
 
+/// 变量声明的右侧：普通值，或 `@detached: { ... }` 形式的分离规则集。
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum VariableInit {
+    Value(Value),
+    Ruleset(Vec<RuleBody>),
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct RuleSet {
     pub selectors: Vec<Selector>,
+    /// `.selector when (...) { }` 中的守卫条件，不成立时整条规则不输出。
+    pub guard: Option<Guard>,
     pub body: Vec<RuleBody>,
+    pub span: Span,
+}
+
+/// `when` 守卫：逗号（或 `or`）分隔的分支任一成立即通过，分支内以 `and` 连接的条件需全部成立。
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Guard {
+    pub alternatives: Vec<Vec<GuardCondition>>,
 }
 
+/// 单个括号条件，例如 `(@a > 0)`、`not (@dark)`；无比较运算符时判断值是否为 `true`。
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GuardCondition {
+    pub negated: bool,
+    pub lhs: Value,
+    pub comparison: Option<(GuardOperator, Value)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum GuardOperator {
+    Eq,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct AtRule {
     pub name: String,
-    pub params: String,
+    /// `@media`、`@container` 等的前导条件，其中的变量与运算在求值阶段处理。
+    pub params: Value,
     pub body: Vec<RuleBody>,
+    pub span: Span,
 }
 
+/// 以 `;` 结尾、没有规则体的 at-rule，如 `@namespace svg url(...);`、`@layer base;`；
+/// 参数中的变量在求值阶段替换，其余原样输出。
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BodylessAtRule {
+    pub name: String,
+    pub params: Value,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum RuleBody {
     Declaration(Declaration),
     NestedRule(RuleSet),
     AtRule(AtRule),
+    BodylessAtRule(BodylessAtRule),
     DetachedCall(DetachedCall),
+    Each(EachCall),
     Variable(VariableDeclaration),
     MixinDefinition(MixinDefinition),
     MixinCall(MixinCall),
+    /// 规则集或 at-rule 内的 `@import`：LESS 导入在展开时被替换为目标文件的内容，
+    /// 仅 CSS 导入保留到求值阶段，由求值器提升到样式表顶部。
+    Import(ImportStatement),
+    Comment(Comment),
 }
 
+/// 语句之间的 `/* ... */` 块注释或 `//` 行注释，`text` 含定界符；值与选择器内部的注释不会保留。
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Comment {
+    pub text: String,
+    pub span: Span,
+}
+
+impl Comment {
+    /// `/*! ... */` 形式的版权注释，压缩输出时同样保留。
+    pub fn is_preserved(&self) -> bool {
+        self.text.starts_with("/*!")
+    }
+
+    /// `// ...` 行注释，只用于格式化等源码工具，求值时总是丢弃。
+    pub fn is_line(&self) -> bool {
+        self.text.starts_with("//")
+    }
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Selector {
     pub value: String,
 }
 
+impl Selector {
+    /// 可作为 mixin 调用时的元素序列：`.btn.primary` 为 `[".btn", ".primary"]`，
+    /// `#ns > .a` 为 `["#ns", ".a"]`（与 less.js 一致，匹配时忽略组合符）；
+    /// 含有 `.class` / `#id` 以外成分的选择器返回 `None`。
+    pub(crate) fn mixin_elements(&self) -> Option<Vec<&str>> {
+        let value = self.value.as_str();
+        let mut elements = Vec::new();
+        let mut chars = value.char_indices().peekable();
+        while let Some((start, ch)) = chars.next() {
+            match ch {
+                '.' | '#' => {
+                    let mut end = start + 1;
+                    while let Some(&(idx, next)) = chars.peek() {
+                        if !(next.is_alphanumeric() || next == '-' || next == '_') {
+                            break;
+                        }
+                        end = idx + next.len_utf8();
+                        chars.next();
+                    }
+                    if end == start + 1 {
+                        return None;
+                    }
+                    elements.push(&value[start..end]);
+                }
+                '>' => {}
+                ch if ch.is_whitespace() => {}
+                _ => return None,
+            }
+        }
+        (!elements.is_empty()).then_some(elements)
+    }
+}
+
 impl Display for Selector {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.write_str(&self.value)
@@ -59,13 +200,26 @@ impl Display for Selector {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Declaration {
     pub name: String,
     pub value: Value,
     pub important: bool,
+    pub merge: PropertyMerge,
+    pub span: Span,
+}
+
+/// 属性合并标记：`prop+: a` 以逗号合并，`prop+_: a` 以空格合并。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PropertyMerge {
+    None,
+    Comma,
+    Space,
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Value {
     pub pieces: Vec<ValuePiece>,
 }
@@ -77,9 +231,70 @@ impl Value {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ValuePiece {
     Literal(String),
     VariableRef(String),
+    /// `@@name`：先取 `@name` 的值，再以该值为变量名二次查找。
+    IndirectVariableRef(String),
+    /// `$color`：引用所在规则集（或外层规则集）中 `color` 属性的值。
+    PropertyRef(String),
+    Lookup(Lookup),
+}
+
+/// `@config[primary]`、`.mixin()[@result]`：把分离规则集或 mixin 的展开结果当作映射取值，
+/// 可连续查找，如 `@config[@colors][primary]`。
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Lookup {
+    pub target: LookupTarget,
+    /// 方括号中的键：`name` 或 `$name` 取属性，`@name` 取变量，`@@name` 与 `$@name`
+    /// 以变量的值为名称，空键取最后一条声明。
+    pub keys: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LookupTarget {
+    Variable(String),
+    /// 参数只能是值，不接受规则集。
+    Mixin(MixinCall),
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.pieces.iter().try_for_each(|piece| match piece {
+            ValuePiece::Literal(text) => f.write_str(text),
+            ValuePiece::VariableRef(name) => write!(f, "@{name}"),
+            ValuePiece::IndirectVariableRef(name) => write!(f, "@@{name}"),
+            ValuePiece::PropertyRef(name) => write!(f, "${name}"),
+            ValuePiece::Lookup(lookup) => write!(f, "{lookup}"),
+        })
+    }
+}
+
+impl Display for Lookup {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.target {
+            LookupTarget::Variable(name) => write!(f, "@{name}")?,
+            LookupTarget::Mixin(call) => {
+                for segment in call.namespace.iter().chain([&call.name]) {
+                    f.write_str(segment)?;
+                }
+                f.write_str("(")?;
+                for (index, arg) in call.args.iter().enumerate() {
+                    if index > 0 {
+                        f.write_str(", ")?;
+                    }
+                    if let MixinArgument::Value(value) = arg {
+                        write!(f, "{}", value.to_string().trim())?;
+                    }
+                }
+                f.write_str(")")?;
+            }
+        }
+        self.keys.iter().try_for_each(|key| write!(f, "[{key}]"))
+    }
 }
 
 impl Stylesheet {
@@ -89,38 +304,76 @@ impl Stylesheet {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ImportStatement {
     pub raw: String,
     pub path: Option<String>,
     pub is_css: bool,
+    /// 括号中的导入选项（已转为小写），例如 `reference`、`inline`。
+    pub options: Vec<String>,
+    pub span: Span,
+}
+
+/// `@plugin "name";`，按名称加载 `FunctionRegistry` 中注册的插件。
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PluginStatement {
+    pub name: String,
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MixinDefinition {
     pub name: String,
     pub params: Vec<MixinParam>,
+    /// `.mixin(@a) when (...) { }` 的守卫，在参数绑定后求值。
+    pub guard: Option<Guard>,
     pub body: Vec<RuleBody>,
+    pub span: Span,
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MixinParam {
     pub name: String,
     pub default: Option<Value>,
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MixinCall {
+    /// 命名空间路径，例如 `#bundle > .button()` 中的 `#bundle`；直接调用时为空。
+    pub namespace: Vec<String>,
     pub name: String,
     pub args: Vec<MixinArgument>,
+    /// `.mixin() !important;`：展开出的所有声明都标记为 `!important`。
+    pub important: bool,
+    pub span: Span,
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum MixinArgument {
     Value(Value),
     Ruleset(Vec<RuleBody>),
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct DetachedCall {
     pub name: String,
+    pub span: Span,
+}
+
+/// `each(@list, { ... })`：对列表或映射（分离规则集中的声明与变量）的每一项求值一次规则集，
+/// 其中可以使用 `@value`、`@key` 与从 1 开始的 `@index`。
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EachCall {
+    pub list: Value,
+    /// 匿名 mixin 写法 `each(@list, #(@v, @k, @i) { ... })` 中依次代替 `value`、`key`、`index` 的变量名。
+    pub params: Vec<String>,
+    /// 内联的规则集，或引用分离规则集的变量（`each(@list, @rules)`）。
+    pub ruleset: MixinArgument,
+    pub span: Span,
 }
@@ -0,0 +1,193 @@
+//! C ABI（`capi` feature），供 Python / Ruby / Go 等构建工具不经 Node 直接嵌入编译器。
+//!
+//! 头文件 `include/less_oxide.h` 随仓库提交；`build.rs` 在启用该 feature 时用 cbindgen
+//! 把头文件生成到 `OUT_DIR`，测试检查两者一致。
+//! 所有权约定：传入的字符串与选项只在调用期间借用；返回的 [`LessOxideResult`] 及其中的
+//! 全部字符串都由本库分配，必须且只能交给 [`less_oxide_result_free`] 释放。
+
+use crate::{
+    compile_file_with_metadata, compile_with_metadata, CompileOptions, CompileOutput, LessError,
+    LessResult, OutputStyle, SourceMapOptions,
+};
+use std::ffi::{c_char, CStr, CString};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::PathBuf;
+use std::ptr;
+
+/// 编译选项；传入 `NULL` 时全部取默认值。字符串字段可为 `NULL`，须为 UTF-8。
+#[repr(C)]
+pub struct LessOxideOptions {
+    /// 是否压缩输出。
+    pub minify: bool,
+    /// 源文件路径，用于解析相对 `@import`；`less_oxide_compile_file` 忽略此项。
+    pub filename: *const c_char,
+    /// 额外的 `@import` 检索目录数组，共 `include_paths_len` 项。
+    pub include_paths: *const *const c_char,
+    /// `include_paths` 的长度。
+    pub include_paths_len: usize,
+    /// 输出格式：`expanded` / `compact` / `compressed`，`NULL` 时由 `minify` 决定。
+    pub output_style: *const c_char,
+    /// 是否生成 source map，结果见 [`LessOxideResult::map`]。
+    pub source_map: bool,
+}
+
+/// 编译结果；成功时 `error` 为 `NULL`，失败时只有 `error` 非空。
+#[repr(C)]
+pub struct LessOxideResult {
+    /// 生成的 CSS。
+    pub css: *mut c_char,
+    /// source map JSON，未开启 `source_map` 时为 `NULL`。
+    pub map: *mut c_char,
+    /// 通过 `@import` 读取的文件路径数组，共 `imports_len` 项。
+    pub imports: *mut *mut c_char,
+    /// `imports` 的长度。
+    pub imports_len: usize,
+    /// 错误信息（含位置），成功时为 `NULL`。
+    pub error: *mut c_char,
+}
+
+/// 编译 LESS 源码，返回的结果须用 [`less_oxide_result_free`] 释放，不会返回 `NULL`。
+///
+/// # Safety
+///
+/// `source` 必须是以 NUL 结尾的有效字符串；`options` 为 `NULL` 或指向有效的
+/// [`LessOxideOptions`]，其中的指针在调用期间有效。
+#[no_mangle]
+pub unsafe extern "C" fn less_oxide_compile(
+    source: *const c_char,
+    options: *const LessOxideOptions,
+) -> *mut LessOxideResult {
+    run(|| {
+        let source = read_str(source, "source")?;
+        compile_with_metadata(source, to_compile_options(options)?)
+    })
+}
+
+/// 编译 LESS 文件，`@import` 相对于文件所在目录解析；释放规则同 [`less_oxide_compile`]。
+///
+/// # Safety
+///
+/// `path` 必须是以 NUL 结尾的有效字符串；`options` 的要求同 [`less_oxide_compile`]。
+#[no_mangle]
+pub unsafe extern "C" fn less_oxide_compile_file(
+    path: *const c_char,
+    options: *const LessOxideOptions,
+) -> *mut LessOxideResult {
+    run(|| {
+        let path = read_str(path, "path")?;
+        compile_file_with_metadata(path, to_compile_options(options)?)
+    })
+}
+
+/// 释放编译结果及其中的全部字符串；传入 `NULL` 时什么也不做。
+///
+/// # Safety
+///
+/// `result` 必须为 `NULL` 或由本库返回且尚未释放的指针，释放后不得再访问。
+#[no_mangle]
+pub unsafe extern "C" fn less_oxide_result_free(result: *mut LessOxideResult) {
+    if result.is_null() {
+        return;
+    }
+    let result = Box::from_raw(result);
+    for text in [result.css, result.map, result.error] {
+        free_string(text);
+    }
+    if !result.imports.is_null() {
+        let imports = Box::from_raw(ptr::slice_from_raw_parts_mut(
+            result.imports,
+            result.imports_len,
+        ));
+        for &path in imports.iter() {
+            free_string(path);
+        }
+    }
+}
+
+/// 执行编译并转换为 C 结果；panic 不会越过 FFI 边界，而是作为错误返回。
+fn run(compile: impl FnOnce() -> LessResult<CompileOutput>) -> *mut LessOxideResult {
+    let result = panic::catch_unwind(AssertUnwindSafe(compile))
+        .unwrap_or_else(|_| Err(LessError::eval("编译时发生内部错误")));
+    let result = match result {
+        Ok(output) => {
+            let imports: Box<[*mut c_char]> = output
+                .dependencies
+                .iter()
+                .map(|path| to_c_string(path.to_string_lossy().into_owned()))
+                .collect();
+            LessOxideResult {
+                css: to_c_string(output.css),
+                map: output.source_map.map_or(ptr::null_mut(), to_c_string),
+                imports_len: imports.len(),
+                imports: Box::into_raw(imports).cast(),
+                error: ptr::null_mut(),
+            }
+        }
+        Err(err) => LessOxideResult {
+            css: ptr::null_mut(),
+            map: ptr::null_mut(),
+            imports: ptr::null_mut(),
+            imports_len: 0,
+            error: to_c_string(err.to_string()),
+        },
+    };
+    Box::into_raw(Box::new(result))
+}
+
+unsafe fn to_compile_options(options: *const LessOxideOptions) -> LessResult<CompileOptions> {
+    let mut compile_options = CompileOptions::default();
+    let Some(opt) = options.as_ref() else {
+        return Ok(compile_options);
+    };
+    compile_options.minify = opt.minify;
+    if opt.source_map {
+        compile_options.source_map = Some(SourceMapOptions::default());
+    }
+    if !opt.output_style.is_null() {
+        let name = read_str(opt.output_style, "output_style")?;
+        compile_options.output_style = Some(
+            OutputStyle::from_name(name)
+                .ok_or_else(|| LessError::eval(format!("未知的 output_style 选项: {name}")))?,
+        );
+    }
+    if !opt.filename.is_null() {
+        let path = PathBuf::from(read_str(opt.filename, "filename")?);
+        if let Some(parent) = path.parent() {
+            let dir = parent.to_path_buf();
+            compile_options.current_dir = Some(dir.clone());
+            compile_options.include_paths.push(dir);
+        }
+    }
+    if !opt.include_paths.is_null() {
+        for idx in 0..opt.include_paths_len {
+            let path = read_str(*opt.include_paths.add(idx), "include_paths")?;
+            compile_options.include_paths.push(PathBuf::from(path));
+        }
+    }
+    Ok(compile_options)
+}
+
+unsafe fn read_str<'a>(text: *const c_char, name: &str) -> LessResult<&'a str> {
+    if text.is_null() {
+        return Err(LessError::eval(format!("{name} 不能为 NULL")));
+    }
+    CStr::from_ptr(text)
+        .to_str()
+        .map_err(|_| LessError::eval(format!("{name} 不是有效的 UTF-8")))
+}
+
+/// 转换为 C 字符串；C 字符串无法表示的 NUL 字符被丢弃。
+fn to_c_string(text: String) -> *mut c_char {
+    let text = CString::new(text).unwrap_or_else(|err| {
+        let mut bytes = err.into_vec();
+        bytes.retain(|&byte| byte != 0);
+        CString::new(bytes).unwrap_or_default()
+    });
+    text.into_raw()
+}
+
+unsafe fn free_string(text: *mut c_char) {
+    if !text.is_null() {
+        drop(CString::from_raw(text));
+    }
+}
@@ -29,9 +29,183 @@ pub fn parse_color(input: &str) -> Option<Rgba> {
     if lowered.starts_with("rgb") {
         return parse_rgb_function(&lowered, false);
     }
-    None
+    named_color(&lowered)
 }
 
+/// CSS 颜色关键字（含 `transparent`），按名称返回颜色。
+pub fn named_color(name: &str) -> Option<Rgba> {
+    if name == "transparent" {
+        return Some(Rgba {
+            r: 0.0,
+            g: 0.0,
+            b: 0.0,
+            a: 0.0,
+        });
+    }
+    let index = NAMED_COLORS
+        .binary_search_by(|(candidate, _)| candidate.cmp(&name))
+        .ok()?;
+    let value = NAMED_COLORS[index].1;
+    Some(Rgba {
+        r: ((value >> 16) & 0xff) as f64 / 255.0,
+        g: ((value >> 8) & 0xff) as f64 / 255.0,
+        b: (value & 0xff) as f64 / 255.0,
+        a: 1.0,
+    })
+}
+
+/// 按名称排序，供 `named_color` 二分查找。
+const NAMED_COLORS: &[(&str, u32)] = &[
+    ("aliceblue", 0xf0f8ff),
+    ("antiquewhite", 0xfaebd7),
+    ("aqua", 0x00ffff),
+    ("aquamarine", 0x7fffd4),
+    ("azure", 0xf0ffff),
+    ("beige", 0xf5f5dc),
+    ("bisque", 0xffe4c4),
+    ("black", 0x000000),
+    ("blanchedalmond", 0xffebcd),
+    ("blue", 0x0000ff),
+    ("blueviolet", 0x8a2be2),
+    ("brown", 0xa52a2a),
+    ("burlywood", 0xdeb887),
+    ("cadetblue", 0x5f9ea0),
+    ("chartreuse", 0x7fff00),
+    ("chocolate", 0xd2691e),
+    ("coral", 0xff7f50),
+    ("cornflowerblue", 0x6495ed),
+    ("cornsilk", 0xfff8dc),
+    ("crimson", 0xdc143c),
+    ("cyan", 0x00ffff),
+    ("darkblue", 0x00008b),
+    ("darkcyan", 0x008b8b),
+    ("darkgoldenrod", 0xb8860b),
+    ("darkgray", 0xa9a9a9),
+    ("darkgreen", 0x006400),
+    ("darkgrey", 0xa9a9a9),
+    ("darkkhaki", 0xbdb76b),
+    ("darkmagenta", 0x8b008b),
+    ("darkolivegreen", 0x556b2f),
+    ("darkorange", 0xff8c00),
+    ("darkorchid", 0x9932cc),
+    ("darkred", 0x8b0000),
+    ("darksalmon", 0xe9967a),
+    ("darkseagreen", 0x8fbc8f),
+    ("darkslateblue", 0x483d8b),
+    ("darkslategray", 0x2f4f4f),
+    ("darkslategrey", 0x2f4f4f),
+    ("darkturquoise", 0x00ced1),
+    ("darkviolet", 0x9400d3),
+    ("deeppink", 0xff1493),
+    ("deepskyblue", 0x00bfff),
+    ("dimgray", 0x696969),
+    ("dimgrey", 0x696969),
+    ("dodgerblue", 0x1e90ff),
+    ("firebrick", 0xb22222),
+    ("floralwhite", 0xfffaf0),
+    ("forestgreen", 0x228b22),
+    ("fuchsia", 0xff00ff),
+    ("gainsboro", 0xdcdcdc),
+    ("ghostwhite", 0xf8f8ff),
+    ("gold", 0xffd700),
+    ("goldenrod", 0xdaa520),
+    ("gray", 0x808080),
+    ("green", 0x008000),
+    ("greenyellow", 0xadff2f),
+    ("grey", 0x808080),
+    ("honeydew", 0xf0fff0),
+    ("hotpink", 0xff69b4),
+    ("indianred", 0xcd5c5c),
+    ("indigo", 0x4b0082),
+    ("ivory", 0xfffff0),
+    ("khaki", 0xf0e68c),
+    ("lavender", 0xe6e6fa),
+    ("lavenderblush", 0xfff0f5),
+    ("lawngreen", 0x7cfc00),
+    ("lemonchiffon", 0xfffacd),
+    ("lightblue", 0xadd8e6),
+    ("lightcoral", 0xf08080),
+    ("lightcyan", 0xe0ffff),
+    ("lightgoldenrodyellow", 0xfafad2),
+    ("lightgray", 0xd3d3d3),
+    ("lightgreen", 0x90ee90),
+    ("lightgrey", 0xd3d3d3),
+    ("lightpink", 0xffb6c1),
+    ("lightsalmon", 0xffa07a),
+    ("lightseagreen", 0x20b2aa),
+    ("lightskyblue", 0x87cefa),
+    ("lightslategray", 0x778899),
+    ("lightslategrey", 0x778899),
+    ("lightsteelblue", 0xb0c4de),
+    ("lightyellow", 0xffffe0),
+    ("lime", 0x00ff00),
+    ("limegreen", 0x32cd32),
+    ("linen", 0xfaf0e6),
+    ("magenta", 0xff00ff),
+    ("maroon", 0x800000),
+    ("mediumaquamarine", 0x66cdaa),
+    ("mediumblue", 0x0000cd),
+    ("mediumorchid", 0xba55d3),
+    ("mediumpurple", 0x9370db),
+    ("mediumseagreen", 0x3cb371),
+    ("mediumslateblue", 0x7b68ee),
+    ("mediumspringgreen", 0x00fa9a),
+    ("mediumturquoise", 0x48d1cc),
+    ("mediumvioletred", 0xc71585),
+    ("midnightblue", 0x191970),
+    ("mintcream", 0xf5fffa),
+    ("mistyrose", 0xffe4e1),
+    ("moccasin", 0xffe4b5),
+    ("navajowhite", 0xffdead),
+    ("navy", 0x000080),
+    ("oldlace", 0xfdf5e6),
+    ("olive", 0x808000),
+    ("olivedrab", 0x6b8e23),
+    ("orange", 0xffa500),
+    ("orangered", 0xff4500),
+    ("orchid", 0xda70d6),
+    ("palegoldenrod", 0xeee8aa),
+    ("palegreen", 0x98fb98),
+    ("paleturquoise", 0xafeeee),
+    ("palevioletred", 0xdb7093),
+    ("papayawhip", 0xffefd5),
+    ("peachpuff", 0xffdab9),
+    ("peru", 0xcd853f),
+    ("pink", 0xffc0cb),
+    ("plum", 0xdda0dd),
+    ("powderblue", 0xb0e0e6),
+    ("purple", 0x800080),
+    ("rebeccapurple", 0x663399),
+    ("red", 0xff0000),
+    ("rosybrown", 0xbc8f8f),
+    ("royalblue", 0x4169e1),
+    ("saddlebrown", 0x8b4513),
+    ("salmon", 0xfa8072),
+    ("sandybrown", 0xf4a460),
+    ("seagreen", 0x2e8b57),
+    ("seashell", 0xfff5ee),
+    ("sienna", 0xa0522d),
+    ("silver", 0xc0c0c0),
+    ("skyblue", 0x87ceeb),
+    ("slateblue", 0x6a5acd),
+    ("slategray", 0x708090),
+    ("slategrey", 0x708090),
+    ("snow", 0xfffafa),
+    ("springgreen", 0x00ff7f),
+    ("steelblue", 0x4682b4),
+    ("tan", 0xd2b48c),
+    ("teal", 0x008080),
+    ("thistle", 0xd8bfd8),
+    ("tomato", 0xff6347),
+    ("turquoise", 0x40e0d0),
+    ("violet", 0xee82ee),
+    ("wheat", 0xf5deb3),
+    ("white", 0xffffff),
+    ("whitesmoke", 0xf5f5f5),
+    ("yellow", 0xffff00),
+    ("yellowgreen", 0x9acd32),
+];
+
 pub fn lighten(color: Rgba, amount: f64) -> Rgba {
     let (h, s, l) = rgb_to_hsl(color);
     let new_l = (l + amount).clamp(0.0, 1.0);
@@ -52,10 +226,234 @@ pub fn fade(color: Rgba, amount: f64) -> Rgba {
     .clamp()
 }
 
-pub fn overlay(top: Rgba, bottom: Rgba) -> Rgba {
-    color_blend(blend_overlay, top, bottom)
+/// 混合模式函数：`backdrop` 为底色、`source` 为叠加色，与 less.js 的参数顺序一致。
+pub fn multiply(backdrop: Rgba, source: Rgba) -> Rgba {
+    color_blend(blend_multiply, backdrop, source)
+}
+
+pub fn screen(backdrop: Rgba, source: Rgba) -> Rgba {
+    color_blend(blend_screen, backdrop, source)
+}
+
+pub fn overlay(backdrop: Rgba, source: Rgba) -> Rgba {
+    color_blend(blend_overlay, backdrop, source)
+}
+
+pub fn softlight(backdrop: Rgba, source: Rgba) -> Rgba {
+    color_blend(blend_softlight, backdrop, source)
+}
+
+pub fn hardlight(backdrop: Rgba, source: Rgba) -> Rgba {
+    color_blend(|cb, cs| blend_overlay(cs, cb), backdrop, source)
+}
+
+pub fn difference(backdrop: Rgba, source: Rgba) -> Rgba {
+    color_blend(|cb, cs| (cb - cs).abs(), backdrop, source)
+}
+
+pub fn exclusion(backdrop: Rgba, source: Rgba) -> Rgba {
+    color_blend(|cb, cs| cb + cs - 2.0 * cb * cs, backdrop, source)
+}
+
+pub fn average(backdrop: Rgba, source: Rgba) -> Rgba {
+    color_blend(|cb, cs| (cb + cs) / 2.0, backdrop, source)
+}
+
+pub fn negation(backdrop: Rgba, source: Rgba) -> Rgba {
+    color_blend(|cb, cs| 1.0 - (cb + cs - 1.0).abs(), backdrop, source)
+}
+
+pub fn saturate(color: Rgba, amount: f64) -> Rgba {
+    let (h, s, l) = rgb_to_hsl(color);
+    hsl_to_rgb(h, (s + amount).clamp(0.0, 1.0), l, color.a)
+}
+
+pub fn desaturate(color: Rgba, amount: f64) -> Rgba {
+    saturate(color, -amount)
+}
+
+pub fn greyscale(color: Rgba) -> Rgba {
+    desaturate(color, 1.0)
+}
+
+/// 旋转色相，`degrees` 可为负数。
+pub fn spin(color: Rgba, degrees: f64) -> Rgba {
+    let (h, s, l) = rgb_to_hsl(color);
+    let hue = (h * 360.0 + degrees).rem_euclid(360.0) / 360.0;
+    hsl_to_rgb(hue, s, l, color.a)
+}
+
+pub fn fade_in(color: Rgba, amount: f64) -> Rgba {
+    fade(color, color.a + amount)
+}
+
+pub fn fade_out(color: Rgba, amount: f64) -> Rgba {
+    fade(color, color.a - amount)
+}
+
+/// 按权重混合两种颜色，算法与 less.js 的 `mix` 保持一致（`weight` 为 0~1）。
+pub fn mix(first: Rgba, second: Rgba, weight: f64) -> Rgba {
+    let w = weight * 2.0 - 1.0;
+    let a = first.a - second.a;
+    let w1 = (if (w * a - -1.0).abs() < f64::EPSILON {
+        w
+    } else {
+        (w + a) / (1.0 + w * a)
+    } + 1.0)
+        / 2.0;
+    let w2 = 1.0 - w1;
+    Rgba {
+        r: first.r * w1 + second.r * w2,
+        g: first.g * w1 + second.g * w2,
+        b: first.b * w1 + second.b * w2,
+        a: first.a * weight + second.a * (1.0 - weight),
+    }
+    .clamp()
+}
+
+/// 颜色四则运算：按 0~255 的通道值逐通道计算 `op`（`+ - * /`），结果不截断，输出时再裁剪；
+/// 透明度按 lessc 的规则合成为 `lhs.a * (1 - rhs.a) + rhs.a`。
+pub fn operate(lhs: Rgba, op: char, rhs: Rgba) -> Rgba {
+    let apply = |left: f64, right: f64| {
+        let (left, right) = (left * 255.0, right * 255.0);
+        let value = match op {
+            '+' => left + right,
+            '-' => left - right,
+            '*' => left * right,
+            _ => left / right,
+        };
+        value / 255.0
+    };
+    Rgba {
+        r: apply(lhs.r, rhs.r),
+        g: apply(lhs.g, rhs.g),
+        b: apply(lhs.b, rhs.b),
+        a: lhs.a * (1.0 - rhs.a) + rhs.a,
+    }
+}
+
+pub fn tint(color: Rgba, amount: f64) -> Rgba {
+    mix(WHITE, color, amount)
+}
+
+pub fn shade(color: Rgba, amount: f64) -> Rgba {
+    mix(BLACK, color, amount)
+}
+
+/// 根据亮度在深浅两色之间选择对比色，`threshold` 为 0~1。
+pub fn contrast(color: Rgba, dark: Rgba, light: Rgba, threshold: f64) -> Rgba {
+    let (dark, light) = if luma(dark) > luma(light) {
+        (light, dark)
+    } else {
+        (dark, light)
+    };
+    if luma(color) < threshold {
+        light
+    } else {
+        dark
+    }
+}
+
+/// 感知亮度（WCAG 相对亮度），返回 0~1。
+pub fn luma(color: Rgba) -> f64 {
+    let linear = |channel: f64| {
+        if channel <= 0.03928 {
+            channel / 12.92
+        } else {
+            ((channel + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    0.2126 * linear(color.r) + 0.7152 * linear(color.g) + 0.0722 * linear(color.b)
+}
+
+/// 由 HSL 分量构造颜色：色相单位为度，其余分量为 0~1。
+pub fn from_hsl(hue: f64, saturation: f64, lightness: f64, alpha: f64) -> Rgba {
+    let h = hue.rem_euclid(360.0) / 360.0;
+    hsl_to_rgb(
+        h,
+        saturation.clamp(0.0, 1.0),
+        lightness.clamp(0.0, 1.0),
+        alpha.clamp(0.0, 1.0),
+    )
+}
+
+/// 由 HSV 分量构造颜色，换算方式与 less.js 的 `hsv()` 一致。
+pub fn from_hsv(hue: f64, saturation: f64, value: f64, alpha: f64) -> Rgba {
+    const PERMUTATIONS: [[usize; 3]; 6] = [
+        [0, 3, 1],
+        [2, 0, 1],
+        [1, 0, 3],
+        [1, 2, 0],
+        [3, 1, 0],
+        [0, 1, 2],
+    ];
+    let h = hue.rem_euclid(360.0);
+    let s = saturation.clamp(0.0, 1.0);
+    let v = value.clamp(0.0, 1.0);
+    let sector = ((h / 60.0).floor() as usize) % 6;
+    let f = h / 60.0 - sector as f64;
+    let candidates = [
+        v,
+        v * (1.0 - s),
+        v * (1.0 - f * s),
+        v * (1.0 - (1.0 - f) * s),
+    ];
+    let [r, g, b] = PERMUTATIONS[sector];
+    Rgba {
+        r: candidates[r],
+        g: candidates[g],
+        b: candidates[b],
+        a: alpha,
+    }
+    .clamp()
+}
+
+/// 返回 HSL 分量：色相（0~360）、饱和度与亮度（0~1）。
+pub fn to_hsl(color: Rgba) -> (f64, f64, f64) {
+    let (h, s, l) = rgb_to_hsl(color);
+    (h * 360.0, s, l)
+}
+
+/// 不透明颜色输出十六进制，否则输出 `rgba(...)`。
+pub fn format_color(color: Rgba) -> String {
+    if color.clamp().a >= 1.0 {
+        format_hex(color)
+    } else {
+        format_rgba(color)
+    }
+}
+
+/// 输出 `#aarrggbb` 形式（IE 滤镜使用的 ARGB 十六进制）。
+pub fn format_argb(color: Rgba) -> String {
+    let c = color.clamp();
+    format!(
+        "#{:02x}{:02x}{:02x}{:02x}",
+        to_channel(c.a),
+        to_channel(c.r),
+        to_channel(c.g),
+        to_channel(c.b)
+    )
+}
+
+/// 返回 0~255 范围的通道值。
+pub fn channel_value(value: f64) -> u8 {
+    to_channel(value)
 }
 
+const WHITE: Rgba = Rgba {
+    r: 1.0,
+    g: 1.0,
+    b: 1.0,
+    a: 1.0,
+};
+
+const BLACK: Rgba = Rgba {
+    r: 0.0,
+    g: 0.0,
+    b: 0.0,
+    a: 1.0,
+};
+
 pub fn format_hex(color: Rgba) -> String {
     let c = color.clamp();
     format!(
@@ -79,6 +477,10 @@ pub fn format_rgba(color: Rgba) -> String {
 }
 
 fn parse_hex(hex: &str) -> Option<Rgba> {
+    // 非十六进制字符（包括多字节字符）直接拒绝，下面按字节切片才不会越过字符边界。
+    if !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
     match hex.len() {
         3 => {
             let r = hex_value(&hex[0..1])?;
@@ -199,6 +601,20 @@ fn blend_overlay(base: f64, overlay: f64) -> f64 {
     }
 }
 
+fn blend_softlight(cb: f64, cs: f64) -> f64 {
+    let (d, e) = if cs > 0.5 {
+        let d = if cb > 0.25 {
+            cb.sqrt()
+        } else {
+            ((16.0 * cb - 12.0) * cb + 4.0) * cb
+        };
+        (d, 1.0)
+    } else {
+        (1.0, cb)
+    };
+    cb - (1.0 - 2.0 * cs) * e * (d - cb)
+}
+
 fn hex_value(hex: &str) -> Option<u8> {
     u8::from_str_radix(hex, 16).ok()
 }
@@ -0,0 +1,648 @@
+use crate::ast::{ImportStatement, Statement, Stylesheet};
+use crate::css_modules;
+use crate::diagnostics::{Diagnostic, Warning};
+use crate::error::{LessError, LessResult};
+use crate::evaluator::{EvaluatedStylesheet, Evaluator};
+use crate::importer::{expand_imports, ParseCache};
+use crate::lint;
+use crate::minify::{minify_nodes, minify_stylesheet};
+use crate::optimize;
+use crate::parser::LessParser;
+use crate::prefix::{self, BrowserTarget};
+use crate::serializer::{CssEmitter, Serializer};
+use crate::sourcemap::{self, SourceMapOptions};
+use crate::stack;
+use crate::stats::{self, CompileStats, FileStats};
+use crate::urls;
+use crate::visit::{walk_stylesheet, Visitor};
+use crate::{CompileOptions, CompileOutput, OutputStyle, RewriteUrls, VariableInfo};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+/// 可复用的编译器：在多次编译之间保留被导入文件的解析结果，
+/// 文件内容未变化时直接复用 AST，适合监听模式或大型项目的重复构建。
+#[derive(Debug)]
+pub struct Compiler {
+    options: CompileOptions,
+    cache: ParseCache,
+}
+
+impl Compiler {
+    pub fn new(options: CompileOptions) -> Self {
+        Self {
+            options,
+            cache: ParseCache::default(),
+        }
+    }
+
+    pub fn options(&self) -> &CompileOptions {
+        &self.options
+    }
+
+    /// 编译 LESS 源码，`@import` 按构造时的选项解析。
+    pub fn compile(&mut self, source: &str) -> LessResult<CompileOutput> {
+        let options = self.options.clone();
+        self.compile_source(source, options, None)
+    }
+
+    /// 编译入口文件；未配置目录时以文件所在目录解析 `@import`。
+    pub fn compile_file<P: AsRef<Path>>(&mut self, path: P) -> LessResult<CompileOutput> {
+        let path = path.as_ref();
+        let source = read_source(path)?;
+        let options = self.file_options(path);
+        self.compile_source(&source, options, Some(path))
+    }
+
+    /// 编译入口文件并把 CSS 写入 `output`，参见 [`crate::compile_file_to`]。
+    pub fn compile_file_to<P: AsRef<Path>, Q: AsRef<Path>>(
+        &mut self,
+        input: P,
+        output: Q,
+    ) -> LessResult<CompileOutput> {
+        let (input, output) = (input.as_ref(), output.as_ref());
+        let source = read_source(input)?;
+        let mut options = self.file_options(input);
+        let mut map_path = output.as_os_str().to_owned();
+        map_path.push(".map");
+        let map_path = PathBuf::from(map_path);
+        if let Some(source_map) = options
+            .source_map
+            .as_mut()
+            .filter(|map| !map.inline && map.url.is_none())
+        {
+            source_map.url = map_path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned());
+        }
+        let result = self.compile_source(&source, options, Some(input))?;
+        write_output(output, &result.css)?;
+        if let Some(map) = &result.source_map {
+            write_output(&map_path, map)?;
+        }
+        Ok(result)
+    }
+
+    /// 求值 LESS 源码，返回序列化之前的样式表，参见 [`crate::evaluate`]。
+    pub fn evaluate(&mut self, source: &str) -> LessResult<EvaluatedStylesheet> {
+        let options = self.options.clone();
+        let (result, errors) = self.run(source, options, None, None);
+        match errors.into_iter().next() {
+            Some(err) => Err(err),
+            None => result.map(|evaluated| evaluated.stylesheet),
+        }
+    }
+
+    /// 求值 LESS 源码并交给 `emitter` 生成产物，参见 [`crate::compile_with_emitter`]。
+    pub fn compile_with_emitter<E: CssEmitter + ?Sized>(
+        &mut self,
+        source: &str,
+        emitter: &mut E,
+    ) -> LessResult<E::Output> {
+        let stylesheet = self.evaluate(source)?;
+        Ok(emitter.emit(&stylesheet))
+    }
+
+    /// 从 `reader` 读取源码，把 CSS 逐条规则写入 `writer`，参见 [`crate::compile_stream`]。
+    pub fn compile_stream(
+        &mut self,
+        mut reader: impl Read,
+        mut writer: impl Write + Send,
+    ) -> LessResult<()> {
+        let mut source = String::new();
+        reader
+            .read_to_string(&mut source)
+            .map_err(|err| LessError::eval(format!("读取输入失败: {err}")))?;
+        let options = self.options.clone();
+        let (result, errors) = self.run(&source, options, None, Some(&mut writer));
+        match errors.into_iter().next() {
+            Some(err) => Err(err),
+            None => result.map(drop),
+        }
+    }
+
+    /// 编译并把错误收集为诊断信息，参见 [`crate::compile_with_diagnostics`]。
+    pub fn compile_with_diagnostics(&mut self, source: &str) -> CompileOutput {
+        let options = self.options.clone();
+        let (result, errors) = self.run(source, options, None, None);
+        let (mut output, errors) = match result {
+            Ok(evaluated) => (
+                evaluated.into_output(|file| self.file_source(file, source, None)),
+                errors,
+            ),
+            Err(err) => (
+                CompileOutput {
+                    css: String::new(),
+                    dependencies: Vec::new(),
+                    diagnostics: Vec::new(),
+                    class_names: BTreeMap::new(),
+                    variables: Vec::new(),
+                    source_map: None,
+                    stats: None,
+                },
+                errors.into_iter().chain([err]).collect(),
+            ),
+        };
+        output
+            .diagnostics
+            .extend(errors.into_iter().map(Diagnostic::from));
+        output
+    }
+
+    /// 检查未使用与未定义的变量和 mixin，参见 [`crate::lint`]。
+    pub fn lint(&mut self, source: &str) -> Vec<Diagnostic> {
+        let options = self.options.clone();
+        self.lint_source(source, options, None)
+    }
+
+    /// 检查入口文件；被导入的文件只提供定义，其中的问题不会报告。
+    pub fn lint_file<P: AsRef<Path>>(&mut self, path: P) -> Vec<Diagnostic> {
+        let path = path.as_ref();
+        match read_source(path) {
+            Ok(source) => {
+                let options = self.file_options(path);
+                self.lint_source(&source, options, Some(path))
+            }
+            Err(err) => vec![Diagnostic::from(err)],
+        }
+    }
+
+    /// 丢弃所有缓存的解析结果。
+    pub fn clear_cache(&mut self) {
+        self.cache.clear();
+    }
+
+    /// 编译文件时的选项：未配置目录时以文件所在目录解析 `@import`。
+    fn file_options(&self, path: &Path) -> CompileOptions {
+        let mut options = self.options.clone();
+        if options.current_dir.is_none() {
+            if let Some(parent) = path.parent() {
+                options.current_dir = Some(parent.to_path_buf());
+            }
+        }
+        if options.include_paths.is_empty() {
+            if let Some(parent) = path.parent() {
+                options.include_paths.push(parent.to_path_buf());
+            }
+        }
+        options
+    }
+
+    fn lint_source(
+        &mut self,
+        source: &str,
+        options: CompileOptions,
+        entry: Option<&Path>,
+    ) -> Vec<Diagnostic> {
+        let result = stack::grow(stack::nesting_depth(source), || {
+            self.lint_ast(source, options, entry)
+        });
+        match result {
+            Ok(diagnostics) => diagnostics,
+            Err(err) => {
+                let err = err.fill_line_text(|file| {
+                    self.file_source(file.map(PathBuf::as_path), source, entry)
+                });
+                vec![Diagnostic::from(err)]
+            }
+        }
+    }
+
+    fn lint_ast(
+        &mut self,
+        source: &str,
+        options: CompileOptions,
+        entry: Option<&Path>,
+    ) -> LessResult<Vec<Diagnostic>> {
+        let parser = LessParser::new();
+        let mut ast = match entry {
+            Some(path) => parser.parse_file(source, path)?,
+            None => parser.parse(source)?,
+        };
+        let mut dependencies = Vec::new();
+        if options.current_dir.is_some()
+            || !options.include_paths.is_empty()
+            || options.import_loader.is_some()
+        {
+            (ast, dependencies) =
+                expand_imports(&parser, ast, &options, &mut self.cache, entry, None)?;
+        }
+        let depth = dependencies
+            .iter()
+            .map(|path| self.cache.nesting_depth(path))
+            .fold(stack::nesting_depth(source), usize::max);
+        stack::grow(depth, move || {
+            let predefined = options
+                .global_vars
+                .keys()
+                .chain(options.modify_vars.keys())
+                .map(|name| name.trim_start_matches('@').to_string());
+            let mut diagnostics = lint::lint_stylesheet(&ast, entry, predefined);
+            for location in diagnostics.iter_mut().filter_map(|d| d.span.as_mut()) {
+                location.fill_line_text(source);
+            }
+            Ok(diagnostics)
+        })
+    }
+
+    /// 容错模式下被跳过的错误同样视为失败，返回其中第一个。
+    fn compile_source(
+        &mut self,
+        source: &str,
+        options: CompileOptions,
+        entry: Option<&Path>,
+    ) -> LessResult<CompileOutput> {
+        let (result, errors) = self.run(source, options, entry, None);
+        match errors.into_iter().next() {
+            Some(err) => Err(err),
+            None => result.map(|evaluated| {
+                evaluated.into_output(|file| self.file_source(file, source, entry))
+            }),
+        }
+    }
+
+    /// `file` 的源码：入口（或直接编译的字符串）为 `source`，被导入的文件从解析缓存中取。
+    fn file_source<'s>(
+        &'s self,
+        file: Option<&Path>,
+        source: &'s str,
+        entry: Option<&Path>,
+    ) -> Option<&'s str> {
+        match file {
+            Some(file) if Some(file) != entry => self.cache.source(file),
+            _ => Some(source),
+        }
+    }
+
+    /// 执行序列化之前的全部编译步骤，同时返回容错模式下被跳过的错误；所有错误都已补全出错行。
+    /// 给出 `output` 时 CSS 直接写入其中，参见 [`evaluate`]。
+    fn run(
+        &mut self,
+        source: &str,
+        options: CompileOptions,
+        entry: Option<&Path>,
+        output: Option<&mut (dyn Write + Send)>,
+    ) -> (LessResult<Evaluated>, Vec<LessError>) {
+        let mut recovered = Vec::new();
+        let result = stack::grow(stack::nesting_depth(source), || {
+            self.compile_ast(source, options, entry, &mut recovered, output)
+        });
+        let fill = |err: LessError| {
+            err.fill_line_text(|file| self.file_source(file.map(PathBuf::as_path), source, entry))
+        };
+        let result = result.map(|mut evaluated| {
+            evaluated.warnings = std::mem::take(&mut evaluated.warnings)
+                .into_iter()
+                .map(|warning| Warning {
+                    error: fill(warning.error),
+                    ..warning
+                })
+                .collect();
+            evaluated
+        });
+        (
+            result.map_err(fill),
+            recovered.into_iter().map(fill).collect(),
+        )
+    }
+
+    fn compile_ast(
+        &mut self,
+        source: &str,
+        options: CompileOptions,
+        entry: Option<&Path>,
+        recovered: &mut Vec<LessError>,
+        output: Option<&mut (dyn Write + Send)>,
+    ) -> LessResult<Evaluated> {
+        let parser = if options.error_recovery {
+            LessParser::with_error_recovery()
+        } else {
+            LessParser::new()
+        };
+        let started = options.collect_stats.then(Instant::now);
+        let mut ast = match entry {
+            Some(path) => parser.parse_file(source, path)?,
+            None => parser.parse(source)?,
+        };
+        let mut files = started.map(|started| {
+            vec![FileStats {
+                path: entry.map(Path::to_path_buf),
+                parse_time: started.elapsed(),
+            }]
+        });
+        if !options.global_vars.is_empty() || !options.modify_vars.is_empty() {
+            let globals = parse_variables(&parser, &options.global_vars, "globalVars")?;
+            let modified = parse_variables(&parser, &options.modify_vars, "modifyVars")?;
+            ast.statements = globals
+                .into_iter()
+                .chain(ast.statements)
+                .chain(modified)
+                .collect();
+        }
+        let mut dependencies = Vec::new();
+        if options.current_dir.is_some()
+            || !options.include_paths.is_empty()
+            || options.import_loader.is_some()
+        {
+            (ast, dependencies) = expand_imports(
+                &parser,
+                ast,
+                &options,
+                &mut self.cache,
+                entry,
+                files.as_mut(),
+            )?;
+        }
+        recovered.extend(parser.take_errors());
+        // 被导入的文件可能比入口嵌套得更深，求值与释放 AST 时按其中最深者预留栈空间。
+        let depth = dependencies
+            .iter()
+            .map(|path| self.cache.nesting_depth(path))
+            .fold(stack::nesting_depth(source), usize::max);
+        stack::grow(depth, move || {
+            evaluate(ast, options, entry, dependencies, files, recovered, output)
+        })
+    }
+}
+
+/// 导入展开之后的步骤：URL 改写、求值以及各项后处理。`files` 为 `Some` 时（`collect_stats`）
+/// 带上各文件的解析耗时生成统计信息。
+///
+/// 给出 `output` 时（`compile_stream`）CSS 写入其中，返回结果中的样式表为空。不需要整个样式表的
+/// 后处理（`merge_rules`、`css_modules`、`collect_stats`）都未开启、且 `@import` 都位于顶层时，
+/// 每条顶层语句求值后立即写出；否则求值完成后再写出，有错误时不写出任何内容。
+fn evaluate(
+    mut ast: Stylesheet,
+    mut options: CompileOptions,
+    entry: Option<&Path>,
+    dependencies: Vec<PathBuf>,
+    files: Option<Vec<FileStats>>,
+    recovered: &mut Vec<LessError>,
+    output: Option<&mut (dyn Write + Send)>,
+) -> LessResult<Evaluated> {
+    if options.rewrite_urls != RewriteUrls::Off {
+        let base = entry
+            .and_then(Path::parent)
+            .or(options.current_dir.as_deref())
+            .unwrap_or(Path::new(""));
+        urls::rewrite_urls(&mut ast, options.rewrite_urls, base);
+    }
+
+    if options.output_style == Some(OutputStyle::Compressed) {
+        options.minify = true;
+    }
+    let minify = options.minify;
+    let keep_empty_rules = options.keep_empty_rules;
+    let output_style = options.output_style;
+    let dump_line_numbers = options.dump_line_numbers;
+    let source_map = options.source_map.take();
+    let merge_rules = options.merge_rules;
+    let autoprefix = std::mem::take(&mut options.autoprefix);
+    let css_modules = options.css_modules.then(|| {
+        let root = options
+            .css_modules_root
+            .clone()
+            .or_else(|| std::env::current_dir().ok());
+        let path = entry.map_or_else(String::new, |entry| {
+            css_modules::module_path(entry, root.as_deref())
+        });
+        format!("{}\0{path}", options.css_modules_salt)
+    });
+    let mut stats = files.map(|files| CompileStats {
+        max_nesting_depth: stats::nesting_depth(&ast),
+        files,
+        ..CompileStats::default()
+    });
+    let started = stats.is_some().then(Instant::now);
+    let serializer = Serializer::new(minify)
+        .keep_empty_rules(keep_empty_rules)
+        .output_style(output_style)
+        .dump_line_numbers(dump_line_numbers);
+    let mut evaluator = Evaluator::new(options);
+    let streaming = output.is_some()
+        && !merge_rules
+        && css_modules.is_none()
+        && stats.is_none()
+        && recovered.is_empty()
+        && imports_only_at_top_level(&ast);
+    let output = match output {
+        Some(writer) if streaming => {
+            let written = stream_css(
+                &mut evaluator,
+                &ast,
+                &serializer,
+                minify,
+                &autoprefix,
+                writer,
+            );
+            recovered.extend(evaluator.take_errors());
+            written?;
+            let warnings = evaluator.take_warnings();
+            return Ok(Evaluated {
+                stylesheet: EvaluatedStylesheet::default(),
+                serializer,
+                dependencies,
+                class_names: BTreeMap::new(),
+                variables: evaluator.top_level_variables(),
+                warnings,
+                source_map,
+                stats: None,
+            });
+        }
+        output => output,
+    };
+    let stylesheet = evaluator.evaluate(&ast);
+    recovered.extend(evaluator.take_errors());
+    let mut stylesheet = stylesheet?;
+    let warnings = evaluator.take_warnings();
+    let variables = evaluator.top_level_variables();
+    if merge_rules {
+        optimize::merge_rules(&mut stylesheet);
+    }
+    if !autoprefix.is_empty() {
+        prefix::add_prefixes(&mut stylesheet, &autoprefix);
+    }
+    let class_names = match css_modules {
+        Some(seed) => css_modules::scope_class_names(&mut stylesheet, &seed),
+        None => BTreeMap::new(),
+    };
+    if minify {
+        minify_stylesheet(&mut stylesheet);
+    }
+    if let (Some(stats), Some(started)) = (stats.as_mut(), started) {
+        stats.evaluate_time = started.elapsed();
+        stats.mixin_expansions = evaluator.mixin_expansions();
+        stats.count_nodes(&stylesheet);
+    }
+    if let Some(mut writer) = output {
+        if recovered.is_empty() {
+            serializer
+                .write_css(std::mem::take(&mut stylesheet), &mut writer)
+                .map_err(write_error)?;
+        }
+    }
+
+    Ok(Evaluated {
+        stylesheet,
+        serializer,
+        dependencies,
+        class_names,
+        variables,
+        warnings,
+        source_map,
+        stats,
+    })
+}
+
+/// 逐条求值顶层语句，补全前缀、压缩后立即写入 `writer`，不保留整个样式表的求值结果。
+/// `@charset` 与 `@import` 必须位于输出开头，由调用方保证它们只出现在顶层，因此可以在求值前确定。
+fn stream_css<'a>(
+    evaluator: &mut Evaluator<'a>,
+    ast: &'a Stylesheet,
+    serializer: &Serializer,
+    minify: bool,
+    autoprefix: &[BrowserTarget],
+    mut writer: &mut (dyn Write + Send),
+) -> LessResult<()> {
+    evaluator.prepare(ast)?;
+    let mut charset = None;
+    let mut imports: Vec<String> = Vec::new();
+    for statement in &ast.statements {
+        match statement {
+            Statement::Charset(value) => {
+                charset.get_or_insert(value.as_str());
+            }
+            Statement::Import(import) if !imports.contains(&import.raw) => {
+                imports.push(import.raw.clone());
+            }
+            _ => {}
+        }
+    }
+    let mut statements = ast.statements.iter();
+    let mut pending = Vec::new().into_iter();
+    let mut failed = None;
+    let nodes = std::iter::from_fn(|| loop {
+        if let Some(node) = pending.next() {
+            return Some(node);
+        }
+        let statement = statements.next()?;
+        let mut produced = Vec::new();
+        if let Err(err) = evaluator.evaluate_statement(statement, &mut produced) {
+            failed = Some(err);
+            return None;
+        }
+        if !autoprefix.is_empty() {
+            prefix::prefix_nodes(&mut produced, autoprefix);
+        }
+        if minify {
+            minify_nodes(&mut produced);
+        }
+        pending = produced.into_iter();
+    });
+    let written = serializer.write_nodes(charset, &imports, nodes, &mut writer);
+    match failed {
+        Some(err) => Err(err),
+        None => written.map_err(write_error),
+    }
+}
+
+/// 顶层以外（规则集、mixin、`(reference)` 导入中）没有 CSS `@import`。
+fn imports_only_at_top_level(ast: &Stylesheet) -> bool {
+    struct Imports(usize);
+
+    impl Visitor for Imports {
+        fn visit_import(&mut self, _import: &ImportStatement) {
+            self.0 += 1;
+        }
+    }
+
+    let mut all = Imports(0);
+    walk_stylesheet(&mut all, ast);
+    let top_level = ast
+        .statements
+        .iter()
+        .filter(|statement| matches!(statement, Statement::Import(_)))
+        .count();
+    all.0 == top_level
+}
+
+fn write_error(err: std::io::Error) -> LessError {
+    LessError::eval(format!("写入输出失败: {err}"))
+}
+
+/// 完成求值与各项优化、尚未序列化的编译结果。
+struct Evaluated {
+    stylesheet: EvaluatedStylesheet,
+    serializer: Serializer,
+    dependencies: Vec<PathBuf>,
+    class_names: BTreeMap<String, String>,
+    variables: Vec<VariableInfo>,
+    warnings: Vec<Warning>,
+    source_map: Option<SourceMapOptions>,
+    stats: Option<CompileStats>,
+}
+
+impl Evaluated {
+    /// 序列化并组装编译结果；`content` 按文件返回源码，供 source map 的 `sourcesContent` 使用。
+    fn into_output<'s>(self, content: impl Fn(Option<&Path>) -> Option<&'s str>) -> CompileOutput {
+        let (css, source_map) = match &self.source_map {
+            None => (self.serializer.to_css(&self.stylesheet), None),
+            Some(options) => {
+                let (mut css, mappings) = self.serializer.to_css_mapped(&self.stylesheet, true);
+                let map = sourcemap::generate(&mappings, options, content);
+                if let Some(comment) = sourcemap::url_comment(&map, options) {
+                    if !css.is_empty() && !css.ends_with('\n') {
+                        css.push('\n');
+                    }
+                    css.push_str(&comment);
+                }
+                (css, (!options.inline).then_some(map))
+            }
+        };
+        CompileOutput {
+            css,
+            dependencies: self.dependencies,
+            diagnostics: self.warnings.into_iter().map(Diagnostic::from).collect(),
+            class_names: self.class_names,
+            variables: self.variables,
+            source_map,
+            stats: self.stats,
+        }
+    }
+}
+
+fn read_source(path: &Path) -> LessResult<String> {
+    fs::read_to_string(path)
+        .map_err(|err| LessError::eval(format!("读取文件 {} 失败: {err}", path.display())))
+}
+
+/// 写入输出文件，所在目录不存在时先创建。
+fn write_output(path: &Path, contents: &str) -> LessResult<()> {
+    let write = || {
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, contents)
+    };
+    write().map_err(|err| LessError::eval(format!("写入文件 {} 失败: {err}", path.display())))
+}
+
+/// 把 `globalVars` / `modifyVars` 解析为变量声明语句，键按名称排序以保证输出稳定。
+fn parse_variables(
+    parser: &LessParser,
+    vars: &HashMap<String, String>,
+    option: &str,
+) -> LessResult<Vec<Statement>> {
+    let mut names: Vec<_> = vars.keys().collect();
+    names.sort();
+    let mut source = String::new();
+    for name in names {
+        let value = vars[name].trim().trim_end_matches(';');
+        source.push_str(&format!("@{}: {value};\n", name.trim_start_matches('@')));
+    }
+    parser
+        .parse_file(&source, Path::new(option))
+        .map(|stylesheet| stylesheet.statements)
+}
@@ -0,0 +1,72 @@
+//! `CompileOptions::css_modules` 开启时的类名局部化：`.btn` → `.btn_1a2b3c`。
+//!
+//! 后缀是 `css_modules_salt`、入口文件相对 `css_modules_root` 的路径与类名的 FNV-1a 哈希，
+//! 与源码内容及项目所在的绝对路径无关；`:global(...)` 中的选择器保持原样。
+
+use crate::evaluator::{EvaluatedNode, EvaluatedStylesheet};
+use crate::selector::rename_classes;
+use std::collections::BTreeMap;
+use std::path::{Component, Path};
+
+/// 参与哈希的入口路径：相对 `root`（不在其下时为原路径），统一用 `/` 分隔并去掉 `.`，
+/// 不同平台上得到相同的类名。
+pub(crate) fn module_path(entry: &Path, root: Option<&Path>) -> String {
+    let relative = root
+        .and_then(|root| entry.strip_prefix(root).ok())
+        .unwrap_or(entry);
+    relative
+        .components()
+        .filter(|component| *component != Component::CurDir)
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// 改写全部规则的类名，返回原类名到新类名的映射。
+pub(crate) fn scope_class_names(
+    stylesheet: &mut EvaluatedStylesheet,
+    seed: &str,
+) -> BTreeMap<String, String> {
+    let mut class_names = BTreeMap::new();
+    scope_nodes(&mut stylesheet.nodes, seed, &mut class_names);
+    class_names
+}
+
+fn scope_nodes(
+    nodes: &mut [EvaluatedNode],
+    seed: &str,
+    class_names: &mut BTreeMap<String, String>,
+) {
+    for node in nodes {
+        match node {
+            EvaluatedNode::Rule(rule) => {
+                let mut rename = |name: &str| {
+                    class_names
+                        .entry(name.to_string())
+                        .or_insert_with(|| format!("{name}_{:06x}", fnv1a(seed, name) & 0xff_ffff))
+                        .clone()
+                };
+                for selector in &mut rule.selectors {
+                    *selector = rename_classes(selector, &mut rename);
+                }
+            }
+            EvaluatedNode::AtRule(at_rule) => {
+                let name = at_rule.name.to_ascii_lowercase();
+                // `@keyframes` 的子规则是 `from` / `50%` 之类的关键帧选择器，不含类名。
+                if !name.ends_with("keyframes") {
+                    scope_nodes(&mut at_rule.children, seed, class_names);
+                }
+            }
+            EvaluatedNode::Raw(_) | EvaluatedNode::Comment(_) => {}
+        }
+    }
+}
+
+fn fnv1a(seed: &str, name: &str) -> u32 {
+    seed.bytes()
+        .chain([0])
+        .chain(name.bytes())
+        .fold(0x811c_9dc5, |hash, byte| {
+            (hash ^ u32::from(byte)).wrapping_mul(0x0100_0193)
+        })
+}
@@ -0,0 +1,55 @@
+//! 容错编译产生的诊断信息，结构与编辑器 / LSP 的诊断保持一致。
+
+use crate::error::{LessError, SourceLocation};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// 出错位置；导入文件缺失等无法定位到节点的错误为 `None`。
+    pub span: Option<SourceLocation>,
+    /// 不含位置片段的错误描述。
+    pub message: String,
+    /// 稳定的类别：`parse-error`、`eval-error`；求值警告 `unit-mismatch`（单位不兼容的运算沿用左侧单位）、
+    /// `unknown-function`（未知函数原样输出）、`deprecated`（已弃用的写法）、
+    /// `undefined-variable`（`allow_undefined_variables` 下按原文输出的变量）；或 [`crate::lint`] 的
+    /// `unused-variable`、`unused-mixin`、`undefined-variable`、`undefined-mixin`。
+    pub code: &'static str,
+}
+
+impl From<LessError> for Diagnostic {
+    fn from(err: LessError) -> Self {
+        let code = match err {
+            LessError::ParseError { .. } => "parse-error",
+            LessError::EvalError { .. } => "eval-error",
+        };
+        Diagnostic {
+            severity: Severity::Error,
+            span: err.location().cloned(),
+            message: err.message().to_string(),
+            code,
+        }
+    }
+}
+
+/// 求值中产生的非致命问题，编译结果中以 [`Severity::Warning`] 的诊断返回。
+#[derive(Debug)]
+pub(crate) struct Warning {
+    pub(crate) code: &'static str,
+    pub(crate) error: LessError,
+}
+
+impl From<Warning> for Diagnostic {
+    fn from(warning: Warning) -> Self {
+        Diagnostic {
+            severity: Severity::Warning,
+            code: warning.code,
+            ..Diagnostic::from(warning.error)
+        }
+    }
+}
@@ -1,12 +1,49 @@
-use thiserror::Error;
+use std::fmt;
+use std::path::PathBuf;
+
+use crate::ast::Span;
 
 /// 编译过程中统一的错误类型。
-#[derive(Debug, Error)]
+#[derive(Debug)]
 pub enum LessError {
-    #[error("解析失败: {message} (位置 {position})")]
-    ParseError { message: String, position: usize },
-    #[error("语义求值失败: {0}")]
-    EvalError(String),
+    ParseError {
+        message: String,
+        position: usize,
+        location: Option<Box<SourceLocation>>,
+    },
+    EvalError {
+        message: String,
+        location: Option<Box<SourceLocation>>,
+    },
+}
+
+/// 错误在源码中的位置，行列号从 1 开始；`line_text` 为出错行的原文，用于渲染带 `^` 的片段。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceLocation {
+    /// 出错的文件；直接编译字符串时为 `None`。
+    pub file: Option<PathBuf>,
+    pub line: usize,
+    pub column: usize,
+    pub line_text: Option<String>,
+}
+
+impl SourceLocation {
+    /// 节点起点的位置，不含源码行。
+    pub(crate) fn from_span(span: &Span) -> Self {
+        SourceLocation {
+            file: span.file.as_deref().map(|path| path.to_path_buf()),
+            line: span.line,
+            column: span.column,
+            line_text: None,
+        }
+    }
+
+    /// 从所在文件的完整源码中补上出错行。
+    pub(crate) fn fill_line_text(&mut self, source: &str) {
+        if self.line_text.is_none() {
+            self.line_text = source_line(source, self.line);
+        }
+    }
 }
 
 pub type LessResult<T> = Result<T, LessError>;
@@ -16,10 +53,154 @@ impl LessError {
         LessError::ParseError {
             message: message.into(),
             position,
+            location: None,
         }
     }
 
     pub fn eval<S: Into<String>>(message: S) -> Self {
-        LessError::EvalError(message.into())
+        LessError::EvalError {
+            message: message.into(),
+            location: None,
+        }
+    }
+
+    /// 不含位置信息的错误描述。
+    pub fn message(&self) -> &str {
+        match self {
+            LessError::ParseError { message, .. } | LessError::EvalError { message, .. } => message,
+        }
+    }
+
+    pub fn location(&self) -> Option<&SourceLocation> {
+        match self {
+            LessError::ParseError { location, .. } | LessError::EvalError { location, .. } => {
+                location.as_deref()
+            }
+        }
+    }
+
+    fn location_mut(&mut self) -> &mut Option<Box<SourceLocation>> {
+        match self {
+            LessError::ParseError { location, .. } | LessError::EvalError { location, .. } => {
+                location
+            }
+        }
+    }
+
+    /// 以 `span` 作为出错位置；已带有位置的错误保持不变，因此最内层的节点优先。
+    pub(crate) fn at(mut self, span: &Span) -> Self {
+        let location = self.location_mut();
+        if location.is_none() {
+            *location = Some(Box::new(SourceLocation::from_span(span)));
+        }
+        self
+    }
+
+    /// 根据解析错误的字节偏移计算行列号并附上源码行。
+    pub(crate) fn with_source(mut self, source: &str, file: Option<PathBuf>) -> Self {
+        if let LessError::ParseError {
+            position, location, ..
+        } = &mut self
+        {
+            if location.is_none() {
+                let (line, column) = line_column(source, *position);
+                *location = Some(Box::new(SourceLocation {
+                    file,
+                    line,
+                    column,
+                    line_text: source_line(source, line),
+                }));
+            }
+        }
+        self
+    }
+
+    /// 为缺少源码行的位置补上原文，`lookup` 按文件返回完整源码。
+    pub(crate) fn fill_line_text<'s>(
+        mut self,
+        lookup: impl FnOnce(Option<&PathBuf>) -> Option<&'s str>,
+    ) -> Self {
+        if let Some(location) = self.location_mut() {
+            if location.line_text.is_none() {
+                if let Some(source) = lookup(location.file.as_ref()) {
+                    location.fill_line_text(source);
+                }
+            }
+        }
+        self
+    }
+}
+
+/// 计算字节偏移所在的行列号（从 1 开始，列按字符计）。
+fn line_column(source: &str, position: usize) -> (usize, usize) {
+    let position = position.min(source.len());
+    let before = source.get(..position).unwrap_or(source);
+    let line_start = before.rfind('\n').map_or(0, |idx| idx + 1);
+    let line = before.matches('\n').count() + 1;
+    (line, before[line_start..].chars().count() + 1)
+}
+
+/// 第 `line` 行（从 1 开始）的原文；与 `str::lines` 不同，末尾换行之后的空行同样可取。
+fn source_line(source: &str, line: usize) -> Option<String> {
+    let text = source.split('\n').nth(line.checked_sub(1)?)?;
+    Some(text.strip_suffix('\r').unwrap_or(text).to_string())
+}
+
+impl fmt::Display for LessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LessError::ParseError {
+                message,
+                position,
+                location: None,
+            } => write!(f, "解析失败: {message} (位置 {position})"),
+            LessError::ParseError {
+                message,
+                location: Some(location),
+                ..
+            } => write!(f, "解析失败: {message}\n{location}"),
+            LessError::EvalError {
+                message,
+                location: None,
+            } => write!(f, "语义求值失败: {message}"),
+            LessError::EvalError {
+                message,
+                location: Some(location),
+            } => write!(f, "语义求值失败: {message}\n{location}"),
+        }
+    }
+}
+
+impl std::error::Error for LessError {}
+
+/// 渲染为类似 rustc 的片段：
+///
+/// ```text
+///  --> styles/main.less:3:9
+///   |
+/// 3 | .a { color red; }
+///   |         ^
+/// ```
+impl fmt::Display for SourceLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let file = self
+            .file
+            .as_ref()
+            .map_or_else(|| "<input>".to_string(), |path| path.display().to_string());
+        let gutter = " ".repeat(self.line.to_string().len());
+        write!(f, "{gutter}--> {file}:{}:{}", self.line, self.column)?;
+        if let Some(text) = &self.line_text {
+            let caret: String = text
+                .chars()
+                .take(self.column.saturating_sub(1))
+                .map(|ch| if ch == '\t' { '\t' } else { ' ' })
+                .collect();
+            write!(
+                f,
+                "\n{gutter} |\n{} | {text}\n{gutter} | {caret}^",
+                self.line
+            )?;
+        }
+        Ok(())
     }
 }
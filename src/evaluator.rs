@@ -1,121 +1,478 @@
 use crate::ast::{
-    AtRule, Declaration, MixinArgument, MixinCall, MixinDefinition, RuleBody, RuleSet, Statement,
-    Stylesheet, Value, ValuePiece,
+    AtRule, BodylessAtRule, Comment, Declaration, DetachedCall, EachCall, Guard, GuardCondition,
+    GuardOperator, ImportStatement, Lookup, LookupTarget, MixinArgument, MixinCall,
+    MixinDefinition, MixinParam, PropertyMerge, RuleBody, RuleSet, Span, Statement, Stylesheet,
+    Value, ValuePiece, VariableDeclaration, VariableInit,
+};
+use crate::color::{self, Rgba};
+use crate::diagnostics::Warning;
+use crate::error::{LessError, LessResult, SourceLocation};
+use crate::functions::{self, CustomFunction, FunctionRegistry, FunctionValue};
+use crate::selector::ParentSelector;
+use crate::units::{self, Unit};
+use crate::utils::{format_quantity, parse_quantity, Quantity};
+use crate::{
+    CompileOptions, MathMode, VariableInfo, DEFAULT_MAX_MIXIN_DEPTH, DEFAULT_MAX_SELECTORS,
 };
-use crate::color;
-use crate::error::{LessError, LessResult};
-use crate::CompileOptions;
 use indexmap::IndexMap;
-use once_cell::sync::Lazy;
-use regex::Regex;
-
-/// 经过语义求值后的规则信息。
-#[derive(Debug, Clone)]
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::mem;
+use std::path::Path;
+use std::rc::Rc;
+use std::sync::Arc;
+
+/// 经过语义求值后的规则信息：变量、mixin、运算与嵌套都已展开，只剩扁平的 CSS 规则，
+/// 由 [`crate::evaluate`] 返回，供自定义压缩、关键 CSS 提取等工具直接处理。
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct EvaluatedStylesheet {
+    /// 首个 `@charset` 的编码字符串（含引号），序列化时位于输出最前。
+    pub charset: Option<String>,
+    /// 原样输出的 CSS `@import` 语句（含结尾分号），位于其余规则之前。
     pub imports: Vec<String>,
     pub nodes: Vec<EvaluatedNode>,
 }
 
+/// 样式表或 at-rule 中的一个输出节点。
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum EvaluatedNode {
     Rule(EvaluatedRule),
     AtRule(EvaluatedAtRule),
+    /// `@import (inline)` 引入的原始 CSS 文本。
+    Raw(String),
+    /// 需要输出的块注释，含定界符。
+    Comment(String),
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct EvaluatedRule {
+    /// 父选择器已展开的完整选择器列表。
     pub selectors: Vec<String>,
     pub declarations: Vec<EvaluatedDeclaration>,
+    /// 规则所在的源文件（取自 `Span::file`），直接编译字符串时为 `None`；
+    /// 导入展开后据此区分节点来源，留给 source map 等后续处理读取。
+    pub source: Option<Arc<Path>>,
+    /// 规则在源文件中的行号与列号，从 1 开始；由 mixin 展开的规则为 mixin 定义中的位置。
+    pub line: usize,
+    pub column: usize,
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct EvaluatedAtRule {
+    /// 不含 `@` 的名称，如 `media`。
     pub name: String,
+    /// 求值后的前导条件，如 `(min-width: 768px)`。
     pub params: String,
+    /// 直接写在 at-rule 中的声明，如 `@font-face` 的描述符。
     pub declarations: Vec<EvaluatedDeclaration>,
     pub children: Vec<EvaluatedNode>,
+    /// at-rule 所在的源文件。
+    pub source: Option<Arc<Path>>,
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct EvaluatedDeclaration {
     pub name: String,
+    /// 求值后的值，不含 `!important`。
     pub value: String,
     pub important: bool,
+    /// 源码中的 `+:` / `+_:` 标记；同名声明在求值时已合并。
+    pub merge: PropertyMerge,
+    /// 紧挨在该声明之前的注释，输出时位于声明上方。
+    pub comments: Vec<String>,
+    /// 声明所在的源文件；mixin 展开的声明记录 mixin 定义所在的文件。
+    pub source: Option<Arc<Path>>,
 }
 
 /// 负责维护变量与 mixin 作用域并输出扁平化 CSS 规则。
-pub struct Evaluator {
-    scopes: Vec<IndexMap<String, VariableValue>>,
-    mixin_scopes: Vec<IndexMap<String, MixinDefinition>>,
+///
+/// 求值过程借用 `'a` 生命周期的样式表：作用域中登记的变量、mixin 与分离规则集都引用 AST 本身，
+/// mixin 展开与命名空间登记不再复制规则体。
+pub struct Evaluator<'a> {
+    /// 变量作用域栈，自栈顶向下查找；调用 mixin 与分离规则集时定义处的作用域链被移到栈顶，
+    /// 因此栈中的顺序即查找顺序，不一定是嵌套顺序。
+    scopes: Vec<Scope<'a>>,
+    /// 下一个新建作用域的编号。
+    next_scope: ScopeId,
+    /// 已退出、但其中定义的 mixin 被返回给调用方的 mixin 作用域，供这些 mixin 调用时找回定义处的变量。
+    closures: HashMap<ScopeId, Scope<'a>>,
+    /// 以选择器元素拼接而成的名称（`.btn.primary`、`#ns.a`）为键登记的 mixin。
+    mixin_scopes: Vec<IndexMap<Cow<'a, str>, Mixin<'a>>>,
+    /// 正在惰性求值的变量名，用于检测循环引用。
+    resolving: Vec<String>,
+    /// 当前惰性求值中查找命中的最深作用域下标，未命中或查找了 mixin 时为 `usize::MAX`；
+    /// 不超过变量定义所在作用域时结果与调用方无关，可以缓存。
+    lookup_depth: usize,
+    math: MathMode,
+    /// 当前可用的自定义函数：全局注册的函数加上已通过 `@plugin` 加载的插件函数。
+    custom_functions: HashMap<String, CustomFunction>,
+    registry: FunctionRegistry,
+    /// 当前正在展开的 mixin 层数及其上限，递归 mixin 超过上限时报错。
+    mixin_depth: usize,
+    max_mixin_depth: usize,
+    /// 已展开的 mixin 调用次数（守卫不成立的不计），供 `collect_stats` 统计。
+    mixin_expansions: usize,
+    /// 单条规则展开父选择器后允许的最大选择器数。
+    max_selectors: usize,
+    /// 已产生的选择器与声明文本的字节数及其上限（`max_output_size`）。
+    output_size: usize,
+    max_output_size: Option<usize>,
+    /// 容错模式下出错的语句或规则体项被跳过，错误累积在 `errors` 中。
+    error_recovery: bool,
+    errors: Vec<LessError>,
+    /// 单位不兼容的加减是否报错；关闭时沿用左侧单位并记下警告。
+    strict_units: bool,
+    /// 未定义的变量按原文输出并记下警告，而不是报错。
+    allow_undefined_variables: bool,
+    /// 求值中产生的警告，所在声明求值后补上位置。算术与函数求值只借用 `&self`，因此放在 `RefCell` 中。
+    warnings: RefCell<Vec<Warning>>,
+    /// 是否输出普通注释（`keep_comments` 且非压缩）；`/*!` 注释总是输出。
+    keep_comments: bool,
+    custom_property_interpolation: bool,
+    /// 是否保留没有声明、也没有嵌套输出的空规则。
+    keep_empty_rules: bool,
+    /// 样式表中出现的首个 `@charset`，其余的被忽略。
+    charset: Option<String>,
+    /// 保留到求值阶段的 CSS `@import`（包括规则集内的），序列化时统一置顶；重复的只保留首个。
+    imports: Vec<String>,
+    /// 顶层变量最后一次声明的位置，按首次声明的顺序排列。
+    top_level_spans: IndexMap<&'a str, &'a Span>,
+    /// 规则体中尚未归属的注释，附到下一条声明上，遇到嵌套规则或规则体结束时作为独立节点输出。
+    comments: Vec<String>,
 }
 
-impl Evaluator {
+impl<'a> Evaluator<'a> {
     pub fn new(options: CompileOptions) -> Self {
-        let _ = options;
         Self {
-            scopes: vec![IndexMap::new()],
+            scopes: vec![Scope::new(0, None)],
+            next_scope: 1,
+            closures: HashMap::new(),
             mixin_scopes: vec![IndexMap::new()],
+            resolving: Vec::new(),
+            lookup_depth: 0,
+            math: options.math,
+            custom_functions: options.functions.functions().clone(),
+            registry: options.functions,
+            mixin_depth: 0,
+            max_mixin_depth: options.max_mixin_depth.unwrap_or(DEFAULT_MAX_MIXIN_DEPTH),
+            mixin_expansions: 0,
+            max_selectors: options.max_selectors.unwrap_or(DEFAULT_MAX_SELECTORS),
+            output_size: 0,
+            max_output_size: options.max_output_size,
+            error_recovery: options.error_recovery,
+            errors: Vec::new(),
+            strict_units: options.strict_units,
+            allow_undefined_variables: options.allow_undefined_variables,
+            warnings: RefCell::new(Vec::new()),
+            keep_comments: options.keep_comments && !options.minify,
+            comments: Vec::new(),
+            custom_property_interpolation: options.custom_property_interpolation,
+            keep_empty_rules: options.keep_empty_rules,
+            charset: None,
+            imports: Vec::new(),
+            top_level_spans: IndexMap::new(),
+        }
+    }
+
+    /// 取出容错模式下被跳过的求值错误。
+    pub fn take_errors(&mut self) -> Vec<LessError> {
+        std::mem::take(&mut self.errors)
+    }
+
+    /// 求值中展开 mixin 的总次数。
+    pub fn mixin_expansions(&self) -> usize {
+        self.mixin_expansions
+    }
+
+    /// 取出求值中产生的警告，例如非严格单位模式下单位不兼容的运算。
+    pub fn take_warnings(&mut self) -> Vec<Warning> {
+        self.warnings.take()
+    }
+
+    fn warn(&self, code: &'static str, message: String) {
+        self.warnings.borrow_mut().push(Warning {
+            code,
+            error: LessError::eval(message),
+        });
+    }
+
+    /// 同一提示只记录第一次，用于未知函数等在多次求值中会重复出现的问题。
+    fn warn_once(&self, code: &'static str, message: String) {
+        let seen = self
+            .warnings
+            .borrow()
+            .iter()
+            .any(|warning| warning.code == code && warning.error.message() == message);
+        if !seen {
+            self.warn(code, message);
+        }
+    }
+
+    /// 为 `from` 之后新增、尚无位置的警告补上所在节点的位置。算术求值出错时值按原文保留，
+    /// 因此 `strict_units` 下单位不兼容的运算在这里转为错误。
+    fn locate_warnings(&self, from: usize, span: &Span) -> LessResult<()> {
+        let mut warnings = self.warnings.borrow_mut();
+        let located: Vec<_> = warnings
+            .drain(from..)
+            .map(|warning| Warning {
+                error: warning.error.at(span),
+                ..warning
+            })
+            .collect();
+        if self.strict_units {
+            if let Some(mismatch) = located.iter().position(|w| w.code == "unit-mismatch") {
+                return Err(located.into_iter().nth(mismatch).unwrap().error);
+            }
+        }
+        warnings.extend(located);
+        Ok(())
+    }
+
+    /// 求值后调用：返回全部顶层变量（不含分离规则集）的最终值与声明位置。
+    /// 值无法求出的变量（如引用了未定义的变量）被跳过。
+    pub fn top_level_variables(&mut self) -> Vec<VariableInfo> {
+        let spans = std::mem::take(&mut self.top_level_spans);
+        spans
+            .into_iter()
+            .filter_map(|(name, span)| match self.lookup_variable(name) {
+                Ok(VariableValue::Text(value)) => Some(VariableInfo {
+                    name: name.to_string(),
+                    value: value.to_string(),
+                    location: SourceLocation::from_span(span),
+                }),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// 执行 `eval`；容错模式下记录其错误并恢复作用域栈，调用方继续处理后续节点。
+    fn recoverable(&mut self, eval: impl FnOnce(&mut Self) -> LessResult<()>) -> LessResult<()> {
+        let scopes = self.scopes.len();
+        let mixin_scopes = self.mixin_scopes.len();
+        match eval(self) {
+            // 超出输出上限后继续求值只会不断报同样的错，直接终止。
+            Err(err) if self.error_recovery && !self.output_exceeded() => {
+                self.scopes.truncate(scopes);
+                self.mixin_scopes.truncate(mixin_scopes);
+                self.errors.push(err);
+                Ok(())
+            }
+            result => result,
+        }
+    }
+
+    fn output_exceeded(&self) -> bool {
+        self.max_output_size
+            .is_some_and(|limit| self.output_size > limit)
+    }
+
+    /// 累计求值产生的文本长度，超过 `max_output_size` 时报错，避免失控的 mixin 展开耗尽内存。
+    fn charge_output(&mut self, bytes: usize) -> LessResult<()> {
+        self.output_size += bytes;
+        match self.max_output_size {
+            Some(limit) if self.output_size > limit => {
+                Err(LessError::eval(format!("编译输出超过 {limit} 字节的上限")))
+            }
+            _ => Ok(()),
         }
     }
 
-    pub fn evaluate(&mut self, stylesheet: Stylesheet) -> LessResult<EvaluatedStylesheet> {
-        let mut imports = Vec::new();
+    pub fn evaluate(&mut self, stylesheet: &'a Stylesheet) -> LessResult<EvaluatedStylesheet> {
         let mut nodes = Vec::new();
-        for statement in stylesheet.statements {
-            match statement {
-                Statement::Import(import) => {
-                    imports.push(import.raw);
+        self.prepare(stylesheet)?;
+        for statement in &stylesheet.statements {
+            self.evaluate_statement(statement, &mut nodes)?;
+        }
+        Ok(EvaluatedStylesheet {
+            charset: self.charset.take(),
+            imports: std::mem::take(&mut self.imports),
+            nodes,
+        })
+    }
+
+    /// 登记顶层的变量、mixin 并加载插件；逐条调用 [`Self::evaluate_statement`] 之前调用一次。
+    pub(crate) fn prepare(&mut self, stylesheet: &'a Stylesheet) -> LessResult<()> {
+        self.declare_statements(&stylesheet.statements);
+        self.load_plugins(&stylesheet.statements)
+    }
+
+    /// 求值一条顶层语句，产生的节点追加到 `nodes`；`@charset` 与 `@import` 只记录下来，不产生节点。
+    pub(crate) fn evaluate_statement(
+        &mut self,
+        statement: &'a Statement,
+        nodes: &mut Vec<EvaluatedNode>,
+    ) -> LessResult<()> {
+        self.recoverable(|this| this.eval_statement(statement, nodes))
+    }
+
+    fn eval_statement(
+        &mut self,
+        statement: &'a Statement,
+        nodes: &mut Vec<EvaluatedNode>,
+    ) -> LessResult<()> {
+        match statement {
+            Statement::Import(import) => self.add_import(import),
+            Statement::Plugin(_) | Statement::Reference(_) => {}
+            Statement::Charset(charset) => {
+                self.charset.get_or_insert_with(|| charset.clone());
+            }
+            Statement::Variable(_) => {}
+            Statement::RawCss(content) => {
+                self.charge_output(content.len())?;
+                nodes.push(EvaluatedNode::Raw(content.clone()));
+            }
+            Statement::Comment(comment) => {
+                if self.keeps_comment(comment) {
+                    nodes.push(EvaluatedNode::Comment(comment.text.clone()));
                 }
+            }
+            Statement::RuleSet(rule) => {
+                let mut produced = self.eval_ruleset(rule, &[])?;
+                nodes.append(&mut produced);
+            }
+            Statement::AtRule(at_rule) => {
+                let mut produced = self.eval_at_rule(at_rule, &[])?;
+                nodes.append(&mut produced);
+            }
+            Statement::BodylessAtRule(at_rule) => {
+                nodes.push(self.eval_bodyless_at_rule(at_rule)?);
+            }
+            Statement::MixinDefinition(_) => {}
+            Statement::MixinCall(call) => {
+                let mut declarations = Vec::new();
+                let mut produced = Vec::new();
+                self.expand_mixin(call, &[], &mut declarations, &mut produced)?;
+                if !declarations.is_empty() {
+                    return Err(LessError::eval("顶层 mixin 调用产生了无法附加的声明"));
+                }
+                nodes.extend(produced);
+                nodes.extend(self.take_comment_nodes());
+            }
+            Statement::DetachedCall(call) => {
+                let mut declarations = Vec::new();
+                let mut produced = Vec::new();
+                self.invoke_detached_ruleset(call, &[], &mut declarations, &mut produced)?;
+                if !declarations.is_empty() {
+                    return Err(LessError::eval(format!(
+                        "顶层调用的规则集 @{} 产生了无法附加的声明",
+                        call.name
+                    )));
+                }
+                nodes.extend(produced);
+                nodes.extend(self.take_comment_nodes());
+            }
+            Statement::Each(call) => {
+                let mut declarations = Vec::new();
+                let mut produced = Vec::new();
+                self.eval_each(call, &[], &mut declarations, &mut produced)?;
+                if !declarations.is_empty() {
+                    return Err(LessError::eval("顶层 each() 产生了无法附加的声明").at(&call.span));
+                }
+                nodes.extend(produced);
+                nodes.extend(self.take_comment_nodes());
+            }
+        }
+        Ok(())
+    }
+
+    fn add_import(&mut self, import: &ImportStatement) {
+        if !self.imports.contains(&import.raw) {
+            self.imports.push(import.raw.clone());
+        }
+    }
+
+    /// 预先登记顶层（含 `(reference)` 导入）的变量与 mixin，使其在整个样式表中可见，
+    /// 与 lessc 一样允许先调用、后定义。
+    fn declare_statements(&mut self, statements: &'a [Statement]) {
+        for statement in statements {
+            match statement {
                 Statement::Variable(var) => {
-                    let value = self.eval_value(&var.value)?;
-                    self.set_variable_text(var.name, value);
-                }
-                Statement::RuleSet(rule) => {
-                    let mut produced = self.eval_ruleset(rule, &[])?;
-                    nodes.append(&mut produced);
-                }
-                Statement::AtRule(at_rule) => {
-                    let evaluated = self.eval_at_rule(at_rule, &[])?;
-                    nodes.push(EvaluatedNode::AtRule(evaluated));
-                }
-                Statement::MixinDefinition(def) => {
-                    self.set_mixin(def);
-                }
-                Statement::MixinCall(call) => {
-                    let mut declarations = Vec::new();
-                    let mut produced = Vec::new();
-                    self.expand_mixin(call, &[], &mut declarations, &mut produced)?;
-                    if !declarations.is_empty() {
-                        return Err(LessError::eval("顶层 mixin 调用产生了无法附加的声明"));
+                    if matches!(var.value, VariableInit::Value(_)) {
+                        self.top_level_spans.insert(&var.name, &var.span);
                     }
-                    nodes.extend(produced);
+                    self.declare_variable(var);
+                }
+                Statement::MixinDefinition(def) => self.set_mixin(
+                    Cow::Borrowed(&def.name),
+                    Mixin::from_definition(def, self.current_scope()),
+                ),
+                Statement::RuleSet(rule) => self.register_ruleset(rule),
+                Statement::Reference(inner) => self.declare_statements(inner),
+                _ => {}
+            }
+        }
+    }
+
+    /// 求值前加载顶层（含导入文件与 `(reference)` 导入）的所有 `@plugin`，
+    /// 插件函数与变量、mixin 一样在整个样式表中可用，不受 `@plugin` 所在位置的限制。
+    fn load_plugins(&mut self, statements: &'a [Statement]) -> LessResult<()> {
+        for statement in statements {
+            match statement {
+                Statement::Plugin(plugin) => {
+                    self.recoverable(|this| this.load_plugin(&plugin.name))?
                 }
+                Statement::Reference(inner) => self.load_plugins(inner)?,
+                _ => {}
             }
         }
-        Ok(EvaluatedStylesheet { imports, nodes })
+        Ok(())
     }
 
+    /// 求值规则集；内部未带位置的错误归到该规则集的位置。
     fn eval_ruleset(
         &mut self,
-        rule: RuleSet,
+        rule: &'a RuleSet,
+        parent_selectors: &[String],
+    ) -> LessResult<Vec<EvaluatedNode>> {
+        self.eval_ruleset_body(rule, parent_selectors)
+            .map_err(|err| err.at(&rule.span))
+    }
+
+    fn eval_ruleset_body(
+        &mut self,
+        rule: &'a RuleSet,
         parent_selectors: &[String],
     ) -> LessResult<Vec<EvaluatedNode>> {
+        if let Some(guard) = &rule.guard {
+            if !self.eval_guard(guard)? {
+                return Ok(Vec::new());
+            }
+        }
+
         self.push_scope();
         self.push_mixin_scope();
 
-        let selectors = self.combine_selectors(parent_selectors, &rule.selectors);
+        self.declare_body(&rule.body);
+        let warnings = self.warnings.borrow().len();
+        let own_selectors = self.interpolate_selectors(&rule.selectors)?;
+        self.locate_warnings(warnings, &rule.span)?;
+        let selectors = self.combine_selectors(parent_selectors, own_selectors)?;
         let mut declarations = Vec::new();
         let mut pending_nodes: Vec<EvaluatedNode> = Vec::new();
 
-        for item in rule.body {
-            self.handle_rule_body_item(item, &selectors, &mut declarations, &mut pending_nodes)?;
+        for item in &rule.body {
+            self.recoverable(|this| {
+                this.handle_rule_body_item(item, &selectors, &mut declarations, &mut pending_nodes)
+            })?;
         }
 
+        pending_nodes.extend(self.take_comment_nodes());
+        Self::merge_properties(&mut declarations);
         let mut output = Vec::new();
-        if !declarations.is_empty() {
+        if !declarations.is_empty() || (self.keep_empty_rules && pending_nodes.is_empty()) {
+            self.charge_output(selectors.iter().map(String::len).sum())?;
             output.push(EvaluatedNode::Rule(EvaluatedRule {
-                selectors: selectors.clone(),
+                selectors,
                 declarations,
+                source: rule.span.file.clone(),
+                line: rule.span.line,
+                column: rule.span.column,
             }));
         }
 
@@ -128,117 +485,565 @@ impl Evaluator {
 
     fn handle_rule_body_item(
         &mut self,
-        item: RuleBody,
+        item: &'a RuleBody,
         selectors: &[String],
         declarations: &mut Vec<EvaluatedDeclaration>,
         pending_nodes: &mut Vec<EvaluatedNode>,
     ) -> LessResult<()> {
         match item {
-            RuleBody::Variable(var) => {
-                let value = self.eval_value(&var.value)?;
-                self.set_variable_text(var.name, value);
-            }
+            RuleBody::Variable(_) => {}
             RuleBody::Declaration(decl) => {
                 let evaluated = self.eval_declaration(decl)?;
                 declarations.push(evaluated);
             }
             RuleBody::NestedRule(nested) => {
+                pending_nodes.extend(self.take_comment_nodes());
                 let nested_output = self.eval_ruleset(nested, selectors)?;
                 pending_nodes.extend(nested_output);
             }
-            RuleBody::MixinDefinition(def) => {
-                self.set_mixin(def);
-            }
+            RuleBody::MixinDefinition(_) => {}
             RuleBody::MixinCall(call) => {
                 self.expand_mixin(call, selectors, declarations, pending_nodes)?;
             }
             RuleBody::AtRule(at_rule) => {
+                pending_nodes.extend(self.take_comment_nodes());
                 let evaluated = self.eval_at_rule(at_rule, selectors)?;
-                pending_nodes.push(EvaluatedNode::AtRule(evaluated));
+                pending_nodes.extend(evaluated);
+            }
+            RuleBody::BodylessAtRule(at_rule) => {
+                pending_nodes.extend(self.take_comment_nodes());
+                pending_nodes.push(self.eval_bodyless_at_rule(at_rule)?);
             }
             RuleBody::DetachedCall(call) => {
-                self.invoke_detached_ruleset(&call.name, selectors, declarations, pending_nodes)?;
+                self.invoke_detached_ruleset(call, selectors, declarations, pending_nodes)?;
             }
+            RuleBody::Each(call) => {
+                self.eval_each(call, selectors, declarations, pending_nodes)?;
+            }
+            RuleBody::Import(import) => self.add_import(import),
+            RuleBody::Comment(comment) => self.buffer_comment(comment),
         }
         Ok(())
     }
 
     fn expand_mixin(
         &mut self,
-        call: MixinCall,
+        call: &'a MixinCall,
+        selectors: &[String],
+        declarations: &mut Vec<EvaluatedDeclaration>,
+        pending_nodes: &mut Vec<EvaluatedNode>,
+    ) -> LessResult<()> {
+        if self.mixin_depth >= self.max_mixin_depth {
+            return Err(LessError::eval(format!(
+                "mixin {} 递归展开超过 {} 层",
+                Self::mixin_call_path(call),
+                self.max_mixin_depth
+            ))
+            .at(&call.span));
+        }
+        let first_declaration = declarations.len();
+        let first_node = pending_nodes.len();
+        self.mixin_depth += 1;
+        let result = self.expand_mixin_body(call, selectors, declarations, pending_nodes);
+        self.mixin_depth -= 1;
+        result.map_err(|err| err.at(&call.span))?;
+        if call.important {
+            for decl in &mut declarations[first_declaration..] {
+                decl.important = true;
+            }
+            Self::mark_important(&mut pending_nodes[first_node..]);
+        }
+        Ok(())
+    }
+
+    fn expand_mixin_body(
+        &mut self,
+        call: &'a MixinCall,
         selectors: &[String],
         declarations: &mut Vec<EvaluatedDeclaration>,
         pending_nodes: &mut Vec<EvaluatedNode>,
     ) -> LessResult<()> {
-        let definition = self.resolve_mixin(&call.name)?;
+        let returned = self.with_mixin_scope(call, |this, definition| {
+            this.mixin_expansions += 1;
+            this.declare_body(definition.body);
+            for body_item in definition.body {
+                this.handle_rule_body_item(body_item, selectors, declarations, pending_nodes)?;
+            }
+            Ok(this.returned_definitions(definition.body))
+        })?;
+        if let Some(returned) = returned {
+            self.accept_returned(returned);
+        }
+        Ok(())
+    }
+
+    /// 与 less.js 一样在 mixin 的作用域中执行 `eval`：实参在调用处求值，随后把 mixin 定义处的作用域链
+    /// 移到栈顶并进入新作用域，绑定参数（含默认值与 `@arguments`）。因此 mixin 中的变量先在自身与定义处查找，
+    /// 找不到时才回到调用处。守卫不成立时不执行 `eval`，返回 `None`。
+    fn with_mixin_scope<T>(
+        &mut self,
+        call: &'a MixinCall,
+        eval: impl FnOnce(&mut Self, Mixin<'a>) -> LessResult<T>,
+    ) -> LessResult<Option<T>> {
+        let (definition, namespace_vars) = self.resolve_mixin_call(call)?;
         if call.args.len() > definition.params.len() {
             return Err(LessError::eval(format!(
                 "mixin {} 参数过多: 期望 {} 个，实际 {} 个",
-                call.name,
+                Self::mixin_call_path(call),
                 definition.params.len(),
                 call.args.len()
             )));
         }
+        let mut args = Vec::with_capacity(call.args.len());
+        for arg in &call.args {
+            args.push(match arg {
+                MixinArgument::Value(value) => match self.detached_ruleset_reference(value)? {
+                    Some(ruleset) => VariableValue::DetachedRuleset(ruleset),
+                    None => VariableValue::Text(Rc::from(self.eval_variable_value(value)?)),
+                },
+                MixinArgument::Ruleset(body) => VariableValue::DetachedRuleset(Ruleset {
+                    body,
+                    scope: self.current_scope(),
+                }),
+            });
+        }
 
+        let hoisted = self.hoist_scope_chain(definition.scope);
         self.push_scope();
         self.push_mixin_scope();
+        let result = self
+            .bind_mixin_params(definition, namespace_vars, args)
+            .and_then(|matched| match matched {
+                true => eval(self, definition).map(Some),
+                false => Ok(None),
+            });
+        self.pop_mixin_scope();
+        self.pop_scope();
+        self.restore_scope_chain(hoisted);
+        result
+    }
 
-        for (arg_value, param) in call.args.iter().zip(definition.params.iter()) {
-            match arg_value {
-                MixinArgument::Value(value) => {
-                    let evaluated = self.eval_value(value)?;
-                    self.set_variable_text(param.name.clone(), evaluated);
-                }
-                MixinArgument::Ruleset(body) => {
-                    self.set_variable_ruleset(param.name.clone(), body.clone());
-                }
+    /// 在当前作用域中绑定已求值的实参与默认值，返回守卫是否成立。
+    fn bind_mixin_params(
+        &mut self,
+        definition: Mixin<'a>,
+        namespace_vars: Vec<&'a VariableDeclaration>,
+        args: Vec<VariableValue<'a>>,
+    ) -> LessResult<bool> {
+        for var in namespace_vars {
+            self.declare_variable(var);
+        }
+
+        // `@arguments`：按参数顺序以空格连接的全部实参（含默认值）。
+        let mut arguments = Vec::new();
+        let provided = args.len();
+        for (value, param) in args.into_iter().zip(definition.params) {
+            if let VariableValue::Text(text) = &value {
+                arguments.push(text.to_string());
             }
+            self.set_variable(&param.name, value);
         }
 
-        if call.args.len() < definition.params.len() {
-            for param in definition.params.iter().skip(call.args.len()) {
-                if let Some(default) = &param.default {
-                    let evaluated = self.eval_value(default)?;
-                    self.set_variable_text(param.name.clone(), evaluated);
-                } else {
-                    self.pop_mixin_scope();
-                    self.pop_scope();
-                    return Err(LessError::eval(format!(
-                        "mixin {} 缺少必填参数 @{}",
-                        definition.name, param.name
-                    )));
+        for param in definition.params.iter().skip(provided) {
+            let Some(default) = &param.default else {
+                return Err(LessError::eval(format!(
+                    "mixin {} 缺少必填参数 @{}",
+                    definition.name, param.name
+                )));
+            };
+            let evaluated = self.eval_variable_value(default)?;
+            self.set_variable_text(&param.name, Rc::from(evaluated.as_str()));
+            arguments.push(evaluated);
+        }
+        self.set_variable_text("arguments", Rc::from(arguments.join(" ")));
+
+        match definition.guard {
+            Some(guard) => self.eval_guard(guard),
+            None => Ok(true),
+        }
+    }
+
+    /// mixin 展开后返回给调用方的定义：定义体中声明的变量（在 mixin 作用域中求值）与 mixin。
+    /// 返回 mixin 或分离规则集时保留当前作用域，供它们之后被调用时找回定义处的参数与变量。
+    fn returned_definitions(&mut self, body: &'a [RuleBody]) -> Returned<'a> {
+        let mut variables = Vec::new();
+        let mut mixins = Vec::new();
+        let scope = self.current_scope();
+        for item in body {
+            match item {
+                // 求值失败的变量不返回，与调用方从未用到它们时的行为一致。
+                RuleBody::Variable(var) => {
+                    if let Ok(value) = self.lookup_variable(&var.name) {
+                        variables.push((var.name.as_str(), value));
+                    }
                 }
+                RuleBody::MixinDefinition(def) => {
+                    mixins.push((def.name.as_str(), Mixin::from_definition(def, scope)));
+                }
+                _ => {}
+            }
+        }
+        let captures = !mixins.is_empty()
+            || variables
+                .iter()
+                .any(|(_, value)| matches!(value, VariableValue::DetachedRuleset(_)));
+        if captures {
+            if let Some(frame) = self.scopes.last() {
+                self.closures.insert(scope, frame.clone());
             }
         }
+        (variables, mixins)
+    }
 
-        for body_item in definition.body {
-            self.handle_rule_body_item(body_item, selectors, declarations, pending_nodes)?;
+    /// 把 mixin 返回的变量与 mixin 登记到调用处的作用域；调用处自身已有的同名定义优先。
+    fn accept_returned(&mut self, (variables, mixins): Returned<'a>) {
+        if let Some(scope) = self.scopes.last_mut() {
+            for (name, value) in variables {
+                scope.variables.entry(name).or_insert(value);
+            }
         }
+        if let Some(scope) = self.mixin_scopes.last_mut() {
+            for (name, mixin) in mixins {
+                scope.entry(Cow::Borrowed(name)).or_insert(mixin);
+            }
+        }
+    }
 
-        self.pop_mixin_scope();
+    /// 在 `scope` 所在的作用域链上新建作用域执行 `eval`：定义处的作用域链先被移到栈顶，结束后恢复原顺序。
+    fn with_closure_scope<T>(
+        &mut self,
+        scope: ScopeId,
+        eval: impl FnOnce(&mut Self) -> LessResult<T>,
+    ) -> LessResult<T> {
+        let hoisted = self.hoist_scope_chain(scope);
+        self.push_scope();
+        let result = eval(self);
         self.pop_scope();
-        Ok(())
+        self.restore_scope_chain(hoisted);
+        result
+    }
+
+    /// 把作用域 `scope` 及其各层外层作用域按由外到内的顺序移到栈顶，其余作用域保持原有的相对顺序；
+    /// 已退出的作用域从 `closures` 中取回。返回各层原来在栈中的位置（取回的为 `None`）。
+    fn hoist_scope_chain(&mut self, scope: ScopeId) -> Vec<Option<usize>> {
+        let mut chain = Vec::new();
+        let mut next = Some(scope);
+        while let Some(id) = next {
+            if let Some(index) = self.scopes.iter().rposition(|frame| frame.id == id) {
+                chain.push((Some(index), id));
+                next = self.scopes[index].parent;
+            } else if let Some(frame) = self.closures.get(&id) {
+                chain.push((None, id));
+                next = frame.parent;
+            } else {
+                break;
+            }
+        }
+        let top = self.scopes.len() - chain.iter().filter(|(index, _)| index.is_some()).count();
+        let in_place = chain
+            .iter()
+            .rev()
+            .enumerate()
+            .all(|(offset, (index, _))| *index == Some(top + offset));
+        if in_place {
+            return Vec::new();
+        }
+
+        let mut indices: Vec<usize> = chain.iter().filter_map(|(index, _)| *index).collect();
+        indices.sort_unstable();
+        let mut removed: HashMap<usize, Scope<'a>> = HashMap::new();
+        for &index in indices.iter().rev() {
+            removed.insert(index, self.scopes.remove(index));
+        }
+        let mut positions = Vec::with_capacity(chain.len());
+        for (index, id) in chain.into_iter().rev() {
+            let frame = match index {
+                Some(index) => removed.remove(&index),
+                None => self.closures.get(&id).cloned(),
+            };
+            if let Some(frame) = frame {
+                self.scopes.push(frame);
+                positions.push(index);
+            }
+        }
+        positions
     }
 
+    /// 撤销 [`Self::hoist_scope_chain`]：栈顶的各层放回原位，取回的作用域存回 `closures`。
+    fn restore_scope_chain(&mut self, positions: Vec<Option<usize>>) {
+        if positions.is_empty() {
+            return;
+        }
+        let frames = self.scopes.split_off(self.scopes.len() - positions.len());
+        let mut restored = Vec::with_capacity(frames.len());
+        for (index, frame) in positions.into_iter().zip(frames) {
+            match index {
+                Some(index) => restored.push((index, frame)),
+                None => {
+                    self.closures.insert(frame.id, frame);
+                }
+            }
+        }
+        restored.sort_unstable_by_key(|(index, _)| *index);
+        for (index, frame) in restored {
+            self.scopes.insert(index, frame);
+        }
+    }
+
+    /// 调用分离规则集；与 mixin 共用 `max_mixin_depth` 计数，`@d: { @d(); }` 之类的递归调用会报错而不是耗尽栈空间。
     fn invoke_detached_ruleset(
         &mut self,
-        name: &str,
+        call: &DetachedCall,
         selectors: &[String],
         declarations: &mut Vec<EvaluatedDeclaration>,
         pending_nodes: &mut Vec<EvaluatedNode>,
     ) -> LessResult<()> {
-        let body = self.resolve_ruleset_variable(name)?;
-        for item in body {
-            self.handle_rule_body_item(item, selectors, declarations, pending_nodes)?;
+        if self.mixin_depth >= self.max_mixin_depth {
+            return Err(LessError::eval(format!(
+                "分离规则集 @{} 递归调用超过 {} 层",
+                call.name, self.max_mixin_depth
+            ))
+            .at(&call.span));
+        }
+        let ruleset = self
+            .resolve_ruleset_variable(&call.name)
+            .map_err(|err| err.at(&call.span))?;
+        self.mixin_depth += 1;
+        let result = self.with_closure_scope(ruleset.scope, |this| {
+            this.declare_body(ruleset.body);
+            ruleset.body.iter().try_for_each(|item| {
+                this.handle_rule_body_item(item, selectors, declarations, pending_nodes)
+            })
+        });
+        self.mixin_depth -= 1;
+        result.map_err(|err| err.at(&call.span))
+    }
+
+    /// 展开 `each()`：为每一项新建作用域并绑定 `@value`、`@key` 与 `@index`（或匿名 mixin 的参数名），
+    /// 再按分离规则集调用的方式求值规则集；与 mixin 共用 `max_mixin_depth` 计数。
+    fn eval_each(
+        &mut self,
+        call: &'a EachCall,
+        selectors: &[String],
+        declarations: &mut Vec<EvaluatedDeclaration>,
+        pending_nodes: &mut Vec<EvaluatedNode>,
+    ) -> LessResult<()> {
+        if self.mixin_depth >= self.max_mixin_depth {
+            return Err(LessError::eval(format!(
+                "each() 递归展开超过 {} 层",
+                self.max_mixin_depth
+            ))
+            .at(&call.span));
+        }
+        let ruleset = match &call.ruleset {
+            MixinArgument::Ruleset(body) => Some(Ruleset {
+                body,
+                scope: self.current_scope(),
+            }),
+            MixinArgument::Value(value) => self
+                .detached_ruleset_reference(value)
+                .map_err(|err| err.at(&call.span))?,
+        }
+        .ok_or_else(|| LessError::eval("each() 的第二个参数必须是规则集").at(&call.span))?;
+        let items = self
+            .each_items(&call.list)
+            .map_err(|err| err.at(&call.span))?;
+        let name = |index: usize, default: &'static str| {
+            call.params.get(index).map_or(default, String::as_str)
+        };
+        let (value_name, key_name, index_name) =
+            (name(0, "value"), name(1, "key"), name(2, "index"));
+        self.mixin_depth += 1;
+        let result = items
+            .into_iter()
+            .enumerate()
+            .try_for_each(|(index, (key, value))| {
+                self.with_closure_scope(ruleset.scope, |this| {
+                    this.set_variable_text(value_name, value);
+                    this.set_variable_text(key_name, key);
+                    this.set_variable_text(index_name, Rc::from((index + 1).to_string()));
+                    this.declare_body(ruleset.body);
+                    ruleset.body.iter().try_for_each(|item| {
+                        this.handle_rule_body_item(item, selectors, declarations, pending_nodes)
+                    })
+                })
+            });
+        self.mixin_depth -= 1;
+        result.map_err(|err| err.at(&call.span))
+    }
+
+    /// `each()` 遍历的 `(键, 值)`：分离规则集按其中的声明与变量（键为属性名或 `@name`）遍历，
+    /// 其余值按逗号列表（只有一项时按空格列表）遍历，键为从 1 开始的序号。
+    fn each_items(&mut self, list: &'a Value) -> LessResult<Vec<(Rc<str>, Rc<str>)>> {
+        let Some(ruleset) = self.detached_ruleset_reference(list)? else {
+            let text = self.eval_variable_value(list)?;
+            let parts: Vec<String> = Self::split_top_level_commas(&text)
+                .into_iter()
+                .filter(|part| !part.is_empty())
+                .collect();
+            return Ok(functions::list_items(&parts)
+                .into_iter()
+                .enumerate()
+                .map(|(index, item)| (Rc::from((index + 1).to_string()), Rc::from(item)))
+                .collect());
+        };
+        self.with_closure_scope(ruleset.scope, |this| {
+            this.declare_body(ruleset.body);
+            let mut items = Vec::new();
+            for item in ruleset.body {
+                let (key, value) = match item {
+                    RuleBody::Declaration(decl) => (this.interpolate(&decl.name)?, &decl.value),
+                    RuleBody::Variable(VariableDeclaration {
+                        name,
+                        value: VariableInit::Value(value),
+                        ..
+                    }) => (format!("@{name}"), value),
+                    _ => continue,
+                };
+                let value = this.eval_variable_value(value)?;
+                items.push((Rc::from(key), Rc::from(value)));
+            }
+            Ok(items)
+        })
+    }
+
+    fn eval_lookup_text(&mut self, lookup: &'a Lookup) -> LessResult<Rc<str>> {
+        match self.eval_lookup(lookup)? {
+            VariableValue::Text(value) => Ok(value),
+            _ => Err(LessError::eval(format!(
+                "{lookup} 的结果是规则集，不能作为文本使用"
+            ))),
+        }
+    }
+
+    /// 求值 `@config[key]` 与 `.mixin()[key]`：依次在分离规则集或 mixin 展开后的作用域中按键取值，
+    /// 取到的值仍是规则集时可以继续查找。
+    fn eval_lookup(&mut self, lookup: &'a Lookup) -> LessResult<VariableValue<'a>> {
+        let mut keys = lookup.keys.iter();
+        let mut current = match &lookup.target {
+            LookupTarget::Variable(name) => self.lookup_variable(name)?,
+            LookupTarget::Mixin(call) => {
+                self.lookup_depth = usize::MAX;
+                let Some(key) = keys.next() else {
+                    return Err(LessError::eval(format!("{lookup} 缺少查找键")));
+                };
+                let key = self.lookup_key(key)?;
+                if self.mixin_depth >= self.max_mixin_depth {
+                    return Err(LessError::eval(format!(
+                        "mixin {} 递归展开超过 {} 层",
+                        Self::mixin_call_path(call),
+                        self.max_mixin_depth
+                    )));
+                }
+                self.mixin_depth += 1;
+                let result = self.with_mixin_scope(call, |this, definition| {
+                    this.declare_body(definition.body);
+                    this.ruleset_member(definition.body, &key)
+                });
+                self.mixin_depth -= 1;
+                result?.ok_or_else(|| {
+                    LessError::eval(format!(
+                        "没有与 {} 匹配的 mixin 定义",
+                        Self::mixin_call_path(call)
+                    ))
+                })?
+            }
+        };
+        for key in keys {
+            let VariableValue::DetachedRuleset(ruleset) = current else {
+                return Err(LessError::eval(format!(
+                    "{lookup} 中的 [{key}] 只能在规则集中查找"
+                )));
+            };
+            let key = self.lookup_key(key)?;
+            current = self.with_closure_scope(ruleset.scope, |this| {
+                this.declare_body(ruleset.body);
+                this.ruleset_member(ruleset.body, &key)
+            })?;
+        }
+        Ok(current)
+    }
+
+    /// 把查找键解析为变量名或属性名；`@@name` 与 `$@name` 中的变量在查找发生处的作用域中求值。
+    fn lookup_key(&mut self, key: &str) -> LessResult<LookupKey> {
+        Ok(if key.is_empty() {
+            LookupKey::LastDeclaration
+        } else if let Some(name) = key.strip_prefix("@@") {
+            LookupKey::Variable(Self::unquote(&self.resolve_variable_text(name)?).to_string())
+        } else if let Some(name) = key.strip_prefix('@') {
+            LookupKey::Variable(name.to_string())
+        } else if let Some(name) = key.strip_prefix("$@") {
+            LookupKey::Property(Self::unquote(&self.resolve_variable_text(name)?).to_string())
+        } else {
+            LookupKey::Property(key.trim_start_matches('$').to_string())
+        })
+    }
+
+    /// 在已进入其作用域的规则体中取成员：变量只查规则体自身的声明；
+    /// 属性需要先求值整个规则体（含其中的 mixin 调用），同名属性取最后一条。
+    fn ruleset_member(
+        &mut self,
+        body: &'a [RuleBody],
+        key: &LookupKey,
+    ) -> LessResult<VariableValue<'a>> {
+        if let LookupKey::Variable(name) = key {
+            if !self
+                .scopes
+                .last()
+                .is_some_and(|scope| scope.variables.contains_key(name.as_str()))
+            {
+                return Err(LessError::eval(format!("规则集中未定义变量 @{name}")));
+            }
+            return self.lookup_variable(name);
+        }
+        let comments = std::mem::take(&mut self.comments);
+        let mut declarations = Vec::new();
+        let mut nodes = Vec::new();
+        let result = body.iter().try_for_each(|item| {
+            self.handle_rule_body_item(item, &[], &mut declarations, &mut nodes)
+        });
+        self.comments = comments;
+        result?;
+        let found = match key {
+            LookupKey::Property(name) => declarations
+                .into_iter()
+                .rev()
+                .find(|decl| decl.name == *name),
+            _ => declarations.pop(),
+        };
+        match (found, key) {
+            (Some(decl), _) => Ok(VariableValue::Text(Rc::from(decl.value))),
+            (None, LookupKey::Property(name)) => {
+                Err(LessError::eval(format!("规则集中未定义属性 {name}")))
+            }
+            (None, _) => Err(LessError::eval("规则集中没有可供查找的声明")),
         }
-        Ok(())
     }
 
+    /// 求值 at-rule；`@media` 内嵌套的 `@media` 会被冒泡为同级节点并以 `and` 合并条件，
+    /// 因此返回值可能包含多个节点。
     fn eval_at_rule(
         &mut self,
-        at_rule: AtRule,
+        at_rule: &'a AtRule,
+        selectors: &[String],
+    ) -> LessResult<Vec<EvaluatedNode>> {
+        self.eval_at_rule_body(at_rule, selectors)
+            .map_err(|err| err.at(&at_rule.span))
+    }
+
+    fn eval_at_rule_body(
+        &mut self,
+        at_rule: &'a AtRule,
         selectors: &[String],
-    ) -> LessResult<EvaluatedAtRule> {
+    ) -> LessResult<Vec<EvaluatedNode>> {
+        let selectors = if Self::is_rooted(&at_rule.name) {
+            &[]
+        } else {
+            selectors
+        };
+        let warnings = self.warnings.borrow().len();
+        let params = self.eval_at_rule_params(&at_rule.params)?;
+        self.locate_warnings(warnings, &at_rule.span)?;
         self.push_scope();
         self.push_mixin_scope();
 
@@ -246,12 +1051,10 @@ impl Evaluator {
         let mut at_rule_declarations = Vec::new();
         let mut children: Vec<EvaluatedNode> = Vec::new();
 
-        for item in at_rule.body {
+        self.declare_body(&at_rule.body);
+        for item in &at_rule.body {
             match item {
-                RuleBody::Variable(var) => {
-                    let value = self.eval_value(&var.value)?;
-                    self.set_variable_text(var.name, value);
-                }
+                RuleBody::Variable(_) => {}
                 RuleBody::Declaration(decl) => {
                     let evaluated = self.eval_declaration(decl)?;
                     if selectors.is_empty() {
@@ -261,12 +1064,11 @@ impl Evaluator {
                     }
                 }
                 RuleBody::NestedRule(nested) => {
+                    children.extend(self.take_comment_nodes());
                     let nested_output = self.eval_ruleset(nested, selectors)?;
                     children.extend(nested_output);
                 }
-                RuleBody::MixinDefinition(def) => {
-                    self.set_mixin(def);
-                }
+                RuleBody::MixinDefinition(_) => {}
                 RuleBody::MixinCall(call) => {
                     if selectors.is_empty() {
                         self.expand_mixin(
@@ -285,34 +1087,56 @@ impl Evaluator {
                     }
                 }
                 RuleBody::AtRule(inner) => {
+                    children.extend(self.take_comment_nodes());
                     let evaluated = self.eval_at_rule(inner, selectors)?;
-                    children.push(EvaluatedNode::AtRule(evaluated));
+                    children.extend(evaluated);
+                }
+                RuleBody::BodylessAtRule(inner) => {
+                    children.extend(self.take_comment_nodes());
+                    children.push(self.eval_bodyless_at_rule(inner)?);
                 }
                 RuleBody::DetachedCall(call) => {
                     if selectors.is_empty() {
                         self.invoke_detached_ruleset(
-                            &call.name,
+                            call,
                             selectors,
                             &mut at_rule_declarations,
                             &mut children,
                         )?;
                     } else {
                         self.invoke_detached_ruleset(
-                            &call.name,
+                            call,
                             selectors,
                             &mut scoped_declarations,
                             &mut children,
                         )?;
                     }
                 }
+                RuleBody::Each(call) => {
+                    let declarations = if selectors.is_empty() {
+                        &mut at_rule_declarations
+                    } else {
+                        &mut scoped_declarations
+                    };
+                    self.eval_each(call, selectors, declarations, &mut children)?;
+                }
+                RuleBody::Import(import) => self.add_import(import),
+                RuleBody::Comment(comment) => self.buffer_comment(comment),
             }
         }
+        children.extend(self.take_comment_nodes());
 
+        Self::merge_properties(&mut scoped_declarations);
+        Self::merge_properties(&mut at_rule_declarations);
         let mut scoped_nodes = Vec::new();
         if !selectors.is_empty() && !scoped_declarations.is_empty() {
+            self.charge_output(selectors.iter().map(String::len).sum())?;
             scoped_nodes.push(EvaluatedNode::Rule(EvaluatedRule {
                 selectors: selectors.to_vec(),
                 declarations: scoped_declarations,
+                source: at_rule.span.file.clone(),
+                line: at_rule.span.line,
+                column: at_rule.span.column,
             }));
         }
         scoped_nodes.extend(children);
@@ -320,41 +1144,515 @@ impl Evaluator {
         self.pop_mixin_scope();
         self.pop_scope();
 
-        Ok(EvaluatedAtRule {
-            name: at_rule.name,
-            params: at_rule.params,
+        let evaluated = EvaluatedAtRule {
+            name: at_rule.name.clone(),
+            params,
             declarations: if selectors.is_empty() {
                 at_rule_declarations
             } else {
                 Vec::new()
             },
             children: scoped_nodes,
-        })
+            source: at_rule.span.file.clone(),
+        };
+        if Self::is_nestable(&evaluated.name) {
+            Ok(Self::bubble_nested(evaluated))
+        } else {
+            Ok(vec![EvaluatedNode::AtRule(evaluated)])
+        }
     }
 
-    fn eval_declaration(&mut self, decl: Declaration) -> LessResult<EvaluatedDeclaration> {
-        let name = self.interpolate_property_name(&decl.name)?;
-        let mut value = self.eval_value(&decl.value)?;
-        let mut important = decl.important;
-        if !important {
-            if let Some(stripped) = Self::strip_important(&value) {
-                value = stripped;
-                important = true;
+    /// 求值 at-rule 的前导条件：替换 `@var` 与 `@{var}`（在外层作用域中查找），
+    /// 再计算括号内的运算，如 `(min-width: (@bp + 1px))` → `(min-width: 601px)`；
+    /// 转义字符串最后才展开为其内容，`@tablet: ~"(min-width: 768px)"` 可作为完整的媒体查询且其中的文本不参与运算。
+    fn eval_at_rule_params(&mut self, params: &'a Value) -> LessResult<String> {
+        let mut raw = String::new();
+        for piece in &params.pieces {
+            match piece {
+                ValuePiece::Literal(text) => raw.push_str(text),
+                ValuePiece::VariableRef(name) => {
+                    raw.push_str(&self.resolve_variable_text(name)?);
+                }
+                ValuePiece::IndirectVariableRef(name) => {
+                    raw.push_str(&self.resolve_indirect_variable(name)?);
+                }
+                ValuePiece::Lookup(lookup) => raw.push_str(&self.eval_lookup_text(lookup)?),
+                ValuePiece::PropertyRef(name) => raw.push_str(&self.resolve_property(name)?),
             }
         }
-        Ok(EvaluatedDeclaration {
-            name,
-            value,
-            important,
-        })
+        let interpolated = self.interpolate(&raw)?;
+        let evaluated = self.evaluate_feature_expressions(&interpolated);
+        Ok(Self::unescape_strings(&evaluated).into_owned())
     }
 
-    fn interpolate_property_name(&self, raw: &str) -> LessResult<String> {
-        if !raw.contains("@{") {
-            return Ok(raw.trim().to_string());
+    /// 由内向外处理括号：`(name: value)` 只计算 `value`（含顶层 `/` 的值如 `16/9` 与自定义属性的值保持原样），
+    /// 纯运算的括号组替换为结果；`selector()` 与其余内容（关键字、`not` / `and` / `or` 等）不变。
+    fn evaluate_feature_expressions(&self, input: &str) -> String {
+        if !input.contains('(') {
+            return input.to_string();
         }
-        let mut chars = raw.chars().peekable();
-        let mut output = String::new();
+        let mut output = String::with_capacity(input.len());
+        let mut idx = 0;
+        while let Some(ch) = input[idx..].chars().next() {
+            match ch {
+                '"' | '\'' => {
+                    let end = Self::find_string_end(input, idx);
+                    output.push_str(&input[idx..end]);
+                    idx = end;
+                }
+                '(' => {
+                    let Some(close) = Self::find_matching_paren(input, idx) else {
+                        output.push_str(&input[idx..]);
+                        break;
+                    };
+                    let function = output
+                        .rfind(|c: char| !(c.is_alphanumeric() || c == '-' || c == '_'))
+                        .map_or(output.as_str(), |start| &output[start + 1..]);
+                    // `selector()` 中是选择器，其中的 `:` 与括号都不是特性表达式。
+                    if function.eq_ignore_ascii_case("selector") {
+                        output.push_str(&input[idx..=close]);
+                        idx = close + 1;
+                        continue;
+                    }
+                    let is_function = !function.is_empty();
+                    let inner = self.evaluate_feature_expressions(&input[idx + 1..close]);
+                    idx = close + 1;
+                    if let Some((name, value)) = inner.split_once(':') {
+                        let value = value.trim();
+                        // 自定义属性的值原样保留。
+                        let value = if value.contains('/') || name.trim().starts_with("--") {
+                            None
+                        } else {
+                            self.evaluate_arithmetic(value, true).ok().flatten()
+                        };
+                        match value {
+                            Some(value) => output.push_str(&format!("({}: {value})", name.trim())),
+                            None => {
+                                output.push('(');
+                                output.push_str(&inner);
+                                output.push(')');
+                            }
+                        }
+                    } else if let (false, Ok(Some(value))) =
+                        (is_function, self.evaluate_arithmetic(&inner, true))
+                    {
+                        output.push_str(&value);
+                    } else {
+                        output.push('(');
+                        output.push_str(&inner);
+                        output.push(')');
+                    }
+                }
+                _ => {
+                    output.push(ch);
+                    idx += ch.len_utf8();
+                }
+            }
+        }
+        output
+    }
+
+    /// 无规则体的 at-rule 只替换参数中的变量，整体作为原样文本输出。
+    fn eval_bodyless_at_rule(&mut self, at_rule: &'a BodylessAtRule) -> LessResult<EvaluatedNode> {
+        let params = self
+            .eval_value(&at_rule.params)
+            .map_err(|err| err.at(&at_rule.span))?;
+        Ok(EvaluatedNode::Raw(if params.is_empty() {
+            format!("@{};", at_rule.name)
+        } else {
+            format!("@{} {params};", at_rule.name)
+        }))
+    }
+
+    /// 规则体自成一体的 at-rule（可带 `-webkit-` 等厂商前缀）：其中的 `from`、`50%`
+    /// 等选择器与声明不与外层选择器组合，嵌套在规则中时原样输出到外层规则之后。
+    fn is_rooted(name: &str) -> bool {
+        let name = name.to_ascii_lowercase();
+        let unprefixed = match name.strip_prefix('-') {
+            Some(rest) => rest.split_once('-').map_or(rest, |(_, name)| name),
+            None => &name,
+        };
+        matches!(
+            unprefixed,
+            "keyframes"
+                | "counter-style"
+                | "page"
+                | "font-face"
+                | "font-feature-values"
+                | "property"
+                | "viewport"
+        )
+    }
+
+    /// 嵌套时条件以 `and` 合并的 at-rule。
+    fn is_nestable(name: &str) -> bool {
+        name.eq_ignore_ascii_case("media") || name.eq_ignore_ascii_case("container")
+    }
+
+    /// 将子节点中的同名 `@media` / `@container` 提升为外层规则的同级节点，条件与外层合并，
+    /// 与 lessc 的输出保持一致；提升后为空的外层规则会被丢弃。
+    fn bubble_nested(mut outer: EvaluatedAtRule) -> Vec<EvaluatedNode> {
+        let mut kept = Vec::new();
+        let mut bubbled = Vec::new();
+        for child in std::mem::take(&mut outer.children) {
+            match child {
+                EvaluatedNode::AtRule(mut inner)
+                    if inner.name.eq_ignore_ascii_case(&outer.name) =>
+                {
+                    inner.params = Self::merge_media_params(&outer.params, &inner.params);
+                    bubbled.push(EvaluatedNode::AtRule(inner));
+                }
+                other => kept.push(other),
+            }
+        }
+        outer.children = kept;
+
+        let mut output = Vec::with_capacity(bubbled.len() + 1);
+        if !outer.declarations.is_empty() || !outer.children.is_empty() {
+            output.push(EvaluatedNode::AtRule(outer));
+        }
+        output.extend(bubbled);
+        output
+    }
+
+    /// 合并两段媒体查询条件，逗号分隔的查询列表按笛卡尔积展开。
+    fn merge_media_params(outer: &str, inner: &str) -> String {
+        let outer_queries = Self::split_top_level_commas(outer);
+        let inner_queries = Self::split_top_level_commas(inner);
+        let mut merged = Vec::with_capacity(outer_queries.len() * inner_queries.len());
+        for outer_query in &outer_queries {
+            for inner_query in &inner_queries {
+                match (outer_query.is_empty(), inner_query.is_empty()) {
+                    (true, _) => merged.push(inner_query.clone()),
+                    (_, true) => merged.push(outer_query.clone()),
+                    _ => merged.push(format!("{outer_query} and {inner_query}")),
+                }
+            }
+        }
+        merged.join(", ")
+    }
+
+    fn split_top_level_commas(input: &str) -> Vec<String> {
+        let mut parts = Vec::new();
+        let mut depth = 0usize;
+        let mut current = String::new();
+        let mut quote: Option<char> = None;
+        for ch in input.chars() {
+            if let Some(q) = quote {
+                if ch == q {
+                    quote = None;
+                }
+                current.push(ch);
+                continue;
+            }
+            match ch {
+                '"' | '\'' => quote = Some(ch),
+                '(' => depth += 1,
+                ')' => depth = depth.saturating_sub(1),
+                ',' if depth == 0 => {
+                    parts.push(current.trim().to_string());
+                    current.clear();
+                    continue;
+                }
+                _ => {}
+            }
+            current.push(ch);
+        }
+        parts.push(current.trim().to_string());
+        parts
+    }
+
+    fn eval_guard(&mut self, guard: &'a Guard) -> LessResult<bool> {
+        for conditions in &guard.alternatives {
+            let mut passed = true;
+            for condition in conditions {
+                if !self.eval_guard_condition(condition)? {
+                    passed = false;
+                    break;
+                }
+            }
+            if passed {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    fn eval_guard_condition(&mut self, condition: &'a GuardCondition) -> LessResult<bool> {
+        let lhs = self.eval_value(&condition.lhs)?;
+        let result = match &condition.comparison {
+            // 没有顶层比较运算符时可能是嵌套的条件组合，如 `((@a > 1) and (@b < 2))`。
+            None => self.eval_condition(&lhs)?,
+            Some((operator, rhs)) => {
+                let rhs = self.eval_value(rhs)?;
+                Self::compare_guard_operands(&lhs, *operator, &rhs)
+            }
+        };
+        Ok(result != condition.negated)
+    }
+
+    /// 求值文本形式的条件（`if()` / `boolean()` 的参数与守卫中的嵌套条件），与 less.js 一致：
+    /// `or` 的优先级低于 `and`，`not` 作用于其后的括号条件，没有比较运算符的条件判断值是否为 `true`。
+    fn eval_condition(&mut self, text: &str) -> LessResult<bool> {
+        let text = text.trim();
+        let alternatives = Self::split_condition(text, "or");
+        if alternatives.len() > 1 {
+            for alternative in alternatives {
+                if self.eval_condition(alternative)? {
+                    return Ok(true);
+                }
+            }
+            return Ok(false);
+        }
+        let conjuncts = Self::split_condition(text, "and");
+        if conjuncts.len() > 1 {
+            for conjunct in conjuncts {
+                if !self.eval_condition(conjunct)? {
+                    return Ok(false);
+                }
+            }
+            return Ok(true);
+        }
+        if let Some(rest) = text
+            .strip_prefix("not")
+            .filter(|rest| rest.starts_with(|c: char| c.is_whitespace() || c == '('))
+        {
+            return Ok(!self.eval_condition(rest)?);
+        }
+        if text.starts_with('(') && Self::find_matching_paren(text, 0) == Some(text.len() - 1) {
+            return self.eval_condition(&text[1..text.len() - 1]);
+        }
+        match Self::split_comparison(text) {
+            Some((lhs, operator, rhs)) => {
+                let lhs = self.condition_operand(lhs)?;
+                let rhs = self.condition_operand(rhs)?;
+                Ok(Self::compare_guard_operands(&lhs, operator, &rhs))
+            }
+            None => Ok(self.condition_operand(text)? == "true"),
+        }
+    }
+
+    fn condition_operand(&mut self, text: &str) -> LessResult<String> {
+        let (text, _) = self.rewrite_function_calls(text.trim())?;
+        let value = self.compute_function_arg(&text);
+        Ok(Self::unescape_strings(&value).into_owned())
+    }
+
+    /// 按括号与字符串之外的关键字（`and` / `or`）切分条件。
+    fn split_condition<'t>(text: &'t str, keyword: &str) -> Vec<&'t str> {
+        let mut parts = Vec::new();
+        let (mut depth, mut start, mut idx) = (0usize, 0, 0);
+        while let Some(ch) = text[idx..].chars().next() {
+            match ch {
+                '"' | '\'' => {
+                    idx = Self::find_string_end(text, idx);
+                    continue;
+                }
+                '(' => depth += 1,
+                ')' => depth = depth.saturating_sub(1),
+                _ if depth == 0 && text[idx..].starts_with(keyword) => {
+                    let before = text[..idx].chars().next_back();
+                    let after = text[idx + keyword.len()..].chars().next();
+                    let boundary = |c: Option<char>, paren: char| {
+                        c.is_some_and(|c| c.is_whitespace() || c == paren)
+                    };
+                    if boundary(before, ')') && boundary(after, '(') {
+                        parts.push(&text[start..idx]);
+                        idx += keyword.len();
+                        start = idx;
+                        continue;
+                    }
+                }
+                _ => {}
+            }
+            idx += ch.len_utf8();
+        }
+        parts.push(&text[start..]);
+        parts
+    }
+
+    /// 找到括号与字符串之外的第一个比较运算符，返回两侧操作数。
+    fn split_comparison(text: &str) -> Option<(&str, GuardOperator, &str)> {
+        let (mut depth, mut idx) = (0usize, 0);
+        while let Some(ch) = text[idx..].chars().next() {
+            match ch {
+                '"' | '\'' => {
+                    idx = Self::find_string_end(text, idx);
+                    continue;
+                }
+                '(' => depth += 1,
+                ')' => depth = depth.saturating_sub(1),
+                '<' | '>' | '=' if depth == 0 => {
+                    let next = text[idx + 1..].chars().next();
+                    let (operator, len) = match (ch, next) {
+                        ('>', Some('=')) | ('=', Some('>')) => (GuardOperator::Ge, 2),
+                        ('<', Some('=')) | ('=', Some('<')) => (GuardOperator::Le, 2),
+                        ('>', _) => (GuardOperator::Gt, 1),
+                        ('<', _) => (GuardOperator::Lt, 1),
+                        _ => (GuardOperator::Eq, 1),
+                    };
+                    return Some((&text[..idx], operator, &text[idx + len..]));
+                }
+                _ => {}
+            }
+            idx += ch.len_utf8();
+        }
+        None
+    }
+
+    /// 数值按单位换算后比较大小；其它值只支持 `=`，去掉引号后按文本比较。
+    fn compare_guard_operands(lhs: &str, operator: GuardOperator, rhs: &str) -> bool {
+        let ordering = match (
+            functions::parse_dimension(lhs),
+            functions::parse_dimension(rhs),
+        ) {
+            (Some(left), Some(right)) => {
+                let right_value = if left.unit.is_empty() || right.unit.is_empty() {
+                    Some(right.value)
+                } else {
+                    units::convert(right.value, &right.unit.to_string(), &left.unit.to_string())
+                };
+                right_value.and_then(|value| left.value.partial_cmp(&value))
+            }
+            _ => {
+                if Self::unquote(lhs) == Self::unquote(rhs) {
+                    Some(std::cmp::Ordering::Equal)
+                } else {
+                    None
+                }
+            }
+        };
+        match ordering {
+            Some(ordering) => match operator {
+                GuardOperator::Eq => ordering.is_eq(),
+                GuardOperator::Lt => ordering.is_lt(),
+                GuardOperator::Le => ordering.is_le(),
+                GuardOperator::Gt => ordering.is_gt(),
+                GuardOperator::Ge => ordering.is_ge(),
+            },
+            None => false,
+        }
+    }
+
+    fn eval_declaration(&mut self, decl: &'a Declaration) -> LessResult<EvaluatedDeclaration> {
+        let name = self
+            .interpolate(&decl.name)
+            .map_err(|err| err.at(&decl.span))?;
+        let warnings = self.warnings.borrow().len();
+        let value = if name.starts_with("--") {
+            self.eval_custom_property(&decl.value)
+        } else {
+            self.eval_value(&decl.value)
+        }
+        .map_err(|err| err.at(&decl.span))?;
+        self.locate_warnings(warnings, &decl.span)?;
+        self.charge_output(name.len() + value.len())
+            .map_err(|err| err.at(&decl.span))?;
+        Ok(EvaluatedDeclaration {
+            name,
+            value,
+            important: decl.important,
+            merge: decl.merge,
+            comments: std::mem::take(&mut self.comments),
+            source: decl.span.file.clone(),
+        })
+    }
+
+    /// 自定义属性的值原样输出；开启 `custom_property_interpolation` 时只替换 `@{var}`。
+    fn eval_custom_property(&mut self, value: &Value) -> LessResult<String> {
+        let raw = value.to_string();
+        if self.custom_property_interpolation {
+            self.interpolate(&raw)
+        } else {
+            Ok(raw)
+        }
+    }
+
+    fn mark_important(nodes: &mut [EvaluatedNode]) {
+        for node in nodes {
+            match node {
+                EvaluatedNode::Rule(rule) => {
+                    for decl in &mut rule.declarations {
+                        decl.important = true;
+                    }
+                }
+                EvaluatedNode::AtRule(at_rule) => {
+                    for decl in &mut at_rule.declarations {
+                        decl.important = true;
+                    }
+                    Self::mark_important(&mut at_rule.children);
+                }
+                EvaluatedNode::Raw(_) | EvaluatedNode::Comment(_) => {}
+            }
+        }
+    }
+
+    /// 注释是否进入输出：开启 `keep_comments` 时的块注释，以及总是保留的 `/*! ... */`。
+    fn keeps_comment(&self, comment: &Comment) -> bool {
+        (self.keep_comments && !comment.is_line()) || comment.is_preserved()
+    }
+
+    fn buffer_comment(&mut self, comment: &Comment) {
+        if self.keeps_comment(comment) {
+            self.comments.push(comment.text.clone());
+        }
+    }
+
+    /// 取出尚未附到声明上的注释，作为独立节点输出。
+    fn take_comment_nodes(&mut self) -> Vec<EvaluatedNode> {
+        self.comments
+            .drain(..)
+            .map(EvaluatedNode::Comment)
+            .collect()
+    }
+
+    /// 合并带 `+` / `+_` 标记的同名属性：结果保留在第一次出现的位置，
+    /// `+` 另起一个逗号分组，`+_` 以空格追加到当前分组；任一声明带 `!important` 则结果也带。
+    fn merge_properties(declarations: &mut Vec<EvaluatedDeclaration>) {
+        if declarations
+            .iter()
+            .all(|decl| decl.merge == PropertyMerge::None)
+        {
+            return;
+        }
+        let mut merged: Vec<EvaluatedDeclaration> = Vec::with_capacity(declarations.len());
+        let mut groups: HashMap<String, (usize, Vec<String>)> = HashMap::new();
+        for decl in declarations.drain(..) {
+            if decl.merge == PropertyMerge::None {
+                merged.push(decl);
+                continue;
+            }
+            match groups.get_mut(&decl.name) {
+                Some((index, parts)) => {
+                    match (decl.merge, parts.last_mut()) {
+                        (PropertyMerge::Space, Some(last)) => {
+                            last.push(' ');
+                            last.push_str(&decl.value);
+                        }
+                        _ => parts.push(decl.value),
+                    }
+                    merged[*index].important |= decl.important;
+                    merged[*index].comments.extend(decl.comments);
+                }
+                None => {
+                    groups.insert(decl.name.clone(), (merged.len(), vec![decl.value.clone()]));
+                    merged.push(decl);
+                }
+            }
+        }
+        for (index, parts) in groups.into_values() {
+            merged[index].value = parts.join(", ");
+        }
+        *declarations = merged;
+    }
+
+    /// 替换文本中的 `@{var}` 插值，用于属性名、选择器以及字符串 / `url()` 值。
+    fn interpolate(&mut self, raw: &str) -> LessResult<String> {
+        if !raw.contains("@{") {
+            return Ok(raw.trim().to_string());
+        }
+        let mut chars = raw.chars().peekable();
+        let mut output = String::new();
         while let Some(ch) = chars.next() {
             if ch == '@' && chars.peek() == Some(&'{') {
                 chars.next();
@@ -367,10 +1665,14 @@ impl Evaluator {
                     name.push(next);
                 }
                 if name.is_empty() {
-                    return Err(LessError::eval("属性插值缺少变量名"));
+                    return Err(LessError::eval(format!("插值缺少变量名: {raw}")));
+                }
+                if self.skip_undefined_variable(&name) {
+                    output.push_str(&format!("@{{{name}}}"));
+                    continue;
                 }
                 let value = self.resolve_variable_text(&name)?;
-                output.push_str(value.trim());
+                output.push_str(Self::unquote(&Self::unescape_strings(&value)));
             } else {
                 output.push(ch);
             }
@@ -378,7 +1680,53 @@ impl Evaluator {
         Ok(output.trim().to_string())
     }
 
-    fn eval_value(&mut self, value: &Value) -> LessResult<String> {
+    /// 对选择器做变量插值；插值结果中若包含逗号，会被拆分为多个选择器。
+    fn interpolate_selectors(
+        &mut self,
+        selectors: &[crate::ast::Selector],
+    ) -> LessResult<Vec<String>> {
+        let mut result = Vec::with_capacity(selectors.len());
+        for selector in selectors {
+            if !selector.value.contains("@{") {
+                result.push(selector.value.clone());
+                continue;
+            }
+            let interpolated = self.interpolate(&selector.value)?;
+            result.extend(
+                interpolated
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|part| !part.is_empty())
+                    .map(str::to_string),
+            );
+        }
+        Ok(result)
+    }
+
+    /// 去掉字符串值外层的引号（含 `~"..."` 转义形式），插值时使用其原始内容。
+    fn unquote(value: &str) -> &str {
+        let trimmed = value.trim();
+        let inner = trimmed.strip_prefix('~').unwrap_or(trimmed);
+        for quote in ['"', '\''] {
+            if inner.len() >= 2 && inner.starts_with(quote) && inner.ends_with(quote) {
+                return &inner[1..inner.len() - 1];
+            }
+        }
+        trimmed
+    }
+
+    fn eval_value(&mut self, value: &'a Value) -> LessResult<String> {
+        let evaluated = self.eval_variable_value(value)?;
+        if Self::contains_escaped_string(&evaluated) {
+            Ok(Self::unescape_strings(&evaluated).into_owned())
+        } else {
+            Ok(evaluated)
+        }
+    }
+
+    /// 求值赋给变量或 mixin 参数的值：`~"..."` 保留转义标记，
+    /// 引用处因而仍把它当作转义字符串，不会对其内容做算术运算。
+    fn eval_variable_value(&mut self, value: &'a Value) -> LessResult<String> {
         let mut buffer = String::new();
         for piece in &value.pieces {
             match piece {
@@ -387,34 +1735,78 @@ impl Evaluator {
                     let resolved = self.resolve_variable_text(name)?;
                     buffer.push_str(&resolved);
                 }
+                ValuePiece::IndirectVariableRef(name) => {
+                    buffer.push_str(&self.resolve_indirect_variable(name)?);
+                }
+                ValuePiece::Lookup(lookup) => buffer.push_str(&self.eval_lookup_text(lookup)?),
+                ValuePiece::PropertyRef(name) => buffer.push_str(&self.resolve_property(name)?),
             }
         }
+        if buffer.contains("@{") {
+            buffer = self.interpolate(&buffer)?;
+        }
         self.compute_value(buffer.trim())
     }
 
     fn compute_value(&mut self, input: &str) -> LessResult<String> {
-        if input.is_empty() {
-            return Ok(String::new());
+        if !Self::contains_escaped_string(input) {
+            return self.compute_expression(input);
         }
-        if let Some(color) = self.evaluate_color_function(input)? {
-            return Ok(color);
+        // `~"..."` 内容原样输出，不参与算术求值；转义标记由调用方去除。
+        Ok(self
+            .evaluate_function_calls(input)?
+            .unwrap_or_else(|| input.to_string()))
+    }
+
+    fn contains_escaped_string(input: &str) -> bool {
+        input.contains("~\"") || input.contains("~'")
+    }
+
+    /// 去掉 `~"..."` / `~'...'` 的转义标记与引号。
+    fn unescape_strings(input: &str) -> Cow<'_, str> {
+        if !Self::contains_escaped_string(input) {
+            return Cow::Borrowed(input);
         }
-        if let Some(inline) = self.replace_inline_color_functions(input)? {
-            return Ok(inline);
+        let mut output = String::with_capacity(input.len());
+        let mut idx = 0;
+        while let Some(ch) = input[idx..].chars().next() {
+            match ch {
+                '"' | '\'' => {
+                    let end = Self::find_string_end(input, idx);
+                    output.push_str(&input[idx..end]);
+                    idx = end;
+                }
+                '~' if matches!(input[idx + 1..].chars().next(), Some('"' | '\'')) => {
+                    let end = Self::find_string_end(input, idx + 1);
+                    output.push_str(Self::unquote(&input[idx..end]));
+                    idx = end;
+                }
+                _ => {
+                    output.push(ch);
+                    idx += ch.len_utf8();
+                }
+            }
+        }
+        Cow::Owned(output)
+    }
+
+    /// 先求值函数调用，再对结果做算术运算（如 `darken(@c, 10%) + #111`）。
+    fn compute_expression(&mut self, input: &str) -> LessResult<String> {
+        if input.is_empty() {
+            return Ok(String::new());
         }
+        let evaluated = self.evaluate_function_calls(input)?;
+        let input = evaluated.as_deref().unwrap_or(input);
         if input.contains("var(") {
             return Ok(input.to_string());
         }
         if input.contains("url(") {
             return Ok(input.to_string());
         }
-        if input.contains("unit(") {
-            return Ok(input.to_string());
-        }
         if input.contains("calc(") {
             return Ok(input.to_string());
         }
-        match self.evaluate_arithmetic(input) {
+        match self.evaluate_arithmetic(input, false) {
             Ok(Some(value)) => return Ok(value),
             Ok(None) => {}
             Err(_) => return Ok(input.to_string()),
@@ -422,386 +1814,460 @@ impl Evaluator {
         Ok(input.to_string())
     }
 
-    fn evaluate_color_function(&mut self, input: &str) -> LessResult<Option<String>> {
-        static COLOR_FN_RE: Lazy<Regex> = Lazy::new(|| {
-            Regex::new(r"(?ix)^(?P<name>lighten|darken|fade)\s*\(\s*(?P<color>[^,]+)\s*,\s*(?P<amount>[^)]+)\)$")
-                .expect("颜色函数正则编译失败")
-        });
-
-        if let Some(result) = self.evaluate_overlay_function(input)? {
-            return Ok(Some(result));
-        }
-
-        if let Some(caps) = COLOR_FN_RE.captures(input) {
-            let name = caps.name("name").unwrap().as_str().to_ascii_lowercase();
-            let color_arg = caps.name("color").unwrap().as_str().trim();
-            let amount_arg = caps.name("amount").unwrap().as_str().trim();
-
-            let color = color::parse_color(color_arg)
-                .ok_or_else(|| LessError::eval(format!("无法解析颜色参数: {color_arg}")))?;
-            let amount = Self::parse_percentage(amount_arg)?;
-
-            let result = match name.as_str() {
-                "lighten" => color::lighten(color, amount),
-                "darken" => color::darken(color, amount),
-                "fade" => color::fade(color, amount),
-                _ => return Ok(None),
-            };
-
-            let output = if name == "fade" {
-                color::format_rgba(result)
-            } else {
-                color::format_hex(result)
-            };
-
-            return Ok(Some(output));
+    /// 扫描值中的函数调用，由内向外求值已注册的内置函数；未知函数保持原样，
+    /// 仅继续处理其参数中的嵌套调用。
+    fn evaluate_function_calls(&mut self, input: &str) -> LessResult<Option<String>> {
+        if !input.contains('(') {
+            return Ok(None);
         }
-        Ok(None)
+        let (output, changed) = self.rewrite_function_calls(input)?;
+        Ok(changed.then_some(output))
     }
 
-    fn evaluate_overlay_function(&self, input: &str) -> LessResult<Option<String>> {
-        let trimmed = input.trim();
-        if !trimmed.to_ascii_lowercase().starts_with("overlay(") {
-            return Ok(None);
-        }
-        let start = trimmed
-            .find('(')
-            .ok_or_else(|| LessError::eval("overlay 函数缺少 '('"))?
-            + 1;
-        let end = trimmed
-            .rfind(')')
-            .ok_or_else(|| LessError::eval("overlay 函数缺少 ')'"))?;
-        let body = &trimmed[start..end];
-        let (first, second) = Self::split_overlay_args(body)?;
-        let top_color = color::parse_color(first.trim())
-            .ok_or_else(|| LessError::eval(format!("无法解析颜色参数: {first}")))?;
-        let bottom_color = color::parse_color(second.trim())
-            .ok_or_else(|| LessError::eval(format!("无法解析颜色参数: {second}")))?;
-        let blended = color::overlay(top_color, bottom_color);
-        Ok(Some(color::format_hex(blended)))
-    }
-
-    fn split_overlay_args(input: &str) -> LessResult<(String, String)> {
-        let mut depth = 0i32;
-        let mut split = None;
-        for (idx, ch) in input.char_indices() {
+    fn rewrite_function_calls(&mut self, input: &str) -> LessResult<(String, bool)> {
+        let mut output = String::with_capacity(input.len());
+        let mut changed = false;
+        let mut idx = 0;
+        while let Some(ch) = input[idx..].chars().next() {
             match ch {
-                '(' => depth += 1,
-                ')' => {
-                    if depth > 0 {
-                        depth -= 1;
+                '"' | '\'' => {
+                    let end = Self::find_string_end(input, idx);
+                    output.push_str(&input[idx..end]);
+                    idx = end;
+                }
+                '(' => {
+                    let open = idx;
+                    let Some(close) = Self::find_matching_paren(input, open) else {
+                        output.push_str(&input[open..]);
+                        break;
+                    };
+                    let mut name_len: usize = output
+                        .chars()
+                        .rev()
+                        .take_while(|c| c.is_alphanumeric() || *c == '-' || *c == '_')
+                        .map(char::len_utf8)
+                        .sum();
+                    if name_len == 0 && Self::ends_with_format_call(&output) {
+                        name_len = 1;
                     }
+                    let name = output[output.len() - name_len..].to_ascii_lowercase();
+                    let inner = &input[open + 1..close];
+                    idx = close + 1;
+
+                    if Self::is_opaque_function(&name) {
+                        output.push_str(&input[open..idx]);
+                        continue;
+                    }
+                    if matches!(name.as_str(), "if" | "boolean")
+                        && !self.custom_functions.contains_key(&name)
+                    {
+                        let result = self.call_conditional_function(&name, inner)?;
+                        output.truncate(output.len() - name_len);
+                        output.push_str(&result);
+                        changed = true;
+                        continue;
+                    }
+                    let (inner, inner_changed) = self.rewrite_function_calls(inner)?;
+                    changed |= inner_changed;
+                    if let Some(result) = self.call_function(&name, &inner)? {
+                        output.truncate(output.len() - name_len);
+                        output.push_str(&result);
+                        changed = true;
+                        continue;
+                    }
+                    // `progid:...gradient(...)`、`a.b(...)` 之类带限定前缀的名称不是函数调用。
+                    if !output[..output.len() - name_len].ends_with(['.', ':']) {
+                        self.check_unknown_function(&name);
+                    }
+                    output.push('(');
+                    output.push_str(&inner);
+                    output.push(')');
                 }
-                ',' if depth == 0 => {
-                    split = Some(idx);
-                    break;
+                _ => {
+                    output.push(ch);
+                    idx += ch.len_utf8();
                 }
-                _ => {}
             }
         }
-        let idx = split.ok_or_else(|| LessError::eval("overlay 函数参数不完整"))?;
-        let first = input[..idx].to_string();
-        let second = input[idx + 1..].to_string();
-        Ok((first, second))
+        Ok((output, changed))
     }
 
-    fn replace_inline_color_functions(&mut self, input: &str) -> LessResult<Option<String>> {
-        static INLINE_COLOR_FN_RE: Lazy<Regex> = Lazy::new(|| {
-            Regex::new(
-                r"(?xi)(lighten|darken|fade)\s*\(\s*((?:[^()]+|\([^()]*\))+?)\s*,\s*([^)]+)\)",
-            )
-            .expect("颜色函数正则编译失败")
-        });
+    /// 既未注册、也不是 CSS 原生函数的名称原样输出，并记下 `unknown-function` 警告。
+    fn check_unknown_function(&self, name: &str) {
+        let registered =
+            self.custom_functions.contains_key(name) || functions::lookup(name).is_some();
+        if !registered
+            && name
+                .trim_start_matches('-')
+                .starts_with(char::is_alphabetic)
+            && !functions::is_css_function(name)
+        {
+            self.warn_once(
+                "unknown-function",
+                format!("未知的函数 {name}()，按原样输出"),
+            );
+        }
+    }
 
-        let mut output = String::with_capacity(input.len());
-        let mut last = 0;
-        let mut changed = false;
+    /// 调用自定义函数或内置函数（自定义函数优先）；未注册的函数返回 `None`。
+    fn call_function(&self, name: &str, inner: &str) -> LessResult<Option<String>> {
+        let custom = self.custom_functions.get(name);
+        let builtin = functions::lookup(name);
+        if custom.is_none() && builtin.is_none() {
+            return Ok(None);
+        }
+        if name == "e" && custom.is_none() {
+            self.warn_once(
+                "deprecated",
+                "e() 已弃用，请改用 ~\"...\" 转义字符串".to_string(),
+            );
+        }
+        let args = Self::split_top_level_commas(inner)
+            .into_iter()
+            .filter(|arg| !arg.is_empty())
+            .map(|arg| self.compute_function_arg(&arg))
+            .collect::<Vec<_>>();
+        match (custom, builtin) {
+            (Some(function), _) => {
+                let args: Vec<FunctionValue> =
+                    args.iter().map(|arg| FunctionValue::parse(arg)).collect();
+                Ok(function(&args)?.map(|value| value.to_string()))
+            }
+            (None, Some(function)) => function(&args),
+            (None, None) => Ok(None),
+        }
+    }
 
-        for caps in INLINE_COLOR_FN_RE.captures_iter(input) {
-            let matched = caps.get(0).unwrap();
-            output.push_str(&input[last..matched.start()]);
+    /// `boolean(cond)` 与 `if(cond, a, b)`：条件按守卫的规则求值，`boolean` 返回 `true` / `false`；
+    /// `if` 只求值选中的分支，条件不成立且未给出第三个参数时结果为空。
+    fn call_conditional_function(&mut self, name: &str, inner: &str) -> LessResult<String> {
+        let args: Vec<String> = Self::split_top_level_commas(inner)
+            .into_iter()
+            .filter(|arg| !arg.is_empty())
+            .collect();
+        if name == "boolean" {
+            functions::expect_arg_range(name, &args, 1, 1)?;
+            return Ok(self.eval_condition(&args[0])?.to_string());
+        }
+        functions::expect_arg_range(name, &args, 2, 3)?;
+        let branch = if self.eval_condition(&args[0])? {
+            &args[1]
+        } else {
+            args.get(2).map_or("", String::as_str)
+        };
+        let (branch, _) = self.rewrite_function_calls(branch)?;
+        Ok(self.compute_function_arg(&branch))
+    }
 
-            let name = caps.get(1).unwrap().as_str().to_ascii_lowercase();
-            let color_arg = caps.get(2).unwrap().as_str().trim();
-            let amount_arg = caps.get(3).unwrap().as_str().trim();
+    fn load_plugin(&mut self, name: &str) -> LessResult<()> {
+        let plugin = self
+            .registry
+            .plugin(name)
+            .ok_or_else(|| LessError::eval(format!("未注册的插件: {name}")))?;
+        for (function_name, function) in plugin.functions() {
+            self.custom_functions
+                .insert(function_name.clone(), function.clone());
+        }
+        Ok(())
+    }
 
-            let color = color::parse_color(color_arg)
-                .ok_or_else(|| LessError::eval(format!("无法解析颜色参数: {color_arg}")))?;
-            let amount = Self::parse_percentage(amount_arg)?;
+    /// 内容需原样保留、不参与函数求值的 CSS 函数。`calc()` 不在其列：
+    /// 与 less.js 3.x 一致，其中的嵌套函数照常求值，只有 `calc` 自身的运算符保持原样。
+    fn is_opaque_function(name: &str) -> bool {
+        matches!(name, "url" | "var")
+    }
 
-            let replacement = match name.as_str() {
-                "lighten" => color::format_hex(color::lighten(color, amount)),
-                "darken" => color::format_hex(color::darken(color, amount)),
-                "fade" => color::format_rgba(color::fade(color, amount)),
-                _ => unreachable!(),
-            };
+    /// 格式化函数 `%(...)`：`%` 前不能是数值，否则是百分比后跟括号。
+    fn ends_with_format_call(output: &str) -> bool {
+        let mut chars = output.chars().rev();
+        chars.next() == Some('%')
+            && !chars
+                .next()
+                .is_some_and(|c| c.is_alphanumeric() || c == '.' || c == '%')
+    }
 
-            output.push_str(&replacement);
-            last = matched.end();
-            changed = true;
+    /// 函数参数在传入内置函数前先尝试做算术求值（视同位于括号内），字符串参数保持原样。
+    fn compute_function_arg(&self, arg: &str) -> String {
+        let trimmed = arg.trim();
+        if trimmed.starts_with(['"', '\'', '~']) {
+            return trimmed.to_string();
         }
-
-        if !changed {
-            return Ok(None);
+        match self.evaluate_arithmetic(arg, true) {
+            Ok(Some(value)) => value,
+            _ => arg.trim().to_string(),
         }
+    }
 
-        output.push_str(&input[last..]);
-        Ok(Some(output))
+    fn find_string_end(input: &str, start: usize) -> usize {
+        let mut chars = input[start..].char_indices();
+        let (_, quote) = chars.next().unwrap();
+        while let Some((offset, ch)) = chars.next() {
+            if ch == '\\' {
+                chars.next();
+            } else if ch == quote {
+                return start + offset + ch.len_utf8();
+            }
+        }
+        input.len()
     }
 
-    fn parse_percentage(raw: &str) -> LessResult<f64> {
-        let cleaned = raw.trim();
-        if cleaned.ends_with('%') {
-            let number = cleaned[..cleaned.len() - 1].trim();
-            let value: f64 = number
-                .parse()
-                .map_err(|_| LessError::eval(format!("无法解析百分比: {raw}")))?;
-            Ok((value / 100.0).clamp(0.0, 1.0))
-        } else {
-            let value: f64 = cleaned
-                .parse()
-                .map_err(|_| LessError::eval(format!("无法解析数值: {raw}")))?;
-            Ok(value.clamp(0.0, 1.0))
+    fn find_matching_paren(input: &str, open: usize) -> Option<usize> {
+        let mut depth = 0usize;
+        let mut idx = open;
+        while let Some(ch) = input[idx..].chars().next() {
+            match ch {
+                '"' | '\'' => {
+                    idx = Self::find_string_end(input, idx);
+                    continue;
+                }
+                '(' => depth += 1,
+                ')' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Some(idx);
+                    }
+                }
+                _ => {}
+            }
+            idx += ch.len_utf8();
         }
+        None
     }
 
-    fn evaluate_arithmetic(&self, input: &str) -> LessResult<Option<String>> {
-        let cleaned = input.replace(['(', ')'], " ");
-        let expression = Self::strip_outer_parentheses(cleaned.trim());
+    /// 算术求值：按优先级解析 `+ - * /` 与括号；以空白并列的多个表达式（如 `margin` 简写）
+    /// 分别求值后再以空格拼接。`in_parens` 表示表达式整体已处于括号（或函数参数）中。
+    fn evaluate_arithmetic(&self, input: &str, in_parens: bool) -> LessResult<Option<String>> {
+        let expression = input.trim();
         if expression.is_empty() || !Self::contains_operator(expression) {
             return Ok(None);
         }
 
-        let tokens = self.tokenize_expression(expression)?;
-        if tokens.is_empty() {
+        let tokens = Self::tokenize_expression(expression)?;
+        if tokens.is_empty() || !self.math_allowed(&tokens, in_parens) {
             return Ok(None);
         }
 
-        let mut iter = tokens.into_iter();
-        let mut current = match iter.next() {
-            Some(Token::Quantity(q)) => q,
-            _ => return Err(LessError::eval("算术表达式缺少初始数值".to_string())),
+        let mut parser = ExpressionParser {
+            tokens: &tokens,
+            pos: 0,
+            strict_units: self.strict_units,
+            warnings: Vec::new(),
         };
-
-        let mut results: Vec<Quantity> = Vec::new();
-
-        while let Some(token) = iter.next() {
-            match token {
-                Token::Operator(op) => {
-                    let rhs = match iter.next() {
-                        Some(Token::Quantity(q)) => q,
-                        _ => return Err(LessError::eval("算术表达式缺少右侧数值".to_string())),
-                    };
-                    current = Self::apply_operator(current, op, rhs)?;
-                }
-                Token::Quantity(next_qty) => {
-                    results.push(current);
-                    current = next_qty;
-                }
+        let mut results = Vec::new();
+        let parsed = (|| {
+            while !parser.is_finished() {
+                results.push(parser.parse_sum()?);
             }
+            Ok(())
+        })();
+        for message in parser.warnings {
+            self.warn("unit-mismatch", message);
         }
-
-        results.push(current);
+        parsed?;
 
         let output = results
-            .into_iter()
-            .map(Self::format_quantity)
+            .iter()
+            .map(Operand::to_css)
             .collect::<Vec<_>>()
             .join(" ");
 
         Ok(Some(output))
     }
 
-    fn tokenize_expression(&self, input: &str) -> LessResult<Vec<Token>> {
+    fn tokenize_expression(input: &str) -> LessResult<Vec<Token>> {
+        let chars: Vec<char> = input.chars().collect();
         let mut tokens = Vec::new();
-        let mut current = String::new();
-        let mut prev_was_operator = true;
+        let mut idx = 0;
+        let mut after_space = false;
 
-        for ch in input.chars() {
-            if ch.is_whitespace() {
-                let trimmed_current = current.trim();
-                if trimmed_current == "-" || trimmed_current == "+" {
+        while idx < chars.len() {
+            let ch = chars[idx];
+            let prev_is_operand = matches!(tokens.last(), Some(Token::Operand(_) | Token::Close));
+            match ch {
+                c if c.is_whitespace() => {
+                    after_space = true;
+                    idx += 1;
                     continue;
                 }
-
-                if !current.is_empty() {
-                    Self::push_token(&mut tokens, &mut current)?;
+                '(' => tokens.push(Token::Open),
+                ')' => tokens.push(Token::Close),
+                '-' | '+' => {
+                    let next = chars.get(idx + 1).copied();
+                    // `10px -5px` 中空格后紧贴数值的 `-` 是新的负数，而不是减号。
+                    let unary = !prev_is_operand
+                        || (ch == '-' && after_space && next.is_some_and(|c| !c.is_whitespace()));
+                    if !unary {
+                        tokens.push(Token::Operator(ch));
+                    } else if next.is_some_and(|c| c.is_ascii_digit() || c == '.') {
+                        let end = Self::operand_end(&chars, idx + 1);
+                        let text: String = chars[idx..end].iter().collect();
+                        tokens.push(Token::Operand(Operand::Number(parse_quantity(&text)?)));
+                        idx = end;
+                        after_space = false;
+                        continue;
+                    } else if ch == '-' {
+                        tokens.push(Token::Negate);
+                    }
                 }
-                continue;
-            }
-
-            if Self::is_operator(ch) {
-                if ch == '-' && prev_was_operator {
-                    current.push(ch);
+                '*' | '/' => tokens.push(Token::Operator(ch)),
+                _ => {
+                    let mut end = Self::operand_end(&chars, idx);
+                    // `rgb(...)` / `rgba(...)` 整体作为一个颜色操作数。
+                    if chars.get(end) == Some(&'(') {
+                        let name: String = chars[idx..end].iter().collect();
+                        if matches!(name.to_ascii_lowercase().as_str(), "rgb" | "rgba") {
+                            end = Self::closing_paren(&chars, end)
+                                .map_or(chars.len(), |close| close + 1);
+                        }
+                    }
+                    let text: String = chars[idx..end].iter().collect();
+                    tokens.push(Token::Operand(Operand::parse(&text)?));
+                    idx = end;
+                    after_space = false;
                     continue;
                 }
-                if !current.is_empty() {
-                    Self::push_token(&mut tokens, &mut current)?;
-                }
-                tokens.push(Token::Operator(ch));
-                prev_was_operator = true;
-            } else {
-                current.push(ch);
-                prev_was_operator = false;
             }
-        }
-
-        if !current.is_empty() {
-            Self::push_token(&mut tokens, &mut current)?;
+            after_space = false;
+            idx += 1;
         }
 
         Ok(tokens)
     }
 
-    fn push_token(tokens: &mut Vec<Token>, current: &mut String) -> LessResult<()> {
-        let trimmed = current.trim();
-        if trimmed.is_empty() {
-            current.clear();
-            return Ok(());
-        }
-
-        if trimmed == "-" || trimmed == "+" {
-            return Err(LessError::eval("算术表达式缺少数值内容".to_string()));
+    /// 按 `math` 选项检查每个运算符所处的括号层级，存在不允许计算的运算时整段保持原样。
+    fn math_allowed(&self, tokens: &[Token], in_parens: bool) -> bool {
+        let mut depth = usize::from(in_parens);
+        for token in tokens {
+            match token {
+                Token::Open => depth += 1,
+                Token::Close => depth = depth.saturating_sub(1),
+                Token::Operator(op) => {
+                    let allowed = match self.math {
+                        MathMode::Always => true,
+                        MathMode::ParensDivision => *op != '/' || depth > 0,
+                        MathMode::Strict => depth > 0,
+                    };
+                    if !allowed {
+                        return false;
+                    }
+                }
+                Token::Operand(_) | Token::Negate => {}
+            }
         }
+        true
+    }
 
-        if trimmed.len() == 1 && Self::is_operator(trimmed.chars().next().unwrap()) {
-            tokens.push(Token::Operator(trimmed.chars().next().unwrap()));
-        } else {
-            let quantity = Self::parse_quantity(trimmed)?;
-            tokens.push(Token::Quantity(quantity));
+    fn closing_paren(chars: &[char], open: usize) -> Option<usize> {
+        let mut depth = 0usize;
+        for (idx, ch) in chars.iter().enumerate().skip(open) {
+            match ch {
+                '(' => depth += 1,
+                ')' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Some(idx);
+                    }
+                }
+                _ => {}
+            }
         }
-
-        current.clear();
-        Ok(())
+        None
     }
 
-    fn parse_quantity(token: &str) -> LessResult<Quantity> {
-        let trimmed = token.trim();
-        if trimmed.is_empty() {
-            return Err(LessError::eval("缺少数值内容".to_string()));
-        }
+    fn operand_end(chars: &[char], start: usize) -> usize {
+        chars[start..]
+            .iter()
+            .position(|c| c.is_whitespace() || matches!(c, '(' | ')') || Self::is_operator(*c))
+            .map_or(chars.len(), |offset| start + offset)
+    }
 
-        let mut value_part = String::new();
-        let mut unit_part = String::new();
-        for ch in trimmed.chars() {
-            if ch.is_ascii_digit()
-                || ch == '.'
-                || ((ch == '-' || ch == '+') && value_part.is_empty())
-            {
-                value_part.push(ch);
-            } else if ch.is_ascii_alphabetic() || ch == '%' {
-                unit_part.push(ch);
-            } else if ch.is_whitespace() {
-                continue;
-            } else {
-                return Err(LessError::eval(format!("无法解析数值片段: {token}")));
+    /// 数值之间按单位计算；只要一侧是颜色，数值就视为各通道都等于该值的颜色，逐通道计算。
+    /// 单位无法换算的加减在 `strict_units` 时报错，否则沿用左侧单位；两种情况都把提示追加到 `warnings`。
+    fn operate(
+        lhs: Operand,
+        op: char,
+        rhs: Operand,
+        strict_units: bool,
+        warnings: &mut Vec<String>,
+    ) -> LessResult<Operand> {
+        match (lhs, rhs) {
+            (Operand::Number(lhs), Operand::Number(rhs)) => {
+                Self::apply_operator(lhs, op, rhs, strict_units, warnings).map(Operand::Number)
             }
+            (lhs, rhs) => Ok(Operand::Color(
+                color::operate(lhs.to_color(), op, rhs.to_color()),
+                None,
+            )),
         }
-
-        if value_part.is_empty() {
-            return Err(LessError::eval(format!("缺少数值部分: {token}")));
-        }
-
-        let value: f64 = value_part
-            .parse()
-            .map_err(|_| LessError::eval(format!("无法解析数值 {value_part}")))?;
-
-        Ok(Quantity {
-            value,
-            unit: unit_part,
-        })
     }
 
-    fn apply_operator(lhs: Quantity, op: char, rhs: Quantity) -> LessResult<Quantity> {
+    fn apply_operator(
+        lhs: Quantity,
+        op: char,
+        rhs: Quantity,
+        strict_units: bool,
+        warnings: &mut Vec<String>,
+    ) -> LessResult<Quantity> {
         match op {
             '+' | '-' => {
-                if lhs.unit != rhs.unit {
-                    return Err(LessError::eval(format!(
-                        "不同单位无法相加/相减: {}{} 与 {}{}",
-                        lhs.value, lhs.unit, rhs.value, rhs.unit
-                    )));
-                }
-                let value = if op == '+' {
-                    lhs.value + rhs.value
-                } else {
-                    lhs.value - rhs.value
+                let (unit, rhs_value) = match Self::align_units(&lhs, &rhs) {
+                    Some(aligned) => aligned,
+                    None if strict_units => {
+                        let message = format!(
+                            "不同单位无法相加/相减: {}{} 与 {}{}",
+                            lhs.value, lhs.unit, rhs.value, rhs.unit
+                        );
+                        warnings.push(message.clone());
+                        return Err(LessError::eval(message));
+                    }
+                    // 与 less.js 的默认行为一致：忽略右侧单位，结果沿用左侧单位。
+                    None => {
+                        warnings.push(format!(
+                            "不同单位相加/相减: {}{} 与 {}{}，结果沿用 {}",
+                            lhs.value, lhs.unit, rhs.value, rhs.unit, lhs.unit
+                        ));
+                        (lhs.unit.clone(), rhs.value)
+                    }
                 };
-                Ok(Quantity {
-                    value,
-                    unit: lhs.unit,
-                })
-            }
-            '*' => {
-                if !lhs.unit.is_empty() && !rhs.unit.is_empty() {
-                    return Err(LessError::eval("暂不支持两个带单位数值相乘".to_string()));
-                }
-                let value = lhs.value * rhs.value;
-                let unit = if lhs.unit.is_empty() {
-                    rhs.unit
+                let value = if op == '+' {
+                    lhs.value + rhs_value
                 } else {
-                    lhs.unit
+                    lhs.value - rhs_value
                 };
                 Ok(Quantity { value, unit })
             }
+            '*' => Ok(Quantity {
+                value: lhs.value * rhs.value,
+                unit: lhs.unit.multiply(&rhs.unit),
+            }),
             '/' => {
                 if rhs.value.abs() < f64::EPSILON {
                     return Err(LessError::eval("除法分母不能为 0".to_string()));
                 }
-                if !rhs.unit.is_empty() {
-                    return Err(LessError::eval("暂不支持被除数携带单位".to_string()));
-                }
                 Ok(Quantity {
                     value: lhs.value / rhs.value,
-                    unit: lhs.unit,
+                    unit: lhs.unit.divide(&rhs.unit),
                 })
             }
             _ => Err(LessError::eval(format!("未知的运算符 {op}"))),
         }
     }
 
-    fn format_quantity(quantity: Quantity) -> String {
-        let mut value = quantity.value;
-        if value.abs() < 1e-9 {
-            value = 0.0;
-        }
-        let mut formatted = format!("{value:.4}");
-        while formatted.contains('.') && formatted.ends_with('0') {
-            formatted.pop();
+    /// 加减前统一单位：纯数字沿用另一侧的单位（`2px + 3 * 4`），
+    /// 可换算的单位把右侧换算成左侧单位（`1cm + 10mm`）。返回结果单位与换算后的右侧数值，
+    /// 无法换算时返回 `None`。
+    fn align_units(lhs: &Quantity, rhs: &Quantity) -> Option<(Unit, f64)> {
+        if rhs.unit.is_empty() || lhs.unit == rhs.unit {
+            return Some((lhs.unit.clone(), rhs.value));
         }
-        if formatted.ends_with('.') {
-            formatted.pop();
-        }
-        if quantity.unit.is_empty() {
-            formatted
-        } else {
-            format!("{formatted}{}", quantity.unit)
-        }
-    }
-
-    fn strip_outer_parentheses<'a>(input: &'a str) -> &'a str {
-        let mut trimmed = input.trim();
-        loop {
-            if trimmed.starts_with('(') && trimmed.ends_with(')') {
-                let mut depth = 0;
-                let mut balanced = true;
-                for (idx, ch) in trimmed.chars().enumerate() {
-                    if ch == '(' {
-                        depth += 1;
-                    } else if ch == ')' {
-                        depth -= 1;
-                        if depth == 0 && idx != trimmed.len() - 1 {
-                            balanced = false;
-                            break;
-                        }
-                    }
-                }
-                if balanced && depth == 0 && trimmed.len() > 2 {
-                    trimmed = trimmed[1..trimmed.len() - 1].trim();
-                    continue;
-                }
-            }
-            return trimmed;
+        if lhs.unit.is_empty() {
+            return Some((rhs.unit.clone(), rhs.value));
         }
+        let converted = match (lhs.unit.simple(), rhs.unit.simple()) {
+            (Some(to), Some(from)) => units::convert(rhs.value, from, to),
+            _ => None,
+        };
+        converted.map(|value| (lhs.unit.clone(), value))
     }
 
     fn contains_operator(input: &str) -> bool {
@@ -810,22 +2276,24 @@ impl Evaluator {
             if !Self::is_operator(ch) {
                 continue;
             }
-            if ch == '-' {
-                if chars.get(idx + 1) == Some(&'-') {
-                    continue;
-                }
+            if ch == '-' && chars.get(idx + 1) == Some(&'-') {
+                continue;
             }
 
             let prev = idx.checked_sub(1).and_then(|i| chars.get(i)).copied();
             let next = chars.get(idx + 1).copied();
 
-            let prev_ok = prev.map_or(true, |c| {
+            // `14px/2` 这类紧贴单位的 `* / +` 也视为运算；`-` 需排除 `inline-flex` 等连字符。
+            let prev_ok = prev.is_none_or(|c| {
                 c.is_whitespace()
                     || c.is_ascii_digit()
                     || matches!(c, '(' | ')' | '+' | '-' | '*' | '/')
+                    || (ch != '-'
+                        && (c.is_ascii_alphabetic() || c == '%')
+                        && next.is_some_and(|n| n.is_ascii_digit()))
             });
 
-            let next_ok = next.map_or(true, |c| {
+            let next_ok = next.is_none_or(|c| {
                 c.is_whitespace()
                     || c.is_ascii_digit()
                     || c == '@'
@@ -843,62 +2311,319 @@ impl Evaluator {
         matches!(ch, '+' | '-' | '*' | '/')
     }
 
-    fn resolve_variable_text(&self, name: &str) -> LessResult<String> {
+    fn resolve_variable_text(&mut self, name: &str) -> LessResult<Rc<str>> {
+        if self.skip_undefined_variable(name) {
+            self.lookup_depth = usize::MAX;
+            return Ok(Rc::from(format!("@{name}")));
+        }
         match self.lookup_variable(name)? {
             VariableValue::Text(value) => Ok(value),
             VariableValue::DetachedRuleset(_) => Err(LessError::eval(format!(
                 "变量 @{name} 不是可作为文本使用的值"
             ))),
+            VariableValue::Lazy(..) => unreachable!("惰性变量在查找时已求值"),
         }
     }
 
-    fn resolve_ruleset_variable(&self, name: &str) -> LessResult<Vec<RuleBody>> {
+    /// `@@name`：以 `@name` 的值作为变量名再取一次值。宽松模式下 `@name` 未定义时整体按原文输出，
+    /// 只报告一次 `undefined-variable`。
+    fn resolve_indirect_variable(&mut self, name: &str) -> LessResult<Rc<str>> {
+        if self.skip_undefined_variable(name) {
+            self.lookup_depth = usize::MAX;
+            return Ok(Rc::from(format!("@@{name}")));
+        }
+        let target = self.resolve_variable_text(name)?;
+        self.resolve_variable_text(Self::unquote(&target))
+    }
+
+    fn resolve_ruleset_variable(&mut self, name: &str) -> LessResult<Ruleset<'a>> {
         match self.lookup_variable(name)? {
-            VariableValue::DetachedRuleset(body) => Ok(body),
-            VariableValue::Text(_) => {
+            VariableValue::DetachedRuleset(ruleset) => Ok(ruleset),
+            VariableValue::Text(_) | VariableValue::Lazy(..) => {
                 Err(LessError::eval(format!("变量 @{name} 不是可调用的规则集")))
             }
         }
     }
 
-    fn lookup_variable(&self, name: &str) -> LessResult<VariableValue> {
-        for scope in self.scopes.iter().rev() {
-            if let Some(value) = scope.get(name) {
-                return Ok(value.clone());
+    /// `allow_undefined_variables` 开启且变量未定义时记下 `undefined-variable` 警告，由调用方按原文输出。
+    fn skip_undefined_variable(&self, name: &str) -> bool {
+        let undefined = self.allow_undefined_variables
+            && !self
+                .scopes
+                .iter()
+                .any(|scope| scope.variables.contains_key(name));
+        if undefined {
+            self.warn_once(
+                "undefined-variable",
+                format!("未定义的变量 @{name}，按原文输出"),
+            );
+        }
+        undefined
+    }
+
+    /// 自内向外查找变量；与 less.js 一样，惰性变量在引用处的作用域中求值，
+    /// 只有求值时没有用到定义处以内的作用域才缓存结果。
+    fn lookup_variable(&mut self, name: &str) -> LessResult<VariableValue<'a>> {
+        let Some(index) = self
+            .scopes
+            .iter()
+            .rposition(|scope| scope.variables.contains_key(name))
+        else {
+            self.lookup_depth = usize::MAX;
+            return Err(LessError::eval(format!("未定义的变量 @{name}")));
+        };
+        self.lookup_depth = self.lookup_depth.max(index);
+        let (value, span) = match &self.scopes[index].variables[name] {
+            VariableValue::Lazy(value, span) => (*value, *span),
+            resolved => return Ok(resolved.clone()),
+        };
+        if self.resolving.iter().any(|pending| pending == name) {
+            return Err(LessError::eval(format!("变量 @{name} 存在循环引用")));
+        }
+
+        self.resolving.push(name.to_string());
+        let warnings = self.warnings.borrow().len();
+        let outer_depth = mem::replace(&mut self.lookup_depth, 0);
+        let result = match self.detached_ruleset_reference(value) {
+            Ok(Some(ruleset)) => Ok(VariableValue::DetachedRuleset(ruleset)),
+            Ok(None) => self
+                .eval_variable_value(value)
+                .map(|text| VariableValue::Text(Rc::from(text))),
+            Err(err) => Err(err),
+        }
+        .map_err(|err| err.at(span));
+        let located = self.locate_warnings(warnings, span);
+        let result = result.and_then(|value| located.map(|()| value));
+        self.resolving.pop();
+        let depth = mem::replace(&mut self.lookup_depth, 0);
+        self.lookup_depth = outer_depth.max(depth);
+
+        let resolved = result?;
+        if depth <= index {
+            self.scopes[index].variables[name] = resolved.clone();
+        }
+        Ok(resolved)
+    }
+
+    /// 求值 `$prop`：与 less.js 一样由内向外查找所在规则集中最后一条同名声明（不论在引用之前还是之后），
+    /// 并在声明所在的作用域中求值。
+    fn resolve_property(&mut self, name: &str) -> LessResult<String> {
+        let Some(index) = self
+            .scopes
+            .iter()
+            .rposition(|scope| scope.properties.contains_key(name))
+        else {
+            return Err(LessError::eval(format!("未定义的属性 ${name}")));
+        };
+        let decl = self.scopes[index].properties[name];
+        self.lookup_depth = self.lookup_depth.max(index);
+        let pending = format!("${name}");
+        if self.resolving.contains(&pending) {
+            return Err(LessError::eval(format!("属性 ${name} 存在循环引用")));
+        }
+
+        self.resolving.push(pending);
+        let inner_scopes = self.scopes.split_off(index + 1);
+        let result = self
+            .eval_variable_value(&decl.value)
+            .map_err(|err| err.at(&decl.span));
+        self.scopes.extend(inner_scopes);
+        self.resolving.pop();
+        result
+    }
+
+    /// 值恰好是单个变量引用且该变量是分离规则集时（如 `.mixin(@rules)`、`@alias: @rules;`），
+    /// 返回规则集本身，以便继续作为规则集传递。
+    fn detached_ruleset_reference(&mut self, value: &'a Value) -> LessResult<Option<Ruleset<'a>>> {
+        let mut refs = value.pieces.iter().filter(|piece| match piece {
+            ValuePiece::Literal(text) => !text.trim().is_empty(),
+            _ => true,
+        });
+        let name = match (refs.next(), refs.next()) {
+            (Some(ValuePiece::VariableRef(name)), None) => name,
+            (Some(ValuePiece::Lookup(lookup)), None) => {
+                return match self.eval_lookup(lookup)? {
+                    VariableValue::DetachedRuleset(ruleset) => Ok(Some(ruleset)),
+                    _ => Ok(None),
+                };
+            }
+            _ => return Ok(None),
+        };
+        if !self
+            .scopes
+            .iter()
+            .any(|scope| scope.variables.contains_key(name.as_str()))
+        {
+            return Ok(None);
+        }
+        match self.lookup_variable(name)? {
+            VariableValue::DetachedRuleset(ruleset) => Ok(Some(ruleset)),
+            _ => Ok(None),
+        }
+    }
+
+    /// 登记一条变量声明，同一作用域内后出现的声明覆盖先出现的声明。
+    fn declare_variable(&mut self, var: &'a VariableDeclaration) {
+        let value = match &var.value {
+            VariableInit::Value(value) => VariableValue::Lazy(value, &var.span),
+            VariableInit::Ruleset(body) => VariableValue::DetachedRuleset(Ruleset {
+                body,
+                scope: self.current_scope(),
+            }),
+        };
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.variables.insert(&var.name, value);
+        }
+    }
+
+    /// 进入作用域时预先登记规则体中的变量与 mixin（含可作为 mixin 调用的子规则集），
+    /// 它们在整个作用域内可见，与声明顺序无关。
+    fn declare_body(&mut self, body: &'a [RuleBody]) {
+        for item in body {
+            match item {
+                RuleBody::Variable(var) => self.declare_variable(var),
+                RuleBody::MixinDefinition(def) => self.set_mixin(
+                    Cow::Borrowed(&def.name),
+                    Mixin::from_definition(def, self.current_scope()),
+                ),
+                RuleBody::NestedRule(rule) => self.register_ruleset(rule),
+                // 名称含插值的声明要到求值时才知道属性名，不能被 `$prop` 引用。
+                RuleBody::Declaration(decl) if !decl.name.contains("@{") => {
+                    if let Some(scope) = self.scopes.last_mut() {
+                        scope.properties.insert(&decl.name, decl);
+                    }
+                }
+                _ => {}
             }
         }
-        Err(LessError::eval(format!("未定义的变量 @{name}")))
     }
 
-    fn set_variable_text(&mut self, name: String, value: String) {
+    fn set_variable_text(&mut self, name: &'a str, value: Rc<str>) {
         if let Some(scope) = self.scopes.last_mut() {
-            scope.insert(name, VariableValue::Text(value));
+            scope.variables.insert(name, VariableValue::Text(value));
         }
     }
 
-    fn set_variable_ruleset(&mut self, name: String, body: Vec<RuleBody>) {
+    fn set_variable(&mut self, name: &'a str, value: VariableValue<'a>) {
         if let Some(scope) = self.scopes.last_mut() {
-            scope.insert(name, VariableValue::DetachedRuleset(body));
+            scope.variables.insert(name, value);
         }
     }
 
-    fn set_mixin(&mut self, definition: MixinDefinition) {
+    fn current_scope(&self) -> ScopeId {
+        self.scopes.last().map_or(0, |scope| scope.id)
+    }
+
+    fn set_mixin(&mut self, key: Cow<'a, str>, mixin: Mixin<'a>) {
         if let Some(scope) = self.mixin_scopes.last_mut() {
-            scope.insert(definition.name.clone(), definition);
+            scope.insert(key, mixin);
         }
     }
 
-    fn resolve_mixin(&self, name: &str) -> LessResult<MixinDefinition> {
+    /// 解析 mixin 调用目标，与 less.js 一致：由内向外逐层查找作用域，登记名称为调用路径前缀的
+    /// mixin 或规则集中，最长的匹配优先；路径未匹配完时进入其定义体继续查找剩余部分，
+    /// 因此 `.btn.primary()` 既可以匹配 `.btn.primary { }`，也可以匹配 `.btn { .primary { } }`。
+    /// 同时收集路径上经过的定义体中声明的变量，供展开时可见。
+    fn resolve_mixin_call(
+        &self,
+        call: &MixinCall,
+    ) -> LessResult<(Mixin<'a>, Vec<&'a VariableDeclaration>)> {
+        let path: Vec<&str> = call
+            .namespace
+            .iter()
+            .chain([&call.name])
+            .map(String::as_str)
+            .collect();
+        let keys: Vec<String> = (1..=path.len()).map(|len| path[..len].concat()).collect();
         for scope in self.mixin_scopes.iter().rev() {
-            if let Some(def) = scope.get(name) {
-                return Ok(def.clone());
+            for (len, key) in keys.iter().enumerate().rev() {
+                let Some(mixin) = scope.get(key.as_str()) else {
+                    continue;
+                };
+                let rest = &path[len + 1..];
+                if rest.is_empty() {
+                    return Ok((*mixin, Vec::new()));
+                }
+                let mut variables = Vec::new();
+                if let Some(member) =
+                    Self::find_member_mixin(mixin.body, rest, mixin.scope, &mut variables)
+                {
+                    return Ok((member, variables));
+                }
+            }
+        }
+        Err(LessError::eval(format!(
+            "未定义的 mixin {}",
+            Self::mixin_call_path(call)
+        )))
+    }
+
+    /// 在命名空间内部按剩余路径查找成员 mixin，无参数的子规则集同样可被调用；
+    /// 找到时 `variables` 中追加沿途各定义体的变量声明。
+    fn find_member_mixin(
+        body: &'a [RuleBody],
+        path: &[&str],
+        scope: ScopeId,
+        variables: &mut Vec<&'a VariableDeclaration>,
+    ) -> Option<Mixin<'a>> {
+        let collected = variables.len();
+        variables.extend(body.iter().filter_map(|item| match item {
+            RuleBody::Variable(var) => Some(var),
+            _ => None,
+        }));
+        for item in body.iter().rev() {
+            let found = match item {
+                RuleBody::MixinDefinition(def) if def.name == path[0] => match &path[1..] {
+                    [] => Some(Mixin::from_definition(def, scope)),
+                    rest => Self::find_member_mixin(&def.body, rest, scope, variables),
+                },
+                RuleBody::NestedRule(rule) => rule.selectors.iter().find_map(|selector| {
+                    let elements = selector.mixin_elements()?;
+                    match path.strip_prefix(elements.as_slice())? {
+                        [] => Some(Mixin::from_ruleset(rule, selector.value.trim(), scope)),
+                        rest => Self::find_member_mixin(&rule.body, rest, scope, variables),
+                    }
+                }),
+                _ => None,
+            };
+            if found.is_some() {
+                return found;
             }
         }
-        Err(LessError::eval(format!("未定义的 mixin {name}")))
+        variables.truncate(collected);
+        None
+    }
+
+    /// 规则集照常输出，同时其中每个只由 `.class` 与 `#id` 组成的选择器都登记为无参数的 mixin
+    /// （`.a, .b { }` 可通过 `.a;` 或 `.b;` 调用，`.btn.primary { }` 可通过 `.btn.primary();` 调用）；
+    /// 规则集同时视为命名空间，可通过 `#ns > .mixin()` 访问其中的成员。
+    fn register_ruleset(&mut self, rule: &'a RuleSet) {
+        for selector in &rule.selectors {
+            let Some(elements) = selector.mixin_elements() else {
+                continue;
+            };
+            let key = match elements.as_slice() {
+                [single] => Cow::Borrowed(*single),
+                _ => Cow::Owned(elements.concat()),
+            };
+            let mixin = Mixin::from_ruleset(rule, selector.value.trim(), self.current_scope());
+            self.set_mixin(key, mixin);
+        }
+    }
+
+    fn mixin_call_path(call: &MixinCall) -> String {
+        let mut path = call.namespace.join(" > ");
+        if !path.is_empty() {
+            path.push_str(" > ");
+        }
+        path.push_str(&call.name);
+        path
     }
 
     fn push_scope(&mut self) {
-        self.scopes.push(IndexMap::new());
+        let parent = self.scopes.last().map(|scope| scope.id);
+        self.scopes.push(Scope::new(self.next_scope, parent));
+        self.next_scope += 1;
     }
 
     fn pop_scope(&mut self) {
@@ -914,62 +2639,271 @@ impl Evaluator {
     }
 
     /// 合并父子选择器，支持 `&` 占位符。
+    /// 按 lessc 的顺序组合父子选择器：外层遍历子选择器，内层遍历父选择器；
+    /// 生成的选择器数超过 `max_selectors` 时报错，避免多个 `&` 与长选择器列表组合爆炸。
     fn combine_selectors(
         &self,
         parents: &[String],
-        current: &[crate::ast::Selector],
-    ) -> Vec<String> {
+        current: Vec<String>,
+    ) -> LessResult<Vec<String>> {
         if parents.is_empty() {
-            return current.iter().map(|s| s.value.clone()).collect();
+            return Ok(current);
         }
 
-        let mut result = Vec::new();
-        for parent in parents {
-            for child in current {
-                let selector = if child.value.contains('&') {
-                    child.value.replace('&', parent).trim().to_string()
-                } else {
-                    format!("{} {}", parent.trim(), child.value.trim())
-                };
-                result.push(selector);
+        let children: Vec<ParentSelector> = current
+            .iter()
+            .map(|child| ParentSelector::parse(child))
+            .collect();
+        let total = children.iter().fold(0usize, |total, child| {
+            total.saturating_add(child.combinations(parents.len()))
+        });
+        if total > self.max_selectors {
+            return Err(LessError::eval(format!(
+                "选择器组合数超过上限 {}：{} 个父选择器与 {} 展开过多",
+                self.max_selectors,
+                parents.len(),
+                current.join(", ")
+            )));
+        }
+
+        let mut result = Vec::with_capacity(total);
+        for child in &children {
+            child.combine(parents, &mut result);
+        }
+        Ok(result)
+    }
+}
+
+#[derive(Debug)]
+enum Token {
+    Operand(Operand),
+    Operator(char),
+    /// 一元负号，如 `-(@gap / 2)`。
+    Negate,
+    Open,
+    Close,
+}
+
+/// 递归下降解析算术 token：`sum := product (('+'|'-') product)*`，
+/// `product := unary (('*'|'/') unary)*`，`unary := '-' unary | primary`。
+struct ExpressionParser<'a> {
+    tokens: &'a [Token],
+    pos: usize,
+    strict_units: bool,
+    /// 非严格单位模式下单位不兼容的运算提示。
+    warnings: Vec<String>,
+}
+
+impl ExpressionParser<'_> {
+    fn operate(&mut self, lhs: Operand, op: char, rhs: Operand) -> LessResult<Operand> {
+        Evaluator::operate(lhs, op, rhs, self.strict_units, &mut self.warnings)
+    }
+
+    fn is_finished(&self) -> bool {
+        self.pos >= self.tokens.len()
+    }
+
+    fn peek_operator(&self, candidates: &[char]) -> Option<char> {
+        match self.tokens.get(self.pos) {
+            Some(Token::Operator(op)) if candidates.contains(op) => Some(*op),
+            _ => None,
+        }
+    }
+
+    fn parse_sum(&mut self) -> LessResult<Operand> {
+        let mut lhs = self.parse_product()?;
+        while let Some(op) = self.peek_operator(&['+', '-']) {
+            self.pos += 1;
+            let rhs = self.parse_product()?;
+            lhs = self.operate(lhs, op, rhs)?;
+        }
+        Ok(lhs)
+    }
+
+    fn parse_product(&mut self) -> LessResult<Operand> {
+        let mut lhs = self.parse_unary()?;
+        while let Some(op) = self.peek_operator(&['*', '/']) {
+            self.pos += 1;
+            let rhs = self.parse_unary()?;
+            lhs = self.operate(lhs, op, rhs)?;
+        }
+        Ok(lhs)
+    }
+
+    fn parse_unary(&mut self) -> LessResult<Operand> {
+        if matches!(self.tokens.get(self.pos), Some(Token::Negate)) {
+            self.pos += 1;
+            return match self.parse_unary()? {
+                Operand::Number(operand) => Ok(Operand::Number(Quantity {
+                    value: -operand.value,
+                    unit: operand.unit,
+                })),
+                color => {
+                    let minus_one = Quantity {
+                        value: -1.0,
+                        unit: Unit::default(),
+                    };
+                    self.operate(Operand::Number(minus_one), '*', color)
+                }
+            };
+        }
+        self.parse_primary()
+    }
+
+    fn parse_primary(&mut self) -> LessResult<Operand> {
+        let token = self.tokens.get(self.pos);
+        self.pos += 1;
+        match token {
+            Some(Token::Operand(operand)) => Ok(operand.clone()),
+            Some(Token::Open) => {
+                let inner = self.parse_sum()?;
+                match self.tokens.get(self.pos) {
+                    Some(Token::Close) => {
+                        self.pos += 1;
+                        Ok(inner)
+                    }
+                    _ => Err(LessError::eval("算术表达式括号不匹配".to_string())),
+                }
             }
+            _ => Err(LessError::eval("算术表达式缺少数值".to_string())),
         }
-        result
     }
+}
+
+/// 算术表达式中的操作数。
+#[derive(Debug, Clone)]
+enum Operand {
+    Number(Quantity),
+    /// 颜色及其原文；未参与运算的颜色按原文输出，运算结果为 `None`，按 `format_color` 输出。
+    Color(Rgba, Option<String>),
+}
 
-    /// 检测并剥离 `!important` 标记，返回去除后的值。
-    fn strip_important(value: &str) -> Option<String> {
-        let trimmed = value.trim_end();
-        if trimmed.ends_with("!important") {
-            let idx = trimmed.len() - "!important".len();
-            let without = trimmed[..idx].trim_end();
-            return Some(without.to_string());
+impl Operand {
+    /// `#rgb` / `#rrggbb`、`rgb()` / `rgba()` 与颜色关键字解析为颜色，其余按数值解析。
+    fn parse(text: &str) -> LessResult<Self> {
+        let lowered = text.to_ascii_lowercase();
+        if text.starts_with('#')
+            || lowered.starts_with("rgb")
+            || color::named_color(&lowered).is_some()
+        {
+            return color::parse_color(text)
+                .map(|color| Operand::Color(color, Some(text.to_string())))
+                .ok_or_else(|| LessError::eval(format!("无法解析颜色 {text}")));
+        }
+        parse_quantity(text).map(Operand::Number)
+    }
+
+    fn to_color(&self) -> Rgba {
+        match self {
+            Operand::Number(quantity) => {
+                let channel = quantity.value / 255.0;
+                Rgba {
+                    r: channel,
+                    g: channel,
+                    b: channel,
+                    a: 1.0,
+                }
+            }
+            Operand::Color(color, _) => *color,
+        }
+    }
+
+    fn to_css(&self) -> String {
+        match self {
+            Operand::Number(quantity) => format_quantity(quantity),
+            Operand::Color(_, Some(text)) => text.clone(),
+            Operand::Color(color, None) => color::format_color(*color),
         }
-        None
     }
 }
 
+type ScopeId = usize;
+
+/// mixin 展开后返回给调用方的变量与 mixin。
+type Returned<'a> = (Vec<(&'a str, VariableValue<'a>)>, Vec<(&'a str, Mixin<'a>)>);
+
+/// 一层变量作用域。
 #[derive(Debug, Clone)]
-struct Quantity {
-    value: f64,
-    unit: String,
+struct Scope<'a> {
+    id: ScopeId,
+    /// 创建时的外层作用域，即定义处的作用域链中的上一层。
+    parent: Option<ScopeId>,
+    variables: IndexMap<&'a str, VariableValue<'a>>,
+    /// 规则体中按属性名登记的声明，供 `$prop` 引用，同名时后者覆盖前者。
+    properties: IndexMap<&'a str, &'a Declaration>,
 }
 
-#[derive(Debug)]
-enum Token {
-    Quantity(Quantity),
-    Operator(char),
+impl Scope<'_> {
+    fn new(id: ScopeId, parent: Option<ScopeId>) -> Self {
+        Self {
+            id,
+            parent,
+            variables: IndexMap::new(),
+            properties: IndexMap::new(),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
-enum VariableValue {
-    Text(String),
-    DetachedRuleset(Vec<RuleBody>),
+enum VariableValue<'a> {
+    /// 求值结果；多次引用同一变量时共享同一份文本。
+    Text(Rc<str>),
+    DetachedRuleset(Ruleset<'a>),
+    /// 尚未求值的变量声明及其位置，首次读取时才计算。
+    Lazy(&'a Value, &'a Span),
+}
+
+/// 解析后的查找键，见 [`Lookup::keys`]。
+enum LookupKey {
+    Variable(String),
+    Property(String),
+    LastDeclaration,
+}
+
+/// 分离规则集及其定义处的作用域，调用时其中的变量先在定义处查找。
+#[derive(Debug, Clone, Copy)]
+struct Ruleset<'a> {
+    body: &'a [RuleBody],
+    scope: ScopeId,
+}
+
+/// 作用域中登记的可调用 mixin：mixin 定义，或以 `.class` / `#id` 选择器声明的规则集。
+#[derive(Debug, Clone, Copy)]
+struct Mixin<'a> {
+    name: &'a str,
+    params: &'a [MixinParam],
+    guard: Option<&'a Guard>,
+    body: &'a [RuleBody],
+    /// 定义所在的作用域。
+    scope: ScopeId,
+}
+
+impl<'a> Mixin<'a> {
+    fn from_ruleset(rule: &'a RuleSet, name: &'a str, scope: ScopeId) -> Self {
+        Self {
+            name,
+            params: &[],
+            guard: rule.guard.as_ref(),
+            body: &rule.body,
+            scope,
+        }
+    }
+
+    fn from_definition(definition: &'a MixinDefinition, scope: ScopeId) -> Self {
+        Self {
+            name: &definition.name,
+            params: &definition.params,
+            guard: definition.guard.as_ref(),
+            body: &definition.body,
+            scope,
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::parser::LessParser;
     use crate::CompileOptions;
 
     #[test]
@@ -978,11 +2912,88 @@ mod tests {
         assert!(!Evaluator::contains_operator("border-radius"));
     }
 
+    #[test]
+    fn arithmetic_respects_precedence_and_parentheses() {
+        let evaluator = Evaluator::new(CompileOptions::default());
+        let eval = |input: &str| {
+            evaluator
+                .evaluate_arithmetic(input, false)
+                .unwrap()
+                .unwrap()
+        };
+        assert_eq!(eval("2px + 3 * 4"), "14px");
+        assert_eq!(eval("(10px - 2px) / 2"), "4px");
+        assert_eq!(eval("2 * (3 + (4 - 1)) - 1"), "11");
+        assert_eq!(eval("-(12px / 2)"), "-6px");
+        assert_eq!(eval("(12px * 0.75) (12px * 1.5)"), "9px 18px");
+        assert_eq!(eval("0 0 -12px 2px"), "0 0 -12px 2px");
+    }
+
+    #[test]
+    fn arithmetic_coerces_compatible_units() {
+        let evaluator = Evaluator::new(CompileOptions::default());
+        let eval = |input: &str| {
+            evaluator
+                .evaluate_arithmetic(input, false)
+                .unwrap()
+                .unwrap()
+        };
+        assert_eq!(eval("1cm + 10mm"), "2cm");
+        assert_eq!(eval("1s - 500ms"), "0.5s");
+        assert_eq!(eval("2px * 3px"), "6px");
+        assert_eq!(eval("10px / 2px"), "5");
+        assert_eq!(eval("(10px * 2px) / 4px"), "5px");
+        assert_eq!(eval("3 / 2s"), "1.5s");
+        assert_eq!(eval("1px + 1em"), "2px");
+
+        let strict = Evaluator::new(CompileOptions {
+            strict_units: true,
+            ..CompileOptions::default()
+        });
+        assert!(strict.evaluate_arithmetic("1px + 1em", false).is_err());
+    }
+
+    #[test]
+    fn evaluated_nodes_record_source_file() {
+        let parser = LessParser::new();
+        let lib = parser
+            .parse_file(".mixin() { color: red; }", Path::new("lib/mixins.less"))
+            .unwrap();
+        let entry = parser
+            .parse_file(
+                ".a { .mixin(); margin: 0; }\n@media print { .b { c: d; } }",
+                Path::new("main.less"),
+            )
+            .unwrap();
+        let stylesheet =
+            Stylesheet::new(lib.statements.into_iter().chain(entry.statements).collect());
+        let mut evaluator = Evaluator::new(CompileOptions::default());
+        let evaluated = evaluator.evaluate(&stylesheet).unwrap();
+        let source = |file: &Option<Arc<Path>>| file.as_deref().map(Path::to_path_buf);
+        let EvaluatedNode::Rule(rule) = &evaluated.nodes[0] else {
+            panic!("expected rule");
+        };
+        assert_eq!(source(&rule.source), Some("main.less".into()));
+        let files: Vec<_> = rule
+            .declarations
+            .iter()
+            .map(|d| source(&d.source))
+            .collect();
+        assert_eq!(
+            files,
+            [Some("lib/mixins.less".into()), Some("main.less".into())]
+        );
+        let EvaluatedNode::AtRule(media) = &evaluated.nodes[1] else {
+            panic!("expected at-rule");
+        };
+        assert_eq!(source(&media.source), Some("main.less".into()));
+    }
+
     #[test]
     fn overlay_function_is_evaluated() {
         let mut evaluator = Evaluator::new(CompileOptions::default());
         let value = evaluator
-            .evaluate_color_function("overlay(rgba(255, 255, 255, 0.05), #2c2c2c)")
+            .evaluate_function_calls("overlay(rgba(255, 255, 255, 0.05), #2c2c2c)")
             .unwrap();
         assert_eq!(value, Some("#373737".to_string()));
     }
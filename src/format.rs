@@ -0,0 +1,517 @@
+//! LESS 源码格式化：解析后按统一风格重新输出，不做求值。
+//!
+//! 每条语句与声明独占一行，块按层级缩进，值与选择器中的连续空白合并为一个空格，
+//! 字符串引号可统一为单引号或双引号。块注释与 `//` 行注释保留在原位置（行尾注释仍在行尾），
+//! 语句之间的空行最多保留一行；值与选择器内部的注释会丢失。
+
+use crate::ast::{
+    AtRule, BodylessAtRule, Comment, Declaration, EachCall, Guard, GuardOperator, ImportStatement,
+    MixinArgument, MixinCall, MixinDefinition, PropertyMerge, RuleBody, RuleSet, Span, Statement,
+    Stylesheet, Value, VariableDeclaration, VariableInit,
+};
+use crate::error::LessResult;
+use crate::parser::LessParser;
+use crate::scss::{self, ScssState};
+use crate::utils::collapse_whitespace;
+
+/// [`format_source`](crate::format_source) 的排版选项。
+#[derive(Debug, Clone)]
+pub struct FormatOptions {
+    /// 每级缩进的空格数，默认 2；`use_tabs` 时忽略。
+    pub indent_width: usize,
+    /// 以制表符缩进。
+    pub use_tabs: bool,
+    /// 字符串的引号风格，默认保持原样。
+    pub quote_style: QuoteStyle,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        Self {
+            indent_width: 2,
+            use_tabs: false,
+            quote_style: QuoteStyle::Preserve,
+        }
+    }
+}
+
+/// 字符串引号风格；内容中含有目标引号或转义引号的字符串保持原样。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum QuoteStyle {
+    #[default]
+    Preserve,
+    Single,
+    Double,
+}
+
+pub(crate) fn format_source(source: &str, options: &FormatOptions) -> LessResult<String> {
+    let stylesheet = LessParser::new().parse(source)?;
+    Ok(render(source, &stylesheet, options, None))
+}
+
+/// 按选项输出整份样式表；`scss` 为 `Some` 时以 SCSS 语法输出（见 [`crate::scss`]）。
+pub(crate) fn render(
+    source: &str,
+    stylesheet: &Stylesheet,
+    options: &FormatOptions,
+    scss: Option<ScssState>,
+) -> String {
+    let mut formatter = Formatter {
+        source,
+        options,
+        scss,
+        out: String::new(),
+    };
+    let nodes = flatten_statements(&stylesheet.statements);
+    formatter.nodes(&nodes, 0);
+    let mut output = formatter.out;
+    if !output.is_empty() {
+        output.push('\n');
+    }
+    output
+}
+
+/// 语句与规则体项的统一视图。
+pub(crate) enum Node<'a> {
+    Import(&'a ImportStatement),
+    Plugin(&'a str),
+    Charset(&'a str),
+    BodylessAtRule(&'a BodylessAtRule),
+    AtRule(&'a AtRule),
+    RuleSet(&'a RuleSet),
+    Variable(&'a VariableDeclaration),
+    MixinDefinition(&'a MixinDefinition),
+    MixinCall(&'a MixinCall),
+    DetachedCall(&'a str, &'a Span),
+    Each(&'a EachCall),
+    Declaration(&'a Declaration),
+    RawCss(&'a str),
+    Comment(&'a Comment),
+}
+
+impl Node<'_> {
+    fn span(&self) -> Option<&Span> {
+        match self {
+            Node::BodylessAtRule(at_rule) => Some(&at_rule.span),
+            Node::AtRule(at_rule) => Some(&at_rule.span),
+            Node::RuleSet(rule) => Some(&rule.span),
+            Node::Variable(variable) => Some(&variable.span),
+            Node::MixinDefinition(definition) => Some(&definition.span),
+            Node::MixinCall(call) => Some(&call.span),
+            Node::DetachedCall(_, span) => Some(span),
+            Node::Each(call) => Some(&call.span),
+            Node::Declaration(declaration) => Some(&declaration.span),
+            Node::Comment(comment) => Some(&comment.span),
+            Node::Import(import) => Some(&import.span),
+            Node::Plugin(_) | Node::Charset(_) | Node::RawCss(_) => None,
+        }
+    }
+}
+
+fn flatten_statements(statements: &[Statement]) -> Vec<Node<'_>> {
+    let mut nodes = Vec::with_capacity(statements.len());
+    for statement in statements {
+        nodes.push(match statement {
+            Statement::Import(import) => Node::Import(import),
+            Statement::Plugin(plugin) => Node::Plugin(&plugin.name),
+            Statement::Charset(charset) => Node::Charset(charset),
+            Statement::BodylessAtRule(at_rule) => Node::BodylessAtRule(at_rule),
+            Statement::AtRule(at_rule) => Node::AtRule(at_rule),
+            Statement::RuleSet(rule) => Node::RuleSet(rule),
+            Statement::Variable(variable) => Node::Variable(variable),
+            Statement::MixinDefinition(definition) => Node::MixinDefinition(definition),
+            Statement::MixinCall(call) => Node::MixinCall(call),
+            Statement::DetachedCall(call) => Node::DetachedCall(&call.name, &call.span),
+            Statement::Each(call) => Node::Each(call),
+            Statement::Reference(inner) => {
+                nodes.extend(flatten_statements(inner));
+                continue;
+            }
+            Statement::RawCss(content) => Node::RawCss(content),
+            Statement::Comment(comment) => Node::Comment(comment),
+        });
+    }
+    nodes
+}
+
+fn body_nodes(body: &[RuleBody]) -> Vec<Node<'_>> {
+    body.iter()
+        .map(|item| match item {
+            RuleBody::Declaration(declaration) => Node::Declaration(declaration),
+            RuleBody::NestedRule(rule) => Node::RuleSet(rule),
+            RuleBody::AtRule(at_rule) => Node::AtRule(at_rule),
+            RuleBody::BodylessAtRule(at_rule) => Node::BodylessAtRule(at_rule),
+            RuleBody::DetachedCall(call) => Node::DetachedCall(&call.name, &call.span),
+            RuleBody::Each(call) => Node::Each(call),
+            RuleBody::Variable(variable) => Node::Variable(variable),
+            RuleBody::MixinDefinition(definition) => Node::MixinDefinition(definition),
+            RuleBody::MixinCall(call) => Node::MixinCall(call),
+            RuleBody::Import(import) => Node::Import(import),
+            RuleBody::Comment(comment) => Node::Comment(comment),
+        })
+        .collect()
+}
+
+pub(crate) struct Formatter<'a> {
+    source: &'a str,
+    options: &'a FormatOptions,
+    pub(crate) scss: Option<ScssState>,
+    pub(crate) out: String,
+}
+
+impl Formatter<'_> {
+    fn nodes(&mut self, nodes: &[Node<'_>], depth: usize) {
+        for (index, node) in nodes.iter().enumerate() {
+            if index > 0 {
+                if let Node::Comment(comment) = node {
+                    if self.trails_previous_line(&comment.span) {
+                        self.out.push(' ');
+                        self.out.push_str(&comment.text);
+                        continue;
+                    }
+                }
+                self.out.push('\n');
+                if node
+                    .span()
+                    .is_some_and(|span| self.follows_blank_line(span))
+                {
+                    self.out.push('\n');
+                }
+            }
+            self.indent(depth);
+            self.node(node, depth);
+        }
+    }
+
+    pub(crate) fn node(&mut self, node: &Node<'_>, depth: usize) {
+        if self.scss.is_some() && self.scss_node(node, depth) {
+            return;
+        }
+        match node {
+            Node::Import(import) => {
+                let text = self.text(&import.raw);
+                match text.strip_prefix("@import ") {
+                    Some(target) if !import.options.is_empty() => {
+                        let options = import.options.join(", ");
+                        self.out.push_str(&format!("@import ({options}) {target}"));
+                    }
+                    _ => self.out.push_str(&text),
+                }
+            }
+            Node::Plugin(name) => {
+                let name = self.quote(name);
+                self.out.push_str(&format!("@plugin {name};"));
+            }
+            // `@charset` 只接受双引号，不参与引号转换。
+            Node::Charset(charset) => self.out.push_str(&format!("@charset {charset};")),
+            Node::BodylessAtRule(at_rule) => {
+                let params = self.params(&at_rule.params);
+                self.out.push('@');
+                self.out.push_str(&at_rule.name);
+                if !params.is_empty() {
+                    self.out.push(' ');
+                    self.out.push_str(&params);
+                }
+                self.out.push(';');
+            }
+            Node::AtRule(at_rule) => {
+                let params = self.params(&at_rule.params);
+                self.out.push('@');
+                self.out.push_str(&at_rule.name);
+                if !params.is_empty() {
+                    self.out.push(' ');
+                    self.out.push_str(&params);
+                }
+                self.block(&at_rule.body, depth);
+            }
+            Node::RuleSet(rule) => {
+                let selectors = self.selectors(rule, depth);
+                self.out.push_str(&selectors);
+                if let Some(guard) = &rule.guard {
+                    let guard = self.guard(guard);
+                    self.out.push_str(&guard);
+                }
+                self.block(&rule.body, depth);
+            }
+            Node::Variable(variable) => {
+                self.out.push('@');
+                self.out.push_str(&variable.name);
+                self.out.push(':');
+                match &variable.value {
+                    VariableInit::Value(value) => {
+                        let value = self.value(value);
+                        self.out.push(' ');
+                        self.out.push_str(&value);
+                        self.out.push(';');
+                    }
+                    VariableInit::Ruleset(body) => self.block(body, depth),
+                }
+            }
+            Node::MixinDefinition(definition) => {
+                let params: Vec<String> = definition
+                    .params
+                    .iter()
+                    .map(|param| match &param.default {
+                        Some(default) => format!("@{}: {}", param.name, self.value(default)),
+                        None => format!("@{}", param.name),
+                    })
+                    .collect();
+                self.out.push_str(&definition.name);
+                self.out.push_str(&format!("({})", params.join(", ")));
+                if let Some(guard) = &definition.guard {
+                    let guard = self.guard(guard);
+                    self.out.push_str(&guard);
+                }
+                self.block(&definition.body, depth);
+            }
+            Node::MixinCall(call) => self.mixin_call(call, depth),
+            Node::DetachedCall(name, _) => {
+                self.out.push_str(&format!("@{name}();"));
+            }
+            Node::Each(call) => self.each(call, depth),
+            Node::Declaration(declaration) => {
+                let value = if declaration.name.starts_with("--") {
+                    declaration.value.to_string().trim().to_string()
+                } else {
+                    self.value(&declaration.value)
+                };
+                let name = self.text(&declaration.name);
+                self.out.push_str(&name);
+                self.out.push_str(match declaration.merge {
+                    PropertyMerge::None => "",
+                    PropertyMerge::Comma => "+",
+                    PropertyMerge::Space => "+_",
+                });
+                self.out.push(':');
+                if !value.is_empty() {
+                    self.out.push(' ');
+                    self.out.push_str(&value);
+                }
+                if declaration.important {
+                    self.out.push_str(" !important");
+                }
+                self.out.push(';');
+            }
+            Node::RawCss(content) => self.out.push_str(content.trim()),
+            Node::Comment(comment) => self.out.push_str(&comment.text),
+        }
+    }
+
+    fn mixin_call(&mut self, call: &MixinCall, depth: usize) {
+        let path: Vec<&str> = call
+            .namespace
+            .iter()
+            .chain([&call.name])
+            .map(String::as_str)
+            .collect();
+        self.out.push_str(&path.join(" > "));
+        self.out.push('(');
+        for (index, arg) in call.args.iter().enumerate() {
+            if index > 0 {
+                self.out.push_str(", ");
+            }
+            match arg {
+                MixinArgument::Value(value) => {
+                    let value = self.value(value);
+                    self.out.push_str(&value);
+                }
+                MixinArgument::Ruleset(body) => {
+                    // `block` 以空格开头，参数中的规则集紧跟在括号或逗号之后。
+                    let start = self.out.len();
+                    self.block(body, depth);
+                    self.out.remove(start);
+                }
+            }
+        }
+        self.out.push(')');
+        if call.important {
+            self.out.push_str(" !important");
+        }
+        self.out.push(';');
+    }
+
+    fn each(&mut self, call: &EachCall, depth: usize) {
+        let list = self.value(&call.list);
+        self.out.push_str(&format!("each({list}, "));
+        match &call.ruleset {
+            MixinArgument::Value(value) => {
+                let value = self.value(value);
+                self.out.push_str(&value);
+            }
+            MixinArgument::Ruleset(body) => {
+                if !call.params.is_empty() {
+                    let params: Vec<String> =
+                        call.params.iter().map(|name| format!("@{name}")).collect();
+                    self.out.push_str(&format!(".({})", params.join(", ")));
+                }
+                let start = self.out.len();
+                self.block(body, depth);
+                if call.params.is_empty() {
+                    self.out.remove(start);
+                }
+            }
+        }
+        self.out.push_str(");");
+    }
+
+    /// 输出 ` { ... }`，空块为 ` {}`。
+    pub(crate) fn block(&mut self, body: &[RuleBody], depth: usize) {
+        if body.is_empty() {
+            self.out.push_str(" {}");
+            return;
+        }
+        self.out.push_str(" {\n");
+        self.nodes(&body_nodes(body), depth + 1);
+        self.out.push('\n');
+        self.indent(depth);
+        self.out.push('}');
+    }
+
+    /// 选择器逐行书写；含有被逗号拆开的括号（如 `:is(.a, .b)`）时写在同一行。
+    fn selectors(&self, rule: &RuleSet, depth: usize) -> String {
+        let selectors: Vec<String> = rule
+            .selectors
+            .iter()
+            .map(|selector| self.text(&selector.value))
+            .collect();
+        let balanced = selectors.iter().all(|selector| {
+            selector.matches('(').count() == selector.matches(')').count()
+                && selector.matches('[').count() == selector.matches(']').count()
+        });
+        if balanced {
+            selectors.join(&format!(",\n{}", self.indentation(depth)))
+        } else {
+            selectors.join(", ")
+        }
+    }
+
+    fn guard(&self, guard: &Guard) -> String {
+        let alternatives: Vec<String> = guard
+            .alternatives
+            .iter()
+            .map(|conditions| {
+                conditions
+                    .iter()
+                    .map(|condition| {
+                        let mut text = String::new();
+                        if condition.negated {
+                            text.push_str("not ");
+                        }
+                        text.push('(');
+                        text.push_str(&self.value(&condition.lhs));
+                        if let Some((operator, rhs)) = &condition.comparison {
+                            let operator = match operator {
+                                GuardOperator::Eq => "=",
+                                GuardOperator::Lt => "<",
+                                GuardOperator::Le => "=<",
+                                GuardOperator::Gt => ">",
+                                GuardOperator::Ge => ">=",
+                            };
+                            text.push_str(&format!(" {operator} {}", self.value(rhs)));
+                        }
+                        text.push(')');
+                        text
+                    })
+                    .collect::<Vec<_>>()
+                    .join(" and ")
+            })
+            .collect();
+        format!(" when {}", alternatives.join(", "))
+    }
+
+    pub(crate) fn value(&self, value: &Value) -> String {
+        match self.scss {
+            Some(_) => self.text(&scss::value_text(value, false)),
+            None => self.text(&value.to_string()),
+        }
+    }
+
+    /// at-rule 的前导参数；SCSS 中其中的变量需写成插值。
+    fn params(&self, value: &Value) -> String {
+        match self.scss {
+            Some(_) => self.text(&scss::value_text(value, true)),
+            None => self.text(&value.to_string()),
+        }
+    }
+
+    /// 合并空白并按选项统一引号。
+    pub(crate) fn text(&self, text: &str) -> String {
+        let text = collapse_whitespace(text);
+        let text = match self.scss {
+            Some(_) => scss::convert_text(&text),
+            None => text,
+        };
+        let target = match self.options.quote_style {
+            QuoteStyle::Preserve => return text,
+            QuoteStyle::Single => '\'',
+            QuoteStyle::Double => '"',
+        };
+        let mut output = String::with_capacity(text.len());
+        let mut chars = text.char_indices().peekable();
+        while let Some((start, ch)) = chars.next() {
+            if ch != '"' && ch != '\'' {
+                output.push(ch);
+                continue;
+            }
+            let mut end = text.len();
+            while let Some((idx, next)) = chars.next() {
+                if next == '\\' {
+                    chars.next();
+                } else if next == ch {
+                    end = idx + 1;
+                    break;
+                }
+            }
+            let string = &text[start..end];
+            let content = &string[1..string.len().saturating_sub(1).max(1)];
+            let convertible = ch != target
+                && string.len() >= 2
+                && string.ends_with(ch)
+                && !content.contains(target)
+                && !content.contains('\\');
+            if convertible {
+                output.push(target);
+                output.push_str(content);
+                output.push(target);
+            } else {
+                output.push_str(string);
+            }
+        }
+        output
+    }
+
+    /// 给未加引号的名称（如插件名）加上引号。
+    fn quote(&self, name: &str) -> String {
+        match self.options.quote_style {
+            QuoteStyle::Single => format!("'{name}'"),
+            QuoteStyle::Preserve | QuoteStyle::Double => format!("\"{name}\""),
+        }
+    }
+
+    pub(crate) fn indentation(&self, depth: usize) -> String {
+        if self.options.use_tabs {
+            "\t".repeat(depth)
+        } else {
+            " ".repeat(depth * self.options.indent_width)
+        }
+    }
+
+    pub(crate) fn indent(&mut self, depth: usize) {
+        let indentation = self.indentation(depth);
+        self.out.push_str(&indentation);
+    }
+
+    /// 节点之前的源码中是否有空行。
+    fn follows_blank_line(&self, span: &Span) -> bool {
+        let before = &self.source[..span.start.min(self.source.len())];
+        let gap_start = before.trim_end_matches(char::is_whitespace).len();
+        before[gap_start..].matches('\n').count() >= 2
+    }
+
+    /// 注释与前一个节点是否在同一行。
+    fn trails_previous_line(&self, span: &Span) -> bool {
+        let before = &self.source[..span.start.min(self.source.len())];
+        let line = before.rsplit('\n').next().unwrap_or(before);
+        !line.trim().is_empty()
+    }
+}
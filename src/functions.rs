@@ -0,0 +1,1378 @@
+//! LESS 内置函数注册表：函数名 → 实现，求值器在值文本中识别到函数调用后查表执行。
+//! 每个实现接收已求值、按顶层逗号拆分的参数文本，返回 `Ok(None)` 表示无法在编译期求值，
+//! 此时调用保持原样输出。
+
+use crate::color::{self, Rgba};
+use crate::error::{LessError, LessResult};
+use crate::units::{self, Unit};
+use crate::utils::{format_number, format_quantity, Quantity};
+use once_cell::sync::Lazy;
+use regex::RegexBuilder;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+pub type BuiltinFn = fn(&[String]) -> LessResult<Option<String>>;
+
+/// 调用方注册的自定义函数：参数为已求值并按类型识别的 [`FunctionValue`]，
+/// 返回 `Ok(None)` 表示保持调用原样输出。
+pub type CustomFunction =
+    Arc<dyn Fn(&[FunctionValue]) -> LessResult<Option<FunctionValue>> + Send + Sync>;
+
+/// 自定义函数的参数与返回值。
+#[derive(Debug, Clone, PartialEq)]
+pub enum FunctionValue {
+    /// 数值及其单位，如 `12px`、`50%`；纯数字的 `unit` 为空。
+    Dimension { value: f64, unit: String },
+    /// `#rrggbb`、`rgba(...)` 等颜色，通道取值 0~255，`alpha` 取值 0~1；
+    /// `red` 之类的颜色关键字作为 [`FunctionValue::Keyword`] 传入。
+    Color { r: u8, g: u8, b: u8, alpha: f64 },
+    /// 带引号的字符串，`value` 为引号内的内容。
+    Quoted { value: String, quote: char },
+    /// 其余文本（关键字、列表、`~"..."` 转义字符串等），按原文输出。
+    Keyword(String),
+}
+
+impl FunctionValue {
+    /// 按已求值的参数文本识别类型。
+    pub(crate) fn parse(raw: &str) -> Self {
+        let trimmed = raw.trim();
+        if let Some(quantity) = parse_dimension(trimmed) {
+            return Self::Dimension {
+                value: quantity.value,
+                unit: quantity.unit.to_string(),
+            };
+        }
+        if let Some(quoted) = QuotedString::parse(trimmed).filter(|quoted| !quoted.escaped) {
+            return Self::Quoted {
+                value: quoted.content.to_string(),
+                quote: quoted.quote,
+            };
+        }
+        if !trimmed.starts_with(char::is_alphabetic) || trimmed.starts_with("rgb") {
+            if let Some(color) = color::parse_color(trimmed) {
+                return Self::Color {
+                    r: color::channel_value(color.r),
+                    g: color::channel_value(color.g),
+                    b: color::channel_value(color.b),
+                    alpha: color.a,
+                };
+            }
+        }
+        Self::Keyword(trimmed.to_string())
+    }
+}
+
+impl fmt::Display for FunctionValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Dimension { value, unit } => write!(f, "{}{unit}", format_number(*value)),
+            Self::Color { r, g, b, alpha } => f.write_str(&color::format_color(Rgba {
+                r: f64::from(*r) / 255.0,
+                g: f64::from(*g) / 255.0,
+                b: f64::from(*b) / 255.0,
+                a: *alpha,
+            })),
+            Self::Quoted { value, quote } => write!(f, "{quote}{value}{quote}"),
+            Self::Keyword(text) => f.write_str(text),
+        }
+    }
+}
+
+/// 自定义函数注册表，通过 `CompileOptions::functions` 传入。
+///
+/// `register` 登记的函数全局可用；`register_plugin` 登记的函数组只有在样式表中
+/// 出现 `@plugin "name";` 后才生效。自定义函数优先于同名内置函数。
+#[derive(Clone, Default)]
+pub struct FunctionRegistry {
+    functions: HashMap<String, CustomFunction>,
+    plugins: HashMap<String, FunctionRegistry>,
+}
+
+impl FunctionRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 注册全局函数，函数名大小写不敏感。
+    pub fn register<F>(&mut self, name: &str, function: F) -> &mut Self
+    where
+        F: Fn(&[FunctionValue]) -> LessResult<Option<FunctionValue>> + Send + Sync + 'static,
+    {
+        self.functions
+            .insert(name.to_ascii_lowercase(), Arc::new(function));
+        self
+    }
+
+    /// 注册插件：`@plugin "name";` 会把 `functions` 中的函数加入当前编译。
+    pub fn register_plugin(&mut self, name: &str, functions: FunctionRegistry) -> &mut Self {
+        self.plugins.insert(name.to_string(), functions);
+        self
+    }
+
+    pub(crate) fn functions(&self) -> &HashMap<String, CustomFunction> {
+        &self.functions
+    }
+
+    /// 按 `@plugin` 中写的名称查找插件，同时兼容 `"path/to/name.js"` 形式。
+    pub(crate) fn plugin(&self, name: &str) -> Option<&FunctionRegistry> {
+        self.plugins.get(name).or_else(|| {
+            let stem = name.rsplit(['/', '\\']).next().unwrap_or(name);
+            let stem = stem.strip_suffix(".js").unwrap_or(stem);
+            self.plugins.get(stem)
+        })
+    }
+}
+
+impl fmt::Debug for FunctionRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FunctionRegistry")
+            .field("functions", &self.functions.keys().collect::<Vec<_>>())
+            .field("plugins", &self.plugins)
+            .finish()
+    }
+}
+
+static BUILTINS: Lazy<HashMap<&'static str, BuiltinFn>> = Lazy::new(|| {
+    let entries: &[(&'static str, BuiltinFn)] = &[
+        ("rgb", rgb),
+        ("rgba", rgba),
+        ("hsl", hsl),
+        ("hsla", hsla),
+        ("hsv", hsv),
+        ("hsva", hsva),
+        ("argb", argb),
+        ("lighten", lighten),
+        ("darken", darken),
+        ("saturate", saturate),
+        ("desaturate", desaturate),
+        ("spin", spin),
+        ("fade", fade),
+        ("fadein", fadein),
+        ("fadeout", fadeout),
+        ("mix", mix),
+        ("tint", tint),
+        ("shade", shade),
+        ("greyscale", greyscale),
+        ("contrast", contrast),
+        ("multiply", multiply),
+        ("screen", screen),
+        ("overlay", overlay),
+        ("softlight", softlight),
+        ("hardlight", hardlight),
+        ("difference", difference),
+        ("exclusion", exclusion),
+        ("average", average),
+        ("negation", negation),
+        ("hue", hue),
+        ("saturation", saturation),
+        ("lightness", lightness),
+        ("red", red),
+        ("green", green),
+        ("blue", blue),
+        ("alpha", alpha),
+        ("luma", luma),
+        ("ceil", ceil),
+        ("floor", floor),
+        ("round", round),
+        ("percentage", percentage),
+        ("abs", abs),
+        ("sqrt", sqrt),
+        ("pow", pow),
+        ("mod", modulo),
+        ("min", min),
+        ("max", max),
+        ("pi", pi),
+        ("unit", unit),
+        ("convert", convert),
+        ("e", escape_string),
+        ("escape", escape),
+        ("%", format),
+        ("replace", replace),
+        ("length", length),
+        ("extract", extract),
+        ("range", range),
+        ("isnumber", is_number),
+        ("isstring", is_string),
+        ("iscolor", is_color),
+        ("iskeyword", is_keyword),
+        ("isurl", is_url),
+        ("ispixel", is_pixel),
+        ("isem", is_em),
+        ("ispercentage", is_percentage),
+        ("isunit", is_unit),
+    ];
+    entries.iter().copied().collect()
+});
+
+/// 按名称（大小写不敏感）查找内置函数。
+pub fn lookup(name: &str) -> Option<BuiltinFn> {
+    BUILTINS.get(name.to_ascii_lowercase().as_str()).copied()
+}
+
+/// CSS 原生函数（不含 LESS 内置函数已覆盖的名称），求值时原样输出。
+const CSS_FUNCTIONS: &[&str] = &[
+    "calc",
+    "clamp",
+    "var",
+    "env",
+    "attr",
+    "url",
+    "src",
+    "image",
+    "image-set",
+    "cross-fade",
+    "element",
+    "paint",
+    "linear-gradient",
+    "radial-gradient",
+    "conic-gradient",
+    "repeating-linear-gradient",
+    "repeating-radial-gradient",
+    "repeating-conic-gradient",
+    "hwb",
+    "lab",
+    "lch",
+    "oklab",
+    "oklch",
+    "color",
+    "color-mix",
+    "light-dark",
+    "device-cmyk",
+    "translate",
+    "translatex",
+    "translatey",
+    "translatez",
+    "translate3d",
+    "scale",
+    "scalex",
+    "scaley",
+    "scalez",
+    "scale3d",
+    "rotate",
+    "rotatex",
+    "rotatey",
+    "rotatez",
+    "rotate3d",
+    "skew",
+    "skewx",
+    "skewy",
+    "matrix",
+    "matrix3d",
+    "perspective",
+    "blur",
+    "brightness",
+    "drop-shadow",
+    "grayscale",
+    "hue-rotate",
+    "invert",
+    "opacity",
+    "sepia",
+    "cubic-bezier",
+    "steps",
+    "linear",
+    "repeat",
+    "minmax",
+    "fit-content",
+    "counter",
+    "counters",
+    "format",
+    "local",
+    "tech",
+    "selector",
+    "supports",
+    "layer",
+    "symbols",
+    "path",
+    "polygon",
+    "circle",
+    "ellipse",
+    "inset",
+    "xywh",
+    "rect",
+    "ray",
+    "shape",
+    "rem",
+    "sin",
+    "cos",
+    "tan",
+    "asin",
+    "acos",
+    "atan",
+    "atan2",
+    "hypot",
+    "log",
+    "exp",
+    "sign",
+    "anchor",
+    "anchor-size",
+    "view",
+    "scroll",
+    "string",
+    "leader",
+    "target-counter",
+    "target-counters",
+    "target-text",
+    "content",
+    "running",
+    "expression",
+];
+
+/// 是否为 CSS 原生函数；`-webkit-` 等厂商前缀不影响判断。
+pub fn is_css_function(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    let unprefixed = match name.strip_prefix('-') {
+        Some(rest) => rest.split_once('-').map_or(rest, |(_, name)| name),
+        None => &name,
+    };
+    CSS_FUNCTIONS.contains(&unprefixed)
+}
+
+fn rgb(args: &[String]) -> LessResult<Option<String>> {
+    if args.len() != 3 {
+        return Ok(None);
+    }
+    construct_rgb(args, None)
+}
+
+fn rgba(args: &[String]) -> LessResult<Option<String>> {
+    match args.len() {
+        // `rgba(@color, 0.5)` 形式：沿用颜色并替换透明度。
+        2 => {
+            let (Some(color), Some(alpha)) = (color::parse_color(&args[0]), parse_unit(&args[1]))
+            else {
+                return Ok(None);
+            };
+            Ok(Some(color::format_color(color::fade(color, alpha))))
+        }
+        4 => construct_rgb(&args[..3], Some(&args[3])),
+        _ => Ok(None),
+    }
+}
+
+fn construct_rgb(channels: &[String], alpha: Option<&String>) -> LessResult<Option<String>> {
+    let mut values = [0.0; 3];
+    for (slot, raw) in values.iter_mut().zip(channels) {
+        let Some(value) = parse_channel(raw) else {
+            return Ok(None);
+        };
+        *slot = value;
+    }
+    let a = match alpha {
+        Some(raw) => match parse_unit(raw) {
+            Some(a) => a,
+            None => return Ok(None),
+        },
+        None => 1.0,
+    };
+    let [r, g, b] = values;
+    Ok(Some(color::format_color(Rgba { r, g, b, a })))
+}
+
+fn hsl(args: &[String]) -> LessResult<Option<String>> {
+    if args.len() != 3 {
+        return Ok(None);
+    }
+    construct_hue_based(args, None, color::from_hsl)
+}
+
+fn hsla(args: &[String]) -> LessResult<Option<String>> {
+    if args.len() != 4 {
+        return Ok(None);
+    }
+    construct_hue_based(&args[..3], Some(&args[3]), color::from_hsl)
+}
+
+fn hsv(args: &[String]) -> LessResult<Option<String>> {
+    if args.len() != 3 {
+        return Ok(None);
+    }
+    construct_hue_based(args, None, color::from_hsv)
+}
+
+fn hsva(args: &[String]) -> LessResult<Option<String>> {
+    if args.len() != 4 {
+        return Ok(None);
+    }
+    construct_hue_based(&args[..3], Some(&args[3]), color::from_hsv)
+}
+
+fn construct_hue_based(
+    components: &[String],
+    alpha: Option<&String>,
+    build: fn(f64, f64, f64, f64) -> Rgba,
+) -> LessResult<Option<String>> {
+    let hue = parse_number(&components[0]).ok();
+    let second = parse_unit(&components[1]);
+    let third = parse_unit(&components[2]);
+    let a = alpha.map_or(Some(1.0), |raw| parse_unit(raw));
+    match (hue, second, third, a) {
+        (Some(h), Some(s), Some(l), Some(a)) => Ok(Some(color::format_color(build(h, s, l, a)))),
+        _ => Ok(None),
+    }
+}
+
+fn argb(args: &[String]) -> LessResult<Option<String>> {
+    expect_args("argb", args, 1)?;
+    let color = parse_color_arg(&args[0])?;
+    Ok(Some(color::format_argb(color)))
+}
+
+fn lighten(args: &[String]) -> LessResult<Option<String>> {
+    expect_args("lighten", args, 2)?;
+    let color = parse_color_arg(&args[0])?;
+    let amount = parse_percentage(&args[1])?;
+    Ok(Some(color::format_hex(color::lighten(color, amount))))
+}
+
+fn darken(args: &[String]) -> LessResult<Option<String>> {
+    expect_args("darken", args, 2)?;
+    let color = parse_color_arg(&args[0])?;
+    let amount = parse_percentage(&args[1])?;
+    Ok(Some(color::format_hex(color::darken(color, amount))))
+}
+
+fn saturate(args: &[String]) -> LessResult<Option<String>> {
+    expect_args("saturate", args, 2)?;
+    let color = parse_color_arg(&args[0])?;
+    let amount = parse_percentage(&args[1])?;
+    Ok(Some(color::format_color(color::saturate(color, amount))))
+}
+
+fn desaturate(args: &[String]) -> LessResult<Option<String>> {
+    expect_args("desaturate", args, 2)?;
+    let color = parse_color_arg(&args[0])?;
+    let amount = parse_percentage(&args[1])?;
+    Ok(Some(color::format_color(color::desaturate(color, amount))))
+}
+
+fn spin(args: &[String]) -> LessResult<Option<String>> {
+    expect_args("spin", args, 2)?;
+    let color = parse_color_arg(&args[0])?;
+    let degrees = parse_number(&args[1])?;
+    Ok(Some(color::format_color(color::spin(color, degrees))))
+}
+
+fn fade(args: &[String]) -> LessResult<Option<String>> {
+    expect_args("fade", args, 2)?;
+    let color = parse_color_arg(&args[0])?;
+    let amount = parse_percentage(&args[1])?;
+    Ok(Some(color::format_rgba(color::fade(color, amount))))
+}
+
+fn fadein(args: &[String]) -> LessResult<Option<String>> {
+    expect_args("fadein", args, 2)?;
+    let color = parse_color_arg(&args[0])?;
+    let amount = parse_percentage(&args[1])?;
+    Ok(Some(color::format_color(color::fade_in(color, amount))))
+}
+
+fn fadeout(args: &[String]) -> LessResult<Option<String>> {
+    expect_args("fadeout", args, 2)?;
+    let color = parse_color_arg(&args[0])?;
+    let amount = parse_percentage(&args[1])?;
+    Ok(Some(color::format_color(color::fade_out(color, amount))))
+}
+
+fn mix(args: &[String]) -> LessResult<Option<String>> {
+    expect_arg_range("mix", args, 2, 3)?;
+    let first = parse_color_arg(&args[0])?;
+    let second = parse_color_arg(&args[1])?;
+    let weight = optional_percentage(args.get(2), 0.5)?;
+    Ok(Some(color::format_color(color::mix(first, second, weight))))
+}
+
+fn tint(args: &[String]) -> LessResult<Option<String>> {
+    expect_arg_range("tint", args, 1, 2)?;
+    let color = parse_color_arg(&args[0])?;
+    let amount = optional_percentage(args.get(1), 0.5)?;
+    Ok(Some(color::format_color(color::tint(color, amount))))
+}
+
+fn shade(args: &[String]) -> LessResult<Option<String>> {
+    expect_arg_range("shade", args, 1, 2)?;
+    let color = parse_color_arg(&args[0])?;
+    let amount = optional_percentage(args.get(1), 0.5)?;
+    Ok(Some(color::format_color(color::shade(color, amount))))
+}
+
+fn greyscale(args: &[String]) -> LessResult<Option<String>> {
+    expect_args("greyscale", args, 1)?;
+    let color = parse_color_arg(&args[0])?;
+    Ok(Some(color::format_color(color::greyscale(color))))
+}
+
+fn contrast(args: &[String]) -> LessResult<Option<String>> {
+    expect_arg_range("contrast", args, 1, 4)?;
+    let Some(color) = color::parse_color(&args[0]) else {
+        // less.js 对非颜色参数原样返回。
+        return Ok(None);
+    };
+    let dark = match args.get(1) {
+        Some(raw) => parse_color_arg(raw)?,
+        None => color::parse_color("#000").unwrap(),
+    };
+    let light = match args.get(2) {
+        Some(raw) => parse_color_arg(raw)?,
+        None => color::parse_color("#fff").unwrap(),
+    };
+    let threshold = optional_percentage(args.get(3), 0.43)?;
+    Ok(Some(color::format_color(color::contrast(
+        color, dark, light, threshold,
+    ))))
+}
+
+/// 混合模式函数的公共部分：解析两个颜色参数，按 `mode` 混合后输出。
+fn blend(name: &str, args: &[String], mode: fn(Rgba, Rgba) -> Rgba) -> LessResult<Option<String>> {
+    if args.len() < 2 {
+        return Err(LessError::eval(format!("{name} 函数参数不完整")));
+    }
+    let backdrop = parse_color_arg(&args[0])?;
+    let source = parse_color_arg(&args[1])?;
+    Ok(Some(color::format_color(mode(backdrop, source))))
+}
+
+fn multiply(args: &[String]) -> LessResult<Option<String>> {
+    blend("multiply", args, color::multiply)
+}
+
+fn screen(args: &[String]) -> LessResult<Option<String>> {
+    blend("screen", args, color::screen)
+}
+
+fn overlay(args: &[String]) -> LessResult<Option<String>> {
+    blend("overlay", args, color::overlay)
+}
+
+fn softlight(args: &[String]) -> LessResult<Option<String>> {
+    blend("softlight", args, color::softlight)
+}
+
+fn hardlight(args: &[String]) -> LessResult<Option<String>> {
+    blend("hardlight", args, color::hardlight)
+}
+
+fn difference(args: &[String]) -> LessResult<Option<String>> {
+    blend("difference", args, color::difference)
+}
+
+fn exclusion(args: &[String]) -> LessResult<Option<String>> {
+    blend("exclusion", args, color::exclusion)
+}
+
+fn average(args: &[String]) -> LessResult<Option<String>> {
+    blend("average", args, color::average)
+}
+
+fn negation(args: &[String]) -> LessResult<Option<String>> {
+    blend("negation", args, color::negation)
+}
+
+fn hue(args: &[String]) -> LessResult<Option<String>> {
+    channel("hue", args, |c| format_number(color::to_hsl(c).0))
+}
+
+fn saturation(args: &[String]) -> LessResult<Option<String>> {
+    channel("saturation", args, |c| {
+        format!("{}%", format_number(color::to_hsl(c).1 * 100.0))
+    })
+}
+
+fn lightness(args: &[String]) -> LessResult<Option<String>> {
+    channel("lightness", args, |c| {
+        format!("{}%", format_number(color::to_hsl(c).2 * 100.0))
+    })
+}
+
+fn red(args: &[String]) -> LessResult<Option<String>> {
+    channel("red", args, |c| color::channel_value(c.r).to_string())
+}
+
+fn green(args: &[String]) -> LessResult<Option<String>> {
+    channel("green", args, |c| color::channel_value(c.g).to_string())
+}
+
+fn blue(args: &[String]) -> LessResult<Option<String>> {
+    channel("blue", args, |c| color::channel_value(c.b).to_string())
+}
+
+fn alpha(args: &[String]) -> LessResult<Option<String>> {
+    channel("alpha", args, |c| format_number(c.a))
+}
+
+fn luma(args: &[String]) -> LessResult<Option<String>> {
+    channel("luma", args, |c| {
+        format!("{}%", format_number(color::luma(c) * c.a * 100.0))
+    })
+}
+
+fn ceil(args: &[String]) -> LessResult<Option<String>> {
+    map_dimension(args, f64::ceil)
+}
+
+fn floor(args: &[String]) -> LessResult<Option<String>> {
+    map_dimension(args, f64::floor)
+}
+
+fn round(args: &[String]) -> LessResult<Option<String>> {
+    let (value, places) = match args {
+        [value] => (parse_dimension(value), Some(0.0)),
+        [value, places] => (
+            parse_dimension(value),
+            parse_dimension(places).map(|q| q.value),
+        ),
+        _ => return Ok(None),
+    };
+    let (Some(value), Some(places)) = (value, places) else {
+        return Ok(None);
+    };
+    let factor = 10f64.powi(places.max(0.0) as i32);
+    Ok(Some(format_quantity(&Quantity {
+        value: (value.value * factor).round() / factor,
+        unit: value.unit,
+    })))
+}
+
+fn percentage(args: &[String]) -> LessResult<Option<String>> {
+    let [raw] = args else {
+        return Ok(None);
+    };
+    Ok(parse_dimension(raw).map(|value| format!("{}%", format_number(value.value * 100.0))))
+}
+
+fn abs(args: &[String]) -> LessResult<Option<String>> {
+    map_dimension(args, f64::abs)
+}
+
+fn sqrt(args: &[String]) -> LessResult<Option<String>> {
+    map_dimension(args, f64::sqrt)
+}
+
+fn pow(args: &[String]) -> LessResult<Option<String>> {
+    combine_dimensions(args, f64::powf)
+}
+
+fn modulo(args: &[String]) -> LessResult<Option<String>> {
+    let [_, divisor] = args else {
+        return Ok(None);
+    };
+    if parse_dimension(divisor).is_some_and(|q| q.value == 0.0) {
+        return Err(LessError::eval("mod 函数的除数不能为 0".to_string()));
+    }
+    combine_dimensions(args, |lhs, rhs| lhs % rhs)
+}
+
+fn min(args: &[String]) -> LessResult<Option<String>> {
+    pick_dimension(args, |candidate, best| candidate < best)
+}
+
+fn max(args: &[String]) -> LessResult<Option<String>> {
+    pick_dimension(args, |candidate, best| candidate > best)
+}
+
+fn pi(args: &[String]) -> LessResult<Option<String>> {
+    expect_args("pi", args, 0)?;
+    Ok(Some(format_number(std::f64::consts::PI)))
+}
+
+/// `unit(5, px)` 替换单位，`unit(5px)` 去掉单位。
+fn unit(args: &[String]) -> LessResult<Option<String>> {
+    expect_arg_range("unit", args, 1, 2)?;
+    let Some(quantity) = parse_dimension(&args[0]) else {
+        return Ok(None);
+    };
+    let unit = args.get(1).map_or("", |raw| string_content(raw));
+    Ok(Some(format_quantity(&Quantity {
+        value: quantity.value,
+        unit: Unit::from(unit),
+    })))
+}
+
+/// `convert(9s, ms)`：同组单位之间换算，无法换算时原值返回（与 lessc 一致）。
+fn convert(args: &[String]) -> LessResult<Option<String>> {
+    expect_args("convert", args, 2)?;
+    let Some(quantity) = parse_dimension(&args[0]) else {
+        return Ok(None);
+    };
+    let target = string_content(&args[1]);
+    let converted = match units::convert(quantity.value, quantity.unit.name(), target) {
+        Some(value) if !quantity.unit.is_empty() => Quantity {
+            value,
+            unit: Unit::from(target),
+        },
+        _ => quantity,
+    };
+    Ok(Some(format_quantity(&converted)))
+}
+
+/// 对单个数值参数做运算并保留其单位。
+fn map_dimension(args: &[String], op: fn(f64) -> f64) -> LessResult<Option<String>> {
+    let [raw] = args else {
+        return Ok(None);
+    };
+    Ok(parse_dimension(raw).map(|quantity| {
+        format_quantity(&Quantity {
+            value: op(quantity.value),
+            unit: quantity.unit,
+        })
+    }))
+}
+
+/// 两个数值参数的运算，结果沿用第一个参数的单位（与 lessc 一致）。
+fn combine_dimensions(args: &[String], op: fn(f64, f64) -> f64) -> LessResult<Option<String>> {
+    let [lhs, rhs] = args else {
+        return Ok(None);
+    };
+    let (Some(lhs), Some(rhs)) = (parse_dimension(lhs), parse_dimension(rhs)) else {
+        return Ok(None);
+    };
+    Ok(Some(format_quantity(&Quantity {
+        value: op(lhs.value, rhs.value),
+        unit: lhs.unit,
+    })))
+}
+
+/// min/max：单位不一致或含非数值参数时无法比较，交给浏览器按 CSS 原生函数处理。
+fn pick_dimension(args: &[String], better: fn(f64, f64) -> bool) -> LessResult<Option<String>> {
+    let mut best: Option<Quantity> = None;
+    let mut unit = Unit::default();
+    for raw in args {
+        let Some(candidate) = parse_dimension(raw) else {
+            return Ok(None);
+        };
+        if !candidate.unit.is_empty() {
+            if !unit.is_empty() && unit != candidate.unit {
+                return Ok(None);
+            }
+            unit = candidate.unit.clone();
+        }
+        if best
+            .as_ref()
+            .is_none_or(|current| better(candidate.value, current.value))
+        {
+            best = Some(candidate);
+        }
+    }
+    Ok(best.map(|quantity| format_quantity(&quantity)))
+}
+
+/// `e("...")`：结果与 `~"..."` 一样带转义标记，内容不参与算术运算，标记由求值器输出时去除。
+fn escape_string(args: &[String]) -> LessResult<Option<String>> {
+    expect_args("e", args, 1)?;
+    Ok(Some(match QuotedString::parse(&args[0]) {
+        Some(quoted) => format!("~{quote}{}{quote}", quoted.content, quote = quoted.quote),
+        None => args[0].trim().to_string(),
+    }))
+}
+
+fn escape(args: &[String]) -> LessResult<Option<String>> {
+    expect_args("escape", args, 1)?;
+    Ok(Some(percent_encode(
+        string_content(&args[0]),
+        "-_.!~*'/?@&+$,",
+    )))
+}
+
+/// `%("%d px", 10)`：依次替换 `%d`/`%a`/`%s` 占位符，大写形式额外做 URL 编码。
+fn format(args: &[String]) -> LessResult<Option<String>> {
+    let Some((template, rest)) = args.split_first() else {
+        return Err(LessError::eval("函数 % 至少需要 1 个参数".to_string()));
+    };
+    let quoted = QuotedString::parse(template);
+    let mut result = quoted
+        .as_ref()
+        .map_or(template.as_str(), |q| q.content)
+        .to_string();
+    for arg in rest {
+        let Some(pos) = find_placeholder(&result) else {
+            break;
+        };
+        let token = result.as_bytes()[pos + 1] as char;
+        let value = if token.eq_ignore_ascii_case(&'s') {
+            string_content(arg)
+        } else {
+            arg.as_str()
+        };
+        let value = if token.is_ascii_uppercase() {
+            percent_encode(value, "-_.!~*'()")
+        } else {
+            value.to_string()
+        };
+        result.replace_range(pos..pos + 2, &value);
+    }
+    let result = result.replace("%%", "%");
+    Ok(Some(match quoted {
+        Some(q) => q.with_content(&result),
+        None => result,
+    }))
+}
+
+/// `replace(string, pattern, replacement[, flags])`，正则语义与 less.js 相同，
+/// 字符串参数保留原有引号。
+fn replace(args: &[String]) -> LessResult<Option<String>> {
+    expect_arg_range("replace", args, 3, 4)?;
+    let flags = args.get(3).map_or("", |raw| string_content(raw));
+    let pattern = RegexBuilder::new(string_content(&args[1]))
+        .case_insensitive(flags.contains('i'))
+        .multi_line(flags.contains('m'))
+        .build()
+        .map_err(|err| LessError::eval(format!("replace 函数的正则无效: {err}")))?;
+    let replacement = string_content(&args[2]);
+    let quoted = QuotedString::parse(&args[0]);
+    let subject = quoted.as_ref().map_or(args[0].as_str(), |q| q.content);
+    let replaced = if flags.contains('g') {
+        pattern.replace_all(subject, replacement)
+    } else {
+        pattern.replace(subject, replacement)
+    };
+    Ok(Some(match quoted {
+        Some(q) => q.with_content(&replaced),
+        None => replaced.into_owned(),
+    }))
+}
+
+fn length(args: &[String]) -> LessResult<Option<String>> {
+    Ok(Some(list_items(args).len().to_string()))
+}
+
+/// `extract(@list, index)`：列表变量展开后按逗号拆成多个参数，最后一个参数是 1 起始的下标。
+fn extract(args: &[String]) -> LessResult<Option<String>> {
+    let Some((index, list)) = args.split_last() else {
+        return Err(LessError::eval("函数 extract 需要 2 个参数".to_string()));
+    };
+    let Some(index) = parse_dimension(index).filter(|q| q.unit.is_empty()) else {
+        return Ok(None);
+    };
+    let items = list_items(list);
+    let position = index.value as usize;
+    if index.value.fract() != 0.0 || position == 0 || position > items.len() {
+        return Ok(None);
+    }
+    Ok(Some(items[position - 1].clone()))
+}
+
+/// `range()` 单次调用最多生成的项数。
+const MAX_RANGE_ITEMS: usize = 10_000;
+
+/// `range([start,] end[, step])`，生成以空格分隔的数值列表，单位取自 `end`。
+fn range(args: &[String]) -> LessResult<Option<String>> {
+    expect_arg_range("range", args, 1, 3)?;
+    let mut numbers = Vec::with_capacity(args.len());
+    for raw in args {
+        let Some(quantity) = parse_dimension(raw) else {
+            return Ok(None);
+        };
+        numbers.push(quantity);
+    }
+    let (start, end, step) = match numbers.as_slice() {
+        [end] => (1.0, end, 1.0),
+        [start, end] => (start.value, end, 1.0),
+        [start, end, step] => (start.value, end, step.value),
+        _ => unreachable!(),
+    };
+    if !step.is_finite() || step <= 0.0 {
+        return Err(LessError::eval(
+            "range 函数的步长必须是大于 0 的有限数".to_string(),
+        ));
+    }
+    if !start.is_finite() || !end.value.is_finite() {
+        return Err(LessError::eval(
+            "range 函数的起止值必须是有限数".to_string(),
+        ));
+    }
+    // 先算出项数再按下标生成，避免浮点累加卡在原地或巨大区间导致死循环。
+    let span = (end.value - start) / step;
+    if span > MAX_RANGE_ITEMS as f64 {
+        return Err(LessError::eval(format!(
+            "range 函数最多生成 {MAX_RANGE_ITEMS} 项"
+        )));
+    }
+    let count = if span < -1e-9 {
+        0
+    } else {
+        (span + 1e-9).floor() as usize + 1
+    };
+    let items: Vec<String> = (0..count)
+        .map(|idx| {
+            format_quantity(&Quantity {
+                value: start + idx as f64 * step,
+                unit: end.unit.clone(),
+            })
+        })
+        .collect();
+    Ok(Some(items.join(" ")))
+}
+
+/// 列表参数：多个参数即逗号列表，单个参数则按顶层空白拆分。
+pub(crate) fn list_items(args: &[String]) -> Vec<String> {
+    match args {
+        [] => Vec::new(),
+        [single] => split_top_level_spaces(single),
+        _ => args.to_vec(),
+    }
+}
+
+fn split_top_level_spaces(input: &str) -> Vec<String> {
+    let mut items = Vec::new();
+    let mut current = String::new();
+    let mut depth = 0usize;
+    let mut quote: Option<char> = None;
+    for ch in input.trim().chars() {
+        if let Some(q) = quote {
+            if ch == q {
+                quote = None;
+            }
+            current.push(ch);
+            continue;
+        }
+        match ch {
+            '"' | '\'' => quote = Some(ch),
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            ch if ch.is_whitespace() && depth == 0 => {
+                if !current.is_empty() {
+                    items.push(std::mem::take(&mut current));
+                }
+                continue;
+            }
+            _ => {}
+        }
+        current.push(ch);
+    }
+    if !current.is_empty() {
+        items.push(current);
+    }
+    items
+}
+
+fn is_number(args: &[String]) -> LessResult<Option<String>> {
+    type_check("isnumber", args, |raw| parse_dimension(raw).is_some())
+}
+
+fn is_string(args: &[String]) -> LessResult<Option<String>> {
+    type_check("isstring", args, |raw| QuotedString::parse(raw).is_some())
+}
+
+fn is_color(args: &[String]) -> LessResult<Option<String>> {
+    type_check("iscolor", args, |raw| color::parse_color(raw).is_some())
+}
+
+/// 与 less.js 一致：颜色关键字按颜色处理，不算 keyword。
+fn is_keyword(args: &[String]) -> LessResult<Option<String>> {
+    type_check("iskeyword", args, |raw| {
+        let mut chars = raw.chars();
+        chars
+            .next()
+            .is_some_and(|ch| ch.is_ascii_alphabetic() || ch == '-' || ch == '_')
+            && chars.all(|ch| ch.is_ascii_alphanumeric() || ch == '-' || ch == '_')
+            && color::named_color(&raw.to_ascii_lowercase()).is_none()
+    })
+}
+
+fn is_url(args: &[String]) -> LessResult<Option<String>> {
+    type_check("isurl", args, |raw| {
+        raw.len() > 5 && raw[..4].eq_ignore_ascii_case("url(") && raw.ends_with(')')
+    })
+}
+
+fn is_pixel(args: &[String]) -> LessResult<Option<String>> {
+    type_check("ispixel", args, |raw| has_unit(raw, "px"))
+}
+
+fn is_em(args: &[String]) -> LessResult<Option<String>> {
+    type_check("isem", args, |raw| has_unit(raw, "em"))
+}
+
+fn is_percentage(args: &[String]) -> LessResult<Option<String>> {
+    type_check("ispercentage", args, |raw| has_unit(raw, "%"))
+}
+
+fn is_unit(args: &[String]) -> LessResult<Option<String>> {
+    expect_args("isunit", args, 2)?;
+    Ok(Some(
+        has_unit(&args[0], string_content(&args[1])).to_string(),
+    ))
+}
+
+fn type_check(name: &str, args: &[String], check: fn(&str) -> bool) -> LessResult<Option<String>> {
+    expect_args(name, args, 1)?;
+    Ok(Some(check(args[0].trim()).to_string()))
+}
+
+fn has_unit(raw: &str, unit: &str) -> bool {
+    parse_dimension(raw).is_some_and(|q| q.unit.name().eq_ignore_ascii_case(unit))
+}
+
+fn channel<F>(name: &str, args: &[String], extract: F) -> LessResult<Option<String>>
+where
+    F: Fn(Rgba) -> String,
+{
+    expect_args(name, args, 1)?;
+    let color = parse_color_arg(&args[0])?;
+    Ok(Some(extract(color)))
+}
+
+fn expect_args(name: &str, args: &[String], count: usize) -> LessResult<()> {
+    expect_arg_range(name, args, count, count)
+}
+
+pub(crate) fn expect_arg_range(
+    name: &str,
+    args: &[String],
+    min: usize,
+    max: usize,
+) -> LessResult<()> {
+    if args.len() < min || args.len() > max {
+        let expected = if min == max {
+            min.to_string()
+        } else {
+            format!("{min}~{max}")
+        };
+        return Err(LessError::eval(format!(
+            "函数 {name} 参数数量不正确: 期望 {expected} 个，实际 {} 个",
+            args.len()
+        )));
+    }
+    Ok(())
+}
+
+/// 颜色参数；来自变量的 `~"red"` 等转义字符串按其内容解析。
+fn parse_color_arg(raw: &str) -> LessResult<Rgba> {
+    let escaped = QuotedString::parse(raw).filter(|quoted| quoted.escaped);
+    color::parse_color(escaped.map_or(raw, |quoted| quoted.content))
+        .ok_or_else(|| LessError::eval(format!("无法解析颜色参数: {}", raw.trim())))
+}
+
+fn parse_number(raw: &str) -> LessResult<f64> {
+    let cleaned = raw.trim();
+    let number = cleaned.strip_suffix("deg").unwrap_or(cleaned);
+    number
+        .trim()
+        .parse()
+        .map_err(|_| LessError::eval(format!("无法解析数值: {cleaned}")))
+}
+
+/// 解析 rgb 通道：`128` 按 0~255 计，`50%` 按百分比计，结果为 0~1。
+fn parse_channel(raw: &str) -> Option<f64> {
+    let cleaned = raw.trim();
+    if let Some(number) = cleaned.strip_suffix('%') {
+        let value: f64 = number.trim().parse().ok()?;
+        return Some((value / 100.0).clamp(0.0, 1.0));
+    }
+    let value: f64 = cleaned.parse().ok()?;
+    Some((value / 255.0).clamp(0.0, 1.0))
+}
+
+/// 解析 0~1 范围的分量（饱和度、亮度、透明度），同时接受百分比写法。
+fn parse_unit(raw: &str) -> Option<f64> {
+    parse_percentage(raw).ok()
+}
+
+/// 带引号的字符串参数；`escaped` 表示 `~"..."` 形式，输出时不带引号。
+struct QuotedString<'a> {
+    quote: char,
+    escaped: bool,
+    content: &'a str,
+}
+
+impl<'a> QuotedString<'a> {
+    fn parse(raw: &'a str) -> Option<Self> {
+        let trimmed = raw.trim();
+        let (escaped, inner) = match trimmed.strip_prefix('~') {
+            Some(inner) => (true, inner),
+            None => (false, trimmed),
+        };
+        let quote = inner.chars().next().filter(|ch| matches!(ch, '"' | '\''))?;
+        if inner.len() < 2 || !inner.ends_with(quote) {
+            return None;
+        }
+        Some(Self {
+            quote,
+            escaped,
+            content: &inner[1..inner.len() - 1],
+        })
+    }
+
+    fn with_content(&self, content: &str) -> String {
+        if self.escaped {
+            content.to_string()
+        } else {
+            format!("{quote}{content}{quote}", quote = self.quote)
+        }
+    }
+}
+
+/// 字符串参数去掉引号后的内容，非字符串参数原样返回。
+fn string_content(raw: &str) -> &str {
+    QuotedString::parse(raw).map_or(raw.trim(), |q| q.content)
+}
+
+fn find_placeholder(template: &str) -> Option<usize> {
+    let bytes = template.as_bytes();
+    let mut idx = 0;
+    while idx + 1 < bytes.len() {
+        if bytes[idx] == b'%' {
+            match bytes[idx + 1] {
+                b'%' => {
+                    idx += 2;
+                    continue;
+                }
+                b's' | b'S' | b'd' | b'D' | b'a' | b'A' => return Some(idx),
+                _ => {}
+            }
+        }
+        idx += 1;
+    }
+    None
+}
+
+/// 与 JS 的 `encodeURI`/`encodeURIComponent` 一致：字母数字与 `safe` 中的字符保持不变，
+/// 其余按 UTF-8 字节输出 `%XX`。
+fn percent_encode(input: &str, safe: &str) -> String {
+    let mut encoded = String::with_capacity(input.len());
+    for ch in input.chars() {
+        if ch.is_ascii_alphanumeric() || safe.contains(ch) {
+            encoded.push(ch);
+        } else {
+            let mut buf = [0u8; 4];
+            for byte in ch.encode_utf8(&mut buf).bytes() {
+                encoded.push_str(&format!("%{byte:02X}"));
+            }
+        }
+    }
+    encoded
+}
+
+/// 严格解析 `数值+单位` 形式的参数，例如 `1.5px`、`-2`、`50%`；其它文本返回 `None`。
+pub(crate) fn parse_dimension(raw: &str) -> Option<Quantity> {
+    let cleaned = raw.trim();
+    let split = cleaned
+        .find(|ch: char| ch.is_ascii_alphabetic() || ch == '%')
+        .unwrap_or(cleaned.len());
+    let (number, unit) = cleaned.split_at(split);
+    if unit != "%" && !unit.chars().all(|ch| ch.is_ascii_alphabetic()) {
+        return None;
+    }
+    let value: f64 = number.parse().ok()?;
+    Some(Quantity {
+        value,
+        unit: Unit::from(unit),
+    })
+}
+
+fn optional_percentage(raw: Option<&String>, default: f64) -> LessResult<f64> {
+    match raw {
+        Some(raw) => parse_percentage(raw),
+        None => Ok(default),
+    }
+}
+
+/// 解析百分比参数：`20%` 与 `0.2` 均表示 0.2，结果限定在 0~1。
+pub fn parse_percentage(raw: &str) -> LessResult<f64> {
+    let cleaned = raw.trim();
+    if let Some(number) = cleaned.strip_suffix('%') {
+        let number = number.trim();
+        let value: f64 = number
+            .parse()
+            .map_err(|_| LessError::eval(format!("无法解析百分比: {raw}")))?;
+        Ok((value / 100.0).clamp(0.0, 1.0))
+    } else {
+        let value: f64 = cleaned
+            .parse()
+            .map_err(|_| LessError::eval(format!("无法解析数值: {raw}")))?;
+        Ok(value.clamp(0.0, 1.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn call(name: &str, args: &[&str]) -> String {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        lookup(name).unwrap()(&args).unwrap().unwrap()
+    }
+
+    #[test]
+    fn color_operations_match_lessc() {
+        assert_eq!(call("saturate", &["#336699", "20%"]), "#1f66ad");
+        assert_eq!(call("desaturate", &["#336699", "20%"]), "#476685");
+        assert_eq!(call("spin", &["#336699", "30"]), "#333399");
+        assert_eq!(call("mix", &["#ff0000", "#0000ff", "50%"]), "#800080");
+        assert_eq!(call("tint", &["#007fff", "50%"]), "#80bfff");
+        assert_eq!(call("shade", &["#007fff", "50%"]), "#004080");
+        assert_eq!(call("greyscale", &["#336699"]), "#666666");
+        assert_eq!(
+            call("fadeout", &["rgba(0, 0, 0, 0.5)", "20%"]),
+            "rgba(0, 0, 0, 0.3)"
+        );
+        assert_eq!(call("contrast", &["#bbbbbb"]), "#000000");
+        assert_eq!(call("contrast", &["#222222"]), "#ffffff");
+    }
+
+    #[test]
+    fn color_constructors() {
+        assert_eq!(call("rgb", &["255", "0", "0"]), "#ff0000");
+        assert_eq!(call("rgb", &["100%", "50%", "0%"]), "#ff8000");
+        assert_eq!(call("rgba", &["0", "0", "0", "0.2"]), "rgba(0, 0, 0, 0.2)");
+        assert_eq!(call("rgba", &["#336699", "50%"]), "rgba(51, 102, 153, 0.5)");
+        assert_eq!(call("hsl", &["90", "100%", "50%"]), "#80ff00");
+        assert_eq!(
+            call("hsla", &["0", "100%", "50%", "0.5"]),
+            "rgba(255, 0, 0, 0.5)"
+        );
+        assert_eq!(call("hsv", &["90", "100%", "50%"]), "#408000");
+        assert_eq!(call("argb", &["rgba(90, 23, 148, 0.5)"]), "#805a1794");
+        let args = vec!["var(--r)".to_string(), "0".to_string(), "0".to_string()];
+        assert_eq!(lookup("rgb").unwrap()(&args).unwrap(), None);
+    }
+
+    #[test]
+    fn math_functions() {
+        assert_eq!(call("ceil", &["2.4px"]), "3px");
+        assert_eq!(call("floor", &["2.6"]), "2");
+        assert_eq!(call("round", &["1.67px"]), "2px");
+        assert_eq!(call("round", &["1.67", "1"]), "1.7");
+        assert_eq!(call("percentage", &["0.5"]), "50%");
+        assert_eq!(call("abs", &["-18.6%"]), "18.6%");
+        assert_eq!(call("sqrt", &["25cm"]), "5cm");
+        assert_eq!(call("pow", &["2px", "3"]), "8px");
+        assert_eq!(call("mod", &["11cm", "6px"]), "5cm");
+        assert_eq!(call("min", &["5px", "3px", "1"]), "1");
+        assert_eq!(call("max", &["5%", "10%"]), "10%");
+        assert_eq!(call("pi", &[]), "3.1416");
+        assert_eq!(call("unit", &["5", "px"]), "5px");
+        assert_eq!(call("unit", &["5em"]), "5");
+        assert_eq!(call("convert", &["9s", "\"ms\""]), "9000ms");
+        assert_eq!(call("convert", &["1in", "px"]), "96px");
+        let mixed = vec!["10px".to_string(), "5vw".to_string()];
+        assert_eq!(lookup("min").unwrap()(&mixed).unwrap(), None);
+        let keyword = vec!["auto".to_string()];
+        assert_eq!(lookup("ceil").unwrap()(&keyword).unwrap(), None);
+    }
+
+    #[test]
+    fn string_functions() {
+        assert_eq!(call("e", &["\"-ms-filter\""]), "~\"-ms-filter\"");
+        assert_eq!(
+            call("escape", &["'a=1; b=(2)#x'"]),
+            "a%3D1%3B%20b%3D%282%29%23x"
+        );
+        assert_eq!(call("%", &["\"%d px\"", "10"]), "\"10 px\"");
+        assert_eq!(
+            call(
+                "%",
+                &[
+                    "'repetitions: %S file: %s'",
+                    "1 + 2",
+                    "\"directory/file.less\""
+                ]
+            ),
+            "'repetitions: 1%20%2B%202 file: directory/file.less'"
+        );
+        assert_eq!(call("%", &["~\"100%% %a\"", "red"]), "100% red");
+        assert_eq!(
+            call(
+                "replace",
+                &["\"Hello, Mars?\"", "\"Mars\\?\"", "\"Earth!\""]
+            ),
+            "\"Hello, Earth!\""
+        );
+        assert_eq!(call("replace", &["\"bar-1\"", "'1'", "'2'"]), "\"bar-2\"");
+        assert_eq!(
+            call("replace", &["'One + one = 4'", "\"one\"", "2", "\"gi\""]),
+            "'2 + 2 = 4'"
+        );
+    }
+
+    #[test]
+    fn list_functions() {
+        assert_eq!(call("length", &["apple", "pear", "coconut"]), "3");
+        assert_eq!(call("length", &["1px solid rgb(0, 0, 0)"]), "3");
+        assert_eq!(call("extract", &["apple", "pear", "coconut", "2"]), "pear");
+        assert_eq!(call("extract", &["1px 2px 3px", "3"]), "3px");
+        assert_eq!(call("range", &["4"]), "1 2 3 4");
+        assert_eq!(call("range", &["10px", "30px", "10"]), "10px 20px 30px");
+        assert_eq!(call("range", &["0", "1", "0.25"]), "0 0.25 0.5 0.75 1");
+        let range_fn = lookup("range").unwrap();
+        for args in [
+            &["1000000000000"][..],
+            &["0", "10", "0"],
+            &["0", "1", "0.0000001"],
+        ] {
+            let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+            assert!(range_fn(&args).is_err(), "{args:?}");
+        }
+        let out_of_range = vec!["a".to_string(), "b".to_string(), "5".to_string()];
+        assert_eq!(lookup("extract").unwrap()(&out_of_range).unwrap(), None);
+    }
+
+    #[test]
+    fn type_checks() {
+        assert_eq!(call("isnumber", &["1234"]), "true");
+        assert_eq!(call("isnumber", &["56px"]), "true");
+        assert_eq!(call("isnumber", &["#ff0"]), "false");
+        assert_eq!(call("isstring", &["\"string\""]), "true");
+        assert_eq!(call("isstring", &["keyword"]), "false");
+        assert_eq!(call("iscolor", &["#ff0"]), "true");
+        assert_eq!(call("iscolor", &["blue"]), "true");
+        assert_eq!(call("iscolor", &["keyword"]), "false");
+        assert_eq!(call("iskeyword", &["keyword"]), "true");
+        assert_eq!(call("iskeyword", &["blue"]), "false");
+        assert_eq!(call("isurl", &["url(a.png)"]), "true");
+        assert_eq!(call("isurl", &["\"a.png\""]), "false");
+        assert_eq!(call("ispixel", &["56px"]), "true");
+        assert_eq!(call("ispixel", &["7.8%"]), "false");
+        assert_eq!(call("isem", &["7.8em"]), "true");
+        assert_eq!(call("ispercentage", &["7.8%"]), "true");
+        assert_eq!(call("isunit", &["11px", "px"]), "true");
+        assert_eq!(call("isunit", &["2.2%", "\"%\""]), "true");
+        assert_eq!(call("isunit", &["56px", "em"]), "false");
+    }
+
+    #[test]
+    fn channel_extractors() {
+        assert_eq!(call("hue", &["#336699"]), "210");
+        assert_eq!(call("saturation", &["#336699"]), "50%");
+        assert_eq!(call("lightness", &["#336699"]), "40%");
+        assert_eq!(call("red", &["#336699"]), "51");
+        assert_eq!(call("alpha", &["rgba(0, 0, 0, 0.4)"]), "0.4");
+        assert_eq!(call("luma", &["#ffffff"]), "100%");
+    }
+
+    #[test]
+    fn function_values_round_trip() {
+        for text in [
+            "12px",
+            "-0.5",
+            "50%",
+            "#336699",
+            "rgba(0, 0, 0, 0.4)",
+            "'a'",
+            "red",
+            "a b",
+        ] {
+            assert_eq!(FunctionValue::parse(text).to_string(), text);
+        }
+        assert_eq!(
+            FunctionValue::parse(" 1.5em "),
+            FunctionValue::Dimension {
+                value: 1.5,
+                unit: "em".to_string()
+            }
+        );
+        assert_eq!(
+            FunctionValue::parse("#f00"),
+            FunctionValue::Color {
+                r: 255,
+                g: 0,
+                b: 0,
+                alpha: 1.0
+            }
+        );
+        assert_eq!(
+            FunctionValue::parse("~\"x\""),
+            FunctionValue::Keyword("~\"x\"".to_string())
+        );
+    }
+}
@@ -1,24 +1,287 @@
-use crate::ast::{Statement, Stylesheet};
+use crate::ast::{ImportStatement, RuleBody, Span, Statement, Stylesheet, VariableInit};
 use crate::error::{LessError, LessResult};
 use crate::parser::LessParser;
-use std::collections::HashMap;
+use crate::stack;
+use crate::stats::FileStats;
+use crate::{CompileOptions, DEFAULT_MAX_IMPORT_DEPTH};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 use std::fs;
+use std::hash::{Hash, Hasher};
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
+#[cfg(any(feature = "node", test))]
+use std::sync::{mpsc, Mutex, PoisonError};
+#[cfg(any(feature = "node", test))]
+use std::time::Duration;
+use std::time::Instant;
+#[cfg(any(feature = "node", test))]
+use std::{panic, thread};
+
+/// `@import` 的文件来源。默认 [`FsImportLoader`] 读取真实文件系统，
+/// 打包工具或内存构建环境可自行实现以提供虚拟文件。
+pub trait ImportLoader: Send + Sync {
+    /// 判断候选路径是否存在，存在时返回用于缓存与去重的规范路径。
+    fn resolve(&self, candidate: &Path) -> Option<PathBuf>;
+    /// 读取 `resolve` 返回路径对应的源码。
+    fn load(&self, path: &Path) -> LessResult<String>;
+    /// 在常规路径查找之前调用，可将别名（如 `~antd/lib/style`）直接映射为文件；
+    /// `from` 为发起导入的文件，入口源码中的导入为 `None`。返回 `Ok(None)` 时继续常规查找；
+    /// 返回错误时编译失败，`(optional)` 导入也不例外。
+    fn resolve_import(&self, target: &str, from: Option<&Path>) -> LessResult<Option<PathBuf>> {
+        let _ = (target, from);
+        Ok(None)
+    }
+}
+
+impl fmt::Debug for dyn ImportLoader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ImportLoader")
+    }
+}
+
+/// 基于 `std::fs` 的默认加载器。
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FsImportLoader;
+
+impl ImportLoader for FsImportLoader {
+    fn resolve(&self, candidate: &Path) -> Option<PathBuf> {
+        if !candidate.is_file() {
+            return None;
+        }
+        Some(
+            candidate
+                .canonicalize()
+                .unwrap_or_else(|_| candidate.to_path_buf()),
+        )
+    }
+
+    fn load(&self, path: &Path) -> LessResult<String> {
+        fs::read_to_string(path)
+            .map_err(|err| LessError::eval(format!("读取文件 {} 失败: {err}", path.display())))
+    }
+}
+
+/// 导入回调的解析结果：用于缓存与去重的路径及其源码；`Ok(None)` 表示交给常规路径查找。
+#[cfg(any(feature = "node", test))]
+pub(crate) type Resolution = LessResult<Option<(PathBuf, String)>>;
+
+/// 以“导入路径 → 路径与源码”回调实现的加载器：回调返回的源码暂存起来供 `load` 读取，
+/// 回调未处理的导入回退到文件系统。Node 层的 `resolveImport` 经此接入。
+#[cfg(any(feature = "node", test))]
+pub(crate) struct CallbackImportLoader<F> {
+    resolve: F,
+    sources: Mutex<HashMap<PathBuf, String>>,
+}
+
+#[cfg(any(feature = "node", test))]
+impl<F> CallbackImportLoader<F>
+where
+    F: Fn(&str, Option<&Path>) -> Resolution + Send + Sync,
+{
+    pub(crate) fn new(resolve: F) -> Self {
+        Self {
+            resolve,
+            sources: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+#[cfg(any(feature = "node", test))]
+impl<F> ImportLoader for CallbackImportLoader<F>
+where
+    F: Fn(&str, Option<&Path>) -> Resolution + Send + Sync,
+{
+    fn resolve_import(&self, target: &str, from: Option<&Path>) -> LessResult<Option<PathBuf>> {
+        let Some((path, contents)) = (self.resolve)(target, from)? else {
+            return Ok(None);
+        };
+        self.sources
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(path.clone(), contents);
+        Ok(Some(path))
+    }
+
+    fn resolve(&self, candidate: &Path) -> Option<PathBuf> {
+        FsImportLoader.resolve(candidate)
+    }
+
+    fn load(&self, path: &Path) -> LessResult<String> {
+        let cached = self
+            .sources
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(path)
+            .cloned();
+        match cached {
+            Some(content) => Ok(content),
+            None => FsImportLoader.load(path),
+        }
+    }
+}
+
+/// 让只能在当前线程调用的导入回调（如同步编译时的 JS 函数）参与编译。
+///
+/// `compile` 在新线程中执行，它拿到的加载器把每次导入请求转发回当前线程，由当前线程调用
+/// `resolve` 后回复；因此即使编译为深层嵌套的输入另开线程（见 [`stack::grow`]），
+/// 回调也只会在调用方线程上运行，`resolve` 本身无需 `Send`。
+#[cfg(any(feature = "node", test))]
+pub(crate) fn with_local_resolver<T: Send>(
+    mut resolve: impl FnMut(&str, Option<&Path>) -> Resolution,
+    compile: impl FnOnce(Arc<dyn ImportLoader>) -> T + Send,
+) -> T {
+    type Request = (String, Option<PathBuf>, mpsc::Sender<Resolution>);
+    let (sender, requests) = mpsc::channel::<Request>();
+    let loader = CallbackImportLoader::new(move |target: &str, from: Option<&Path>| {
+        let closed = || LessError::eval(format!("导入回调已结束，无法解析 {target}"));
+        let (reply, response) = mpsc::channel();
+        let request = (target.to_string(), from.map(Path::to_path_buf), reply);
+        sender.send(request).map_err(|_| closed())?;
+        response.recv().map_err(|_| closed())?
+    });
+    thread::scope(|scope| {
+        let worker = scope.spawn(move || compile(Arc::new(loader)));
+        loop {
+            match requests.recv_timeout(Duration::from_millis(50)) {
+                Ok((target, from, reply)) => {
+                    let _ = reply.send(resolve(&target, from.as_deref()));
+                }
+                // 编译结束后加载器随之释放，通道随即断开；超时只用于防止加载器被意外保留。
+                Err(mpsc::RecvTimeoutError::Timeout) if !worker.is_finished() => {}
+                Err(_) => break,
+            }
+        }
+        worker
+            .join()
+            .unwrap_or_else(|payload| panic::resume_unwind(payload))
+    })
+}
+
+/// 跨多次编译复用的解析缓存：按路径保存 AST 与源码哈希，内容未变时跳过重新解析。
+#[derive(Debug, Default)]
+pub(crate) struct ParseCache {
+    entries: HashMap<PathBuf, CachedStylesheet>,
+}
+
+#[derive(Debug)]
+struct CachedStylesheet {
+    fingerprint: u64,
+    /// 嵌套超过 [`stack::INLINE_DEPTH`] 的文件不保存 AST：克隆与释放深层 AST 同样需要
+    /// 加大的栈空间，每次编译时重新解析即可。
+    stylesheet: Option<Stylesheet>,
+    /// 原始源码，用于在错误信息中展示出错行。
+    source: String,
+    /// 源码的最大嵌套深度，供求值阶段预留栈空间。
+    depth: usize,
+}
+
+impl ParseCache {
+    /// 返回 `content` 对应的 AST，仅在源码哈希变化时重新解析。
+    pub(crate) fn parse(
+        &mut self,
+        parser: &LessParser,
+        path: &Path,
+        content: &str,
+    ) -> LessResult<Stylesheet> {
+        let mut hasher = DefaultHasher::new();
+        content.hash(&mut hasher);
+        let fingerprint = hasher.finish();
+        if let Some(cached) = self.entries.get(path) {
+            if let Some(stylesheet) = cached
+                .stylesheet
+                .as_ref()
+                .filter(|_| cached.fingerprint == fingerprint)
+            {
+                return Ok(stylesheet.clone());
+            }
+        }
+        let recovered = parser.error_count();
+        let stylesheet = parser.parse_file(content, path)?;
+        let depth = stack::nesting_depth(content);
+        // 容错解析得到的不完整 AST 不缓存，保证下次编译仍能报告同样的错误。
+        let reusable = parser.error_count() == recovered && depth <= stack::INLINE_DEPTH;
+        self.entries.insert(
+            path.to_path_buf(),
+            CachedStylesheet {
+                fingerprint,
+                stylesheet: reusable.then(|| stylesheet.clone()),
+                source: content.to_string(),
+                depth,
+            },
+        );
+        Ok(stylesheet)
+    }
+
+    fn get(&self, path: &Path) -> Option<&Stylesheet> {
+        self.entries
+            .get(path)
+            .and_then(|cached| cached.stylesheet.as_ref())
+    }
+
+    /// 已解析文件的最大嵌套深度，未知时为 0。
+    pub(crate) fn nesting_depth(&self, path: &Path) -> usize {
+        self.entries.get(path).map_or(0, |cached| cached.depth)
+    }
+
+    pub(crate) fn source(&self, path: &Path) -> Option<&str> {
+        self.entries.get(path).map(|cached| cached.source.as_str())
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
 
 pub struct ImportResolver<'a> {
     parser: &'a LessParser,
+    loader: Arc<dyn ImportLoader>,
+    #[cfg(feature = "http")]
+    remote: crate::remote::RemoteOptions,
     include_paths: Vec<PathBuf>,
-    cache: HashMap<PathBuf, Stylesheet>,
+    cache: &'a mut ParseCache,
+    /// 本次编译中已确认内容最新的文件，重复导入时无需再次读取。
+    loaded: HashSet<PathBuf>,
+    /// 正在展开的文件链，首项为入口文件（如有），用于检测并报告循环导入。
     stack: Vec<PathBuf>,
+    /// `@import` 嵌套的最大层数；`stack` 中的入口文件（`has_entry`）不计入层数。
+    max_depth: usize,
+    has_entry: bool,
+    /// 已展开过的文件（规范化路径），默认据此跳过重复导入。
+    imported: HashSet<PathBuf>,
+    /// 为 true 时未声明 `(once)` 的导入每次都重新展开。
+    multiple: bool,
+    /// 按首次出现顺序记录的全部导入文件，供调用方做依赖追踪。
+    dependencies: Vec<PathBuf>,
+    /// 开启 `collect_stats` 时记录每个文件的解析耗时。
+    timings: Option<&'a mut Vec<FileStats>>,
 }
 
 impl<'a> ImportResolver<'a> {
-    pub fn new(parser: &'a LessParser, include_paths: &[PathBuf]) -> Self {
+    pub fn new(
+        parser: &'a LessParser,
+        options: &CompileOptions,
+        cache: &'a mut ParseCache,
+    ) -> Self {
         Self {
             parser,
-            include_paths: include_paths.to_vec(),
-            cache: HashMap::new(),
+            loader: options
+                .import_loader
+                .clone()
+                .unwrap_or_else(|| Arc::new(FsImportLoader)),
+            #[cfg(feature = "http")]
+            remote: options.remote.clone(),
+            include_paths: options.include_paths.clone(),
+            cache,
+            loaded: HashSet::new(),
             stack: Vec::new(),
+            max_depth: options.max_import_depth.unwrap_or(DEFAULT_MAX_IMPORT_DEPTH),
+            has_entry: false,
+            imported: HashSet::new(),
+            multiple: options.multiple_imports,
+            dependencies: Vec::new(),
+            timings: None,
         }
     }
 
@@ -29,103 +292,440 @@ impl<'a> ImportResolver<'a> {
     ) -> LessResult<Vec<Statement>> {
         let mut result = Vec::new();
         for statement in statements {
-            match statement {
-                Statement::Import(import) if !import.is_css => {
-                    if let Some(ref target) = import.path {
-                        let resolved = self.resolve_path(target, current_dir)?;
-                        if self.stack.contains(&resolved) {
-                            return Err(LessError::eval(format!(
-                                "检测到循环导入: {}",
-                                resolved.display()
-                            )));
+            let depth = self.stack.len();
+            if let Err(err) = self.expand_statement(statement, current_dir, &mut result) {
+                self.skip_failed(depth, err)?;
+            }
+        }
+        Ok(result)
+    }
+
+    /// 容错模式下跳过无法展开的导入，保留错误供诊断输出；否则原样返回错误。
+    fn skip_failed(&mut self, depth: usize, err: LessError) -> LessResult<()> {
+        if !self.parser.recovers_errors() {
+            return Err(err);
+        }
+        self.stack.truncate(depth);
+        self.parser.report_error(err);
+        Ok(())
+    }
+
+    /// 展开规则集、at-rule、mixin 与分离规则集内部的 `@import`。
+    fn expand_nested(
+        &mut self,
+        statement: &mut Statement,
+        current_dir: Option<&Path>,
+    ) -> LessResult<()> {
+        match statement {
+            Statement::RuleSet(rule) => self.expand_body(&mut rule.body, current_dir),
+            Statement::AtRule(at_rule) => self.expand_body(&mut at_rule.body, current_dir),
+            Statement::MixinDefinition(definition) => {
+                self.expand_body(&mut definition.body, current_dir)
+            }
+            Statement::Variable(variable) => match &mut variable.value {
+                VariableInit::Ruleset(body) => self.expand_body(body, current_dir),
+                VariableInit::Value(_) => Ok(()),
+            },
+            _ => Ok(()),
+        }
+    }
+
+    fn expand_body(
+        &mut self,
+        body: &mut Vec<RuleBody>,
+        current_dir: Option<&Path>,
+    ) -> LessResult<()> {
+        let mut index = 0;
+        while index < body.len() {
+            match &mut body[index] {
+                RuleBody::Import(_) => {
+                    let RuleBody::Import(import) = body.remove(index) else {
+                        unreachable!("刚刚匹配过 RuleBody::Import");
+                    };
+                    let depth = self.stack.len();
+                    let items = match self.expand_body_import(import, current_dir) {
+                        Ok(items) => items,
+                        Err(err) => {
+                            self.skip_failed(depth, err)?;
+                            Vec::new()
                         }
-                        self.stack.push(resolved.clone());
-                        let stylesheet = self.load_stylesheet(&resolved)?;
-                        let parent = resolved.parent();
-                        let expanded = self.expand(stylesheet.statements, parent)?;
-                        result.extend(expanded);
-                        self.stack.pop();
-                        continue;
+                    };
+                    // 导入的内容在展开时已处理过其中的嵌套导入，直接跳过。
+                    let count = items.len();
+                    body.splice(index..index, items);
+                    index += count;
+                    continue;
+                }
+                RuleBody::NestedRule(rule) => self.expand_body(&mut rule.body, current_dir)?,
+                RuleBody::AtRule(at_rule) => self.expand_body(&mut at_rule.body, current_dir)?,
+                RuleBody::MixinDefinition(definition) => {
+                    self.expand_body(&mut definition.body, current_dir)?
+                }
+                RuleBody::Variable(variable) => {
+                    if let VariableInit::Ruleset(body) = &mut variable.value {
+                        self.expand_body(body, current_dir)?;
                     }
                 }
-                other => result.push(other),
+                _ => {}
             }
+            index += 1;
         }
-        Ok(result)
+        Ok(())
+    }
+
+    /// 展开规则体中的一条 `@import`：导入文件的顶层语句转为所在规则的规则体项，
+    /// 其中的变量与 mixin 只在该规则内可见，规则集成为嵌套规则。
+    fn expand_body_import(
+        &mut self,
+        import: ImportStatement,
+        current_dir: Option<&Path>,
+    ) -> LessResult<Vec<RuleBody>> {
+        let span = import.span.clone();
+        let mut statements = Vec::new();
+        self.expand_statement(Statement::Import(import), current_dir, &mut statements)?;
+        into_body(statements, &span)
+    }
+
+    fn expand_statement(
+        &mut self,
+        statement: Statement,
+        current_dir: Option<&Path>,
+        result: &mut Vec<Statement>,
+    ) -> LessResult<()> {
+        match statement {
+            Statement::Import(import) if Self::has_option(&import, "reference") => {
+                let mut plain = import;
+                plain.options.retain(|opt| opt != "reference");
+                let expanded = self.expand(vec![Statement::Import(plain)], current_dir)?;
+                result.push(Statement::Reference(expanded));
+            }
+            Statement::Import(import) if Self::has_option(&import, "inline") => {
+                let Some(ref target) = import.path else {
+                    result.push(Statement::Import(import));
+                    return Ok(());
+                };
+                let Some(resolved) = self.resolve_import(&import, target, current_dir)? else {
+                    return Ok(());
+                };
+                let content = self.read_source(&resolved)?;
+                result.push(Statement::RawCss(content));
+            }
+            Statement::Import(import) if !import.is_css => {
+                if let Some(ref target) = import.path {
+                    let Some(resolved) = self.resolve_import(&import, target, current_dir)? else {
+                        return Ok(());
+                    };
+                    if self.imported.contains(&resolved) && !self.allows_repeat(&import) {
+                        return Ok(());
+                    }
+                    if let Some(start) = self.stack.iter().position(|path| *path == resolved) {
+                        return Err(self.cycle_error(start, &resolved).at(&import.span));
+                    }
+                    if self.stack.len() - usize::from(self.has_entry) >= self.max_depth {
+                        return Err(LessError::eval(format!(
+                            "@import 嵌套超过 {} 层: {}",
+                            self.max_depth,
+                            resolved.display()
+                        ))
+                        .at(&import.span));
+                    }
+                    self.imported.insert(resolved.clone());
+                    self.stack.push(resolved.clone());
+                    let stylesheet = self.load_stylesheet(&resolved)?;
+                    let parent = resolved.parent();
+                    let expanded = self.expand(stylesheet.statements, parent)?;
+                    result.extend(expanded);
+                    self.stack.pop();
+                }
+            }
+            mut other => {
+                self.expand_nested(&mut other, current_dir)?;
+                result.push(other);
+            }
+        }
+        Ok(())
+    }
+
+    /// 从导入栈中第一次出现 `resolved` 的位置起列出整条导入链，如 `a.less -> b.less -> a.less`。
+    fn cycle_error(&self, start: usize, resolved: &Path) -> LessError {
+        let chain: Vec<String> = self.stack[start..]
+            .iter()
+            .map(PathBuf::as_path)
+            .chain([resolved])
+            .map(|path| path.display().to_string())
+            .collect();
+        LessError::eval(format!("检测到循环导入: {}", chain.join(" -> ")))
+    }
+
+    fn has_option(import: &ImportStatement, option: &str) -> bool {
+        import.options.iter().any(|opt| opt == option)
+    }
+
+    /// 与 lessc 一致默认只展开一次；`(multiple)` 或全局 `multiple_imports` 允许重复展开，
+    /// 显式的 `(once)` 优先于全局设置。
+    fn allows_repeat(&self, import: &ImportStatement) -> bool {
+        if Self::has_option(import, "multiple") {
+            return true;
+        }
+        self.multiple && !Self::has_option(import, "once")
+    }
+
+    /// 解析导入路径；`(optional)` 导入找不到文件时返回 `None` 而不是报错。
+    fn resolve_import(
+        &mut self,
+        import: &ImportStatement,
+        target: &str,
+        current_dir: Option<&Path>,
+    ) -> LessResult<Option<PathBuf>> {
+        // 加载器（如 `resolveImport` 回调）报告的错误即使是 `(optional)` 导入也原样返回。
+        let from = self.stack.last().map(PathBuf::as_path);
+        let resolved = match self.loader.resolve_import(target, from)? {
+            Some(resolved) => resolved,
+            None => match self.resolve_path(target, current_dir) {
+                Ok(resolved) => resolved,
+                Err(_) if Self::has_option(import, "optional") => return Ok(None),
+                Err(err) => return Err(err),
+            },
+        };
+        if !self.dependencies.contains(&resolved) {
+            self.dependencies.push(resolved.clone());
+        }
+        Ok(Some(resolved))
     }
 
     fn load_stylesheet(&mut self, path: &Path) -> LessResult<Stylesheet> {
-        if let Some(cached) = self.cache.get(path) {
-            return Ok(cached.clone());
-        }
-        let content = fs::read_to_string(path)
-            .map_err(|err| LessError::eval(format!("读取文件 {} 失败: {err}", path.display())))?;
-        let stylesheet = self
-            .parser
-            .parse(&content)
-            .map_err(|err| Self::attach_path(err, path))?;
-        self.cache.insert(path.to_path_buf(), stylesheet.clone());
+        if self.loaded.contains(path) {
+            if let Some(cached) = self.cache.get(path) {
+                return Ok(cached.clone());
+            }
+        }
+        let content = self.read_source(path)?;
+        let started = self.timings.is_some().then(Instant::now);
+        let stylesheet = self.cache.parse(self.parser, path, &content)?;
+        if let (Some(timings), Some(started)) = (self.timings.as_mut(), started) {
+            timings.push(FileStats {
+                path: Some(path.to_path_buf()),
+                parse_time: started.elapsed(),
+            });
+        }
+        self.loaded.insert(path.to_path_buf());
         Ok(stylesheet)
     }
 
+    fn read_source(&self, path: &Path) -> LessResult<String> {
+        #[cfg(feature = "http")]
+        if let Some(url) = path.to_str().filter(|url| is_remote(url)) {
+            return crate::remote::fetch(url, &self.remote);
+        }
+        self.loader.load(path)
+    }
+
     fn resolve_path(&self, target: &str, current_dir: Option<&Path>) -> LessResult<PathBuf> {
+        if is_remote(target) {
+            return Self::resolve_remote(target.to_string());
+        }
+        if let Some(base) = current_dir
+            .and_then(Path::to_str)
+            .filter(|dir| is_remote(dir))
+        {
+            return Self::resolve_remote(join_url(base, target));
+        }
+
         let raw = Path::new(target);
         let mut candidates = Vec::new();
         if raw.is_absolute() {
             candidates.push(raw.to_path_buf());
         } else {
-            if let Some(dir) = current_dir {
-                candidates.push(dir.join(raw));
+            match current_dir {
+                Some(dir) => candidates.push(dir.join(raw)),
+                None => candidates.push(raw.to_path_buf()),
             }
             for base in &self.include_paths {
                 candidates.push(base.join(raw));
             }
         }
         for candidate in candidates {
-            if let Some(found) = Self::find_existing(&candidate) {
+            if let Some(found) = self.find_existing(&candidate) {
                 return Ok(found);
             }
         }
         Err(LessError::eval(format!("无法解析 @import 路径 {target}")))
     }
 
-    fn find_existing(candidate: &Path) -> Option<PathBuf> {
+    /// 远程地址不做存在性检查，缺少扩展名时与本地文件一样补上 `.less`。
+    fn resolve_remote(mut url: String) -> LessResult<PathBuf> {
+        if !cfg!(feature = "http") {
+            return Err(LessError::eval(format!(
+                "远程导入 {url} 需要启用 http feature，或使用 (css) 原样输出"
+            )));
+        }
+        let file_name = url.rsplit('/').next().unwrap_or_default();
+        if !file_name.contains('.') {
+            url.push_str(".less");
+        }
+        Ok(PathBuf::from(url))
+    }
+
+    fn find_existing(&self, candidate: &Path) -> Option<PathBuf> {
         let mut attempts = Vec::new();
         attempts.push(candidate.to_path_buf());
         if candidate.extension().is_none() {
             attempts.push(candidate.with_extension("less"));
         }
-        for attempt in attempts {
-            if attempt.exists() && attempt.is_file() {
-                if let Ok(real) = attempt.canonicalize() {
-                    return Some(real);
+        attempts
+            .into_iter()
+            .find_map(|attempt| self.loader.resolve(&attempt))
+    }
+}
+
+/// 把导入文件的顶层语句转换为规则体项；`@charset` 只对整个样式表有意义，在规则内被忽略。
+fn into_body(statements: Vec<Statement>, span: &Span) -> LessResult<Vec<RuleBody>> {
+    let mut body = Vec::with_capacity(statements.len());
+    for statement in statements {
+        body.push(match statement {
+            Statement::Import(import) => RuleBody::Import(import),
+            Statement::BodylessAtRule(at_rule) => RuleBody::BodylessAtRule(at_rule),
+            Statement::AtRule(at_rule) => RuleBody::AtRule(at_rule),
+            Statement::RuleSet(rule) => RuleBody::NestedRule(rule),
+            Statement::Variable(variable) => RuleBody::Variable(variable),
+            Statement::MixinDefinition(definition) => RuleBody::MixinDefinition(definition),
+            Statement::MixinCall(call) => RuleBody::MixinCall(call),
+            Statement::DetachedCall(call) => RuleBody::DetachedCall(call),
+            Statement::Each(call) => RuleBody::Each(call),
+            Statement::Comment(comment) => RuleBody::Comment(comment),
+            Statement::Charset(_) => continue,
+            Statement::Plugin(_) => {
+                return Err(LessError::eval("规则集内导入的文件不能使用 @plugin").at(span));
+            }
+            Statement::Reference(_) | Statement::RawCss(_) => {
+                return Err(LessError::eval(
+                    "规则集内的 @import 不支持 (reference) 与 (inline) 选项",
+                )
+                .at(span));
+            }
+        });
+    }
+    Ok(body)
+}
+
+fn is_remote(target: &str) -> bool {
+    target.starts_with("http://") || target.starts_with("https://")
+}
+
+/// 以远程文件所在目录为基准拼接相对路径，支持 `./` 与 `../`。
+fn join_url(base: &str, target: &str) -> String {
+    if is_remote(target) {
+        return target.to_string();
+    }
+    let scheme_end = base.find("://").map_or(0, |idx| idx + 3);
+    let (scheme, rest) = base.split_at(scheme_end);
+    let mut segments: Vec<&str> = rest.split('/').collect();
+    if let Some(host) = target.strip_prefix('/') {
+        segments.truncate(1);
+        segments.push(host);
+        return format!("{scheme}{}", segments.join("/"));
+    }
+    for part in target.split('/') {
+        match part {
+            "." | "" => {}
+            ".." => {
+                if segments.len() > 1 {
+                    segments.pop();
                 }
-                return Some(attempt);
             }
+            other => segments.push(other),
         }
-        None
     }
+    format!("{scheme}{}", segments.join("/"))
 }
 
+/// 展开样式表中的 `@import`，同时返回所有被导入的文件路径；`entry` 为入口文件，
+/// 被导入文件再次导入它时按循环导入报错。传入 `timings` 时追加每个被导入文件的解析耗时。
 pub fn expand_imports(
     parser: &LessParser,
     stylesheet: Stylesheet,
-    current_dir: Option<&Path>,
-    include_paths: &[PathBuf],
-) -> LessResult<Stylesheet> {
-    let mut resolver = ImportResolver::new(parser, include_paths);
-    let statements = resolver.expand(stylesheet.statements, current_dir)?;
-    Ok(Stylesheet::new(statements))
+    options: &CompileOptions,
+    cache: &mut ParseCache,
+    entry: Option<&Path>,
+    timings: Option<&mut Vec<FileStats>>,
+) -> LessResult<(Stylesheet, Vec<PathBuf>)> {
+    let mut resolver = ImportResolver::new(parser, options, cache);
+    resolver.timings = timings;
+    if let Some(entry) = entry {
+        let resolved = resolver
+            .loader
+            .resolve(entry)
+            .unwrap_or_else(|| entry.to_path_buf());
+        resolver.stack.push(resolved);
+        resolver.has_entry = true;
+    }
+    let statements = resolver.expand(stylesheet.statements, options.current_dir.as_deref())?;
+    Ok((Stylesheet::new(statements), resolver.dependencies))
 }
 
-impl<'a> ImportResolver<'a> {
-    fn attach_path(err: LessError, path: &Path) -> LessError {
-        match err {
-            LessError::ParseError { message, position } => LessError::ParseError {
-                message: format!("{message} (文件: {})", path.display()),
-                position,
-            },
-            other => other,
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    #[test]
+    fn joins_relative_remote_paths() {
+        let base = "https://cdn.example.com/theme/v1";
+        assert_eq!(
+            join_url(base, "vars.less"),
+            "https://cdn.example.com/theme/v1/vars.less"
+        );
+        assert_eq!(
+            join_url(base, "../shared/./mixins.less"),
+            "https://cdn.example.com/theme/shared/mixins.less"
+        );
+        assert_eq!(
+            join_url(base, "/root.less"),
+            "https://cdn.example.com/root.less"
+        );
+    }
+
+    /// 深度超过 `stack::INLINE_DEPTH` 时编译在临时线程中进行，回调仍须在调用方线程上执行。
+    #[test]
+    fn local_resolver_runs_on_calling_thread() {
+        fn nested(depth: usize, inner: &str) -> String {
+            let mut source = String::new();
+            for level in 0..depth {
+                source.push_str(&format!(".n{level} {{ "));
+            }
+            source.push_str(inner);
+            source.push_str(&" }".repeat(depth));
+            source
         }
+
+        let caller = thread::current().id();
+        // `Rc` 使回调不满足 `Send`，若被移到其他线程调用则无法通过编译。
+        let calls = Rc::new(Cell::new(0));
+        let counter = Rc::clone(&calls);
+        let imported = nested(100, "color: @brand;");
+        let css = with_local_resolver(
+            move |target, _| {
+                assert_eq!(thread::current().id(), caller);
+                counter.set(counter.get() + 1);
+                let contents = match target {
+                    "vars" => "@brand: red;".to_string(),
+                    _ => imported.clone(),
+                };
+                Ok(Some((PathBuf::from(format!("{target}.less")), contents)))
+            },
+            |loader| {
+                let options = CompileOptions {
+                    import_loader: Some(loader),
+                    minify: true,
+                    ..CompileOptions::default()
+                };
+                let source = format!("@import \"vars\";\n{}", nested(80, "@import \"deep\";"));
+                crate::compile(&source, options)
+            },
+        )
+        .unwrap();
+        assert_eq!(calls.get(), 2);
+        assert!(css.ends_with("{color:red}"), "{css}");
     }
 }
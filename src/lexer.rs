@@ -0,0 +1,302 @@
+//! 词法分析：把源码切分为带字节区间的记号。
+//!
+//! 解析器先把整份源码切分为记号数组，判断语句类型与读取值、选择器都按下标访问记号，
+//! 不再逐字符扫描原文；字符串、注释、`@{var}` 插值与 `url(...)` 都是完整的记号，
+//! 其中的 `{`、`;`、`:` 不会干扰解析。节点文本由所覆盖记号的原文拼接而成。
+
+/// 记号类别；文本通过 [`Token::text`] 按区间从源码中取出。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum TokenKind {
+    /// 标识符，如 `color`、`-webkit-box`、`--gap`、`when`。
+    Ident,
+    /// `@name`；`@@name` 的第一个 `@` 是 [`TokenKind::Delim`]。
+    AtKeyword,
+    /// `@{name}` 插值，没有 `}` 时延伸到末尾。
+    Interpolation,
+    /// 以数字开头的数值及其单位，如 `10px`、`1.5em`、`50%`。
+    Number,
+    /// 含引号的字符串，没有结尾引号时延伸到末尾。
+    String,
+    /// 不带引号的 `url(...)`，其中的 `//` 不是注释。
+    Url,
+    /// `/* ... */` 或 `// ...` 注释；块注释没有 `*/` 时延伸到末尾。
+    Comment,
+    /// 连续的空白。
+    Whitespace,
+    /// 其余单个字符：括号、`;`、`:`、`,`、`.`、`#`、`>`、`!` 等；`\` 与其转义的字符合为一个记号。
+    Delim(char),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Token {
+    pub(crate) kind: TokenKind,
+    pub(crate) start: usize,
+    pub(crate) end: usize,
+}
+
+impl Token {
+    pub(crate) fn text<'s>(&self, source: &'s str) -> &'s str {
+        &source[self.start..self.end]
+    }
+
+    pub(crate) fn is_delim(&self, ch: char) -> bool {
+        self.kind == TokenKind::Delim(ch)
+    }
+
+    pub(crate) fn is_trivia(&self) -> bool {
+        matches!(self.kind, TokenKind::Whitespace | TokenKind::Comment)
+    }
+
+    /// `//` 行注释。
+    pub(crate) fn is_line_comment(&self, source: &str) -> bool {
+        self.kind == TokenKind::Comment && self.text(source).starts_with("//")
+    }
+
+    /// 字符串是否有结尾引号、块注释是否有 `*/`；其余记号总是完整的。
+    pub(crate) fn is_closed(&self, source: &str) -> bool {
+        let text = self.text(source);
+        match self.kind {
+            TokenKind::String => {
+                let mut chars = text.chars();
+                let quote = chars.next();
+                while let Some(ch) = chars.next() {
+                    if ch == '\\' {
+                        chars.next();
+                    } else if Some(ch) == quote {
+                        return true;
+                    }
+                }
+                false
+            }
+            TokenKind::Comment if text.starts_with("/*") => text.len() >= 4 && text.ends_with("*/"),
+            _ => true,
+        }
+    }
+}
+
+pub(crate) fn is_identifier_char(ch: char) -> bool {
+    ch.is_alphanumeric() || ch == '-' || ch == '_'
+}
+
+/// 从给定偏移开始产生记号的迭代器；切分只取决于起始偏移，因此可以从任意记号边界重新开始。
+pub(crate) struct Lexer<'a> {
+    source: &'a str,
+    position: usize,
+}
+
+impl<'a> Lexer<'a> {
+    pub(crate) fn new(source: &'a str, position: usize) -> Self {
+        Self { source, position }
+    }
+
+    fn peek_char(&self) -> Option<char> {
+        self.char_at(self.position)
+    }
+
+    fn char_after(&self, offset: usize) -> Option<char> {
+        self.char_at(self.position + offset)
+    }
+
+    /// `offset` 处的字符；ASCII 直接取字节，其余才解码，不在字符边界上时为 `None`。
+    fn char_at(&self, offset: usize) -> Option<char> {
+        match *self.source.as_bytes().get(offset)? {
+            byte if byte.is_ascii() => Some(byte as char),
+            _ => self.source.get(offset..)?.chars().next(),
+        }
+    }
+
+    /// 前进到第一个不满足 `pred` 的字符之前。
+    fn eat_while(&mut self, pred: impl Fn(char) -> bool) {
+        while let Some(ch) = self.peek_char().filter(|&ch| pred(ch)) {
+            self.position += ch.len_utf8();
+        }
+    }
+
+    /// 前进到 `pattern` 之后，找不到时前进到末尾。
+    fn eat_through(&mut self, pattern: &str) {
+        let rest = &self.source[self.position..];
+        self.position += memchr::memmem::find(rest.as_bytes(), pattern.as_bytes())
+            .map_or(rest.len(), |offset| offset + pattern.len());
+    }
+
+    fn eat_string(&mut self, quote: char) {
+        self.position += 1;
+        while let Some(ch) = self.peek_char() {
+            self.position += ch.len_utf8();
+            if ch == quote {
+                return;
+            }
+            if ch == '\\' {
+                if let Some(escaped) = self.peek_char() {
+                    self.position += escaped.len_utf8();
+                }
+            }
+        }
+    }
+
+    /// `url(` 之后不以引号开始时整个读到 `)`。
+    fn eat_unquoted_url(&mut self) -> bool {
+        let rest = &self.source[self.position..];
+        let Some(args) = rest
+            .get(..4)
+            .filter(|name| name.eq_ignore_ascii_case("url("))
+            .map(|_| &rest[4..])
+        else {
+            return false;
+        };
+        if args.trim_start().starts_with(['"', '\'']) {
+            return false;
+        }
+        self.position += 4;
+        self.eat_through(")");
+        true
+    }
+
+    fn eat_number(&mut self) {
+        self.eat_while(|ch| ch.is_ascii_digit());
+        if self.peek_char() == Some('.') && self.char_after(1).is_some_and(|ch| ch.is_ascii_digit())
+        {
+            self.position += 1;
+            self.eat_while(|ch| ch.is_ascii_digit());
+        }
+        if self.peek_char() == Some('%') {
+            self.position += 1;
+        } else {
+            self.eat_while(is_identifier_char);
+        }
+    }
+}
+
+impl Iterator for Lexer<'_> {
+    type Item = Token;
+
+    fn next(&mut self) -> Option<Token> {
+        let start = self.position;
+        let ch = self.peek_char()?;
+        let kind = match ch {
+            _ if ch.is_whitespace() => {
+                self.eat_while(char::is_whitespace);
+                TokenKind::Whitespace
+            }
+            '/' if self.char_after(1) == Some('*') => {
+                self.position += 2;
+                self.eat_through("*/");
+                TokenKind::Comment
+            }
+            '/' if self.char_after(1) == Some('/') => {
+                self.eat_while(|ch| ch != '\n');
+                TokenKind::Comment
+            }
+            '"' | '\'' => {
+                self.eat_string(ch);
+                TokenKind::String
+            }
+            '@' if self.char_after(1) == Some('{') => {
+                self.eat_through("}");
+                TokenKind::Interpolation
+            }
+            '@' if self.char_after(1).is_some_and(is_identifier_char) => {
+                self.position += 1;
+                self.eat_while(is_identifier_char);
+                TokenKind::AtKeyword
+            }
+            '0'..='9' => {
+                self.eat_number();
+                TokenKind::Number
+            }
+            _ if is_identifier_char(ch) => {
+                if self.eat_unquoted_url() {
+                    TokenKind::Url
+                } else {
+                    self.eat_while(is_identifier_char);
+                    TokenKind::Ident
+                }
+            }
+            '\\' => {
+                self.position += 1;
+                if let Some(escaped) = self.peek_char() {
+                    self.position += escaped.len_utf8();
+                }
+                TokenKind::Delim('\\')
+            }
+            _ => {
+                self.position += ch.len_utf8();
+                TokenKind::Delim(ch)
+            }
+        };
+        Some(Token {
+            kind,
+            start,
+            end: self.position,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokens(source: &str) -> Vec<(TokenKind, &str)> {
+        Lexer::new(source, 0)
+            .map(|token| (token.kind, token.text(source)))
+            .collect()
+    }
+
+    #[test]
+    fn splits_statements_into_tokens() {
+        use TokenKind::*;
+        assert_eq!(
+            tokens("@w: 1.5em; .m(@a) when (@a > 50%) {}"),
+            [
+                (AtKeyword, "@w"),
+                (Delim(':'), ":"),
+                (Whitespace, " "),
+                (Number, "1.5em"),
+                (Delim(';'), ";"),
+                (Whitespace, " "),
+                (Delim('.'), "."),
+                (Ident, "m"),
+                (Delim('('), "("),
+                (AtKeyword, "@a"),
+                (Delim(')'), ")"),
+                (Whitespace, " "),
+                (Ident, "when"),
+                (Whitespace, " "),
+                (Delim('('), "("),
+                (AtKeyword, "@a"),
+                (Whitespace, " "),
+                (Delim('>'), ">"),
+                (Whitespace, " "),
+                (Number, "50%"),
+                (Delim(')'), ")"),
+                (Whitespace, " "),
+                (Delim('{'), "{"),
+                (Delim('}'), "}"),
+            ]
+        );
+    }
+
+    #[test]
+    fn keeps_strings_comments_and_urls_whole() {
+        use TokenKind::*;
+        assert_eq!(
+            tokens(r#"a: "{;" /* } */ url(//x.png) @{v}\:// c"#),
+            [
+                (Ident, "a"),
+                (Delim(':'), ":"),
+                (Whitespace, " "),
+                (String, r#""{;""#),
+                (Whitespace, " "),
+                (Comment, "/* } */"),
+                (Whitespace, " "),
+                (Url, "url(//x.png)"),
+                (Whitespace, " "),
+                (Interpolation, "@{v}"),
+                (Delim('\\'), "\\:"),
+                (Comment, "// c"),
+            ]
+        );
+        assert_eq!(tokens("'open"), [(String, "'open")]);
+        assert_eq!(tokens("url('a')")[0], (Ident, "url"));
+    }
+}
@@ -1,25 +1,67 @@
 //! less_oxide 库入口，提供面向 Rust 与 Node.js 的 LESS 编译能力。
 //! 内部主要分为三个阶段：解析（Parser）→ 语义求值（Evaluator）→ CSS 序列化（Serializer）。
 
-mod ast;
+pub mod ast;
+#[cfg(feature = "capi")]
+pub mod capi;
 mod color;
+mod compiler;
+mod css_modules;
+mod diagnostics;
 mod error;
 mod evaluator;
+mod format;
+mod functions;
 mod importer;
+mod lexer;
+mod lint;
+mod minify;
+mod optimize;
 mod parser;
+mod prefix;
+#[cfg(feature = "http")]
+mod remote;
+mod scss;
+mod selector;
 mod serializer;
+mod sourcemap;
+mod stack;
+mod stats;
+mod units;
+mod urls;
 mod utils;
+pub mod visit;
+#[cfg(feature = "wasm")]
+mod wasm;
+#[cfg(feature = "watch")]
+mod watch;
 
-use crate::error::{LessError, LessResult};
-use evaluator::Evaluator;
-use importer::expand_imports;
-use parser::LessParser;
-use serializer::Serializer;
-use std::fs;
+use crate::ast::Stylesheet;
+pub use crate::compiler::Compiler;
+pub use crate::diagnostics::{Diagnostic, Severity};
+pub use crate::error::{LessError, LessResult, SourceLocation};
+pub use crate::evaluator::{
+    EvaluatedAtRule, EvaluatedDeclaration, EvaluatedNode, EvaluatedRule, EvaluatedStylesheet,
+};
+pub use crate::format::{FormatOptions, QuoteStyle};
+pub use crate::functions::{CustomFunction, FunctionRegistry, FunctionValue};
+pub use crate::importer::{FsImportLoader, ImportLoader};
+use crate::parser::LessParser;
+pub use crate::prefix::{Browser, BrowserTarget};
+#[cfg(feature = "http")]
+pub use crate::remote::RemoteOptions;
+pub use crate::serializer::{CssEmitter, Serializer};
+pub use crate::sourcemap::SourceMapOptions;
+pub use crate::stats::{CompileStats, FileStats};
+#[cfg(feature = "watch")]
+pub use crate::watch::WatchEvent;
+use std::collections::{BTreeMap, HashMap};
+use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 /// LESS 编译配置，目前只提供基础开关，后续可扩展 source map、模块化等高级能力。
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct CompileOptions {
     /// 是否输出压缩后的 CSS。
     pub minify: bool,
@@ -27,87 +69,1026 @@ pub struct CompileOptions {
     pub current_dir: Option<PathBuf>,
     /// 额外的检索目录。
     pub include_paths: Vec<PathBuf>,
+    /// 自定义 `@import` 加载器；`None` 时读取真实文件系统。设置后即使没有目录信息也会展开导入。
+    pub import_loader: Option<Arc<dyn ImportLoader>>,
+    /// `http://` / `https://` 导入的下载配置（需启用 `http` feature）。
+    #[cfg(feature = "http")]
+    pub remote: RemoteOptions,
+    /// 同一文件被多次 `@import` 时是否重复展开；默认与 lessc 一致只展开一次。
+    pub multiple_imports: bool,
+    /// 算术求值模式，对应 lessc 的 `math` 选项。
+    pub math: MathMode,
+    /// 对应 lessc 的 `strictUnits`：开启时不同单位且无法换算的加减（如 `1px + 2em`）报错；
+    /// 默认与 lessc 一致沿用左侧单位，并在 [`compile_with_diagnostics`] 的结果中给出警告。
+    pub strict_units: bool,
+    /// 迁移用的宽松模式：未定义的 `@var` / `@{var}` 按原文输出并给出 `undefined-variable` 警告，
+    /// 而不是中止编译；默认关闭。
+    pub allow_undefined_variables: bool,
+    /// 自定义函数与 `@plugin` 插件注册表。
+    pub functions: FunctionRegistry,
+    /// mixin 与分离规则集嵌套展开的最大深度，用于终止失控的递归；`None` 时使用 [`DEFAULT_MAX_MIXIN_DEPTH`]。
+    pub max_mixin_depth: Option<usize>,
+    /// `@import` 嵌套的最大层数（入口文件的直接导入为第 1 层）；`None` 时使用 [`DEFAULT_MAX_IMPORT_DEPTH`]。
+    pub max_import_depth: Option<usize>,
+    /// 求值产生的选择器与声明文本（不含缩进与标点）累计允许的最大字节数，超出时报错；
+    /// `None` 时不限制。编译不可信输入时建议设置。
+    pub max_output_size: Option<usize>,
+    /// 父选择器展开后单条规则允许的最大选择器数（多个 `&` 会与父选择器列表做笛卡尔积）；
+    /// `None` 时使用 [`DEFAULT_MAX_SELECTORS`]。
+    pub max_selectors: Option<usize>,
+    /// 插入到源码之前的变量（键可带或不带 `@`），对应 lessc 的 `globalVars`，可被样式表覆盖。
+    pub global_vars: HashMap<String, String>,
+    /// 追加到源码之后的变量，对应 lessc 的 `modifyVars`，覆盖样式表中的同名定义。
+    pub modify_vars: HashMap<String, String>,
+    /// 容错模式：解析或求值出错的语句被跳过并继续编译，错误由 [`compile_with_diagnostics`] 汇总返回。
+    pub error_recovery: bool,
+    /// 在美化输出中保留 `/* ... */` 注释；`/*! ... */` 版权注释无论此项与 `minify` 如何都会保留。
+    pub keep_comments: bool,
+    /// 在 `--custom: value` 的值中替换 `@{var}` 插值；默认关闭，自定义属性的值原样输出且从不参与运算。
+    pub custom_property_interpolation: bool,
+    /// 保留没有任何声明的空规则（如 `.a {}`），默认与 lessc 一致将其省略。
+    pub keep_empty_rules: bool,
+    /// 输出格式；`None` 时由 `minify` 决定（压缩或默认美化格式），`Compressed` 等同于开启 `minify`。
+    pub output_style: Option<OutputStyle>,
+    /// 合并相邻的同选择器规则并去掉完全相同的重复声明（保留最后一条），默认关闭。
+    pub merge_rules: bool,
+    /// 需要补全厂商前缀的目标浏览器（如 `BrowserTarget::parse("safari 12")`）；为空时不做处理。
+    pub autoprefix: Vec<BrowserTarget>,
+    /// 按 CSS Modules 的方式给类名加上哈希后缀，映射见 [`CompileOutput::class_names`]；默认关闭。
+    pub css_modules: bool,
+    /// 计算类名哈希时入口文件路径相对的根目录，`None` 时使用进程的当前工作目录。哈希只取决于
+    /// 相对路径，项目换到其他目录或机器上构建时类名不变；入口不在根目录下时使用原路径。
+    pub css_modules_root: Option<PathBuf>,
+    /// 参与类名哈希的附加字符串，可用于区分不同项目；编译源码字符串（没有入口文件）时
+    /// 类名只由原类名与该值决定。
+    pub css_modules_salt: String,
+    /// 被导入文件中相对 `url()` 的改写方式，对应 lessc 的 `rewriteUrls`；默认不改写。
+    pub rewrite_urls: RewriteUrls,
+    /// 生成 source map（v3，规则级映射）；`None` 时不生成。结果见 [`CompileOutput::source_map`]，
+    /// `compile_stream` 与 [`compile_with_emitter`] 不生成映射。
+    pub source_map: Option<SourceMapOptions>,
+    /// 对应 lessc 的 `dumpLineNumbers: "comments"`：美化输出时在每条规则前写出
+    /// `/* line 12, src/button.less */` 注释，标明规则在源文件中的位置；默认关闭。
+    pub dump_line_numbers: bool,
+    /// 收集规则数、嵌套深度、mixin 展开次数与各阶段耗时等统计，结果见 [`CompileOutput::stats`]；默认关闭。
+    pub collect_stats: bool,
 }
 
-impl Default for CompileOptions {
-    fn default() -> Self {
-        Self {
-            minify: false,
-            current_dir: None,
-            include_paths: Vec::new(),
+/// 默认的 mixin 递归深度上限。
+pub const DEFAULT_MAX_MIXIN_DEPTH: usize = 128;
+
+/// 默认的 `@import` 嵌套层数上限。
+pub const DEFAULT_MAX_IMPORT_DEPTH: usize = 64;
+
+/// 默认的单条规则选择器数上限。
+pub const DEFAULT_MAX_SELECTORS: usize = 4096;
+
+/// 算术求值模式，与 less.js 的 `math` 选项一致。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MathMode {
+    /// 所有运算都在编译期计算。
+    #[default]
+    Always,
+    /// 除法只在括号内计算，`font: 14px/16px` 之类的写法原样保留。
+    ParensDivision,
+    /// 只计算括号内的表达式。
+    Strict,
+}
+
+impl MathMode {
+    /// 按 lessc 的选项名解析：`always` / `parens-division` / `strict`。
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "always" => Some(Self::Always),
+            "parens-division" => Some(Self::ParensDivision),
+            "strict" => Some(Self::Strict),
+            _ => None,
+        }
+    }
+}
+
+/// 相对 `url()` 的改写方式，与 less.js 的 `rewriteUrls` 选项一致。
+///
+/// 改写后的路径相对入口文件所在目录；由变量或插值拼出的 URL 不做处理。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RewriteUrls {
+    /// 保留原样。
+    #[default]
+    Off,
+    /// 只改写以 `./` 或 `../` 开头的路径。
+    Local,
+    /// 改写全部相对路径（不含协议、`/` 或 `#` 开头的地址）。
+    All,
+}
+
+impl RewriteUrls {
+    /// 按 lessc 的选项名解析：`off` / `local` / `all`。
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "off" => Some(Self::Off),
+            "local" => Some(Self::Local),
+            "all" => Some(Self::All),
+            _ => None,
+        }
+    }
+}
+
+/// 输出格式，名称与 lessc / Sass 的 `--style` 一致。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputStyle {
+    /// 与 lessc 默认输出一致：选择器列表逐行书写，每条声明一行，规则间空行，末尾换行。
+    Expanded,
+    /// 每条规则占一行，at-rule 的子规则缩进后逐行输出。
+    Compact,
+    /// 压缩输出，等同于 `minify`。
+    Compressed,
+}
+
+impl OutputStyle {
+    /// 按名称解析：`expanded` / `compact` / `compressed`。
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "expanded" => Some(Self::Expanded),
+            "compact" => Some(Self::Compact),
+            "compressed" => Some(Self::Compressed),
+            _ => None,
+        }
+    }
+}
+
+/// 编译结果及附带信息。
+#[derive(Debug, Clone)]
+pub struct CompileOutput {
+    /// 生成的 CSS 文本。
+    pub css: String,
+    /// 编译过程中通过 `@import` 读取的全部文件（含间接导入），按首次出现顺序排列。
+    pub dependencies: Vec<PathBuf>,
+    /// [`compile_with_diagnostics`] 收集到的错误与警告；其他编译函数出错时直接返回 `Err`。
+    pub diagnostics: Vec<Diagnostic>,
+    /// 开启 `css_modules` 时原类名到改写后类名的映射，否则为空。
+    pub class_names: BTreeMap<String, String>,
+    /// 全部顶层变量（含导入文件与 `global_vars` / `modify_vars` 中的变量）的最终值，
+    /// 按首次声明的顺序排列；分离规则集与无法求值的变量不在其中。
+    pub variables: Vec<VariableInfo>,
+    /// 开启 `source_map` 且不内嵌时的 source map JSON，否则为 `None`。
+    pub source_map: Option<String>,
+    /// 开启 `collect_stats` 时的编译统计，否则为 `None`。
+    pub stats: Option<CompileStats>,
+}
+
+/// 顶层变量的最终求值结果，供设计令牌等工具提取主题。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VariableInfo {
+    /// 不含 `@` 的变量名。
+    pub name: String,
+    /// 求值后的值，与在声明中引用该变量时输出的文本一致。
+    pub value: String,
+    /// 生效的（即最后一次）声明的位置；`line_text` 为 `None`。
+    pub location: SourceLocation,
+}
+
+/// 只解析不求值，返回带位置信息的 AST，供 lint、格式化等工具遍历（参见 [`visit`]）。
+/// `@import` 不会展开。
+///
+/// 任意输入（包括未闭合的字符串与注释、多余的 `}`）都只会返回 [`LessError`]，
+/// 不会 panic 或陷入死循环；`fuzz/` 下的 cargo-fuzz 目标持续验证这一点。
+pub fn parse(source: &str) -> LessResult<Stylesheet> {
+    LessParser::new().parse(source)
+}
+
+/// 容错解析：出错的语句被跳过（跳到 `;` 或配平的 `{}` 之后）继续解析，
+/// 返回其余语句组成的 AST 与全部解析错误，适合编辑器等需要尽量给出结果的场景。
+///
+/// ```
+/// let (stylesheet, errors) = less_oxide::parse_lenient(".a { color: red; }\n} .b { color: blue; }");
+/// assert_eq!(stylesheet.statements.len(), 2);
+/// assert_eq!(errors[0].message(), "多余的 '}'");
+/// ```
+pub fn parse_lenient(source: &str) -> (Stylesheet, Vec<LessError>) {
+    let parser = LessParser::with_error_recovery();
+    match parser.parse(source) {
+        Ok(stylesheet) => (stylesheet, parser.take_errors()),
+        // 只有嵌套超过上限这类无法跳过的错误会走到这里。
+        Err(err) => {
+            let mut errors = parser.take_errors();
+            errors.push(err);
+            (Stylesheet::new(Vec::new()), errors)
         }
     }
 }
 
+/// 解析并把 AST 序列化为 JSON，结构与 [`ast`] 中的类型一一对应，便于非 Rust 工具检查。
+#[cfg(feature = "serde")]
+pub fn parse_to_json(source: &str) -> LessResult<String> {
+    stack::grow(stack::nesting_depth(source), || {
+        let stylesheet = parse(source)?;
+        serde_json::to_string(&stylesheet)
+            .map_err(|err| LessError::eval(format!("序列化 AST 失败: {err}")))
+    })
+}
+
 /// 编译 LESS 源码为 CSS 文本。
 ///
 /// # 参数
 /// * `source` - 待编译的 LESS 字符串
 /// * `options` - 编译配置
 pub fn compile(source: &str, options: CompileOptions) -> LessResult<String> {
-    let parser = LessParser::new();
-    let mut ast = parser.parse(source)?;
-    if options.current_dir.is_some() || !options.include_paths.is_empty() {
-        ast = expand_imports(
-            &parser,
-            ast,
-            options.current_dir.as_deref(),
-            &options.include_paths,
-        )?;
-    }
+    compile_with_metadata(source, options).map(|output| output.css)
+}
+
+/// 与 [`compile`] 相同，但额外返回 `@import` 依赖列表，便于监听模式与打包工具做失效判断。
+pub fn compile_with_metadata(source: &str, options: CompileOptions) -> LessResult<CompileOutput> {
+    Compiler::new(options).compile(source)
+}
+
+/// 求值 LESS 源码并返回尚未序列化的样式表，供自定义压缩、关键 CSS 提取等工具直接处理规则，
+/// 而不必重新解析输出的 CSS 字符串。`merge_rules`、`autoprefix`、`css_modules` 与压缩
+/// 等后处理与 [`compile`] 一致地应用。
+///
+/// ```
+/// use less_oxide::EvaluatedNode;
+///
+/// let stylesheet = less_oxide::evaluate(".a { .b { width: 1px + 1px; } }", Default::default()).unwrap();
+/// let EvaluatedNode::Rule(rule) = &stylesheet.nodes[0] else { unreachable!() };
+/// assert_eq!(rule.selectors, [".a .b"]);
+/// assert_eq!(rule.declarations[0].value, "2px");
+/// ```
+pub fn evaluate(source: &str, options: CompileOptions) -> LessResult<EvaluatedStylesheet> {
+    Compiler::new(options).evaluate(source)
+}
+
+/// 求值 LESS 源码后交给 `emitter` 生成产物，用于从同一份求值结果输出 CSS 以外的格式。
+/// 后处理与 [`evaluate`] 相同，`minify` 等选项仍作用于求值结果；传入与选项一致的 [`Serializer`]
+/// 即得到与 [`compile`] 相同的 CSS。
+///
+/// ```
+/// use less_oxide::{CompileOptions, Serializer};
+///
+/// let css = less_oxide::compile_with_emitter(".a { color: #ff0000; }", CompileOptions::default(), &mut Serializer::minified());
+/// assert_eq!(css.unwrap(), ".a{color:#ff0000}");
+/// ```
+pub fn compile_with_emitter<E: CssEmitter + ?Sized>(
+    source: &str,
+    options: CompileOptions,
+    emitter: &mut E,
+) -> LessResult<E::Output> {
+    Compiler::new(options).compile_with_emitter(source, emitter)
+}
+
+/// 从 `reader` 读取 LESS 源码，把 CSS 写入 `writer`：每条顶层语句求值后立即序列化写出，
+/// 不保留整个样式表的求值结果，也不拼接完整的 CSS 字符串，适合生成的大型样式表。
+///
+/// 源码仍会完整读入并解析（变量与 mixin 允许先使用后定义）。`merge_rules`、`css_modules`
+/// 与 `collect_stats` 需要看到整个样式表，开启其中任一项，或规则集、mixin 中含有 CSS `@import`
+/// （它们需要提到输出开头）时，求值完成后才开始写出。逐条写出时中途出错，`writer` 中会留下
+/// 已写出的部分；求值可能在栈空间更大的临时线程中进行，因此 `writer` 需要实现 `Send`。
+///
+/// ```
+/// let mut css = Vec::new();
+/// less_oxide::compile_stream("@w: 2px;\n.a { width: @w; }".as_bytes(), &mut css, Default::default()).unwrap();
+/// assert_eq!(String::from_utf8(css).unwrap(), ".a {\n  width: 2px;\n}");
+/// ```
+pub fn compile_stream(
+    reader: impl Read,
+    writer: impl Write + Send,
+    options: CompileOptions,
+) -> LessResult<()> {
+    Compiler::new(options).compile_stream(reader, writer)
+}
+
+/// 编译并以诊断列表报告错误，不会失败：开启 `error_recovery` 时跳过出错的语句，
+/// 返回尽力生成的 CSS 与全部错误；未开启时在第一个错误处停止，CSS 为空。
+/// 求值警告（如 `strict_units` 关闭时单位不兼容的运算）以 [`Severity::Warning`] 排在错误之前。
+pub fn compile_with_diagnostics(source: &str, options: CompileOptions) -> CompileOutput {
+    Compiler::new(options).compile_with_diagnostics(source)
+}
+
+/// 格式化 LESS 源码：统一缩进、空白与引号风格后重新输出，保留注释，不做求值。
+///
+/// ```
+/// let formatted = less_oxide::format_source(".a{color:red;.b{margin:0}}", Default::default()).unwrap();
+/// assert_eq!(formatted, ".a {\n  color: red;\n  .b {\n    margin: 0;\n  }\n}\n");
+/// ```
+pub fn format_source(source: &str, options: FormatOptions) -> LessResult<String> {
+    stack::grow(stack::nesting_depth(source), || {
+        crate::format::format_source(source, &options)
+    })
+}
 
-    let minify = options.minify;
-    let mut evaluator = Evaluator::new(options);
-    let stylesheet = evaluator.evaluate(ast)?;
+/// 把 LESS 源码转换为 SCSS：`@var` → `$var`、mixin → `@mixin` / `@include`、守卫 → `@if` 等，
+/// 排版同 [`format_source`] 的默认选项。`@@var`、`@arguments` 等没有对应写法的结构返回错误。
+///
+/// ```
+/// let scss = less_oxide::convert_to_scss("@gap: 4px;\n.m(@x) { margin: @x; }\n.a { .m(@gap); }").unwrap();
+/// assert_eq!(scss, "$gap: 4px;\n@mixin m($x) {\n  margin: $x;\n}\n.a {\n  @include m($gap);\n}\n");
+/// ```
+pub fn convert_to_scss(source: &str) -> LessResult<String> {
+    stack::grow(stack::nesting_depth(source), || {
+        crate::scss::convert_to_scss(source, &FormatOptions::default())
+    })
+}
 
-    let serializer = Serializer::new(minify);
-    Ok(serializer.to_css(&stylesheet))
+/// 静态检查：以警告报告定义后从未使用的变量与 mixin、对未定义变量或 mixin 的引用，
+/// 解析失败时返回单条错误诊断。配置了导入目录或加载器时先展开 `@import`，
+/// 被导入文件中的定义计入检查，但只报告入口源码中的问题。
+///
+/// ```
+/// let diagnostics = less_oxide::lint("@unused: 1px;\n.a { color: @missing; }", Default::default());
+/// let codes: Vec<_> = diagnostics.iter().map(|d| d.code).collect();
+/// assert_eq!(codes, ["unused-variable", "undefined-variable"]);
+/// ```
+pub fn lint(source: &str, options: CompileOptions) -> Vec<Diagnostic> {
+    Compiler::new(options).lint(source)
+}
+
+/// 检查 LESS 文件，`@import` 相对于文件所在目录解析，参见 [`lint`]。
+pub fn lint_file<P: AsRef<Path>>(path: P, options: CompileOptions) -> Vec<Diagnostic> {
+    Compiler::new(options).lint_file(path)
 }
 
 /// 从文件路径编译 LESS，自动处理 @import。
-pub fn compile_file<P: AsRef<Path>>(path: P, mut options: CompileOptions) -> LessResult<String> {
-    let path = path.as_ref();
-    let source = fs::read_to_string(path)
-        .map_err(|err| LessError::eval(format!("读取文件 {} 失败: {err}", path.display())))?;
-    if options.current_dir.is_none() {
-        if let Some(parent) = path.parent() {
-            options.current_dir = Some(parent.to_path_buf());
-        }
+pub fn compile_file<P: AsRef<Path>>(path: P, options: CompileOptions) -> LessResult<String> {
+    compile_file_with_metadata(path, options).map(|output| output.css)
+}
+
+/// 从文件路径编译 LESS，并返回 `@import` 依赖列表（不含入口文件本身）。
+pub fn compile_file_with_metadata<P: AsRef<Path>>(
+    path: P,
+    options: CompileOptions,
+) -> LessResult<CompileOutput> {
+    Compiler::new(options).compile_file(path)
+}
+
+/// 编译 LESS 文件并把 CSS 写入 `output`（所在目录不存在时自动创建），同时返回依赖等元信息。
+///
+/// 开启 `source_map` 且不内嵌时，映射写入 `output` 旁的 `<output>.map`；未设置
+/// [`SourceMapOptions::url`] 时 CSS 末尾的 `sourceMappingURL` 注释指向该文件名。
+pub fn compile_file_to<P: AsRef<Path>, Q: AsRef<Path>>(
+    input: P,
+    output: Q,
+    options: CompileOptions,
+) -> LessResult<CompileOutput> {
+    Compiler::new(options).compile_file_to(input, output)
+}
+
+/// 并行编译多个 LESS 文件，结果与 `entries` 顺序一一对应，单个文件失败不影响其余文件。
+///
+/// 工作线程数取 [`std::thread::available_parallelism`] 与文件数中的较小值，各线程从共享队列
+/// 中依次领取下一个文件，耗时差异较大的入口也能均匀分摊。每个入口使用独立的 [`Compiler`]。
+///
+/// ```no_run
+/// use less_oxide::CompileOptions;
+///
+/// let results = less_oxide::compile_files(vec![
+///     ("packages/a/index.less", CompileOptions::default()),
+///     ("packages/b/index.less", CompileOptions::default()),
+/// ]);
+/// for result in results {
+///     println!("{}", result.map(|output| output.css).unwrap_or_else(|err| err.to_string()));
+/// }
+/// ```
+pub fn compile_files<P: AsRef<Path> + Send>(
+    entries: Vec<(P, CompileOptions)>,
+) -> Vec<LessResult<CompileOutput>> {
+    let total = entries.len();
+    let workers = std::thread::available_parallelism()
+        .map_or(1, usize::from)
+        .min(total);
+    if workers <= 1 {
+        return entries
+            .into_iter()
+            .map(|(path, options)| compile_file_with_metadata(path, options))
+            .collect();
     }
-    if options.include_paths.is_empty() {
-        if let Some(parent) = path.parent() {
-            options.include_paths.push(parent.to_path_buf());
+
+    let queue = std::sync::Mutex::new(entries.into_iter().enumerate());
+    let mut slots: Vec<Option<LessResult<CompileOutput>>> = (0..total).map(|_| None).collect();
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..workers)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut done = Vec::new();
+                    loop {
+                        // 领取后立即释放锁，编译在锁外进行。
+                        let next = queue.lock().unwrap_or_else(|err| err.into_inner()).next();
+                        let Some((idx, (path, options))) = next else {
+                            break;
+                        };
+                        done.push((idx, compile_file_with_metadata(path, options)));
+                    }
+                    done
+                })
+            })
+            .collect();
+        for handle in handles {
+            let done = handle
+                .join()
+                .unwrap_or_else(|panic| std::panic::resume_unwind(panic));
+            for (idx, result) in done {
+                slots[idx] = Some(result);
+            }
         }
-    }
-    compile(&source, options)
+    });
+    slots
+        .into_iter()
+        .map(|slot| slot.expect("每个入口都由某个工作线程编译"))
+        .collect()
 }
 
 #[cfg(feature = "node")]
-use napi::{Error, Result};
+use crate::importer::{with_local_resolver, CallbackImportLoader, Resolution};
+#[cfg(feature = "node")]
+use napi::bindgen_prelude::AsyncTask;
+#[cfg(feature = "node")]
+use napi::threadsafe_function::{
+    ErrorStrategy, ThreadSafeCallContext, ThreadsafeFunction, ThreadsafeFunctionCallMode,
+};
+#[cfg(feature = "node")]
+use napi::{Env, Error, JsFunction, JsUnknown, Result, Task};
 #[cfg(feature = "node")]
 use napi_derive::napi;
+#[cfg(feature = "node")]
+use std::sync::mpsc;
 
 /// Node.js 侧的编译选项对象。
 #[cfg(feature = "node")]
 #[napi(object)]
+#[derive(Default)]
 pub struct JsCompileOptions {
     /// 是否压缩输出 CSS。
     pub minify: Option<bool>,
     /// 源文件路径，用于解析 @import。
     pub filename: Option<String>,
+    /// 额外的 @import 检索目录，排在 `filename` 所在目录之后。
+    pub include_paths: Option<Vec<String>>,
+    /// 算术模式：`always`（默认）、`parens-division` 或 `strict`。
+    pub math: Option<String>,
+    /// 不同单位无法换算的加减是否报错，默认沿用左侧单位。
+    pub strict_units: Option<bool>,
+    /// 未定义的变量是否按原文输出并给出警告，默认报错。
+    pub allow_undefined_variables: Option<bool>,
+    /// 同一文件被多次 @import 时是否重复展开，默认只展开一次。
+    pub multiple_imports: Option<bool>,
+    /// mixin 递归展开的最大深度。
+    pub max_mixin_depth: Option<u32>,
+    /// @import 嵌套的最大层数，默认 64。
+    pub max_import_depth: Option<u32>,
+    /// 求值产生的选择器与声明文本累计允许的最大字节数，默认不限制。
+    pub max_output_size: Option<u32>,
+    /// 父选择器展开后单条规则允许的最大选择器数。
+    pub max_selectors: Option<u32>,
+    /// 是否生成 source map（规则级映射），结果见 `compileLessWithMetadata` 返回值的 `map`。
+    pub source_map: Option<bool>,
+    /// 把 source map 以 base64 data URI 嵌入 CSS 末尾的注释，此时结果中的 `map` 为 `null`；隐含开启 `sourceMap`。
+    pub source_map_file_inline: Option<bool>,
+    /// 写入 `sourceMappingURL` 注释的映射文件地址，缺省时不写注释。
+    #[napi(js_name = "sourceMapURL")]
+    pub source_map_url: Option<String>,
+    /// 映射中的 `sourceRoot`。
+    pub source_map_rootpath: Option<String>,
+    /// 在映射的 `sourcesContent` 中嵌入源文件内容。
+    pub output_source_files: Option<bool>,
+    /// 插入到源码之前的全局变量，如 `{ 'primary-color': '#1890ff' }`。
+    pub global_vars: Option<HashMap<String, String>>,
+    /// 追加到源码之后、覆盖同名定义的变量，用于主题定制。
+    pub modify_vars: Option<HashMap<String, String>>,
+    /// 在非压缩输出中保留 `/* */` 注释；`/*! */` 注释总会保留。
+    pub keep_comments: Option<bool>,
+    /// 在 `--custom` 属性的值中替换 `@{var}` 插值。
+    pub custom_property_interpolation: Option<bool>,
+    /// 保留没有任何声明的空规则。
+    pub keep_empty_rules: Option<bool>,
+    /// 输出格式：`expanded` / `compact` / `compressed`。
+    pub output_style: Option<String>,
+    /// 合并相邻的同选择器规则并去掉重复声明。
+    pub merge_rules: Option<bool>,
+    /// 需要补全厂商前缀的目标浏览器，如 `['safari 12', 'ie 11']`。
+    pub autoprefix: Option<Vec<String>>,
+    /// 给类名加上哈希后缀（CSS Modules），映射见结果中的 `classNames`。
+    pub css_modules: Option<bool>,
+    /// 计算类名哈希时入口文件路径相对的根目录，缺省为进程的当前工作目录。
+    pub css_modules_root: Option<String>,
+    /// 参与类名哈希的附加字符串。
+    pub css_modules_salt: Option<String>,
+    /// 被导入文件中相对 `url()` 的改写方式：`off`（默认）、`local` 或 `all`。
+    pub rewrite_urls: Option<String>,
+    /// 设为 `comments` 时在每条规则前输出标明源文件与行号的注释（仅非压缩输出）。
+    pub dump_line_numbers: Option<String>,
+    /// 收集编译统计，结果见 `compileLessWithMetadata` 返回值的 `stats`。
+    pub collect_stats: Option<bool>,
+    /// 自定义 @import 解析：`(path, from) => { contents, resolvedPath } | null`，
+    /// 返回空值时按 `filename`/`includePaths` 继续查找；抛出异常时编译失败。
+    pub resolve_import: Option<JsFunction>,
+}
+
+/// `resolveImport` 回调的返回值。
+#[cfg(feature = "node")]
+#[napi(object)]
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JsImportResult {
+    /// 导入文件的源码。
+    pub contents: String,
+    /// 用于去重与依赖追踪的路径，缺省时使用原始导入路径。
+    pub resolved_path: Option<String>,
+    /// 非空时以该信息中止编译；`index.js` 把回调中抛出的异常转换为此项。
+    pub error: Option<String>,
+}
+
+/// Node.js 侧的编译结果对象，字段与 less.js `render` 的结果保持一致。
+#[cfg(feature = "node")]
+#[napi(object)]
+pub struct JsCompileOutput {
+    /// 生成的 CSS 文本。
+    pub css: String,
+    /// source map JSON，未生成时为 `null`。
+    pub map: Option<String>,
+    /// 通过 `@import` 读取的全部文件路径。
+    pub imports: Vec<String>,
+    /// 开启 `cssModules` 时原类名到改写后类名的映射。
+    pub class_names: HashMap<String, String>,
+    /// 全部顶层变量的最终值与声明位置，按首次声明的顺序排列。
+    pub variables: Vec<JsVariableInfo>,
+    /// 求值中产生的警告，可直接交给 `console.warn` 输出。
+    pub warnings: Vec<JsWarning>,
+    /// 开启 `collectStats` 时的编译统计。
+    pub stats: Option<JsCompileStats>,
+}
+
+/// 编译统计，耗时以毫秒为单位。
+#[cfg(feature = "node")]
+#[napi(object)]
+pub struct JsCompileStats {
+    /// 输出的规则数。
+    pub rules: u32,
+    /// 输出的声明数。
+    pub declarations: u32,
+    /// 全部规则的选择器数之和。
+    pub selectors: u32,
+    /// 源码中规则的最大嵌套层数。
+    pub max_nesting_depth: u32,
+    /// 展开 mixin 的总次数。
+    pub mixin_expansions: u32,
+    /// 入口与各导入文件的解析耗时。
+    pub files: Vec<JsFileStats>,
+    /// 求值及后处理的耗时。
+    pub evaluate_time: f64,
+}
+
+/// 单个文件的解析耗时。
+#[cfg(feature = "node")]
+#[napi(object)]
+pub struct JsFileStats {
+    /// 文件路径；直接编译字符串时入口为 `null`。
+    pub path: Option<String>,
+    /// 解析耗时（毫秒）。
+    pub parse_time: f64,
+}
+
+#[cfg(feature = "node")]
+impl From<CompileStats> for JsCompileStats {
+    fn from(stats: CompileStats) -> Self {
+        Self {
+            rules: stats.rules as u32,
+            declarations: stats.declarations as u32,
+            selectors: stats.selectors as u32,
+            max_nesting_depth: stats.max_nesting_depth as u32,
+            mixin_expansions: stats.mixin_expansions as u32,
+            files: stats
+                .files
+                .into_iter()
+                .map(|file| JsFileStats {
+                    path: file.path.map(|path| path.to_string_lossy().into_owned()),
+                    parse_time: file.parse_time.as_secs_f64() * 1000.0,
+                })
+                .collect(),
+            evaluate_time: stats.evaluate_time.as_secs_f64() * 1000.0,
+        }
+    }
+}
+
+/// 编译警告，如单位不兼容的运算或未知函数。
+#[cfg(feature = "node")]
+#[napi(object)]
+pub struct JsWarning {
+    /// 警告类别，如 `unit-mismatch`、`unknown-function`、`deprecated`。
+    pub code: String,
+    /// 不含位置的警告描述。
+    pub message: String,
+    /// 所在的文件；直接编译字符串或无法定位时为 `null`。
+    pub file: Option<String>,
+    /// 所在的行号，从 1 开始；无法定位时为 `null`。
+    pub line: Option<u32>,
+    /// 所在的列号，从 1 开始；无法定位时为 `null`。
+    pub column: Option<u32>,
+}
+
+/// 顶层变量的求值结果。
+#[cfg(feature = "node")]
+#[napi(object)]
+pub struct JsVariableInfo {
+    /// 不含 `@` 的变量名。
+    pub name: String,
+    /// 求值后的值。
+    pub value: String,
+    /// 声明所在的文件；直接编译字符串时为 `null`。
+    pub file: Option<String>,
+    /// 声明所在的行号，从 1 开始。
+    pub line: u32,
+    /// 声明所在的列号，从 1 开始。
+    pub column: u32,
 }
 
-/// 暴露给 Node.js 的异步编译函数。
+/// 暴露给 Node.js 的同步编译函数。
 #[cfg(feature = "node")]
 #[napi]
-pub fn compile_less(source: String, options: Option<JsCompileOptions>) -> Result<String> {
-    let opt = options.unwrap_or(JsCompileOptions {
-        minify: None,
-        filename: None,
-    });
+pub fn compile_less(env: Env, source: String, options: Option<JsCompileOptions>) -> Result<String> {
+    compile_sync(env, options, |compile_options| {
+        compile(&source, compile_options)
+    })
+}
+
+/// 解析 LESS 源码并返回 AST 的 JSON 字符串。
+#[cfg(feature = "node")]
+#[napi(js_name = "parseToJson")]
+pub fn parse_less_to_json(source: String) -> Result<String> {
+    crate::parse_to_json(&source).map_err(|err| Error::from_reason(err.to_string()))
+}
+
+/// 编译并返回 `{ css, map, imports, classNames, variables, warnings, stats }` 结构化结果。
+#[cfg(feature = "node")]
+#[napi]
+pub fn compile_less_with_metadata(
+    env: Env,
+    source: String,
+    options: Option<JsCompileOptions>,
+) -> Result<JsCompileOutput> {
+    let output = compile_sync(env, options, |compile_options| {
+        compile_with_metadata(&source, compile_options)
+    })?;
+    Ok(JsCompileOutput::from(output))
+}
+
+#[cfg(feature = "node")]
+impl From<CompileOutput> for JsCompileOutput {
+    fn from(output: CompileOutput) -> Self {
+        JsCompileOutput {
+            css: output.css,
+            map: output.source_map,
+            imports: output
+                .dependencies
+                .iter()
+                .map(|path| path.to_string_lossy().into_owned())
+                .collect(),
+            class_names: output.class_names.into_iter().collect(),
+            variables: output
+                .variables
+                .into_iter()
+                .map(|var| JsVariableInfo {
+                    name: var.name,
+                    value: var.value,
+                    file: var
+                        .location
+                        .file
+                        .map(|path| path.to_string_lossy().into_owned()),
+                    line: var.location.line as u32,
+                    column: var.location.column as u32,
+                })
+                .collect(),
+            warnings: to_js_warnings(output.diagnostics),
+            stats: output.stats.map(JsCompileStats::from),
+        }
+    }
+}
+
+/// `renderFile` 的结果：CSS 与 source map 已写入磁盘，只返回路径与依赖信息。
+#[cfg(feature = "node")]
+#[napi(object)]
+pub struct JsRenderFileOutput {
+    /// 写入的 source map 文件路径；未生成外部映射时为 `null`。
+    pub map_file: Option<String>,
+    /// 通过 `@import` 读取的全部文件路径。
+    pub imports: Vec<String>,
+    /// 求值中产生的警告。
+    pub warnings: Vec<JsWarning>,
+}
+
+/// 编译 `input` 并把 CSS（以及开启 `sourceMap` 时的 `<output>.map`）直接写入磁盘，
+/// 不在 JS 与 Rust 之间传递大段 CSS 文本，适合在 JS 中实现类似 CLI 的构建。
+#[cfg(feature = "node")]
+#[napi]
+pub fn render_file(
+    env: Env,
+    input: String,
+    output: String,
+    options: Option<JsCompileOptions>,
+) -> Result<JsRenderFileOutput> {
+    let result = compile_sync(env, options, |compile_options| {
+        compile_file_to(&input, &output, compile_options)
+    })?;
+    Ok(JsRenderFileOutput {
+        map_file: result.source_map.map(|_| format!("{output}.map")),
+        imports: result
+            .dependencies
+            .iter()
+            .map(|path| path.to_string_lossy().into_owned())
+            .collect(),
+        warnings: to_js_warnings(result.diagnostics),
+    })
+}
+
+#[cfg(feature = "node")]
+fn to_js_warnings(diagnostics: Vec<Diagnostic>) -> Vec<JsWarning> {
+    diagnostics
+        .into_iter()
+        .map(|diag| JsWarning {
+            code: diag.code.to_string(),
+            message: diag.message,
+            file: diag
+                .span
+                .as_ref()
+                .and_then(|span| span.file.as_ref())
+                .map(|path| path.to_string_lossy().into_owned()),
+            line: diag.span.as_ref().map(|span| span.line as u32),
+            column: diag.span.as_ref().map(|span| span.column as u32),
+        })
+        .collect()
+}
+
+/// 在 libuv 线程池中执行的编译任务，避免阻塞 Node 事件循环。
+#[cfg(feature = "node")]
+pub struct CompileTask {
+    input: CompileInput,
+    options: CompileOptions,
+}
+
+#[cfg(feature = "node")]
+enum CompileInput {
+    Source(String),
+    File(PathBuf),
+}
+
+#[cfg(feature = "node")]
+impl Task for CompileTask {
+    type Output = String;
+    type JsValue = String;
+
+    fn compute(&mut self) -> Result<Self::Output> {
+        let options = std::mem::take(&mut self.options);
+        let result = match &self.input {
+            CompileInput::Source(source) => compile(source, options),
+            CompileInput::File(path) => compile_file(path, options),
+        };
+        result.map_err(|err| Error::from_reason(err.to_string()))
+    }
+
+    fn resolve(&mut self, _env: Env, output: Self::Output) -> Result<Self::JsValue> {
+        Ok(output)
+    }
+}
+
+/// 异步编译 LESS 源码，返回 Promise。
+#[cfg(feature = "node")]
+#[napi]
+pub fn compile_less_async(
+    source: String,
+    options: Option<JsCompileOptions>,
+) -> Result<AsyncTask<CompileTask>> {
+    Ok(AsyncTask::new(CompileTask {
+        input: CompileInput::Source(source),
+        options: to_threadsafe_options(options)?,
+    }))
+}
+
+/// 异步编译 LESS 文件，`@import` 相对于文件所在目录解析，返回 Promise。
+#[cfg(feature = "node")]
+#[napi]
+pub fn compile_file_async(
+    path: String,
+    options: Option<JsCompileOptions>,
+) -> Result<AsyncTask<CompileTask>> {
+    Ok(AsyncTask::new(CompileTask {
+        input: CompileInput::File(PathBuf::from(path)),
+        options: to_threadsafe_options(options)?,
+    }))
+}
+
+/// `compileMany` 的一个入口。
+#[cfg(feature = "node")]
+#[napi(object)]
+pub struct JsCompileEntry {
+    /// 入口文件路径，`@import` 相对于它所在目录解析。
+    pub path: String,
+    /// 该入口的编译选项。
+    pub options: Option<JsCompileOptions>,
+}
+
+/// `compileMany` 中单个入口的结果，`result` 与 `error` 恰有一个非空。
+#[cfg(feature = "node")]
+#[napi(object)]
+pub struct JsCompileManyResult {
+    /// 入口文件路径，与传入时一致。
+    pub path: String,
+    /// 编译成功时的结构化结果，与 `compileLessWithMetadata` 相同。
+    pub result: Option<JsCompileOutput>,
+    /// 编译失败时的错误信息。
+    pub error: Option<String>,
+}
+
+/// 在 libuv 线程池中并行编译多个文件的任务，见 [`compile_files`]。
+#[cfg(feature = "node")]
+pub struct CompileManyTask {
+    entries: Vec<(PathBuf, CompileOptions)>,
+}
+
+#[cfg(feature = "node")]
+impl Task for CompileManyTask {
+    type Output = Vec<(PathBuf, LessResult<CompileOutput>)>;
+    type JsValue = Vec<JsCompileManyResult>;
+
+    fn compute(&mut self) -> Result<Self::Output> {
+        let entries = std::mem::take(&mut self.entries);
+        let paths: Vec<PathBuf> = entries.iter().map(|(path, _)| path.clone()).collect();
+        Ok(paths.into_iter().zip(compile_files(entries)).collect())
+    }
+
+    fn resolve(&mut self, _env: Env, output: Self::Output) -> Result<Self::JsValue> {
+        Ok(output
+            .into_iter()
+            .map(|(path, result)| {
+                let path = path.to_string_lossy().into_owned();
+                match result {
+                    Ok(output) => JsCompileManyResult {
+                        path,
+                        result: Some(JsCompileOutput::from(output)),
+                        error: None,
+                    },
+                    Err(err) => JsCompileManyResult {
+                        path,
+                        result: None,
+                        error: Some(err.to_string()),
+                    },
+                }
+            })
+            .collect())
+    }
+}
+
+/// 并行编译多个文件，返回与 `entries` 顺序一致的结果数组的 Promise；
+/// 单个入口失败只体现在该项的 `error` 中，不会使整个 Promise 失败。
+#[cfg(feature = "node")]
+#[napi]
+pub fn compile_many(entries: Vec<JsCompileEntry>) -> Result<AsyncTask<CompileManyTask>> {
+    let entries = entries
+        .into_iter()
+        .map(|entry| {
+            Ok((
+                PathBuf::from(entry.path),
+                to_threadsafe_options(entry.options)?,
+            ))
+        })
+        .collect::<Result<_>>()?;
+    Ok(AsyncTask::new(CompileManyTask { entries }))
+}
+
+/// 在 libuv 线程池中执行的编译所用的选项：`resolveImport` 经 threadsafe function
+/// 回到 JS 线程调用并阻塞等待结果。
+#[cfg(feature = "node")]
+fn to_threadsafe_options(options: Option<JsCompileOptions>) -> Result<CompileOptions> {
+    let (mut compile_options, callback) = to_compile_options(options)?;
+    if let Some(callback) = callback {
+        let tsfn: ThreadsafeFunction<ImportRequest, ErrorStrategy::Fatal> = callback
+            .create_threadsafe_function(0, |ctx: ThreadSafeCallContext<ImportRequest>| {
+                let (target, from) = ctx.value;
+                Ok(vec![
+                    ctx.env.create_string(&target)?.into_unknown(),
+                    match from {
+                        Some(from) => ctx.env.create_string(&from)?.into_unknown(),
+                        None => ctx.env.get_null()?.into_unknown(),
+                    },
+                ])
+            })?;
+        let loader = CallbackImportLoader::new(move |target: &str, from: Option<&Path>| {
+            let (sender, receiver) = mpsc::channel();
+            let from = from.map(|path| path.to_string_lossy().into_owned());
+            // 回调中抛出的异常无法经 threadsafe function 传回，`index.js` 会把它改写为 `{ error }`。
+            tsfn.call_with_return_value(
+                (target.to_string(), from),
+                ThreadsafeFunctionCallMode::Blocking,
+                move |result: Option<JsImportResult>| {
+                    let _ = sender.send(result);
+                    Ok(())
+                },
+            );
+            let result = receiver.recv().map_err(|_| {
+                LessError::eval(format!("resolveImport 未能处理 {target}：JS 线程已退出"))
+            })?;
+            result
+                .map(|result| into_resolution(target, result))
+                .transpose()
+        });
+        compile_options.import_loader = Some(Arc::new(loader));
+    }
+    Ok(compile_options)
+}
+
+/// 在 JS 线程上同步编译。设置了 `resolveImport` 时编译移到新线程进行，导入请求转发回
+/// 当前线程直接调用回调（见 [`with_local_resolver`]），`Env` 与 `JsFunction` 不会离开 JS 线程，
+/// 深层嵌套的输入在编译内部另开线程时也是如此。
+#[cfg(feature = "node")]
+fn compile_sync<T: Send>(
+    env: Env,
+    options: Option<JsCompileOptions>,
+    compile: impl FnOnce(CompileOptions) -> LessResult<T> + Send,
+) -> Result<T> {
+    let (mut compile_options, callback) = to_compile_options(options)?;
+    let result = match callback {
+        None => compile(compile_options),
+        Some(callback) => with_local_resolver(
+            |target, from| call_resolve_import(env, &callback, target, from),
+            move |loader| {
+                compile_options.import_loader = Some(loader);
+                compile(compile_options)
+            },
+        ),
+    };
+    result.map_err(|err| Error::from_reason(err.to_string()))
+}
+
+/// 把 JS 侧选项转换为 [`CompileOptions`]，`resolveImport` 回调单独返回，由调用方按执行线程接入。
+#[cfg(feature = "node")]
+fn to_compile_options(
+    options: Option<JsCompileOptions>,
+) -> Result<(CompileOptions, Option<JsFunction>)> {
+    let opt = options.unwrap_or_default();
     let minify = opt.minify.unwrap_or(false);
+    let math = match opt.math.as_deref() {
+        Some(name) => MathMode::from_name(name)
+            .ok_or_else(|| Error::from_reason(format!("未知的 math 选项: {name}")))?,
+        None => MathMode::default(),
+    };
+    let rewrite_urls = match opt.rewrite_urls.as_deref() {
+        Some(name) => RewriteUrls::from_name(name)
+            .ok_or_else(|| Error::from_reason(format!("未知的 rewriteUrls 选项: {name}")))?,
+        None => RewriteUrls::default(),
+    };
+    let output_style = match opt.output_style.as_deref() {
+        Some(name) => Some(
+            OutputStyle::from_name(name)
+                .ok_or_else(|| Error::from_reason(format!("未知的 outputStyle 选项: {name}")))?,
+        ),
+        None => None,
+    };
+    let dump_line_numbers = match opt.dump_line_numbers.as_deref() {
+        Some("comments") => true,
+        Some(name) => {
+            return Err(Error::from_reason(format!(
+                "未知的 dumpLineNumbers 选项: {name}（目前只支持 comments）"
+            )))
+        }
+        None => false,
+    };
+    let inline_map = opt.source_map_file_inline.unwrap_or(false);
+    let source_map = (opt.source_map.unwrap_or(false) || inline_map).then(|| SourceMapOptions {
+        inline: inline_map,
+        url: opt.source_map_url,
+        root: opt.source_map_rootpath,
+        sources_content: opt.output_source_files.unwrap_or(false),
+    });
+    let autoprefix = opt
+        .autoprefix
+        .unwrap_or_default()
+        .iter()
+        .map(|target| {
+            BrowserTarget::parse(target)
+                .ok_or_else(|| Error::from_reason(format!("无法识别的浏览器目标: {target}")))
+        })
+        .collect::<Result<Vec<_>>>()?;
     let mut compile_options = CompileOptions {
         minify,
+        math,
+        strict_units: opt.strict_units.unwrap_or(false),
+        allow_undefined_variables: opt.allow_undefined_variables.unwrap_or(false),
+        multiple_imports: opt.multiple_imports.unwrap_or(false),
+        max_mixin_depth: opt.max_mixin_depth.map(|depth| depth as usize),
+        max_import_depth: opt.max_import_depth.map(|depth| depth as usize),
+        max_output_size: opt.max_output_size.map(|size| size as usize),
+        max_selectors: opt.max_selectors.map(|limit| limit as usize),
+        global_vars: opt.global_vars.unwrap_or_default(),
+        modify_vars: opt.modify_vars.unwrap_or_default(),
+        keep_comments: opt.keep_comments.unwrap_or(false),
+        custom_property_interpolation: opt.custom_property_interpolation.unwrap_or(false),
+        keep_empty_rules: opt.keep_empty_rules.unwrap_or(false),
+        output_style,
+        merge_rules: opt.merge_rules.unwrap_or(false),
+        autoprefix,
+        css_modules: opt.css_modules.unwrap_or(false),
+        css_modules_root: opt.css_modules_root.map(PathBuf::from),
+        css_modules_salt: opt.css_modules_salt.unwrap_or_default(),
+        rewrite_urls,
+        source_map,
+        dump_line_numbers,
+        collect_stats: opt.collect_stats.unwrap_or(false),
         ..CompileOptions::default()
     };
     if let Some(filename) = opt.filename {
@@ -118,9 +1099,56 @@ pub fn compile_less(source: String, options: Option<JsCompileOptions>) -> Result
             compile_options.include_paths.push(dir);
         }
     }
-    let result =
-        compile(&source, compile_options).map_err(|err| Error::from_reason(err.to_string()))?;
-    Ok(result)
+    if let Some(paths) = opt.include_paths {
+        compile_options
+            .include_paths
+            .extend(paths.into_iter().map(PathBuf::from));
+    }
+    Ok((compile_options, opt.resolve_import))
+}
+
+/// 传给 `resolveImport` 的参数：导入路径与发起导入的文件。
+#[cfg(feature = "node")]
+type ImportRequest = (String, Option<String>);
+
+/// 同步编译时在 JS 线程上直接调用 `resolveImport`；回调抛出的异常（napi 已将其从 `env` 上清除）
+/// 与无法识别的返回值都作为编译错误返回，不会回退到文件系统。
+#[cfg(feature = "node")]
+fn call_resolve_import(
+    env: Env,
+    callback: &JsFunction,
+    target: &str,
+    from: Option<&Path>,
+) -> Resolution {
+    let failed = |err: Error| LessError::eval(format!("resolveImport 处理 {target} 失败: {err}"));
+    let from = match from {
+        Some(from) => env
+            .create_string(&from.to_string_lossy())
+            .map_err(failed)?
+            .into_unknown(),
+        None => env.get_null().map_err(failed)?.into_unknown(),
+    };
+    let args = [
+        env.create_string(target).map_err(failed)?.into_unknown(),
+        from,
+    ];
+    let result: JsUnknown = callback.call(None, &args).map_err(failed)?;
+    let result: Option<JsImportResult> = env.from_js_value(result).map_err(failed)?;
+    result
+        .map(|result| into_resolution(target, result))
+        .transpose()
+}
+
+/// 回调结果中的路径缺省时使用原始导入路径；带有 `error` 时中止编译。
+#[cfg(feature = "node")]
+fn into_resolution(target: &str, result: JsImportResult) -> LessResult<(PathBuf, String)> {
+    if let Some(error) = result.error {
+        return Err(LessError::eval(format!(
+            "resolveImport 处理 {target} 失败: {error}"
+        )));
+    }
+    let path = PathBuf::from(result.resolved_path.as_deref().unwrap_or(target));
+    Ok((path, result.contents))
 }
 
 #[cfg(test)]
@@ -292,4 +1320,227 @@ body {
         assert!(minified.starts_with("@import \"reset.css\";"));
         assert!(minified.contains("body{color:#000}"));
     }
+
+    #[test]
+    fn compile_lazy_variables_last_definition_wins() {
+        let src = r"@var: @a;
+@a: 1px;
+.box {
+  width: @var;
+  height: @b;
+}
+@a: 2px;
+@b: 3px;";
+        let css = compile(src, CompileOptions::default()).unwrap();
+        assert!(css.contains("width: 2px"));
+        assert!(css.contains("height: 3px"));
+    }
+
+    #[test]
+    fn compile_recursive_variable_is_error() {
+        let src = r"@a: @b;
+@b: @a;
+.box {
+  width: @a;
+}";
+        let err = compile(src, CompileOptions::default()).unwrap_err();
+        assert!(err.to_string().contains("循环引用"));
+    }
+
+    #[test]
+    fn compile_interpolation_in_strings_and_urls() {
+        let src = r#"@asset-path: "/static";
+@theme: dark;
+@icon: "@{asset-path}/icons";
+.logo {
+  background: url("@{asset-path}/logo-@{theme}.png");
+  mask: url(@{icon}/mask.svg);
+  content: "theme: @{theme}";
+}"#;
+        let css = compile(src, CompileOptions::default()).unwrap();
+        assert!(css.contains(r#"background: url("/static/logo-dark.png")"#));
+        assert!(css.contains("mask: url(/static/icons/mask.svg)"));
+        assert!(css.contains(r#"content: "theme: dark""#));
+    }
+
+    #[test]
+    fn compile_color_function_library() {
+        let src = r"@brand: #336699;
+.btn {
+  border: 1px solid mix(#ff0000, #0000ff);
+  color: contrast(@brand);
+  background: greyscale(spin(@brand, 30));
+  transform: translate(-50%, -50%);
+}";
+        let css = compile(src, CompileOptions::default()).unwrap();
+        assert!(css.contains("border: 1px solid #800080"));
+        assert!(css.contains("color: #ffffff"));
+        assert!(css.contains("background: #666666"));
+        assert!(css.contains("transform: translate(-50%, -50%)"));
+    }
+
+    #[test]
+    fn compile_color_constructors_with_variables() {
+        let src = r"@h: 120;
+@alpha: 0.5;
+.swatch {
+  color: hsl(@h, 100%, 25%);
+  background: rgba(0, 0, 0, @alpha);
+  border-color: rgb(var(--r), 0, 0);
+}";
+        let css = compile(src, CompileOptions::default()).unwrap();
+        assert!(css.contains("color: #008000"));
+        assert!(css.contains("background: rgba(0, 0, 0, 0.5)"));
+        assert!(css.contains("border-color: rgb(var(--r), 0, 0)"));
+    }
+
+    #[test]
+    fn compile_math_functions() {
+        let src = r"@ratio: 0.5;
+@size: 1.67px;
+.col {
+  width: percentage(@ratio);
+  height: round(@size);
+  margin: floor(@size * 3) ceil(-0.5px);
+  max-width: min(100%, 480px);
+}";
+        let css = compile(src, CompileOptions::default()).unwrap();
+        assert!(css.contains("width: 50%"));
+        assert!(css.contains("height: 2px"));
+        assert!(css.contains("margin: 5px 0px"));
+        assert!(css.contains("max-width: min(100%, 480px)"));
+    }
+
+    #[test]
+    fn compile_string_functions() {
+        let src = r#"@gap: 10px;
+@file: "theme.css";
+.box {
+  width: ~"calc(100% - @{gap})";
+  filter: e("progid:DXImageTransform.Microsoft.Alpha(Opacity=80)");
+  content: %("%d items", 3);
+  background-image: replace(@file, "css$", "png");
+  font: ~"12px/1.5 Arial";
+}"#;
+        let css = compile(src, CompileOptions::default()).unwrap();
+        assert!(css.contains("width: calc(100% - 10px)"));
+        assert!(css.contains("filter: progid:DXImageTransform.Microsoft.Alpha(Opacity=80)"));
+        assert!(css.contains(r#"content: "3 items""#));
+        assert!(css.contains(r#"background-image: "theme.png""#));
+        assert!(css.contains("font: 12px/1.5 Arial"));
+    }
+
+    #[test]
+    fn compile_list_functions() {
+        let src = r"@sizes: 12px 14px 18px;
+@themes: primary, warning, danger;
+.list {
+  font-size: extract(@sizes, 2);
+  z-index: length(@themes);
+  content: extract(@themes, 3);
+  margin: range(2px, 6px, 2);
+}";
+        let css = compile(src, CompileOptions::default()).unwrap();
+        assert!(css.contains("font-size: 14px"));
+        assert!(css.contains("z-index: 3"));
+        assert!(css.contains("content: danger"));
+        assert!(css.contains("margin: 2px 4px 6px"));
+    }
+
+    #[test]
+    fn compile_type_check_functions() {
+        let src = r"@size: 16px;
+@accent: tomato;
+.probe {
+  is-pixel: ispixel(@size);
+  is-color: iscolor(@accent);
+  is-unit: isunit(@size, em);
+  color: darken(@accent, 10%);
+}";
+        let css = compile(src, CompileOptions::default()).unwrap();
+        assert!(css.contains("is-pixel: true"));
+        assert!(css.contains("is-color: true"));
+        assert!(css.contains("is-unit: false"));
+        assert!(css.contains("color: #ff3814"));
+    }
+
+    #[test]
+    fn compile_unit_and_convert() {
+        let src = r"@duration: 0.3s;
+@ratio: 1.5em;
+.fade {
+  transition-duration: convert(@duration, ms);
+  line-height: unit(@ratio);
+  width: unit(5, px);
+  height: convert(2, px);
+  margin: convert(10px, ms);
+}";
+        let css = compile(src, CompileOptions::default()).unwrap();
+        assert!(css.contains("transition-duration: 300ms"));
+        assert!(css.contains("line-height: 1.5"));
+        assert!(css.contains("width: 5px"));
+        assert!(css.contains("height: 2"));
+        assert!(css.contains("margin: 10px"));
+    }
+
+    #[test]
+    fn compile_important_flags() {
+        let src = r#".a {
+  color: red ! important;
+  content: "keep !important";
+  width: 1px /* !important */;
+}"#;
+        let css = compile(src, CompileOptions::default()).unwrap();
+        assert!(css.contains("color: red !important;"));
+        assert!(css.contains(r#"content: "keep !important";"#));
+        assert!(css.contains("width: 1px /* !important */;"));
+    }
+
+    #[test]
+    fn compile_math_modes() {
+        let src = r"@size: 14px;
+.text {
+  line-height: @size/2;
+  width: (@size / 2);
+  height: @size + 2px;
+  margin: percentage(1 / 4);
+}";
+        let always = compile(src, CompileOptions::default()).unwrap();
+        assert!(always.contains("line-height: 7px"));
+
+        let parens = compile(
+            src,
+            CompileOptions {
+                math: MathMode::ParensDivision,
+                ..CompileOptions::default()
+            },
+        )
+        .unwrap();
+        assert!(parens.contains("line-height: 14px/2"));
+        assert!(parens.contains("width: 7px"));
+        assert!(parens.contains("height: 16px"));
+        assert!(parens.contains("margin: 25%"));
+
+        let strict = compile(
+            src,
+            CompileOptions {
+                math: MathMode::Strict,
+                ..CompileOptions::default()
+            },
+        )
+        .unwrap();
+        assert!(strict.contains("width: 7px"));
+        assert!(strict.contains("height: 14px + 2px"));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn parse_to_json_round_trips() {
+        let json = parse_to_json("@a: 1px;\n.a { color: @a !important; }").unwrap();
+        assert!(json.contains(r#""VariableRef":"a""#));
+        assert!(json.contains(r#""line":2"#));
+        let stylesheet: Stylesheet = serde_json::from_str(&json).unwrap();
+        assert_eq!(stylesheet.statements.len(), 2);
+        assert_eq!(serde_json::to_string(&stylesheet).unwrap(), json);
+    }
 }
@@ -0,0 +1,295 @@
+//! 静态检查：定义后从未使用的变量与 mixin，以及对未定义变量或 mixin 的引用。
+//!
+//! 检查按名称进行、不区分作用域：任意位置的声明都算作定义，任意位置的引用都算作使用，
+//! 因此 mixin 中依赖调用方作用域的变量不会被误报。`@@name` 的目标要到求值时才能确定，
+//! 出现时不报告未使用的变量；仍有未展开的 LESS `@import` 时不报告未定义的名称。
+//! 只报告位于入口文件中的节点，被导入的库文件不参与报告。
+
+use crate::ast::{
+    AtRule, BodylessAtRule, Declaration, DetachedCall, EachCall, Guard, ImportStatement, Lookup,
+    LookupTarget, MixinArgument, MixinCall, MixinDefinition, RuleSet, Span, Stylesheet, Value,
+    ValuePiece, VariableDeclaration, VariableInit,
+};
+use crate::diagnostics::{Diagnostic, Severity};
+use crate::error::SourceLocation;
+use crate::visit::{walk_body, walk_stylesheet, Visitor};
+use std::collections::HashSet;
+use std::path::Path;
+
+/// 检查展开导入后的样式表；`predefined` 为 `global_vars` / `modify_vars` 等外部提供的变量名。
+pub(crate) fn lint_stylesheet(
+    stylesheet: &Stylesheet,
+    entry: Option<&Path>,
+    predefined: impl IntoIterator<Item = String>,
+) -> Vec<Diagnostic> {
+    let mut collector = Collector::default();
+    collector.variables_defined.extend(predefined);
+    collector.variables_defined.insert("arguments".to_string());
+    walk_stylesheet(&mut collector, stylesheet);
+    collector.report(entry)
+}
+
+#[derive(Default)]
+struct Collector {
+    /// 当前节点的位置，作为其中引用的报告位置。
+    span: Span,
+    variables: Vec<(String, Span)>,
+    variables_defined: HashSet<String>,
+    variable_refs: Vec<(String, Span)>,
+    mixins: Vec<(String, Span)>,
+    /// 可被调用的名称：mixin 定义，以及规则集选择器中的各个 `.class` 与 `#id`。
+    callable: HashSet<String>,
+    mixin_calls: Vec<(String, Span)>,
+    indirect_refs: bool,
+    unresolved_imports: bool,
+}
+
+impl Collector {
+    fn report(self, entry: Option<&Path>) -> Vec<Diagnostic> {
+        let in_entry = |span: &Span| span.file.as_deref() == entry;
+        let variables_used: HashSet<&str> = self
+            .variable_refs
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect();
+        let mixins_used: HashSet<&str> = self
+            .mixin_calls
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect();
+        let mut diagnostics = Vec::new();
+        if !self.indirect_refs {
+            for (name, span) in &self.variables {
+                if in_entry(span) && !variables_used.contains(name.as_str()) {
+                    diagnostics.push(warning(
+                        span,
+                        format!("变量 @{name} 已定义但从未使用"),
+                        "unused-variable",
+                    ));
+                }
+            }
+        }
+        for (name, span) in &self.mixins {
+            if in_entry(span) && !mixins_used.contains(name.as_str()) {
+                diagnostics.push(warning(
+                    span,
+                    format!("mixin {name} 已定义但从未调用"),
+                    "unused-mixin",
+                ));
+            }
+        }
+        if !self.unresolved_imports {
+            for (name, span) in &self.variable_refs {
+                if in_entry(span) && !self.variables_defined.contains(name) {
+                    diagnostics.push(warning(
+                        span,
+                        format!("未定义的变量 @{name}"),
+                        "undefined-variable",
+                    ));
+                }
+            }
+            for (name, span) in &self.mixin_calls {
+                if in_entry(span) && !self.callable.contains(name) {
+                    diagnostics.push(warning(
+                        span,
+                        format!("未定义的 mixin {name}"),
+                        "undefined-mixin",
+                    ));
+                }
+            }
+        }
+        diagnostics.sort_by_key(|diagnostic| {
+            diagnostic
+                .span
+                .as_ref()
+                .map(|location| (location.line, location.column))
+        });
+        diagnostics
+    }
+
+    fn with_span(&mut self, span: &Span, visit: impl FnOnce(&mut Self)) {
+        let outer = std::mem::replace(&mut self.span, span.clone());
+        visit(self);
+        self.span = outer;
+    }
+
+    fn value_refs(&mut self, value: &Value) {
+        for piece in &value.pieces {
+            match piece {
+                ValuePiece::Literal(text) => self.interpolation_refs(text),
+                ValuePiece::VariableRef(name) => {
+                    self.variable_refs.push((name.clone(), self.span.clone()));
+                }
+                ValuePiece::IndirectVariableRef(name) => {
+                    self.variable_refs.push((name.clone(), self.span.clone()));
+                    self.indirect_refs = true;
+                }
+                ValuePiece::PropertyRef(_) => {}
+                ValuePiece::Lookup(lookup) => self.lookup_refs(lookup),
+            }
+        }
+    }
+
+    /// 查找的目标变量或 mixin，以及键中引用的变量；`[@name]` 视为使用了规则集中的同名变量。
+    fn lookup_refs(&mut self, lookup: &Lookup) {
+        match &lookup.target {
+            LookupTarget::Variable(name) => {
+                self.variable_refs.push((name.clone(), self.span.clone()));
+            }
+            LookupTarget::Mixin(call) => self.visit_mixin_call(call),
+        }
+        for key in &lookup.keys {
+            // `[@@name]` 取的变量要到求值时才能确定，与 `@@name` 一样不再报告未使用的变量。
+            self.indirect_refs |= key.starts_with("@@");
+            let name = key
+                .strip_prefix("@@")
+                .or_else(|| key.strip_prefix("$@"))
+                .or_else(|| key.strip_prefix('@'));
+            if let Some(name) = name {
+                self.variable_refs
+                    .push((name.to_string(), self.span.clone()));
+            }
+        }
+    }
+
+    /// 记录文本中 `@{name}` 形式的插值引用。
+    fn interpolation_refs(&mut self, text: &str) {
+        let mut rest = text;
+        while let Some(start) = rest.find("@{") {
+            rest = &rest[start + 2..];
+            let Some(end) = rest.find('}') else {
+                break;
+            };
+            self.variable_refs
+                .push((rest[..end].trim().to_string(), self.span.clone()));
+            rest = &rest[end + 1..];
+        }
+    }
+
+    fn guard_refs(&mut self, guard: &Guard) {
+        for condition in guard.alternatives.iter().flatten() {
+            self.value_refs(&condition.lhs);
+            if let Some((_, rhs)) = &condition.comparison {
+                self.value_refs(rhs);
+            }
+        }
+    }
+}
+
+impl Visitor for Collector {
+    fn visit_ruleset(&mut self, rule: &RuleSet) {
+        let elements = rule
+            .selectors
+            .iter()
+            .filter_map(|selector| selector.mixin_elements());
+        self.callable.extend(elements.flatten().map(str::to_string));
+        self.with_span(&rule.span, |this| {
+            for selector in &rule.selectors {
+                this.interpolation_refs(&selector.value);
+            }
+            if let Some(guard) = &rule.guard {
+                this.guard_refs(guard);
+            }
+            walk_body(this, &rule.body);
+        });
+    }
+
+    fn visit_at_rule(&mut self, at_rule: &AtRule) {
+        self.with_span(&at_rule.span, |this| {
+            this.value_refs(&at_rule.params);
+            walk_body(this, &at_rule.body);
+        });
+    }
+
+    fn visit_bodyless_at_rule(&mut self, at_rule: &BodylessAtRule) {
+        self.with_span(&at_rule.span, |this| this.value_refs(&at_rule.params));
+    }
+
+    fn visit_mixin_definition(&mut self, definition: &MixinDefinition) {
+        self.mixins
+            .push((definition.name.clone(), definition.span.clone()));
+        self.callable.insert(definition.name.clone());
+        self.with_span(&definition.span, |this| {
+            for param in &definition.params {
+                this.variables_defined.insert(param.name.clone());
+                if let Some(default) = &param.default {
+                    this.value_refs(default);
+                }
+            }
+            if let Some(guard) = &definition.guard {
+                this.guard_refs(guard);
+            }
+            walk_body(this, &definition.body);
+        });
+    }
+
+    fn visit_mixin_call(&mut self, call: &MixinCall) {
+        for name in call.namespace.iter().chain([&call.name]) {
+            self.mixin_calls.push((name.clone(), call.span.clone()));
+        }
+        self.with_span(&call.span, |this| {
+            for arg in &call.args {
+                match arg {
+                    MixinArgument::Value(value) => this.value_refs(value),
+                    MixinArgument::Ruleset(body) => walk_body(this, body),
+                }
+            }
+        });
+    }
+
+    fn visit_variable(&mut self, variable: &VariableDeclaration) {
+        self.variables
+            .push((variable.name.clone(), variable.span.clone()));
+        self.variables_defined.insert(variable.name.clone());
+        self.with_span(&variable.span, |this| match &variable.value {
+            VariableInit::Value(value) => this.value_refs(value),
+            VariableInit::Ruleset(body) => walk_body(this, body),
+        });
+    }
+
+    fn visit_declaration(&mut self, declaration: &Declaration) {
+        self.with_span(&declaration.span, |this| {
+            this.interpolation_refs(&declaration.name);
+            this.value_refs(&declaration.value);
+        });
+    }
+
+    fn visit_detached_call(&mut self, call: &DetachedCall) {
+        self.variable_refs
+            .push((call.name.clone(), call.span.clone()));
+    }
+
+    fn visit_each(&mut self, call: &EachCall) {
+        let defaults = ["value", "key", "index"]
+            .into_iter()
+            .skip(call.params.len());
+        self.variables_defined.extend(
+            call.params
+                .iter()
+                .cloned()
+                .chain(defaults.map(str::to_string)),
+        );
+        self.with_span(&call.span, |this| {
+            this.value_refs(&call.list);
+            match &call.ruleset {
+                MixinArgument::Value(value) => this.value_refs(value),
+                MixinArgument::Ruleset(body) => walk_body(this, body),
+            }
+        });
+    }
+
+    fn visit_import(&mut self, import: &ImportStatement) {
+        if !import.is_css {
+            self.unresolved_imports = true;
+        }
+    }
+}
+
+fn warning(span: &Span, message: String, code: &'static str) -> Diagnostic {
+    Diagnostic {
+        severity: Severity::Warning,
+        span: Some(SourceLocation::from_span(span)),
+        message,
+        code,
+    }
+}
@@ -0,0 +1,381 @@
+//! 压缩输出前对求值结果做的等价改写。
+//!
+//! 只处理声明值与选择器：颜色 `#ffffff` → `#fff`、长度 `0px` → `0`、`0.50` → `.5`、
+//! `00.5em` → `.5em`，字符串统一使用双引号（内容本身含 `"` 时保留原引号），
+//! 以及去掉选择器中 `>` `+` `~` 组合符两侧的空白。字符串内容、`url()` 与自定义属性保持原样；
+//! `calc()` / `min()` / `max()` / `clamp()` 中的零值需要单位，不做裁剪。
+
+use crate::evaluator::{EvaluatedDeclaration, EvaluatedNode, EvaluatedStylesheet};
+
+/// 可省略单位的长度单位。时间、角度与百分比的零值在部分上下文中必须带单位，不在其列。
+const LENGTH_UNITS: &[&str] = &[
+    "px", "em", "rem", "ex", "ch", "vw", "vh", "vmin", "vmax", "cm", "mm", "q", "in", "pt", "pc",
+];
+
+pub(crate) fn minify_stylesheet(stylesheet: &mut EvaluatedStylesheet) {
+    minify_nodes(&mut stylesheet.nodes);
+}
+
+pub(crate) fn minify_nodes(nodes: &mut [EvaluatedNode]) {
+    for node in nodes {
+        match node {
+            EvaluatedNode::Rule(rule) => {
+                for selector in &mut rule.selectors {
+                    *selector = minify_selector(selector);
+                }
+                minify_declarations(&mut rule.declarations);
+            }
+            EvaluatedNode::AtRule(at_rule) => {
+                minify_declarations(&mut at_rule.declarations);
+                minify_nodes(&mut at_rule.children);
+            }
+            EvaluatedNode::Raw(_) | EvaluatedNode::Comment(_) => {}
+        }
+    }
+}
+
+fn minify_declarations(declarations: &mut [EvaluatedDeclaration]) {
+    for decl in declarations {
+        let name = decl.name.trim().to_ascii_lowercase();
+        // 自定义属性原样输出；`flex` 简写中无单位的 0 会被解析为 flex-grow/shrink。
+        if name.starts_with("--") || name == "flex" {
+            continue;
+        }
+        decl.value = minify_value(&decl.value);
+    }
+}
+
+/// 改写值中的颜色与数值记号。
+fn minify_value(value: &str) -> String {
+    let mut output = String::with_capacity(value.len());
+    // 当前所在的函数名，用于识别需要保留单位的数学函数。
+    let mut functions: Vec<String> = Vec::new();
+    let mut idx = 0;
+    while let Some(ch) = value[idx..].chars().next() {
+        let boundary = output
+            .chars()
+            .next_back()
+            .is_none_or(|prev| prev.is_whitespace() || matches!(prev, ',' | '(' | '/'));
+        match ch {
+            '"' | '\'' => {
+                let end = string_end(value, idx);
+                output.push_str(&normalize_quotes(&value[idx..end]));
+                idx = end;
+            }
+            '(' => {
+                let name = function_name(&output).to_ascii_lowercase();
+                if name == "url" {
+                    let end = closing_paren(value, idx);
+                    output.push_str(&value[idx..end]);
+                    idx = end;
+                    continue;
+                }
+                functions.push(name);
+                output.push(ch);
+                idx += 1;
+            }
+            ')' => {
+                functions.pop();
+                output.push(ch);
+                idx += 1;
+            }
+            '#' if boundary => {
+                let hex_len = value[idx + 1..]
+                    .chars()
+                    .take_while(char::is_ascii_hexdigit)
+                    .count();
+                let end = idx + 1 + hex_len;
+                let ends_token = value[end..]
+                    .chars()
+                    .next()
+                    .is_none_or(|next| !next.is_alphanumeric() && next != '-' && next != '_');
+                if ends_token && matches!(hex_len, 6 | 8) {
+                    output.push('#');
+                    output.push_str(&shorten_hex(&value[idx + 1..end]));
+                } else {
+                    output.push_str(&value[idx..end]);
+                }
+                idx = end;
+            }
+            '0'..='9' | '.' | '-' | '+' if boundary => {
+                let end = number_end(value, idx);
+                if end == idx {
+                    output.push(ch);
+                    idx += ch.len_utf8();
+                    continue;
+                }
+                let keep_units = functions
+                    .iter()
+                    .any(|name| matches!(name.as_str(), "calc" | "min" | "max" | "clamp"));
+                output.push_str(&minify_number(&value[idx..end], keep_units));
+                idx = end;
+            }
+            _ => {
+                output.push(ch);
+                idx += ch.len_utf8();
+            }
+        }
+    }
+    output
+}
+
+/// `aabbcc` → `abc`、`aabbccdd` → `abcd`，无法缩短时原样返回。
+fn shorten_hex(hex: &str) -> String {
+    let bytes = hex.as_bytes();
+    let shortenable = bytes
+        .chunks(2)
+        .all(|pair| pair[0].eq_ignore_ascii_case(&pair[1]));
+    if shortenable {
+        bytes.chunks(2).map(|pair| pair[0] as char).collect()
+    } else {
+        hex.to_string()
+    }
+}
+
+/// 数值记号（可带符号、小数与单位）的结束位置；不是数值时返回 `start`。
+fn number_end(value: &str, start: usize) -> usize {
+    let bytes = value.as_bytes();
+    let mut idx = start;
+    if matches!(bytes.get(idx), Some(b'-' | b'+')) {
+        idx += 1;
+    }
+    let digits_start = idx;
+    while bytes.get(idx).is_some_and(u8::is_ascii_digit) {
+        idx += 1;
+    }
+    if bytes.get(idx) == Some(&b'.') && bytes.get(idx + 1).is_some_and(u8::is_ascii_digit) {
+        idx += 1;
+        while bytes.get(idx).is_some_and(u8::is_ascii_digit) {
+            idx += 1;
+        }
+    }
+    if idx == digits_start {
+        return start;
+    }
+    while bytes
+        .get(idx)
+        .is_some_and(|b| b.is_ascii_alphabetic() || *b == b'%')
+    {
+        idx += 1;
+    }
+    // 数值后紧跟标识符字符（如 `1a-2`、`0_x`）时不是单独的数值记号。
+    if bytes
+        .get(idx)
+        .is_some_and(|b| b.is_ascii_digit() || matches!(b, b'-' | b'_' | b'.'))
+    {
+        return start;
+    }
+    idx
+}
+
+/// 去掉整数部分的前导零与小数部分的末尾零；零值省略符号，无单位或可省略单位时写作 `0`。
+fn minify_number(token: &str, keep_units: bool) -> String {
+    let (sign, rest) = match token.strip_prefix('-') {
+        Some(rest) => ("-", rest),
+        None => match token.strip_prefix('+') {
+            Some(rest) => ("+", rest),
+            None => ("", token),
+        },
+    };
+    let unit_start = rest
+        .find(|c: char| c.is_ascii_alphabetic() || c == '%')
+        .unwrap_or(rest.len());
+    let (number, unit) = rest.split_at(unit_start);
+    let (integer, fraction) = number.split_once('.').unwrap_or((number, ""));
+    let integer = integer.trim_start_matches('0');
+    let fraction = fraction.trim_end_matches('0');
+    if integer.is_empty() && fraction.is_empty() {
+        let omit_unit = !keep_units && LENGTH_UNITS.contains(&unit.to_ascii_lowercase().as_str());
+        return if omit_unit {
+            "0".to_string()
+        } else {
+            format!("0{unit}")
+        };
+    }
+    if fraction.is_empty() {
+        format!("{sign}{integer}{unit}")
+    } else {
+        format!("{sign}{integer}.{fraction}{unit}")
+    }
+}
+
+/// 把单引号字符串改写为双引号字符串；内容含 `"` 时原样返回。
+fn normalize_quotes(string: &str) -> String {
+    let Some(inner) = string
+        .strip_prefix('\'')
+        .and_then(|rest| rest.strip_suffix('\''))
+    else {
+        return string.to_string();
+    };
+    if inner.contains('"') {
+        return string.to_string();
+    }
+    let mut output = String::with_capacity(string.len());
+    output.push('"');
+    let mut chars = inner.chars();
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            output.push(ch);
+            continue;
+        }
+        // 双引号中的 `'` 不再需要转义。
+        match chars.next() {
+            Some('\'') => output.push('\''),
+            Some(next) => {
+                output.push('\\');
+                output.push(next);
+            }
+            None => output.push('\\'),
+        }
+    }
+    output.push('"');
+    output
+}
+
+/// 去掉 `>` `+` `~` 组合符两侧的空白；属性选择器与字符串中的内容不变。
+fn minify_selector(selector: &str) -> String {
+    let mut output = String::with_capacity(selector.len());
+    let mut bracket_depth = 0usize;
+    let mut idx = 0;
+    while let Some(ch) = selector[idx..].chars().next() {
+        match ch {
+            '"' | '\'' => {
+                let end = string_end(selector, idx);
+                output.push_str(&normalize_quotes(&selector[idx..end]));
+                idx = end;
+                continue;
+            }
+            '\\' => {
+                let escaped = selector[idx + 1..].chars().next().map_or(0, char::len_utf8);
+                output.push_str(&selector[idx..idx + 1 + escaped]);
+                idx += 1 + escaped;
+                continue;
+            }
+            '[' => bracket_depth += 1,
+            ']' => bracket_depth = bracket_depth.saturating_sub(1),
+            '>' | '+' | '~' if bracket_depth == 0 => {
+                while output.ends_with(char::is_whitespace) {
+                    output.pop();
+                }
+                output.push(ch);
+                idx += 1;
+                while selector[idx..].starts_with(char::is_whitespace) {
+                    idx += 1;
+                }
+                continue;
+            }
+            _ => {}
+        }
+        output.push(ch);
+        idx += ch.len_utf8();
+    }
+    output
+}
+
+fn function_name(output: &str) -> &str {
+    let name_len: usize = output
+        .chars()
+        .rev()
+        .take_while(|c| c.is_alphanumeric() || *c == '-' || *c == '_')
+        .map(char::len_utf8)
+        .sum();
+    &output[output.len() - name_len..]
+}
+
+fn string_end(input: &str, start: usize) -> usize {
+    let mut chars = input[start..].char_indices();
+    let Some((_, quote)) = chars.next() else {
+        return input.len();
+    };
+    while let Some((offset, ch)) = chars.next() {
+        if ch == '\\' {
+            chars.next();
+        } else if ch == quote {
+            return start + offset + ch.len_utf8();
+        }
+    }
+    input.len()
+}
+
+/// `open` 处括号对应的 `)` 之后的位置，括号不配对时返回末尾。
+fn closing_paren(input: &str, open: usize) -> usize {
+    let mut depth = 0usize;
+    let mut idx = open;
+    while let Some(ch) = input[idx..].chars().next() {
+        match ch {
+            '"' | '\'' => {
+                idx = string_end(input, idx);
+                continue;
+            }
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return idx + 1;
+                }
+            }
+            _ => {}
+        }
+        idx += ch.len_utf8();
+    }
+    input.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shortens_colors_and_numbers() {
+        assert_eq!(minify_value("#ffffff"), "#fff");
+        assert_eq!(minify_value("1px solid #AABBCC"), "1px solid #ABC");
+        assert_eq!(minify_value("#11223344 #123456"), "#1234 #123456");
+        assert_eq!(minify_value("0px 0em 0.5em -0.25rem"), "0 0 .5em -.25rem");
+        assert_eq!(minify_value("0s 0% 0deg 10px"), "0s 0% 0deg 10px");
+        assert_eq!(minify_value("rgba(0, 0, 0, 0.5)"), "rgba(0, 0, 0, .5)");
+    }
+
+    #[test]
+    fn trims_redundant_zeros() {
+        assert_eq!(minify_value("0.0"), "0");
+        assert_eq!(minify_value("1.50 00.5em 010px"), "1.5 .5em 10px");
+        assert_eq!(minify_value("-0.0px +0 2.000s"), "0 0 2s");
+        assert_eq!(minify_value("0.0s 00% 1.0"), "0s 0% 1");
+        assert_eq!(minify_value("calc(0.0px + 1.50em)"), "calc(0px + 1.5em)");
+    }
+
+    #[test]
+    fn prefers_double_quotes() {
+        assert_eq!(minify_value("'a b'"), r#""a b""#);
+        assert_eq!(minify_value(r"'it\'s'"), r#""it's""#);
+        assert_eq!(minify_value(r#"'say "hi"'"#), r#"'say "hi"'"#);
+        assert_eq!(minify_value(r"'a\\b'"), r#""a\\b""#);
+        assert_eq!(minify_selector("[title='x']"), r#"[title="x"]"#);
+    }
+
+    #[test]
+    fn keeps_strings_urls_and_math_functions() {
+        assert_eq!(minify_value(r##""#ffffff 0px""##), r##""#ffffff 0px""##);
+        assert_eq!(
+            minify_value("url(img/0px-#ffffff.png) 0px"),
+            "url(img/0px-#ffffff.png) 0"
+        );
+        assert_eq!(minify_value("calc(0px + 1em)"), "calc(0px + 1em)");
+        assert_eq!(minify_value("translate(0px, 0.5px)"), "translate(0, .5px)");
+        assert_eq!(minify_value("h1-0px a0px"), "h1-0px a0px");
+    }
+
+    #[test]
+    fn removes_space_around_combinators() {
+        assert_eq!(minify_selector(".a > .b + .c ~ .d"), ".a>.b+.c~.d");
+        assert_eq!(minify_selector(".a .b"), ".a .b");
+        assert_eq!(
+            minify_selector(r#"[class ~= "a > b"]"#),
+            r#"[class ~= "a > b"]"#
+        );
+        assert_eq!(
+            minify_selector("li:nth-child(2n + 1)"),
+            "li:nth-child(2n+1)"
+        );
+    }
+}
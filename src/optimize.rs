@@ -0,0 +1,56 @@
+//! `CompileOptions::merge_rules` 开启时对求值结果做的合并优化。
+//!
+//! 相邻且选择器完全相同的规则合并为一条，随后去掉名称、值与 `!important` 都相同的重复声明，
+//! 只保留最后一次出现的位置，因此层叠结果不变。
+
+use crate::evaluator::{EvaluatedDeclaration, EvaluatedNode, EvaluatedStylesheet};
+
+pub(crate) fn merge_rules(stylesheet: &mut EvaluatedStylesheet) {
+    merge_nodes(&mut stylesheet.nodes);
+}
+
+fn merge_nodes(nodes: &mut Vec<EvaluatedNode>) {
+    let mut merged: Vec<EvaluatedNode> = Vec::with_capacity(nodes.len());
+    for mut node in nodes.drain(..) {
+        if let EvaluatedNode::AtRule(at_rule) = &mut node {
+            merge_nodes(&mut at_rule.children);
+            dedupe_declarations(&mut at_rule.declarations);
+        }
+        if let (Some(EvaluatedNode::Rule(previous)), EvaluatedNode::Rule(rule)) =
+            (merged.last_mut(), &mut node)
+        {
+            if previous.selectors == rule.selectors {
+                previous.declarations.append(&mut rule.declarations);
+                continue;
+            }
+        }
+        merged.push(node);
+    }
+    for node in &mut merged {
+        if let EvaluatedNode::Rule(rule) = node {
+            dedupe_declarations(&mut rule.declarations);
+        }
+    }
+    *nodes = merged;
+}
+
+/// 删除与后面某条声明完全相同的声明；被删声明前的注释移到保留的那条之前。
+fn dedupe_declarations(declarations: &mut Vec<EvaluatedDeclaration>) {
+    let mut kept: Vec<EvaluatedDeclaration> = Vec::with_capacity(declarations.len());
+    for decl in declarations.drain(..).rev() {
+        match kept.iter_mut().find(|later| same_declaration(later, &decl)) {
+            Some(later) => {
+                let mut comments = decl.comments;
+                comments.append(&mut later.comments);
+                later.comments = comments;
+            }
+            None => kept.push(decl),
+        }
+    }
+    kept.reverse();
+    *declarations = kept;
+}
+
+fn same_declaration(a: &EvaluatedDeclaration, b: &EvaluatedDeclaration) -> bool {
+    a.name.trim() == b.name.trim() && a.value.trim() == b.value.trim() && a.important == b.important
+}
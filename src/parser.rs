@@ -1,79 +1,190 @@
 use crate::ast::*;
 use crate::error::{LessError, LessResult};
+use crate::lexer::{is_identifier_char, Lexer, Token, TokenKind};
+use crate::stack;
+use std::path::Path;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
 
 /// LESS 解析器，负责把源码转换成 AST。
-pub struct LessParser;
+#[derive(Default)]
+pub struct LessParser {
+    /// 为 true 时出错的语句被跳过并记录到 `errors`，解析继续进行。
+    error_recovery: bool,
+    errors: Mutex<Vec<LessError>>,
+}
 
 impl LessParser {
     pub fn new() -> Self {
-        Self
+        Self::default()
+    }
+
+    /// 容错模式：语句解析失败时跳到下一条语句（`;` 或配平的 `{}` 之后）继续，
+    /// 错误通过 [`LessParser::take_errors`] 取出。
+    pub fn with_error_recovery() -> Self {
+        Self {
+            error_recovery: true,
+            ..Self::default()
+        }
+    }
+
+    /// 取出容错模式下累计的解析错误。
+    pub fn take_errors(&self) -> Vec<LessError> {
+        std::mem::take(&mut *self.errors())
+    }
+
+    pub(crate) fn recovers_errors(&self) -> bool {
+        self.error_recovery
+    }
+
+    pub(crate) fn error_count(&self) -> usize {
+        self.errors().len()
+    }
+
+    /// 记录解析之外的前端错误（如导入失败），与解析错误一同由 `take_errors` 取出。
+    pub(crate) fn report_error(&self, err: LessError) {
+        self.errors().push(err);
+    }
+
+    fn errors(&self) -> MutexGuard<'_, Vec<LessError>> {
+        self.errors.lock().unwrap_or_else(PoisonError::into_inner)
     }
 
     pub fn parse(&self, input: &str) -> LessResult<Stylesheet> {
-        let mut cursor = Cursor::new(input);
+        self.parse_source(input, None)
+    }
+
+    /// 解析来自 `path` 的源码，节点位置与错误信息都会带上该文件。
+    pub fn parse_file(&self, input: &str, path: &Path) -> LessResult<Stylesheet> {
+        self.parse_source(input, Some(Arc::from(path)))
+    }
+
+    /// 嵌套较深的源码在栈空间足够的线程中解析，参见 [`stack::grow`]。
+    fn parse_source(&self, input: &str, file: Option<Arc<Path>>) -> LessResult<Stylesheet> {
+        stack::grow(stack::nesting_depth(input), || {
+            self.parse_source_inline(input, file)
+        })
+    }
+
+    fn parse_source_inline(&self, input: &str, file: Option<Arc<Path>>) -> LessResult<Stylesheet> {
+        let line_starts: Vec<usize> = std::iter::once(0)
+            .chain(input.match_indices('\n').map(|(idx, _)| idx + 1))
+            .collect();
+        let mut cursor = Cursor::new(input, &line_starts, file.as_ref());
+        self.parse_statements(&mut cursor)
+            .map_err(|err| err.with_source(input, file.map(|path| path.to_path_buf())))
+    }
+
+    fn parse_statements(&self, cursor: &mut Cursor<'_>) -> LessResult<Stylesheet> {
         let mut statements = Vec::new();
 
         while !cursor.is_eof() {
-            cursor.skip_whitespace_and_comments();
+            let start = cursor.index;
+            match cursor.read_comment() {
+                Ok(Some(comment)) => {
+                    statements.push(Statement::Comment(comment));
+                    continue;
+                }
+                Ok(None) => {}
+                Err(err) => {
+                    self.recover::<Statement>(cursor, start, Err(err), true)?;
+                    continue;
+                }
+            }
             if cursor.is_eof() {
                 break;
             }
 
-            if cursor.starts_with('@') && cursor.lookahead_is_variable_decl()? {
-                let var = self.parse_variable(&mut cursor)?;
-                statements.push(Statement::Variable(var));
-                continue;
+            let start = cursor.index;
+            let statement = self.parse_statement(cursor);
+            if let Some(statement) = self.recover(cursor, start, statement, true)? {
+                statements.push(statement);
             }
+        }
 
-            if cursor.starts_with('@') && cursor.lookahead_is_import()? {
-                let import = self.parse_import(&mut cursor)?;
-                statements.push(Statement::Import(import));
-                continue;
-            }
+        Ok(Stylesheet::new(statements))
+    }
 
-            if cursor.starts_with('@') && cursor.lookahead_is_block_at_rule()? {
-                let at_rule = self.parse_at_rule(&mut cursor)?;
-                statements.push(Statement::AtRule(at_rule));
-                continue;
-            }
+    fn parse_statement(&self, cursor: &mut Cursor<'_>) -> LessResult<Statement> {
+        if cursor.at_delim('}') {
+            return Err(LessError::parse("多余的 '}'", cursor.position()));
+        }
 
-            if cursor.lookahead_is_mixin_definition()? {
-                let mixin = self.parse_mixin_definition(&mut cursor)?;
-                statements.push(Statement::MixinDefinition(mixin));
-                continue;
+        Ok(match cursor.statement_kind(true) {
+            StatementKind::Variable => Statement::Variable(self.parse_variable(cursor)?),
+            StatementKind::Import => Statement::Import(self.parse_import(cursor)?),
+            StatementKind::Plugin => Statement::Plugin(self.parse_plugin(cursor)?),
+            StatementKind::Charset => Statement::Charset(self.parse_charset(cursor)?),
+            StatementKind::AtRule => Statement::AtRule(self.parse_at_rule(cursor)?),
+            StatementKind::DetachedCall => {
+                Statement::DetachedCall(self.parse_detached_call(cursor)?)
             }
-
-            if cursor.lookahead_is_mixin_call()? {
-                let call = self.parse_mixin_call(&mut cursor)?;
-                statements.push(Statement::MixinCall(call));
-                continue;
+            StatementKind::BodylessAtRule => {
+                Statement::BodylessAtRule(self.parse_bodyless_at_rule(cursor)?)
             }
+            StatementKind::Each => Statement::Each(self.parse_each(cursor)?),
+            StatementKind::MixinDefinition => {
+                Statement::MixinDefinition(self.parse_mixin_definition(cursor)?)
+            }
+            StatementKind::MixinCall => Statement::MixinCall(self.parse_mixin_call(cursor)?),
+            _ => Statement::RuleSet(self.parse_ruleset(cursor)?),
+        })
+    }
 
-            let rule = self.parse_ruleset(&mut cursor)?;
-            statements.push(Statement::RuleSet(rule));
+    /// 容错模式下记录 `result` 中的错误，并把游标移到下标为 `start` 的记号所在语句之后；
+    /// 返回 `Ok(None)` 表示该语句已被跳过。
+    fn recover<T>(
+        &self,
+        cursor: &mut Cursor<'_>,
+        start: usize,
+        result: LessResult<T>,
+        top_level: bool,
+    ) -> LessResult<Option<T>> {
+        match result {
+            Ok(value) => Ok(Some(value)),
+            Err(err) if self.error_recovery => {
+                let file = cursor.file.map(|path| path.to_path_buf());
+                self.errors().push(err.with_source(cursor.source, file));
+                cursor.index = start;
+                cursor.skip_statement(top_level);
+                Ok(None)
+            }
+            Err(err) => Err(err),
         }
+    }
 
-        Ok(Stylesheet::new(statements))
+    /// 解析规则体中的一项，容错模式下出错的项被跳过。
+    fn parse_body_item(&self, cursor: &mut Cursor<'_>) -> LessResult<Option<RuleBody>> {
+        let start = cursor.index;
+        let item = self.parse_rule_body_item(cursor);
+        self.recover(cursor, start, item, false)
     }
 
     fn parse_variable(&self, cursor: &mut Cursor<'_>) -> LessResult<VariableDeclaration> {
-        cursor.expect_char('@')?;
-        let name = cursor.read_identifier();
-        cursor.skip_whitespace_and_comments();
-        cursor.expect_char(':')?;
-        cursor.skip_whitespace_and_comments();
-
-        let value = self.read_value(cursor, &[';'])?;
-        if cursor.peek_char() == Some(';') {
-            cursor.advance_char();
-        }
+        let start = cursor.position();
+        let name = cursor.expect_at_keyword("变量名不能为空")?.to_string();
+        cursor.skip_trivia();
+        cursor.expect_delim(':')?;
+        cursor.skip_trivia();
+
+        let value = if cursor.eat_delim('{') {
+            VariableInit::Ruleset(self.parse_mixin_body(cursor)?)
+        } else {
+            VariableInit::Value(self.read_value(cursor, &[';'])?)
+        };
+        cursor.skip_trivia();
+        cursor.eat_delim(';');
 
-        Ok(VariableDeclaration { name, value })
+        Ok(VariableDeclaration {
+            name,
+            value,
+            span: cursor.span_from(start),
+        })
     }
 
     fn parse_ruleset(&self, cursor: &mut Cursor<'_>) -> LessResult<RuleSet> {
-        cursor.skip_whitespace_and_comments();
-        let selector_raw = cursor.read_until('{')?;
+        cursor.skip_trivia();
+        let start = cursor.position();
+        let selector_raw = cursor.read_selector()?;
         let selectors = selector_raw
             .split(',')
             .map(|s| Selector {
@@ -86,13 +197,23 @@ impl LessParser {
             return Err(LessError::parse("缺少合法的选择器", cursor.position()));
         }
 
-        cursor.expect_char('{')?;
+        let guard = if cursor.eat_keyword("when") {
+            let guard = self.parse_guard(cursor)?;
+            cursor.skip_trivia();
+            Some(guard)
+        } else {
+            None
+        };
+
+        cursor.expect_delim('{')?;
         let mut body = Vec::new();
 
         loop {
-            cursor.skip_whitespace_and_comments();
-            if cursor.peek_char() == Some('}') {
-                cursor.advance_char();
+            if let Some(comment) = cursor.read_comment()? {
+                body.push(RuleBody::Comment(comment));
+                continue;
+            }
+            if cursor.eat_delim('}') {
                 break;
             }
 
@@ -100,198 +221,339 @@ impl LessParser {
                 return Err(LessError::parse("缺少匹配的 '}'", cursor.position()));
             }
 
-            let item = self.parse_rule_body_item(cursor)?;
-            body.push(item);
+            if let Some(item) = self.parse_body_item(cursor)? {
+                body.push(item);
+            }
         }
 
-        Ok(RuleSet { selectors, body })
+        Ok(RuleSet {
+            selectors,
+            guard,
+            body,
+            span: cursor.span_from(start),
+        })
     }
 
-    fn parse_at_rule(&self, cursor: &mut Cursor<'_>) -> LessResult<AtRule> {
-        cursor.expect_char('@')?;
-        let name = cursor.read_identifier();
-        if name.is_empty() {
-            return Err(LessError::parse("at-rule 名称不能为空", cursor.position()));
-        }
-        cursor.skip_whitespace_and_comments();
-        let mut params = String::new();
-        let mut paren_depth = 0usize;
-        while let Some(ch) = cursor.peek_char() {
-            if ch == '{' && paren_depth == 0 {
-                break;
-            }
-            match ch {
-                '(' => paren_depth += 1,
-                ')' => {
-                    if paren_depth > 0 {
-                        paren_depth -= 1;
-                    }
+    /// 解析 `when` 之后的守卫条件，直到 `{` 之前。
+    fn parse_guard(&self, cursor: &mut Cursor<'_>) -> LessResult<Guard> {
+        let mut alternatives = Vec::new();
+        loop {
+            let mut conditions = Vec::new();
+            loop {
+                conditions.push(self.parse_guard_condition(cursor)?);
+                cursor.skip_trivia();
+                if !cursor.eat_keyword("and") {
+                    break;
                 }
-                _ => {}
             }
-            params.push(ch);
-            cursor.advance_char();
+            alternatives.push(conditions);
+            if !cursor.eat_delim(',') && !cursor.eat_keyword("or") {
+                break;
+            }
+        }
+        Ok(Guard { alternatives })
+    }
+
+    fn parse_guard_condition(&self, cursor: &mut Cursor<'_>) -> LessResult<GuardCondition> {
+        cursor.skip_trivia();
+        let negated = cursor.eat_keyword("not");
+        if negated {
+            cursor.skip_trivia();
+        }
+        if !cursor.eat_delim('(') {
+            return Err(LessError::parse(
+                "守卫条件需要以 '(' 开始",
+                cursor.position(),
+            ));
         }
-        cursor.skip_whitespace_and_comments();
-        if cursor.starts_with_keyword("when") {
-            cursor.consume_keyword("when");
-            cursor.skip_whitespace_and_comments();
+        cursor.skip_trivia();
+        let lhs = self.read_value(cursor, &['<', '>', '=', ')'])?;
+        let operator = if cursor.eat_delim('=') {
+            Some(if cursor.eat_delim('<') {
+                GuardOperator::Le
+            } else if cursor.eat_delim('>') {
+                GuardOperator::Ge
+            } else {
+                GuardOperator::Eq
+            })
+        } else if cursor.eat_delim('<') {
+            Some(if cursor.eat_delim('=') {
+                GuardOperator::Le
+            } else {
+                GuardOperator::Lt
+            })
+        } else if cursor.eat_delim('>') {
+            Some(if cursor.eat_delim('=') {
+                GuardOperator::Ge
+            } else {
+                GuardOperator::Gt
+            })
+        } else {
+            None
+        };
+        let comparison = match operator {
+            Some(operator) => {
+                cursor.skip_trivia();
+                Some((operator, self.read_value(cursor, &[')'])?))
+            }
+            None => None,
+        };
+        cursor.expect_delim(')')?;
+        Ok(GuardCondition {
+            negated,
+            lhs,
+            comparison,
+        })
+    }
+
+    fn parse_at_rule(&self, cursor: &mut Cursor<'_>) -> LessResult<AtRule> {
+        let start = cursor.position();
+        let name = cursor
+            .expect_at_keyword("at-rule 名称不能为空")?
+            .to_string();
+        cursor.skip_trivia();
+        let params = self.read_value(cursor, &['{'])?;
+        cursor.skip_trivia();
+        if cursor.eat_keyword("when") {
             cursor.skip_guard_condition();
-            cursor.skip_whitespace_and_comments();
         }
-        cursor.expect_char('{')?;
+        cursor.expect_delim('{')?;
         let body = self.parse_at_rule_body(cursor)?;
         Ok(AtRule {
             name,
-            params: params.trim().to_string(),
+            params,
             body,
+            span: cursor.span_from(start),
+        })
+    }
+
+    fn parse_bodyless_at_rule(&self, cursor: &mut Cursor<'_>) -> LessResult<BodylessAtRule> {
+        let start = cursor.position();
+        let name = cursor
+            .expect_at_keyword("at-rule 名称不能为空")?
+            .to_string();
+        cursor.skip_trivia();
+        let params = self.read_value(cursor, &[';', '}'])?;
+        cursor.eat_delim(';');
+        Ok(BodylessAtRule {
+            name,
+            params,
+            span: cursor.span_from(start),
         })
     }
 
     fn parse_at_rule_body(&self, cursor: &mut Cursor<'_>) -> LessResult<Vec<RuleBody>> {
+        self.parse_block_body(cursor, "at-rule 缺少匹配的 '}'")
+    }
+
+    /// 读取 `{` 之后直到配对 `}`（含）的规则体。
+    fn parse_block_body(
+        &self,
+        cursor: &mut Cursor<'_>,
+        unclosed: &'static str,
+    ) -> LessResult<Vec<RuleBody>> {
         let mut body = Vec::new();
         loop {
-            cursor.skip_whitespace_and_comments();
-            match cursor.peek_char() {
-                Some('}') => {
-                    cursor.advance_char();
-                    break;
-                }
-                None => {
-                    return Err(LessError::parse(
-                        "at-rule 缺少匹配的 '}'",
-                        cursor.position(),
-                    ));
-                }
-                _ => {
-                    let item = self.parse_rule_body_item(cursor)?;
-                    body.push(item);
-                }
+            if let Some(comment) = cursor.read_comment()? {
+                body.push(RuleBody::Comment(comment));
+                continue;
+            }
+            if cursor.eat_delim('}') {
+                break;
+            }
+            if cursor.is_eof() {
+                return Err(LessError::parse(unclosed, cursor.position()));
+            }
+            if let Some(item) = self.parse_body_item(cursor)? {
+                body.push(item);
             }
         }
         Ok(body)
     }
 
     fn parse_declaration(&self, cursor: &mut Cursor<'_>) -> LessResult<Declaration> {
-        let name = cursor.read_property_name();
-        cursor.skip_whitespace_and_comments();
-        cursor.expect_char(':')?;
-        cursor.skip_whitespace_and_comments();
-        let value = self.read_value(cursor, &[';', '}'])?;
-        let important = false;
-
-        if cursor.peek_char() == Some(';') {
-            cursor.advance_char();
-        }
+        let start = cursor.position();
+        let raw_name = cursor.read_property_name();
+        let (name, merge) = if let Some(name) = raw_name.strip_suffix("+_") {
+            (name.trim_end().to_string(), PropertyMerge::Space)
+        } else if let Some(name) = raw_name.strip_suffix('+') {
+            (name.trim_end().to_string(), PropertyMerge::Comma)
+        } else {
+            (raw_name, PropertyMerge::None)
+        };
+        cursor.skip_trivia();
+        cursor.expect_delim(':')?;
+        cursor.skip_trivia();
+        let value = self.read_value_with(cursor, &[';', '}'], true)?;
+        let important = cursor.eat_important();
+        cursor.skip_trivia();
+        cursor.eat_delim(';');
 
         Ok(Declaration {
             name,
             value,
             important,
+            merge,
+            span: cursor.span_from(start),
+        })
+    }
+
+    /// `--name: value`：值按原文保留（只去掉首尾空白），不做变量替换与运算，
+    /// 末尾的 `!important` 单独记录。
+    fn parse_custom_property(&self, cursor: &mut Cursor<'_>) -> LessResult<Declaration> {
+        let start = cursor.position();
+        let name = cursor.read_property_name();
+        cursor.skip_whitespace();
+        cursor.expect_delim(':')?;
+        let raw = cursor.read_raw_value();
+        let (value, important) = strip_important(raw.trim());
+        cursor.eat_delim(';');
+
+        Ok(Declaration {
+            name,
+            value: Value::new(vec![ValuePiece::Literal(value.to_string())]),
+            important,
+            merge: PropertyMerge::None,
+            span: cursor.span_from(start),
         })
     }
 
     fn read_value(&self, cursor: &mut Cursor<'_>, terminators: &[char]) -> LessResult<Value> {
+        self.read_value_with(cursor, terminators, false)
+    }
+
+    /// 读取值直到括号外的终止符。`stop_at_important` 为真时，在括号外遇到紧邻终止符的
+    /// `!important` 即停止，交由调用方读取标记；字符串与注释中的同名文本不受影响。
+    fn read_value_with(
+        &self,
+        cursor: &mut Cursor<'_>,
+        terminators: &[char],
+        stop_at_important: bool,
+    ) -> LessResult<Value> {
         let mut pieces = Vec::new();
         let mut current = String::new();
-
         let mut paren_depth = 0usize;
 
-        while let Some(ch) = cursor.peek_char() {
-            if terminators.contains(&ch) && paren_depth == 0 {
-                break;
-            }
-
-            match ch {
-                '\n' => {
-                    current.push(ch);
-                    cursor.advance_char();
+        while let Some(token) = cursor.peek() {
+            let text = cursor.text(token);
+            match token.kind {
+                TokenKind::Delim(ch) if paren_depth == 0 && terminators.contains(&ch) => break,
+                TokenKind::Delim('!')
+                    if stop_at_important
+                        && paren_depth == 0
+                        && cursor.at_trailing_important(terminators) =>
+                {
+                    break;
                 }
-                '\'' | '"' => {
-                    current.push(ch);
-                    cursor.advance_char();
-                    while let Some(next) = cursor.peek_char() {
-                        current.push(next);
-                        cursor.advance_char();
-                        if next == ch {
-                            break;
-                        }
-                        if next == '\\' {
-                            if let Some(escaped) = cursor.peek_char() {
-                                current.push(escaped);
-                                cursor.advance_char();
-                            }
-                        }
-                    }
+                TokenKind::String if !token.is_closed(cursor.source) => {
+                    return Err(LessError::parse("字符串缺少结尾的引号", token.start));
                 }
-                '@' => {
-                    if !current.is_empty() {
-                        pieces.push(ValuePiece::Literal(current.clone()));
-                        current.clear();
-                    }
-                    cursor.advance_char();
-                    let name = cursor.read_identifier();
-                    if name.is_empty() {
-                        return Err(LessError::parse("变量名不能为空", cursor.position()));
-                    }
-                    pieces.push(ValuePiece::VariableRef(name));
+                TokenKind::Comment if !token.is_closed(cursor.source) => {
+                    return Err(LessError::parse("注释缺少结尾的 '*/'", token.start));
                 }
-                '(' => {
-                    paren_depth += 1;
-                    current.push(ch);
-                    cursor.advance_char();
+                // 行注释不进入值；块注释原样保留，其中的 `;`、`!important` 不参与解析。
+                TokenKind::Comment if token.is_line_comment(cursor.source) => {
+                    cursor.index += 1;
+                    continue;
                 }
-                ')' => {
-                    if paren_depth > 0 {
-                        paren_depth -= 1;
-                    }
-                    current.push(ch);
-                    cursor.advance_char();
+                TokenKind::AtKeyword => {
+                    flush_literal(&mut pieces, &mut current);
+                    cursor.index += 1;
+                    let name = text[1..].to_string();
+                    pieces.push(if cursor.at_delim('[') {
+                        ValuePiece::Lookup(Lookup {
+                            target: LookupTarget::Variable(name),
+                            keys: cursor.read_lookup_keys()?,
+                        })
+                    } else {
+                        ValuePiece::VariableRef(name)
+                    });
+                    continue;
                 }
-                _ => {
-                    current.push(ch);
-                    cursor.advance_char();
+                // `@@name`：以变量的值作为变量名再取一次值。
+                TokenKind::Delim('@') => {
+                    flush_literal(&mut pieces, &mut current);
+                    cursor.index += 1;
+                    let Some(name) = cursor.eat_at_keyword() else {
+                        cursor.eat_delim('@');
+                        return Err(LessError::parse("变量名不能为空", cursor.position()));
+                    };
+                    pieces.push(ValuePiece::IndirectVariableRef(name.to_string()));
+                    continue;
+                }
+                TokenKind::Delim('$') if cursor.name_at(cursor.index + 1).is_some() => {
+                    flush_literal(&mut pieces, &mut current);
+                    cursor.index += 1;
+                    let name = cursor.eat_name().unwrap_or_default();
+                    pieces.push(ValuePiece::PropertyRef(name.to_string()));
+                    continue;
                 }
+                TokenKind::Delim('.' | '#')
+                    if current
+                        .chars()
+                        .next_back()
+                        .map_or(pieces.is_empty(), |prev| {
+                            prev.is_whitespace() || matches!(prev, '(' | ',')
+                        })
+                        && cursor.mixin_lookup_follows() =>
+                {
+                    flush_literal(&mut pieces, &mut current);
+                    let call = self.parse_lookup_mixin_call(cursor)?;
+                    pieces.push(ValuePiece::Lookup(Lookup {
+                        target: LookupTarget::Mixin(call),
+                        keys: cursor.read_lookup_keys()?,
+                    }));
+                    continue;
+                }
+                TokenKind::Delim('(') => paren_depth += 1,
+                TokenKind::Delim(')') => paren_depth = paren_depth.saturating_sub(1),
+                _ => {}
             }
+            current.push_str(text);
+            cursor.index += 1;
         }
 
-        if !current.is_empty() {
-            pieces.push(ValuePiece::Literal(current));
-        }
-
+        flush_literal(&mut pieces, &mut current);
         Ok(Value::new(pieces))
     }
 
     fn parse_import(&self, cursor: &mut Cursor<'_>) -> LessResult<ImportStatement> {
-        cursor.expect_char('@')?;
-        let ident = cursor.read_identifier();
-        if !ident.eq_ignore_ascii_case("import") {
+        let start = cursor.position();
+        if !cursor
+            .eat_at_keyword()
+            .is_some_and(|name| name.eq_ignore_ascii_case("import"))
+        {
             return Err(LessError::parse("仅支持 @import 语句", cursor.position()));
         }
+        cursor.skip_trivia();
 
-        let spec = cursor.read_until(';')?;
-        cursor.expect_char(';')?;
-
-        let mut remainder = spec.trim_start();
         let mut options = Vec::new();
-        if remainder.starts_with('(') {
-            if let Some(end) = remainder.find(')') {
-                let opt_str = &remainder[1..end];
-                options = opt_str
-                    .split(|c: char| c == ',' || c.is_whitespace())
-                    .filter(|s| !s.is_empty())
-                    .map(|s| s.trim().to_ascii_lowercase())
-                    .collect();
-                remainder = remainder[end + 1..].trim_start();
-            } else {
-                return Err(LessError::parse("不完整的 @import 选项", cursor.position()));
+        if cursor.eat_delim('(') {
+            loop {
+                match cursor.peek() {
+                    Some(token) if token.is_delim(')') => {
+                        cursor.index += 1;
+                        break;
+                    }
+                    Some(token) if !token.is_delim(';') => {
+                        if !token.is_trivia() && !token.is_delim(',') {
+                            options.push(cursor.text(token).to_ascii_lowercase());
+                        }
+                        cursor.index += 1;
+                    }
+                    _ => return Err(LessError::parse("不完整的 @import 选项", cursor.position())),
+                }
             }
+            cursor.skip_trivia();
         }
 
-        let trimmed = remainder.trim();
-        let path = Self::extract_import_path(trimmed);
+        let path = cursor.import_path();
+        let spec = cursor.read_until(';')?;
+        cursor.expect_delim(';')?;
+
         let mut is_css = options.iter().any(|opt| opt == "css");
-        if !is_css {
+        let force_less = options.iter().any(|opt| opt == "less");
+        if !is_css && !force_less {
             if let Some(ref target) = path {
                 if target.ends_with(".css") {
                     is_css = true;
@@ -303,687 +565,958 @@ impl LessParser {
         }
 
         let mut raw = String::from("@import ");
-        raw.push_str(trimmed);
+        raw.push_str(spec.trim());
         raw.push(';');
 
-        Ok(ImportStatement { raw, path, is_css })
+        Ok(ImportStatement {
+            raw,
+            path,
+            is_css,
+            options,
+            span: cursor.span_from(start),
+        })
     }
 
-    fn extract_import_path(input: &str) -> Option<String> {
-        let trimmed = input.trim();
-        if trimmed.is_empty() {
-            return None;
-        }
-        let first = trimmed.chars().next()?;
-        if first == '"' || first == '\'' {
-            if let Some(end) = trimmed[1..].find(first) {
-                return Some(trimmed[1..1 + end].to_string());
-            }
-            return None;
-        }
-        if trimmed.starts_with("url(") {
-            return None;
-        }
-        let token = trimmed
-            .split_whitespace()
-            .next()
-            .map(|s| s.trim().to_string())?;
-        if token.is_empty() {
-            None
-        } else {
-            Some(token)
-        }
+    fn parse_plugin(&self, cursor: &mut Cursor<'_>) -> LessResult<PluginStatement> {
+        cursor.expect_at_keyword("at-rule 名称不能为空")?;
+        cursor.skip_trivia();
+        let name = cursor.import_path();
+        cursor.read_until(';')?;
+        cursor.expect_delim(';')?;
+        let name =
+            name.ok_or_else(|| LessError::parse("@plugin 缺少插件名称", cursor.position()))?;
+        Ok(PluginStatement { name })
     }
 
-    fn parse_rule_body_item(&self, cursor: &mut Cursor<'_>) -> LessResult<RuleBody> {
-        if cursor.starts_with('@') && cursor.lookahead_is_variable_decl()? {
-            let var = self.parse_variable(cursor)?;
-            return Ok(RuleBody::Variable(var));
-        }
-
-        if cursor.lookahead_is_mixin_definition()? {
-            let mixin = self.parse_mixin_definition(cursor)?;
-            return Ok(RuleBody::MixinDefinition(mixin));
-        }
-
-        if cursor.lookahead_is_mixin_call()? {
-            let call = self.parse_mixin_call(cursor)?;
-            return Ok(RuleBody::MixinCall(call));
+    fn parse_charset(&self, cursor: &mut Cursor<'_>) -> LessResult<String> {
+        cursor.expect_at_keyword("at-rule 名称不能为空")?;
+        let charset = cursor.read_until(';')?;
+        cursor.expect_delim(';')?;
+        let charset = charset.trim();
+        if charset.is_empty() {
+            return Err(LessError::parse("@charset 缺少编码名称", cursor.position()));
         }
+        Ok(charset.to_string())
+    }
 
-        if cursor.starts_with('@') {
-            if cursor.lookahead_is_block_at_rule()? {
-                let at_rule = self.parse_at_rule(cursor)?;
-                return Ok(RuleBody::AtRule(at_rule));
+    fn parse_rule_body_item(&self, cursor: &mut Cursor<'_>) -> LessResult<RuleBody> {
+        Ok(match cursor.statement_kind(false) {
+            StatementKind::Variable => RuleBody::Variable(self.parse_variable(cursor)?),
+            StatementKind::Import => RuleBody::Import(self.parse_import(cursor)?),
+            StatementKind::AtRule => RuleBody::AtRule(self.parse_at_rule(cursor)?),
+            StatementKind::DetachedCall => {
+                RuleBody::DetachedCall(self.parse_detached_call(cursor)?)
             }
-            if cursor.lookahead_is_detached_call()? {
-                let call = self.parse_detached_call(cursor)?;
-                return Ok(RuleBody::DetachedCall(call));
+            StatementKind::BodylessAtRule => {
+                RuleBody::BodylessAtRule(self.parse_bodyless_at_rule(cursor)?)
             }
-        }
-
-        match cursor.detect_body_kind() {
-            Some(BodyKind::Declaration) => {
-                let decl = self.parse_declaration(cursor)?;
-                Ok(RuleBody::Declaration(decl))
+            StatementKind::Each => RuleBody::Each(self.parse_each(cursor)?),
+            StatementKind::MixinDefinition => {
+                RuleBody::MixinDefinition(self.parse_mixin_definition(cursor)?)
             }
-            Some(BodyKind::NestedRule) => {
-                let nested = self.parse_ruleset(cursor)?;
-                Ok(RuleBody::NestedRule(nested))
+            StatementKind::MixinCall => RuleBody::MixinCall(self.parse_mixin_call(cursor)?),
+            StatementKind::CustomProperty => {
+                RuleBody::Declaration(self.parse_custom_property(cursor)?)
             }
-            None => Err(LessError::parse(
-                "无法判断声明或子选择器",
-                cursor.position(),
-            )),
-        }
+            StatementKind::Declaration => RuleBody::Declaration(self.parse_declaration(cursor)?),
+            StatementKind::RuleSet => RuleBody::NestedRule(self.parse_ruleset(cursor)?),
+            _ => {
+                return Err(LessError::parse(
+                    "无法判断声明或子选择器",
+                    cursor.position(),
+                ))
+            }
+        })
     }
 
     fn parse_mixin_definition(&self, cursor: &mut Cursor<'_>) -> LessResult<MixinDefinition> {
+        cursor.skip_trivia();
+        let start = cursor.position();
         let name = cursor.read_mixin_name()?;
-        cursor.skip_whitespace_and_comments();
-        let params = if cursor.peek_char() == Some('(') {
+        cursor.skip_trivia();
+        let params = if cursor.at_delim('(') {
             self.parse_mixin_params(cursor)?
         } else {
             Vec::new()
         };
-        cursor.skip_whitespace_and_comments();
-        if cursor.starts_with_keyword("when") {
-            cursor.consume_keyword("when");
-            cursor.skip_whitespace_and_comments();
-            cursor.skip_guard_condition();
-            cursor.skip_whitespace_and_comments();
-        }
-        cursor.expect_char('{')?;
+        cursor.skip_trivia();
+        let guard = if cursor.eat_keyword("when") {
+            let guard = self.parse_guard(cursor)?;
+            cursor.skip_trivia();
+            Some(guard)
+        } else {
+            None
+        };
+        cursor.expect_delim('{')?;
         let body = self.parse_mixin_body(cursor)?;
-        Ok(MixinDefinition { name, params, body })
+        Ok(MixinDefinition {
+            name,
+            params,
+            guard,
+            body,
+            span: cursor.span_from(start),
+        })
     }
 
     fn parse_mixin_body(&self, cursor: &mut Cursor<'_>) -> LessResult<Vec<RuleBody>> {
-        let mut body = Vec::new();
-        loop {
-            cursor.skip_whitespace_and_comments();
-            match cursor.peek_char() {
-                Some('}') => {
-                    cursor.advance_char();
-                    break;
-                }
-                None => {
-                    return Err(LessError::parse("mixin 缺少匹配的 '}'", cursor.position()));
-                }
-                _ => {
-                    let item = self.parse_rule_body_item(cursor)?;
-                    body.push(item);
-                }
-            }
-        }
-        Ok(body)
+        self.parse_block_body(cursor, "mixin 缺少匹配的 '}'")
     }
 
     fn parse_mixin_params(&self, cursor: &mut Cursor<'_>) -> LessResult<Vec<MixinParam>> {
         let mut params = Vec::new();
-        cursor.expect_char('(')?;
+        cursor.expect_delim('(')?;
         loop {
-            cursor.skip_whitespace_and_comments();
-            if cursor.peek_char() == Some(')') {
-                cursor.advance_char();
+            cursor.skip_trivia();
+            if cursor.eat_delim(')') {
                 break;
             }
-            cursor.expect_char('@')?;
-            let name = cursor.read_identifier();
-            if name.is_empty() {
-                return Err(LessError::parse("mixin 参数名不能为空", cursor.position()));
-            }
-            cursor.skip_whitespace_and_comments();
-            let default = if cursor.peek_char() == Some(':') {
-                cursor.advance_char();
-                cursor.skip_whitespace_and_comments();
-                let value = self.read_value(cursor, &[',', ')'])?;
-                Some(value)
+            let name = cursor
+                .expect_at_keyword("mixin 参数名不能为空")?
+                .to_string();
+            cursor.skip_trivia();
+            let default = if cursor.eat_delim(':') {
+                cursor.skip_trivia();
+                Some(self.read_value(cursor, &[',', ')'])?)
             } else {
                 None
             };
             params.push(MixinParam { name, default });
-            cursor.skip_whitespace_and_comments();
-            match cursor.peek_char() {
-                Some(',') => {
-                    cursor.advance_char();
-                }
-                Some(')') => {
-                    cursor.advance_char();
-                    break;
-                }
-                _ => {
-                    return Err(LessError::parse(
-                        "mixin 参数列表缺少分隔符",
-                        cursor.position(),
-                    ));
-                }
+            cursor.skip_trivia();
+            if cursor.eat_delim(')') {
+                break;
+            }
+            if !cursor.eat_delim(',') {
+                return Err(LessError::parse(
+                    "mixin 参数列表缺少分隔符",
+                    cursor.position(),
+                ));
             }
         }
         Ok(params)
     }
 
     fn parse_mixin_call(&self, cursor: &mut Cursor<'_>) -> LessResult<MixinCall> {
-        let name = cursor.read_mixin_name()?;
-        cursor.skip_whitespace_and_comments();
-        let args = if cursor.peek_char() == Some('(') {
+        let start = cursor.position();
+        let mut namespace = cursor.read_mixin_path()?;
+        let name = namespace.pop().unwrap_or_default();
+        cursor.skip_trivia();
+        let args = if cursor.at_delim('(') {
+            self.parse_mixin_arguments(cursor)?
+        } else {
+            Vec::new()
+        };
+        cursor.skip_trivia();
+        let important = cursor.eat_important();
+        cursor.skip_trivia();
+        // 规则体中的最后一个调用可以省略分号，`}` 留给外层规则结束。
+        if !cursor.at_delim('}') {
+            cursor.expect_delim(';')?;
+        }
+        Ok(MixinCall {
+            namespace,
+            name,
+            args,
+            important,
+            span: cursor.span_from(start),
+        })
+    }
+
+    /// 值中 `.mixin(...)[key]` 的调用部分；参数中的规则集无法作为映射查找的输入，直接报错。
+    fn parse_lookup_mixin_call(&self, cursor: &mut Cursor<'_>) -> LessResult<MixinCall> {
+        let start = cursor.position();
+        let mut namespace = cursor.read_mixin_path()?;
+        let name = namespace.pop().unwrap_or_default();
+        let args = if cursor.at_delim('(') {
             self.parse_mixin_arguments(cursor)?
         } else {
             Vec::new()
         };
-        cursor.skip_whitespace_and_comments();
-        cursor.expect_char(';')?;
-        Ok(MixinCall { name, args })
+        if args
+            .iter()
+            .any(|arg| matches!(arg, MixinArgument::Ruleset(_)))
+        {
+            return Err(LessError::parse(
+                "查找中的 mixin 调用不接受规则集参数",
+                start,
+            ));
+        }
+        Ok(MixinCall {
+            namespace,
+            name,
+            args,
+            important: false,
+            span: cursor.span_from(start),
+        })
     }
 
     fn parse_mixin_arguments(&self, cursor: &mut Cursor<'_>) -> LessResult<Vec<MixinArgument>> {
         let mut args = Vec::new();
-        cursor.expect_char('(')?;
+        cursor.expect_delim('(')?;
         loop {
-            cursor.skip_whitespace_and_comments();
-            if cursor.peek_char() == Some(')') {
-                cursor.advance_char();
+            cursor.skip_trivia();
+            if cursor.eat_delim(')') {
                 break;
             }
-            if cursor.peek_char() == Some('{') {
-                cursor.expect_char('{')?;
-                let body = self.parse_mixin_body(cursor)?;
-                args.push(MixinArgument::Ruleset(body));
+            if cursor.eat_delim('{') {
+                args.push(MixinArgument::Ruleset(self.parse_mixin_body(cursor)?));
             } else {
-                let value = self.read_value(cursor, &[',', ')'])?;
-                args.push(MixinArgument::Value(value));
+                args.push(MixinArgument::Value(self.read_value(cursor, &[',', ')'])?));
             }
-            cursor.skip_whitespace_and_comments();
-            match cursor.peek_char() {
-                Some(',') => {
-                    cursor.advance_char();
-                }
-                Some(')') => {
-                    cursor.advance_char();
-                    break;
-                }
-                _ => {
-                    return Err(LessError::parse(
-                        "mixin 参数调用缺少分隔符",
-                        cursor.position(),
-                    ))
-                }
+            cursor.skip_trivia();
+            if cursor.eat_delim(')') {
+                break;
+            }
+            if !cursor.eat_delim(',') {
+                return Err(LessError::parse(
+                    "mixin 参数调用缺少分隔符",
+                    cursor.position(),
+                ));
             }
         }
         Ok(args)
     }
 
+    /// 解析 `each(list, ruleset)`；第二个参数可以是 `{ ... }`、匿名 mixin `#(@v, @k, @i) { ... }`
+    /// 或分离规则集变量。
+    fn parse_each(&self, cursor: &mut Cursor<'_>) -> LessResult<EachCall> {
+        let start = cursor.position();
+        cursor.eat_keyword("each");
+        cursor.skip_trivia();
+        cursor.expect_delim('(')?;
+        cursor.skip_trivia();
+        let list = self.read_value(cursor, &[','])?;
+        cursor.expect_delim(',')?;
+        cursor.skip_trivia();
+        let mut params = Vec::new();
+        if cursor.eat_delim('.') || cursor.eat_delim('#') {
+            cursor.skip_trivia();
+            params = self
+                .parse_mixin_params(cursor)?
+                .into_iter()
+                .map(|param| param.name)
+                .collect();
+            cursor.skip_trivia();
+        }
+        let ruleset = if cursor.at_delim('{') || !params.is_empty() {
+            cursor.expect_delim('{')?;
+            MixinArgument::Ruleset(self.parse_mixin_body(cursor)?)
+        } else {
+            MixinArgument::Value(self.read_value(cursor, &[')'])?)
+        };
+        cursor.skip_trivia();
+        cursor.expect_delim(')')?;
+        cursor.skip_trivia();
+        if !cursor.at_delim('}') {
+            cursor.expect_delim(';')?;
+        }
+        Ok(EachCall {
+            list,
+            params,
+            ruleset,
+            span: cursor.span_from(start),
+        })
+    }
+
     fn parse_detached_call(&self, cursor: &mut Cursor<'_>) -> LessResult<DetachedCall> {
-        cursor.expect_char('@')?;
-        let name = cursor.read_identifier();
-        if name.is_empty() {
+        let start = cursor.position();
+        let name = cursor
+            .expect_at_keyword("期待可调用的规则集名称")?
+            .to_string();
+        cursor.skip_trivia();
+        cursor.expect_delim('(')?;
+        cursor.skip_trivia();
+        if !cursor.eat_delim(')') {
             return Err(LessError::parse(
-                "期待可调用的规则集名称",
+                "分离规则集调用不接受参数，请改用 mixin",
                 cursor.position(),
             ));
         }
-        cursor.skip_whitespace_and_comments();
-        cursor.expect_char('(')?;
-        cursor.skip_whitespace_and_comments();
-        if cursor.peek_char() != Some(')') {
-            return Err(LessError::parse(
-                "暂不支持带参数的规则集调用",
-                cursor.position(),
-            ));
-        }
-        cursor.advance_char();
-        cursor.skip_whitespace_and_comments();
-        cursor.expect_char(';')?;
-        Ok(DetachedCall { name })
+        cursor.skip_trivia();
+        cursor.expect_delim(';')?;
+        Ok(DetachedCall {
+            name,
+            span: cursor.span_from(start),
+        })
+    }
+}
+
+/// 把累积的字面量文本作为一个值片段收下。
+fn flush_literal(pieces: &mut Vec<ValuePiece>, current: &mut String) {
+    if !current.is_empty() {
+        pieces.push(ValuePiece::Literal(std::mem::take(current)));
     }
 }
 
-/// 带位置指针的输入游标，提供便捷的字符读取与回退功能。
+/// 语句或规则体项的类型，由 [`Cursor::statement_kind`] 根据记号判断。
+enum StatementKind {
+    Variable,
+    Import,
+    Plugin,
+    Charset,
+    AtRule,
+    DetachedCall,
+    BodylessAtRule,
+    Each,
+    MixinDefinition,
+    MixinCall,
+    CustomProperty,
+    Declaration,
+    RuleSet,
+    /// 规则体中既不像声明也不像子规则。
+    Unknown,
+}
+
+/// 解析器读取的记号流：源码在创建时由 [`Lexer`] 一次切分为记号，解析与向前查看都按下标访问，
+/// 节点的偏移取自记号边界。
 struct Cursor<'a> {
     source: &'a str,
-    len: usize,
-    position: usize,
+    tokens: Vec<Token>,
+    /// 下一个待读取记号的下标。
+    index: usize,
+    file: Option<&'a Arc<Path>>,
+    /// 每一行起始处的字节偏移，用于把偏移换算为行列号。
+    line_starts: &'a [usize],
 }
 
 impl<'a> Cursor<'a> {
-    fn new(source: &'a str) -> Self {
+    fn new(source: &'a str, line_starts: &'a [usize], file: Option<&'a Arc<Path>>) -> Self {
         Self {
             source,
-            len: source.len(),
-            position: 0,
+            tokens: Lexer::new(source, 0).collect(),
+            index: 0,
+            file,
+            line_starts,
         }
     }
 
+    /// 下一个记号的起始偏移，读完时为源码长度。
     fn position(&self) -> usize {
-        self.position
+        self.tokens
+            .get(self.index)
+            .map_or(self.source.len(), |token| token.start)
+    }
+
+    /// 从 `start` 到当前位置的区间。
+    fn span_from(&self, start: usize) -> Span {
+        let line = self.line_starts.partition_point(|&offset| offset <= start);
+        let line_start = self.line_starts[line - 1];
+        Span {
+            start,
+            end: self.position(),
+            line,
+            column: self.source[line_start..start].chars().count() + 1,
+            file: self.file.cloned(),
+        }
     }
 
     fn is_eof(&self) -> bool {
-        self.position >= self.len
+        self.index >= self.tokens.len()
+    }
+
+    fn peek(&self) -> Option<Token> {
+        self.token_at(self.index)
     }
 
-    fn starts_with(&self, ch: char) -> bool {
-        self.peek_char() == Some(ch)
+    fn token_at(&self, index: usize) -> Option<Token> {
+        self.tokens.get(index).copied()
     }
 
-    fn peek_char(&self) -> Option<char> {
-        self.source[self.position..].chars().next()
+    fn text(&self, token: Token) -> &'a str {
+        token.text(self.source)
     }
 
-    fn advance_char(&mut self) -> Option<char> {
-        let ch = self.peek_char()?;
-        self.position += ch.len_utf8();
-        Some(ch)
+    fn is_delim_at(&self, index: usize, ch: char) -> bool {
+        self.token_at(index).is_some_and(|token| token.is_delim(ch))
     }
 
-    fn expect_char(&mut self, expect: char) -> LessResult<()> {
-        match self.advance_char() {
-            Some(ch) if ch == expect => Ok(()),
-            Some(ch) => Err(LessError::parse(
-                format!("期待字符 '{expect}', 却得到 '{ch}'"),
-                self.position,
-            )),
-            None => Err(LessError::parse(
+    fn at_delim(&self, ch: char) -> bool {
+        self.is_delim_at(self.index, ch)
+    }
+
+    fn eat_delim(&mut self, ch: char) -> bool {
+        let matched = self.at_delim(ch);
+        if matched {
+            self.index += 1;
+        }
+        matched
+    }
+
+    fn expect_delim(&mut self, expect: char) -> LessResult<()> {
+        let Some(token) = self.peek() else {
+            return Err(LessError::parse(
                 format!("期待字符 '{expect}'"),
-                self.position,
-            )),
+                self.position(),
+            ));
+        };
+        if token.is_delim(expect) {
+            self.index += 1;
+            return Ok(());
+        }
+        let found = self.text(token).chars().next().unwrap_or_default();
+        Err(LessError::parse(
+            format!("期待字符 '{expect}', 却得到 '{found}'"),
+            token.start + found.len_utf8(),
+        ))
+    }
+
+    /// `index` 处可以作为名称的记号：标识符，或只含标识符字符的数值（如 `.col-2` 中的 `2`）。
+    fn name_at(&self, index: usize) -> Option<&'a str> {
+        let token = self.token_at(index)?;
+        let text = self.text(token);
+        match token.kind {
+            TokenKind::Ident => Some(text),
+            TokenKind::Number if text.chars().all(is_identifier_char) => Some(text),
+            _ => None,
+        }
+    }
+
+    fn eat_name(&mut self) -> Option<&'a str> {
+        let name = self.name_at(self.index)?;
+        self.index += 1;
+        Some(name)
+    }
+
+    /// 下一个记号是标识符 `keyword` 时读取它，随后的空白与注释一并跳过。
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        let matched = self
+            .peek()
+            .is_some_and(|token| token.kind == TokenKind::Ident && self.text(token) == keyword);
+        if matched {
+            self.index += 1;
+            self.skip_trivia();
+        }
+        matched
+    }
+
+    /// 读取 `@name`，返回不含 `@` 的名称。
+    fn eat_at_keyword(&mut self) -> Option<&'a str> {
+        let token = self
+            .peek()
+            .filter(|token| token.kind == TokenKind::AtKeyword)?;
+        self.index += 1;
+        Some(&self.text(token)[1..])
+    }
+
+    /// 读取 `@name`；`@` 之后没有名称时以 `empty` 报错。
+    fn expect_at_keyword(&mut self, empty: &'static str) -> LessResult<&'a str> {
+        if let Some(name) = self.eat_at_keyword() {
+            return Ok(name);
         }
+        self.expect_delim('@')?;
+        Err(LessError::parse(empty, self.position()))
     }
 
     fn skip_whitespace(&mut self) {
-        while let Some(ch) = self.peek_char() {
-            if ch.is_whitespace() {
-                self.advance_char();
-            } else {
-                break;
-            }
+        while self
+            .peek()
+            .is_some_and(|token| token.kind == TokenKind::Whitespace)
+        {
+            self.index += 1;
         }
     }
 
-    fn skip_whitespace_and_comments(&mut self) {
-        loop {
-            self.skip_whitespace();
-            if self.starts_with('/') {
-                if self.consume_comment() {
-                    continue;
-                }
-            }
-            break;
+    fn skip_trivia(&mut self) {
+        self.index = self.skip_trivia_from(self.index);
+    }
+
+    /// 从 `index` 起跳过空白与注释，返回其后第一个记号的下标。
+    fn skip_trivia_from(&self, mut index: usize) -> usize {
+        while self.token_at(index).is_some_and(|token| token.is_trivia()) {
+            index += 1;
         }
+        index
     }
 
-    fn consume_comment(&mut self) -> bool {
-        if self.match_str("//") {
-            while let Some(ch) = self.peek_char() {
-                self.advance_char();
-                if ch == '\n' {
-                    break;
-                }
-            }
-            true
-        } else if self.match_str("/*") {
-            while let Some(_) = self.peek_char() {
-                if self.match_str("*/") {
-                    break;
-                }
-                self.advance_char();
+    /// 跳过空白；若随后是注释则读取并返回它，块注释直到文件末尾都没有闭合时报错。
+    fn read_comment(&mut self) -> LessResult<Option<Comment>> {
+        self.skip_whitespace();
+        let Some(token) = self.peek().filter(|token| token.kind == TokenKind::Comment) else {
+            return Ok(None);
+        };
+        if !token.is_closed(self.source) {
+            return Err(LessError::parse("注释缺少结尾的 '*/'", token.start));
+        }
+        self.index += 1;
+        Ok(Some(Comment {
+            text: self.text(token).trim_end().to_string(),
+            span: self.span_from(token.start),
+        }))
+    }
+
+    /// 从当前记号内部的 `offset` 处重新切分记号，用于在 `//` 不是注释的上下文中把它拆开；
+    /// 新切出的记号与原有记号边界重合后，其余记号保持不变。
+    fn resume_at(&mut self, offset: usize) {
+        let rest = &self.tokens[self.index..];
+        let mut fresh = Vec::new();
+        let mut resume = self.tokens.len();
+        for token in Lexer::new(self.source, offset) {
+            fresh.push(token);
+            if let Ok(at) = rest.binary_search_by_key(&token.end, |old| old.start) {
+                resume = self.index + at;
+                break;
             }
-            true
-        } else {
-            false
         }
+        self.tokens.splice(self.index..resume, fresh);
     }
 
-    fn match_str(&mut self, prefix: &str) -> bool {
-        if self.source[self.position..].starts_with(prefix) {
-            self.position += prefix.len();
-            true
-        } else {
-            false
+    /// `index` 处为 `! important`（`!` 与关键字之间允许空白）时返回其后的下标。
+    fn important_end(&self, index: usize) -> Option<usize> {
+        if !self.is_delim_at(index, '!') {
+            return None;
+        }
+        let mut index = index + 1;
+        if self
+            .token_at(index)
+            .is_some_and(|token| token.kind == TokenKind::Whitespace)
+        {
+            index += 1;
         }
+        (self.name_at(index)? == "important").then_some(index + 1)
     }
 
-    fn starts_with_keyword(&self, keyword: &str) -> bool {
-        if !self.source[self.position..].starts_with(keyword) {
+    /// 判断当前位置是否为值末尾的 `!important`（其后只剩空白、注释或终止符）。
+    fn at_trailing_important(&self, terminators: &[char]) -> bool {
+        let Some(end) = self.important_end(self.index) else {
             return false;
-        }
-        let end = self.position + keyword.len();
-        match self.source.get(end..) {
-            Some(rest) => match rest.chars().next() {
-                Some(ch) => !ch.is_alphanumeric() && ch != '-' && ch != '_',
-                None => true,
-            },
+        };
+        match self
+            .token_at(self.skip_trivia_from(end))
+            .map(|token| token.kind)
+        {
+            Some(TokenKind::Delim(ch)) => terminators.contains(&ch),
+            Some(_) => false,
             None => true,
         }
     }
 
-    fn consume_keyword(&mut self, keyword: &str) {
-        self.position += keyword.len();
+    /// 读取 `! important` 标记，不存在时不移动游标。
+    fn eat_important(&mut self) -> bool {
+        let Some(end) = self.important_end(self.index) else {
+            return false;
+        };
+        self.index = end;
+        true
     }
 
-    fn skip_guard_condition(&mut self) {
+    /// 错误恢复时跳过一条语句：遇到括号外的 `;` 或配平的 `{}` 块即停止。
+    /// 规则体内遇到不配对的 `}` 时停在它之前，交给外层规则结束；顶层则一并跳过。
+    /// 字符串最多跳到行末，缺少结尾引号时不会吞掉后续语句；行末之后的内容重新切分。
+    fn skip_statement(&mut self, top_level: bool) {
         let mut depth = 0usize;
-        while let Some(ch) = self.peek_char() {
-            if ch == '{' && depth == 0 {
-                break;
-            }
-            match ch {
-                '(' => depth += 1,
-                ')' => {
-                    if depth > 0 {
-                        depth -= 1;
+        while let Some(token) = self.peek() {
+            match token.kind {
+                TokenKind::String => {
+                    if let Some(newline) = self.text(token).find('\n') {
+                        self.resume_at(token.start + newline + 1);
+                        continue;
+                    }
+                }
+                TokenKind::Delim('{') => depth += 1,
+                TokenKind::Delim('}') if depth == 0 => {
+                    if top_level {
+                        self.index += 1;
+                    }
+                    break;
+                }
+                TokenKind::Delim('}') => {
+                    depth -= 1;
+                    if depth == 0 {
+                        self.index += 1;
+                        break;
                     }
                 }
+                TokenKind::Delim(';') if depth == 0 => {
+                    self.index += 1;
+                    break;
+                }
                 _ => {}
             }
-            self.advance_char();
+            self.index += 1;
         }
     }
 
-    fn read_identifier(&mut self) -> String {
-        let mut ident = String::new();
-        while let Some(ch) = self.peek_char() {
-            if ch.is_alphanumeric() || ch == '-' || ch == '_' {
-                ident.push(ch);
-                self.advance_char();
-            } else {
-                break;
+    /// 跳过 at-rule 的守卫条件，停在括号外的 `{` 之前。
+    fn skip_guard_condition(&mut self) {
+        let mut depth = 0usize;
+        while let Some(token) = self.peek() {
+            match token.kind {
+                TokenKind::Delim('{') if depth == 0 => break,
+                TokenKind::Delim('(') => depth += 1,
+                TokenKind::Delim(')') => depth = depth.saturating_sub(1),
+                _ => {}
             }
+            self.index += 1;
         }
-        ident
     }
 
+    /// 读取属性名，直到 `:`、`;`、`{`、注释或换行之前；`@{var}` 插值是完整的记号。
     fn read_property_name(&mut self) -> String {
         let mut name = String::new();
-        let mut pending_interpolation = false;
-        while let Some(ch) = self.peek_char() {
-            if ch == ':' || ch == ';' {
+        while let Some(token) = self.peek() {
+            let text = self.text(token);
+            if matches!(
+                token.kind,
+                TokenKind::Delim(':' | ';' | '{') | TokenKind::Comment
+            ) || text.contains(char::is_control)
+            {
                 break;
             }
-            if ch == '{' && !pending_interpolation {
-                break;
-            }
-            if ch.is_control() {
-                break;
-            }
-            let ch = self.advance_char().unwrap();
-            name.push(ch);
-            if ch == '@' {
-                pending_interpolation = true;
-            } else if ch == '{' && pending_interpolation {
-                while let Some(inner) = self.advance_char() {
-                    name.push(inner);
-                    if inner == '}' {
-                        pending_interpolation = false;
-                        break;
-                    }
-                }
-            } else if !ch.is_whitespace() {
-                pending_interpolation = false;
-            }
+            name.push_str(text);
+            self.index += 1;
         }
         name.trim().to_string()
     }
 
+    /// 拼接 `end` 之前的记号文本，字符串、注释等记号内部的 `end` 不算；到末尾都没有遇到时报错。
     fn read_until(&mut self, end: char) -> LessResult<String> {
-        let mut result = String::new();
-        while let Some(ch) = self.peek_char() {
-            if ch == end {
-                break;
+        let mut text = String::new();
+        while let Some(token) = self.peek() {
+            if token.is_delim(end) {
+                return Ok(text);
             }
-            result.push(ch);
-            self.advance_char();
+            text.push_str(self.text(token));
+            self.index += 1;
         }
-        if self.peek_char() != Some(end) {
-            return Err(LessError::parse(format!("期待字符 '{end}'"), self.position));
+        Err(LessError::parse(
+            format!("期待字符 '{end}'"),
+            self.position(),
+        ))
+    }
+
+    /// `@import` / `@plugin` 的目标：带引号的字符串取其内容，`url(...)` 没有可展开的路径，
+    /// 其余取到空白之前的文本。不移动游标。
+    fn import_path(&self) -> Option<String> {
+        let token = self.peek()?;
+        match token.kind {
+            TokenKind::String if token.is_closed(self.source) => {
+                let text = self.text(token);
+                Some(text[1..text.len() - 1].to_string())
+            }
+            TokenKind::String | TokenKind::Url => None,
+            TokenKind::Ident
+                if self.text(token) == "url" && self.is_delim_at(self.index + 1, '(') =>
+            {
+                None
+            }
+            _ => {
+                let path: String = self.tokens[self.index..]
+                    .iter()
+                    .take_while(|token| !token.is_trivia() && !token.is_delim(';'))
+                    .map(|token| self.text(*token))
+                    .collect();
+                (!path.is_empty()).then_some(path)
+            }
         }
-        Ok(result)
     }
 
-    fn lookahead_is_variable_decl(&self) -> LessResult<bool> {
-        let mut lookahead = self.clone();
-        lookahead.expect_char('@')?;
-        lookahead.read_identifier();
-        lookahead.skip_whitespace();
-        Ok(lookahead.peek_char() == Some(':'))
+    /// 读取 `{` 之前的选择器文本；空白之后的 `when` 开启守卫条件，停在它之前。行注释不计入选择器。
+    fn read_selector(&mut self) -> LessResult<String> {
+        let mut selector = String::new();
+        let mut after_space = false;
+        loop {
+            let Some(token) = self.peek() else {
+                return Err(LessError::parse("期待字符 '{'", self.position()));
+            };
+            let text = self.text(token);
+            match token.kind {
+                TokenKind::Delim('{') => return Ok(selector),
+                TokenKind::Ident if after_space && text == "when" => return Ok(selector),
+                TokenKind::Comment if token.is_line_comment(self.source) => {}
+                _ => selector.push_str(text),
+            }
+            after_space = token.kind == TokenKind::Whitespace;
+            self.index += 1;
+        }
     }
 
-    fn lookahead_is_import(&self) -> LessResult<bool> {
-        let mut lookahead = self.clone();
-        if !lookahead.starts_with('@') {
-            return Ok(false);
+    /// 原样读取到括号外的 `;` 或 `}` 之前；`()`、`[]`、`{}` 与字符串内部的分隔符不会结束读取。
+    /// 自定义属性的值不是 LESS 语法，其中的 `//`（如 `--url: http://a.com`）不是注释。
+    fn read_raw_value(&mut self) -> String {
+        let mut raw = String::new();
+        let mut depth = 0usize;
+        while let Some(token) = self.peek() {
+            match token.kind {
+                TokenKind::Comment if token.is_line_comment(self.source) => {
+                    raw.push_str("//");
+                    self.resume_at(token.start + 2);
+                    continue;
+                }
+                TokenKind::Delim('(' | '[' | '{') => depth += 1,
+                TokenKind::Delim(';' | '}') if depth == 0 => break,
+                TokenKind::Delim(')' | ']' | '}') => depth = depth.saturating_sub(1),
+                _ => {}
+            }
+            raw.push_str(self.text(token));
+            self.index += 1;
         }
-        lookahead.expect_char('@')?;
-        let ident = lookahead.read_identifier();
-        Ok(ident.eq_ignore_ascii_case("import"))
+        raw
     }
 
-    fn lookahead_is_block_at_rule(&self) -> LessResult<bool> {
-        let mut lookahead = self.clone();
-        if !lookahead.starts_with('@') {
-            return Ok(false);
+    /// 判断下一条语句（`top_level`）或规则体项的类型。LESS 的语句只有读到后面的 `{`、`;` 或 `:`
+    /// 才能区分，这里按下标向前查看记号，不移动游标。
+    fn statement_kind(&self, top_level: bool) -> StatementKind {
+        let Some(token) = self.peek() else {
+            return StatementKind::Unknown;
+        };
+        match token.kind {
+            TokenKind::AtKeyword => return self.at_rule_kind(top_level),
+            TokenKind::Delim('.' | '#') => {
+                if let Some(kind) = self.mixin_kind() {
+                    return kind;
+                }
+            }
+            TokenKind::Ident
+                if self.text(token) == "each"
+                    && self.is_delim_at(self.skip_trivia_from(self.index + 1), '(') =>
+            {
+                return StatementKind::Each;
+            }
+            _ => {}
         }
-        lookahead.advance_char();
-        let ident = lookahead.read_identifier();
-        if ident.is_empty() {
-            return Ok(false);
+        if top_level {
+            StatementKind::RuleSet
+        } else if self.custom_property_follows() {
+            StatementKind::CustomProperty
+        } else {
+            self.body_item_kind()
         }
-        lookahead.skip_whitespace_and_comments();
-        let mut paren_depth = 0usize;
-        while let Some(ch) = lookahead.peek_char() {
-            match ch {
-                '{' if paren_depth == 0 => return Ok(true),
-                '(' => {
-                    paren_depth += 1;
-                    lookahead.advance_char();
-                }
-                ')' => {
-                    if paren_depth > 0 {
-                        paren_depth -= 1;
-                    }
-                    lookahead.advance_char();
-                }
-                ';' => return Ok(false),
-                _ => {
-                    lookahead.advance_char();
-                }
+    }
+
+    /// `@name` 开头的语句：变量声明、专用语法或普通 at-rule。
+    fn at_rule_kind(&self, top_level: bool) -> StatementKind {
+        let name = &self.text(self.tokens[self.index])[1..];
+        let after = self.index + 1;
+        let next = self.skip_trivia_from(after);
+        if self.is_delim_at(next, ':') {
+            // `@page :first { }` 中的 `:` 是页面伪类，而不是变量声明。
+            let pseudo_page = name.eq_ignore_ascii_case("page")
+                && self
+                    .token_at(next + 1)
+                    .is_some_and(|token| token.kind == TokenKind::Ident);
+            if !pseudo_page || !self.block_follows(after, true) {
+                return StatementKind::Variable;
             }
         }
-        Ok(false)
+        if name.eq_ignore_ascii_case("import") {
+            StatementKind::Import
+        } else if top_level && name.eq_ignore_ascii_case("plugin") {
+            StatementKind::Plugin
+        } else if top_level && name.eq_ignore_ascii_case("charset") {
+            StatementKind::Charset
+        } else if self.block_follows(after, true) {
+            StatementKind::AtRule
+        } else if self.detached_call_follows(after) {
+            StatementKind::DetachedCall
+        } else {
+            StatementKind::BodylessAtRule
+        }
     }
 
-    fn lookahead_is_mixin_definition(&self) -> LessResult<bool> {
-        let mut lookahead = self.clone();
-        if !matches!(lookahead.peek_char(), Some('.') | Some('#')) {
-            return Ok(false);
+    /// `.` 或 `#` 开头时判断 mixin 定义（参数列表之后，可带 `when` 守卫，紧跟规则体）
+    /// 或 mixin 调用（路径之后是可选的参数与 `!important`，再以 `;` 或规则体的 `}` 结束）。
+    fn mixin_kind(&self) -> Option<StatementKind> {
+        let name_end = self.mixin_name_end(self.index)?;
+        if let Some(params_end) = self.parens_end(self.skip_trivia_from(name_end)) {
+            let next = self.skip_trivia_from(params_end);
+            let defines = if self.name_at(next) == Some("when") {
+                self.block_follows(next + 1, false)
+            } else {
+                self.is_delim_at(next, '{')
+            };
+            if defines {
+                return Some(StatementKind::MixinDefinition);
+            }
         }
-        lookahead.advance_char();
-        let ident = lookahead.read_identifier();
-        if ident.is_empty() {
-            return Ok(false);
+
+        let mut next = self.skip_trivia_from(self.mixin_path_end(self.index)?);
+        if self.is_delim_at(next, '(') {
+            next = self.skip_trivia_from(self.parens_end(next)?);
         }
-        lookahead.skip_whitespace_and_comments();
-        if lookahead.peek_char() != Some('(') {
-            return Ok(false);
+        if let Some(end) = self.important_end(next) {
+            next = self.skip_trivia_from(end);
         }
-        lookahead.advance_char();
-        let mut depth = 1;
-        while let Some(ch) = lookahead.peek_char() {
-            lookahead.advance_char();
-            match ch {
-                '(' => depth += 1,
-                ')' => {
-                    depth -= 1;
-                    if depth == 0 {
-                        break;
-                    }
-                }
+        (self.is_delim_at(next, ';') || self.is_delim_at(next, '}'))
+            .then_some(StatementKind::MixinCall)
+    }
+
+    /// `--name` 之后（名称中可以有插值）先遇到 `:`，而不是 `;` 或 `{`。
+    fn custom_property_follows(&self) -> bool {
+        let starts_with_dashes = self.peek().is_some_and(|token| {
+            token.kind == TokenKind::Ident && self.text(token).starts_with("--")
+        });
+        starts_with_dashes
+            && self.tokens[self.index..]
+                .iter()
+                .find(|token| matches!(token.kind, TokenKind::Delim(':' | ';' | '{')))
+                .is_some_and(|token| token.is_delim(':'))
+    }
+
+    /// 规则体中的其余项：括号外先遇到 `{` 为子规则，先遇到 `;` 为声明；
+    /// 到达规则体的 `}` 或末尾时，出现过 `:` 才是声明。
+    fn body_item_kind(&self) -> StatementKind {
+        let mut saw_colon = false;
+        for token in &self.tokens[self.index..] {
+            match token.kind {
+                TokenKind::Delim('{') => return StatementKind::RuleSet,
+                TokenKind::Delim(';') => return StatementKind::Declaration,
+                TokenKind::Delim('}') => break,
+                TokenKind::Delim(':') => saw_colon = true,
                 _ => {}
             }
         }
-        if depth != 0 {
-            return Ok(false);
-        }
-        lookahead.skip_whitespace_and_comments();
-        if lookahead.starts_with_keyword("when") {
-            lookahead.consume_keyword("when");
-            lookahead.skip_whitespace_and_comments();
-            lookahead.skip_guard_condition();
-            lookahead.skip_whitespace_and_comments();
-        }
-        Ok(lookahead.peek_char() == Some('{'))
-    }
-
-    fn lookahead_is_mixin_call(&self) -> LessResult<bool> {
-        let mut lookahead = self.clone();
-        if !matches!(lookahead.peek_char(), Some('.') | Some('#')) {
-            return Ok(false);
-        }
-        lookahead.advance_char();
-        let ident = lookahead.read_identifier();
-        if ident.is_empty() {
-            return Ok(false);
-        }
-        lookahead.skip_whitespace_and_comments();
-        if lookahead.peek_char() == Some('(') {
-            lookahead.advance_char();
-            let mut depth = 1;
-            while let Some(ch) = lookahead.peek_char() {
-                lookahead.advance_char();
-                match ch {
-                    '(' => depth += 1,
-                    ')' => {
-                        depth -= 1;
-                        if depth == 0 {
-                            break;
-                        }
-                    }
-                    _ => {}
-                }
-            }
-            if depth != 0 {
-                return Ok(false);
-            }
-            lookahead.skip_whitespace_and_comments();
+        if saw_colon {
+            StatementKind::Declaration
+        } else {
+            StatementKind::Unknown
         }
-        Ok(lookahead.peek_char() == Some(';'))
     }
 
-    fn lookahead_is_detached_call(&self) -> LessResult<bool> {
-        let mut lookahead = self.clone();
-        if !lookahead.starts_with('@') {
-            return Ok(false);
-        }
-        lookahead.advance_char();
-        let ident = lookahead.read_identifier();
-        if ident.is_empty() {
-            return Ok(false);
+    /// 从 `index` 起在括号外遇到 `{`；`stop_at_semicolon` 时先遇到 `;` 即不是。
+    fn block_follows(&self, index: usize, stop_at_semicolon: bool) -> bool {
+        let mut paren_depth = 0usize;
+        for token in self.tokens.get(index..).unwrap_or_default() {
+            match token.kind {
+                TokenKind::Delim('{') if paren_depth == 0 => return true,
+                TokenKind::Delim('(') => paren_depth += 1,
+                TokenKind::Delim(')') => paren_depth = paren_depth.saturating_sub(1),
+                TokenKind::Delim(';') if stop_at_semicolon => return false,
+                _ => {}
+            }
         }
-        lookahead.skip_whitespace_and_comments();
-        if lookahead.peek_char() != Some('(') {
-            return Ok(false);
+        false
+    }
+
+    /// `@name()` 之后紧跟 `;`。
+    fn detached_call_follows(&self, index: usize) -> bool {
+        self.parens_end(self.skip_trivia_from(index))
+            .is_some_and(|end| self.is_delim_at(self.skip_trivia_from(end), ';'))
+    }
+
+    /// `index` 处为 `(` 时返回配平的 `)` 之后的下标；直到末尾都没有闭合时为 `None`。
+    fn parens_end(&self, index: usize) -> Option<usize> {
+        if !self.is_delim_at(index, '(') {
+            return None;
         }
-        lookahead.advance_char();
-        let mut depth = 1;
-        while let Some(ch) = lookahead.peek_char() {
-            lookahead.advance_char();
-            match ch {
-                '(' => depth += 1,
-                ')' => {
+        let mut depth = 0usize;
+        for (offset, token) in self.tokens[index..].iter().enumerate() {
+            match token.kind {
+                TokenKind::Delim('(') => depth += 1,
+                TokenKind::Delim(')') => {
                     depth -= 1;
                     if depth == 0 {
-                        break;
+                        return Some(index + offset + 1);
                     }
                 }
                 _ => {}
             }
         }
-        if depth != 0 {
-            return Ok(false);
+        None
+    }
+
+    /// `index` 处为 `.name` 或 `#name` 时返回其后的下标。
+    fn mixin_name_end(&self, index: usize) -> Option<usize> {
+        let prefixed = self.is_delim_at(index, '.') || self.is_delim_at(index, '#');
+        (prefixed && self.name_at(index + 1).is_some()).then_some(index + 2)
+    }
+
+    /// `#ns > .mixin`、`#ns .mixin` 或 `#ns.mixin` 形式的 mixin 路径之后的下标。
+    fn mixin_path_end(&self, index: usize) -> Option<usize> {
+        let mut end = self.mixin_name_end(index)?;
+        while let Some(next) = self.path_separator_end(end) {
+            end = self.mixin_name_end(next)?;
         }
-        lookahead.skip_whitespace_and_comments();
-        Ok(lookahead.peek_char() == Some(';'))
+        Some(end)
     }
 
-    fn read_mixin_name(&mut self) -> LessResult<String> {
-        match self.peek_char() {
-            Some('.') | Some('#') => {
-                let prefix = self.advance_char().unwrap();
-                let mut name = String::new();
-                name.push(prefix);
-                let ident = self.read_identifier();
-                if ident.is_empty() {
-                    return Err(LessError::parse("mixin 名称不合法", self.position()));
-                }
-                name.push_str(&ident);
-                Ok(name)
-            }
-            _ => Err(LessError::parse("期待 mixin 名称", self.position())),
+    /// 跳过 mixin 路径两段之间的空白、注释与 `>`；其后是 `.` 或 `#` 时返回它的下标。
+    fn path_separator_end(&self, index: usize) -> Option<usize> {
+        let mut next = self.skip_trivia_from(index);
+        if self.is_delim_at(next, '>') {
+            next = self.skip_trivia_from(next + 1);
         }
+        (self.is_delim_at(next, '.') || self.is_delim_at(next, '#')).then_some(next)
     }
 
-    /// 通过向前查看判断接下来的语句类型（声明或子规则）。
-    fn detect_body_kind(&self) -> Option<BodyKind> {
-        let mut iter = self.clone();
-        iter.skip_whitespace_and_comments();
-        let mut saw_colon = false;
-        let mut pending_interpolation = false;
-        while let Some(ch) = iter.peek_char() {
-            match ch {
-                '@' => {
-                    pending_interpolation = true;
-                    iter.advance_char();
-                    continue;
-                }
-                '{' if pending_interpolation => {
-                    iter.advance_char();
-                    while let Some(inner) = iter.peek_char() {
-                        let current = inner;
-                        iter.advance_char();
-                        if current == '}' {
-                            break;
-                        }
-                    }
-                    pending_interpolation = false;
-                    continue;
-                }
-                '{' => return Some(BodyKind::NestedRule),
-                ';' => return Some(BodyKind::Declaration),
-                '}' => {
-                    return if saw_colon {
-                        Some(BodyKind::Declaration)
-                    } else {
-                        None
-                    }
-                }
-                ':' => {
-                    saw_colon = true;
-                }
-                _ => {
-                    pending_interpolation = false;
-                }
+    /// 值中的 `.mixin()[key]` 与 `#ns.mixin[key]`：mixin 路径（及可选的参数）之后紧跟 `[`。
+    fn mixin_lookup_follows(&self) -> bool {
+        let Some(mut end) = self.mixin_path_end(self.index) else {
+            return false;
+        };
+        if self.is_delim_at(end, '(') {
+            match self.parens_end(end) {
+                Some(args_end) => end = args_end,
+                None => return false,
             }
-            iter.advance_char();
         }
-        if saw_colon {
-            Some(BodyKind::Declaration)
-        } else {
-            None
+        self.is_delim_at(end, '[')
+    }
+
+    fn read_mixin_name(&mut self) -> LessResult<String> {
+        let Some(prefix) = self
+            .peek()
+            .filter(|token| token.is_delim('.') || token.is_delim('#'))
+        else {
+            return Err(LessError::parse("期待 mixin 名称", self.position()));
+        };
+        self.index += 1;
+        match self.eat_name() {
+            Some(name) => Ok(format!("{}{name}", self.text(prefix))),
+            None => Err(LessError::parse("mixin 名称不合法", self.position())),
         }
     }
-}
 
-impl<'a> Clone for Cursor<'a> {
-    fn clone(&self) -> Self {
-        Self {
-            source: self.source,
-            len: self.len,
-            position: self.position,
+    /// 读取可能带命名空间的 mixin 路径，支持 `#ns > .mixin`、`#ns .mixin` 与 `#ns.mixin` 三种写法。
+    fn read_mixin_path(&mut self) -> LessResult<Vec<String>> {
+        let mut segments = vec![self.read_mixin_name()?];
+        while let Some(next) = self.path_separator_end(self.index) {
+            self.index = next;
+            segments.push(self.read_mixin_name()?);
         }
+        Ok(segments)
+    }
+
+    /// 读取紧邻的一个或多个 `[key]`；键只能是可带 `@`、`@@`、`$`、`$@` 前缀的标识符，或为空。
+    fn read_lookup_keys(&mut self) -> LessResult<Vec<String>> {
+        let mut keys = Vec::new();
+        while self.eat_delim('[') {
+            self.skip_whitespace();
+            let start = self.position();
+            let prefix = self
+                .peek()
+                .filter(|token| token.is_delim('@') || token.is_delim('$'))
+                .map(|token| self.text(token));
+            if prefix.is_some() {
+                self.index += 1;
+            }
+            let name = match self.peek() {
+                Some(token) if token.kind == TokenKind::AtKeyword => {
+                    self.index += 1;
+                    Some(self.text(token))
+                }
+                _ if prefix == Some("@") => None,
+                _ => self.eat_name(),
+            };
+            self.skip_whitespace();
+            if (prefix.is_some() && name.is_none()) || !self.eat_delim(']') {
+                return Err(LessError::parse("查找键不合法", start));
+            }
+            keys.push(format!(
+                "{}{}",
+                prefix.unwrap_or_default(),
+                name.unwrap_or_default()
+            ));
+        }
+        Ok(keys)
     }
 }
 
-enum BodyKind {
-    Declaration,
-    NestedRule,
+/// 拆出值末尾的 `!important`（`!` 与关键字之间允许空白，大小写不敏感）。
+fn strip_important(value: &str) -> (&str, bool) {
+    let lower = value.to_ascii_lowercase();
+    if let Some(rest) = lower.strip_suffix("important") {
+        if let Some(rest) = rest.trim_end().strip_suffix('!') {
+            return (value[..rest.len()].trim_end(), true);
+        }
+    }
+    (value, false)
 }
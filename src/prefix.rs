@@ -0,0 +1,319 @@
+//! 可选的厂商前缀补全：按目标浏览器列表，在需要前缀的声明之前插入带前缀的版本。
+//!
+//! 内置表只覆盖常见属性（flexbox、`user-select`、`backdrop-filter`、`appearance` 等），
+//! 版本数据取自 caniuse 的近似值；需要完整覆盖时仍应使用 autoprefixer。
+
+use crate::evaluator::{EvaluatedDeclaration, EvaluatedNode, EvaluatedStylesheet};
+
+/// 前缀表认识的浏览器。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Browser {
+    Chrome,
+    Edge,
+    Firefox,
+    Ie,
+    Ios,
+    Opera,
+    Safari,
+    Samsung,
+}
+
+impl Browser {
+    /// 按 browserslist 的名称解析，如 `safari`、`ios_saf`、`ie`。
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "chrome" => Some(Self::Chrome),
+            "edge" => Some(Self::Edge),
+            "firefox" | "ff" => Some(Self::Firefox),
+            "ie" | "explorer" => Some(Self::Ie),
+            "ios" | "ios_saf" => Some(Self::Ios),
+            "opera" => Some(Self::Opera),
+            "safari" => Some(Self::Safari),
+            "samsung" => Some(Self::Samsung),
+            _ => None,
+        }
+    }
+}
+
+/// 单个目标浏览器及其最低版本，如 `safari 12`。
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BrowserTarget {
+    pub browser: Browser,
+    pub version: f32,
+}
+
+impl BrowserTarget {
+    /// 解析 `"<浏览器> <版本>"`，如 `"ie 11"`、`"ios 9.3"`。
+    pub fn parse(input: &str) -> Option<Self> {
+        let mut parts = input.split_whitespace();
+        let browser = Browser::from_name(parts.next()?)?;
+        let version = parts.next()?.parse().ok()?;
+        if parts.next().is_some() {
+            return None;
+        }
+        Some(Self { browser, version })
+    }
+}
+
+/// 插入的内容：给属性名加前缀，或把值替换为带前缀的写法（如 `display: -webkit-flex`）。
+enum Prefixed {
+    Property(&'static str),
+    Value(&'static str),
+}
+
+struct PrefixRule {
+    properties: &'static [&'static str],
+    /// 只在值等于此关键字时适用；`None` 表示任意值。
+    value: Option<&'static str>,
+    prefixed: Prefixed,
+    /// 需要该前缀的浏览器及其最高版本。
+    browsers: &'static [(Browser, f32)],
+}
+
+const FLEX_PROPERTIES: &[&str] = &[
+    "flex",
+    "flex-direction",
+    "flex-wrap",
+    "flex-flow",
+    "flex-grow",
+    "flex-shrink",
+    "flex-basis",
+    "justify-content",
+    "align-items",
+    "align-self",
+    "align-content",
+    "order",
+];
+
+const OLD_WEBKIT: &[(Browser, f32)] = &[
+    (Browser::Chrome, 28.0),
+    (Browser::Safari, 8.0),
+    (Browser::Ios, 8.4),
+];
+
+const RULES: &[PrefixRule] = &[
+    PrefixRule {
+        properties: &["display"],
+        value: Some("flex"),
+        prefixed: Prefixed::Value("-webkit-flex"),
+        browsers: OLD_WEBKIT,
+    },
+    PrefixRule {
+        properties: &["display"],
+        value: Some("flex"),
+        prefixed: Prefixed::Value("-ms-flexbox"),
+        browsers: &[(Browser::Ie, 10.0)],
+    },
+    PrefixRule {
+        properties: &["display"],
+        value: Some("inline-flex"),
+        prefixed: Prefixed::Value("-webkit-inline-flex"),
+        browsers: OLD_WEBKIT,
+    },
+    PrefixRule {
+        properties: &["display"],
+        value: Some("inline-flex"),
+        prefixed: Prefixed::Value("-ms-inline-flexbox"),
+        browsers: &[(Browser::Ie, 10.0)],
+    },
+    PrefixRule {
+        properties: FLEX_PROPERTIES,
+        value: None,
+        prefixed: Prefixed::Property("-webkit-"),
+        browsers: OLD_WEBKIT,
+    },
+    PrefixRule {
+        properties: &["position"],
+        value: Some("sticky"),
+        prefixed: Prefixed::Value("-webkit-sticky"),
+        browsers: &[(Browser::Safari, 12.1), (Browser::Ios, 12.5)],
+    },
+    PrefixRule {
+        properties: &[
+            "transform",
+            "transform-origin",
+            "transition",
+            "animation",
+            "animation-name",
+            "animation-duration",
+            "animation-delay",
+        ],
+        value: None,
+        prefixed: Prefixed::Property("-webkit-"),
+        browsers: &[
+            (Browser::Chrome, 35.0),
+            (Browser::Safari, 8.0),
+            (Browser::Ios, 8.4),
+        ],
+    },
+    PrefixRule {
+        properties: &["transform", "transform-origin"],
+        value: None,
+        prefixed: Prefixed::Property("-ms-"),
+        browsers: &[(Browser::Ie, 9.0)],
+    },
+    PrefixRule {
+        properties: &["user-select"],
+        value: None,
+        prefixed: Prefixed::Property("-webkit-"),
+        browsers: &[
+            (Browser::Chrome, 53.0),
+            (Browser::Opera, 40.0),
+            (Browser::Safari, 17.9),
+            (Browser::Ios, 17.9),
+        ],
+    },
+    PrefixRule {
+        properties: &["user-select"],
+        value: None,
+        prefixed: Prefixed::Property("-moz-"),
+        browsers: &[(Browser::Firefox, 68.0)],
+    },
+    PrefixRule {
+        properties: &["user-select", "hyphens"],
+        value: None,
+        prefixed: Prefixed::Property("-ms-"),
+        browsers: &[(Browser::Ie, 11.0), (Browser::Edge, 18.0)],
+    },
+    PrefixRule {
+        properties: &["backdrop-filter"],
+        value: None,
+        prefixed: Prefixed::Property("-webkit-"),
+        browsers: &[(Browser::Safari, 17.9), (Browser::Ios, 17.9)],
+    },
+    PrefixRule {
+        properties: &["hyphens"],
+        value: None,
+        prefixed: Prefixed::Property("-webkit-"),
+        browsers: &[(Browser::Safari, 16.9), (Browser::Ios, 16.9)],
+    },
+    PrefixRule {
+        properties: &["appearance"],
+        value: None,
+        prefixed: Prefixed::Property("-webkit-"),
+        browsers: &[
+            (Browser::Chrome, 83.0),
+            (Browser::Edge, 83.0),
+            (Browser::Opera, 69.0),
+            (Browser::Safari, 15.3),
+            (Browser::Ios, 15.3),
+            (Browser::Samsung, 13.0),
+        ],
+    },
+    PrefixRule {
+        properties: &["appearance"],
+        value: None,
+        prefixed: Prefixed::Property("-moz-"),
+        browsers: &[(Browser::Firefox, 79.0)],
+    },
+    PrefixRule {
+        properties: &[
+            "mask",
+            "mask-image",
+            "mask-size",
+            "mask-position",
+            "mask-repeat",
+        ],
+        value: None,
+        prefixed: Prefixed::Property("-webkit-"),
+        browsers: &[
+            (Browser::Chrome, 119.0),
+            (Browser::Edge, 119.0),
+            (Browser::Opera, 105.0),
+            (Browser::Safari, 15.3),
+            (Browser::Ios, 15.3),
+            (Browser::Samsung, 24.0),
+        ],
+    },
+    PrefixRule {
+        properties: &["text-size-adjust"],
+        value: None,
+        prefixed: Prefixed::Property("-webkit-"),
+        browsers: &[(Browser::Ios, f32::MAX)],
+    },
+];
+
+pub(crate) fn add_prefixes(stylesheet: &mut EvaluatedStylesheet, targets: &[BrowserTarget]) {
+    prefix_nodes(&mut stylesheet.nodes, targets);
+}
+
+pub(crate) fn prefix_nodes(nodes: &mut [EvaluatedNode], targets: &[BrowserTarget]) {
+    for node in nodes {
+        match node {
+            EvaluatedNode::Rule(rule) => prefix_declarations(&mut rule.declarations, targets),
+            EvaluatedNode::AtRule(at_rule) => {
+                prefix_declarations(&mut at_rule.declarations, targets);
+                prefix_nodes(&mut at_rule.children, targets);
+            }
+            EvaluatedNode::Raw(_) | EvaluatedNode::Comment(_) => {}
+        }
+    }
+}
+
+/// 在每条需要前缀的声明之前插入带前缀的版本；规则中已写出的同名同值声明不再重复插入。
+fn prefix_declarations(declarations: &mut Vec<EvaluatedDeclaration>, targets: &[BrowserTarget]) {
+    let original = std::mem::take(declarations);
+    let mut output = Vec::with_capacity(original.len());
+    for decl in &original {
+        let name = decl.name.trim().to_ascii_lowercase();
+        let value = decl.value.trim();
+        for rule in RULES {
+            let applies = rule.properties.contains(&name.as_str())
+                && rule
+                    .value
+                    .is_none_or(|expected| value.eq_ignore_ascii_case(expected))
+                && targets.iter().any(|target| {
+                    rule.browsers
+                        .iter()
+                        .any(|(browser, max)| *browser == target.browser && target.version <= *max)
+                });
+            if !applies {
+                continue;
+            }
+            let (prefixed_name, prefixed_value) = match rule.prefixed {
+                Prefixed::Property(prefix) => (format!("{prefix}{name}"), value.to_string()),
+                Prefixed::Value(prefixed) => (name.clone(), prefixed.to_string()),
+            };
+            let exists =
+                original
+                    .iter()
+                    .chain(output.iter())
+                    .any(|other: &EvaluatedDeclaration| {
+                        other.name.trim().eq_ignore_ascii_case(&prefixed_name)
+                            && other.value.trim() == prefixed_value
+                    });
+            if !exists {
+                output.push(EvaluatedDeclaration {
+                    name: prefixed_name,
+                    value: prefixed_value,
+                    comments: Vec::new(),
+                    ..decl.clone()
+                });
+            }
+        }
+        output.push(decl.clone());
+    }
+    *declarations = output;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_browser_targets() {
+        assert_eq!(
+            BrowserTarget::parse("ios_saf 9.3"),
+            Some(BrowserTarget {
+                browser: Browser::Ios,
+                version: 9.3
+            })
+        );
+        assert_eq!(
+            BrowserTarget::parse("IE 11").map(|target| target.browser),
+            Some(Browser::Ie)
+        );
+        assert_eq!(BrowserTarget::parse("netscape 4"), None);
+        assert_eq!(BrowserTarget::parse("safari"), None);
+    }
+}
@@ -0,0 +1,134 @@
+//! 远程 `@import` 支持（`http` feature）。
+//! 通过 `ureq` 下载文件；可选的缓存目录以 URL 的 SHA-256 命名保存下载结果，
+//! 文件名在不同平台、不同 Rust 版本之间保持稳定。
+
+use crate::error::{LessError, LessResult};
+use sha2::{Digest, Sha256};
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// 远程导入的下载配置。
+#[derive(Debug, Clone)]
+pub struct RemoteOptions {
+    /// 下载结果的缓存目录，`None` 时每次编译都重新下载。
+    pub cache_dir: Option<PathBuf>,
+    /// 缓存条目的有效期，按缓存文件的修改时间计算：过期后重新下载并覆盖，
+    /// 下载失败时仍使用过期的副本。`None` 时缓存永不过期，需要手动清理缓存目录才会重新下载。
+    pub cache_max_age: Option<Duration>,
+    /// 单个文件的下载超时时间。
+    pub timeout: Duration,
+}
+
+impl Default for RemoteOptions {
+    fn default() -> Self {
+        Self {
+            cache_dir: None,
+            cache_max_age: None,
+            timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+/// 下载远程文件内容，命中未过期的缓存时直接读取本地副本。
+pub(crate) fn fetch(url: &str, options: &RemoteOptions) -> LessResult<String> {
+    let cached = options
+        .cache_dir
+        .as_ref()
+        .map(|dir| dir.join(cache_key(url)));
+    let stale = match &cached {
+        Some(path) => match fs::read_to_string(path) {
+            Ok(content) if is_fresh(path, options.cache_max_age) => return Ok(content),
+            Ok(content) => Some(content),
+            Err(_) => None,
+        },
+        None => None,
+    };
+
+    let content = match download(url, options.timeout) {
+        Ok(content) => content,
+        Err(err) => return stale.ok_or(err),
+    };
+
+    if let Some(path) = &cached {
+        let written = path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::write(path, &content));
+        if let Err(err) = written {
+            return Err(LessError::eval(format!(
+                "写入远程导入缓存 {} 失败: {err}",
+                path.display()
+            )));
+        }
+    }
+    Ok(content)
+}
+
+fn download(url: &str, timeout: Duration) -> LessResult<String> {
+    let response = ureq::AgentBuilder::new()
+        .timeout(timeout)
+        .build()
+        .get(url)
+        .call()
+        .map_err(|err| LessError::eval(format!("下载远程文件 {url} 失败: {err}")))?;
+    response
+        .into_string()
+        .map_err(|err| LessError::eval(format!("读取远程文件 {url} 失败: {err}")))
+}
+
+fn is_fresh(path: &Path, max_age: Option<Duration>) -> bool {
+    let Some(max_age) = max_age else {
+        return true;
+    };
+    fs::metadata(path)
+        .and_then(|meta| meta.modified())
+        .ok()
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+        .is_some_and(|age| age <= max_age)
+}
+
+/// 缓存文件名：URL 的 SHA-256 十六进制摘要。
+fn cache_key(url: &str) -> String {
+    let mut name = String::with_capacity(64 + 5);
+    for byte in Sha256::digest(url.as_bytes()) {
+        let _ = write!(name, "{byte:02x}");
+    }
+    name.push_str(".less");
+    name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cache_key_is_sha256_of_url() {
+        assert_eq!(
+            cache_key("https://cdn.example.com/theme.less"),
+            "4a27e7179f6bd26ff781c252291ff7adca12f46bca3c495ae8e20fc48af911a8.less"
+        );
+    }
+
+    #[test]
+    fn cached_copies_are_used_and_stale_ones_survive_failed_downloads() {
+        let dir = std::env::temp_dir().join(format!("less_oxide_remote_{}", std::process::id()));
+        let url = "http://127.0.0.1:9/theme.less";
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join(cache_key(url)), "@brand: red;").unwrap();
+
+        let mut options = RemoteOptions {
+            cache_dir: Some(dir.clone()),
+            timeout: Duration::from_secs(1),
+            ..RemoteOptions::default()
+        };
+        assert_eq!(fetch(url, &options).unwrap(), "@brand: red;");
+        options.cache_max_age = Some(Duration::ZERO);
+        assert_eq!(fetch(url, &options).unwrap(), "@brand: red;");
+        options.cache_dir = None;
+        assert!(fetch(url, &options).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
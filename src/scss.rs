@@ -0,0 +1,498 @@
+//! LESS → SCSS 转换：复用 [`crate::format`] 的排版，按 SCSS 语法输出 LESS 特有的结构。
+//!
+//! | LESS | SCSS |
+//! | --- | --- |
+//! | `@var: value;`、`@var`、`@{var}` | `$var: value;`、`$var`、`#{$var}` |
+//! | `.m(@a: 1) { }` / `.m(1);` | `@mixin m($a: 1) { }` / `@include m(1);` |
+//! | 调用普通规则集 `.a;` | `@extend .a;` |
+//! | `when (@a > 0)` 守卫 | 包在 `@if $a > 0 { }` 中 |
+//! | `@rules: { }` / `@rules();` | `@mixin rules { }` / `@include rules;` |
+//! | 规则集参数 `.m({ ... })`、`.m(@rules)` 与 `@param();` | `@include m { ... }`、`@include m { @include rules; }` 与 `@content;` |
+//! | `each(@list, { ... @value ... })` | `@each $value in $list { ... }` |
+//! | `~"..."`、`e("...")` | `unquote("...")` |
+//!
+//! 内置函数原样保留；mixin 调用的 `!important` 与属性合并标记 `+:` 没有对应写法，转换时丢弃；
+//! `@@var`、`@arguments` 以及用到 `@key` / `@index` 的 `each()` 无法表达，遇到时返回错误。
+
+use crate::ast::{
+    AtRule, BodylessAtRule, Declaration, DetachedCall, EachCall, Guard, GuardOperator,
+    LookupTarget, MixinArgument, MixinCall, MixinDefinition, RuleBody, RuleSet, Span, Value,
+    ValuePiece, VariableDeclaration, VariableInit,
+};
+use crate::error::{LessError, LessResult};
+use crate::format::{render, FormatOptions, Formatter, Node};
+use crate::parser::LessParser;
+use crate::visit::{walk_body, walk_stylesheet, Visitor};
+use std::borrow::Cow;
+use std::collections::HashSet;
+
+/// SCSS 输出时的上下文。
+pub(crate) struct ScssState {
+    /// 以 `@mixin` 输出的 mixin 名（不含 `.` / `#` 前缀），对其余名称的调用输出为 `@extend`。
+    mixins: HashSet<String>,
+    /// 以 `@mixin` 输出的分离规则集变量名；作为实参传入时改为 `@include m { @include rules; }`。
+    rulesets: HashSet<String>,
+    /// 当前 mixin 中以 `@param();` 调用的规则集参数，输出为 `@content`。
+    content_params: Vec<String>,
+}
+
+pub(crate) fn convert_to_scss(source: &str, options: &FormatOptions) -> LessResult<String> {
+    let stylesheet = LessParser::new().parse(source)?;
+    let mut scan = Scan::default();
+    walk_stylesheet(&mut scan, &stylesheet);
+    if let Some(err) = scan.error {
+        return Err(err.fill_line_text(|_| Some(source)));
+    }
+    let state = ScssState {
+        mixins: scan.mixins,
+        rulesets: scan.rulesets,
+        content_params: Vec::new(),
+    };
+    Ok(render(source, &stylesheet, options, Some(state)))
+}
+
+impl Formatter<'_> {
+    /// 输出与 LESS 写法不同的节点；其余节点返回 `false`，按 LESS 的排版输出。
+    pub(crate) fn scss_node(&mut self, node: &Node<'_>, depth: usize) -> bool {
+        match node {
+            Node::Import(import) if !import.is_css => {
+                let text = self.text(&import.raw);
+                let text = text
+                    .replace(".less\"", ".scss\"")
+                    .replace(".less'", ".scss'");
+                self.out.push_str(&text);
+            }
+            Node::Plugin(name) => {
+                self.out.push_str(&format!("// @plugin \"{name}\";"));
+            }
+            Node::RuleSet(rule) if rule.guard.is_some() => {
+                let guard = self.scss_guard(rule.guard.as_ref().unwrap());
+                let unguarded = RuleSet {
+                    guard: None,
+                    ..(*rule).clone()
+                };
+                self.out.push_str(&format!("@if {guard} {{\n"));
+                self.indent(depth + 1);
+                self.node(&Node::RuleSet(&unguarded), depth + 1);
+                self.out.push('\n');
+                self.indent(depth);
+                self.out.push('}');
+            }
+            Node::Variable(variable) => match &variable.value {
+                VariableInit::Value(value) => {
+                    let value = self.value(value);
+                    self.out.push_str(&format!("${}: {value};", variable.name));
+                }
+                VariableInit::Ruleset(body) => {
+                    self.out.push_str(&format!("@mixin {}", variable.name));
+                    self.block(body, depth);
+                }
+            },
+            // 扫描阶段已排除使用 `@key` / `@index` 或传入分离规则集变量的调用。
+            Node::Each(call) => {
+                let list = self.value(&call.list);
+                let name = call.params.first().map_or("value", String::as_str);
+                self.out.push_str(&format!("@each ${name} in {list}"));
+                if let MixinArgument::Ruleset(body) = &call.ruleset {
+                    self.block(body, depth);
+                }
+            }
+            Node::MixinDefinition(definition) => self.scss_mixin_definition(definition, depth),
+            Node::MixinCall(call) => self.scss_mixin_call(call, depth),
+            Node::DetachedCall(name, _) => {
+                let is_content = self
+                    .scss
+                    .as_ref()
+                    .is_some_and(|state| state.content_params.iter().any(|param| param == name));
+                if is_content {
+                    self.out.push_str("@content;");
+                } else {
+                    self.out.push_str(&format!("@include {name};"));
+                }
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    fn scss_mixin_definition(&mut self, definition: &MixinDefinition, depth: usize) {
+        let content: Vec<String> = definition
+            .params
+            .iter()
+            .filter(|param| calls_detached(&definition.body, &param.name))
+            .map(|param| param.name.clone())
+            .collect();
+        let params: Vec<String> = definition
+            .params
+            .iter()
+            .filter(|param| !content.contains(&param.name))
+            .map(|param| match &param.default {
+                Some(default) => format!("${}: {}", param.name, self.value(default)),
+                None => format!("${}", param.name),
+            })
+            .collect();
+        self.out
+            .push_str(&format!("@mixin {}", mixin_name(&definition.name)));
+        if !params.is_empty() {
+            self.out.push_str(&format!("({})", params.join(", ")));
+        }
+        let outer = self
+            .scss
+            .as_mut()
+            .map(|state| std::mem::replace(&mut state.content_params, content))
+            .unwrap_or_default();
+        match &definition.guard {
+            Some(guard) => {
+                let guard = self.scss_guard(guard);
+                self.out.push_str(" {\n");
+                self.indent(depth + 1);
+                self.out.push_str(&format!("@if {guard}"));
+                self.block(&definition.body, depth + 1);
+                self.out.push('\n');
+                self.indent(depth);
+                self.out.push('}');
+            }
+            None => self.block(&definition.body, depth),
+        }
+        if let Some(state) = self.scss.as_mut() {
+            state.content_params = outer;
+        }
+    }
+
+    fn scss_mixin_call(&mut self, call: &MixinCall, depth: usize) {
+        let name = mixin_name(&call.name);
+        let is_mixin = self
+            .scss
+            .as_ref()
+            .is_some_and(|state| state.mixins.contains(name));
+        if !is_mixin {
+            self.out.push_str(&format!("@extend {};", call.name));
+            return;
+        }
+        let mut args = Vec::new();
+        let mut content = None;
+        for arg in &call.args {
+            match arg {
+                MixinArgument::Value(value) => match self.scss_ruleset_variable(value) {
+                    Some(name) => {
+                        content = Some(Cow::Owned(vec![RuleBody::DetachedCall(DetachedCall {
+                            name: name.to_string(),
+                            span: call.span.clone(),
+                        })]))
+                    }
+                    None => args.push(self.value(value)),
+                },
+                MixinArgument::Ruleset(body) => content = Some(Cow::Borrowed(body.as_slice())),
+            }
+        }
+        self.out.push_str(&format!("@include {name}"));
+        if !args.is_empty() {
+            self.out.push_str(&format!("({})", args.join(", ")));
+        }
+        match content {
+            Some(body) => self.block(&body, depth),
+            None => self.out.push(';'),
+        }
+    }
+
+    /// 实参是否只是对分离规则集变量的引用（`.m(@rules)`），是则返回变量名。
+    fn scss_ruleset_variable<'v>(&self, value: &'v Value) -> Option<&'v str> {
+        let [ValuePiece::VariableRef(name)] = value.pieces.as_slice() else {
+            return None;
+        };
+        self.scss
+            .as_ref()
+            .is_some_and(|state| state.rulesets.contains(name))
+            .then_some(name.as_str())
+    }
+
+    fn scss_guard(&self, guard: &Guard) -> String {
+        let wrap = guard.alternatives.len() > 1 || guard.alternatives.iter().any(|c| c.len() > 1);
+        guard
+            .alternatives
+            .iter()
+            .map(|conditions| {
+                conditions
+                    .iter()
+                    .map(|condition| {
+                        let lhs = self.value(&condition.lhs);
+                        let text = match &condition.comparison {
+                            Some((operator, rhs)) => {
+                                let operator = match operator {
+                                    GuardOperator::Eq => "==",
+                                    GuardOperator::Lt => "<",
+                                    GuardOperator::Le => "<=",
+                                    GuardOperator::Gt => ">",
+                                    GuardOperator::Ge => ">=",
+                                };
+                                format!("{lhs} {operator} {}", self.value(rhs))
+                            }
+                            // LESS 中只有值为 `true` 时条件才成立。
+                            None => format!("{lhs} == true"),
+                        };
+                        if condition.negated {
+                            format!("not ({text})")
+                        } else if wrap {
+                            format!("({text})")
+                        } else {
+                            text
+                        }
+                    })
+                    .collect::<Vec<_>>()
+                    .join(" and ")
+            })
+            .collect::<Vec<_>>()
+            .join(" or ")
+    }
+}
+
+/// 值的 SCSS 原文；`interpolate` 时变量写成 `#{$var}`（用于 at-rule 参数）。
+pub(crate) fn value_text(value: &Value, interpolate: bool) -> String {
+    value
+        .pieces
+        .iter()
+        .map(|piece| match piece {
+            ValuePiece::Literal(text) => text.clone(),
+            ValuePiece::VariableRef(name) | ValuePiece::IndirectVariableRef(name) => {
+                if interpolate {
+                    format!("#{{${name}}}")
+                } else {
+                    format!("${name}")
+                }
+            }
+            // 转换前的检查已拒绝属性引用与查找，这里只保留原文。
+            ValuePiece::PropertyRef(name) => format!("${name}"),
+            ValuePiece::Lookup(lookup) => lookup.to_string(),
+        })
+        .collect()
+}
+
+/// 改写文本中的 `@{var}` 插值与 `~"..."` / `e("...")` 转义。
+pub(crate) fn convert_text(text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut idx = 0;
+    while let Some(ch) = text[idx..].chars().next() {
+        let rest = &text[idx..];
+        if let Some(inner) = rest.strip_prefix("@{") {
+            if let Some(end) = inner.find('}') {
+                output.push_str(&format!("#{{${}}}", inner[..end].trim()));
+                idx += 2 + end + 1;
+                continue;
+            }
+        }
+        if let Some(quoted) = rest.strip_prefix('~') {
+            if let Some(quote @ ('"' | '\'')) = quoted.chars().next() {
+                if let Some(end) = quoted[1..].find(quote) {
+                    let inner = convert_text(&quoted[1..end + 1]);
+                    output.push_str(&format!("unquote({quote}{inner}{quote})"));
+                    idx += 1 + end + 2;
+                    continue;
+                }
+            }
+        }
+        let word_start = output
+            .chars()
+            .next_back()
+            .is_none_or(|prev| !prev.is_alphanumeric() && prev != '-' && prev != '_');
+        if word_start && rest.starts_with("e(") {
+            output.push_str("unquote(");
+            idx += 2;
+            continue;
+        }
+        output.push(ch);
+        idx += ch.len_utf8();
+    }
+    output
+}
+
+fn mixin_name(name: &str) -> &str {
+    name.trim_start_matches(['.', '#'])
+}
+
+/// 规则体（含嵌套规则）中是否以 `@name();` 调用了规则集。
+fn calls_detached(body: &[RuleBody], name: &str) -> bool {
+    struct Finder<'a> {
+        name: &'a str,
+        found: bool,
+    }
+    impl Visitor for Finder<'_> {
+        fn visit_detached_call(&mut self, call: &DetachedCall) {
+            self.found |= call.name == self.name;
+        }
+    }
+    let mut finder = Finder { name, found: false };
+    walk_body(&mut finder, body);
+    finder.found
+}
+
+/// 转换前的检查：收集 mixin 名，并找出无法用 SCSS 表达的变量引用。
+#[derive(Default)]
+struct Scan {
+    mixins: HashSet<String>,
+    rulesets: HashSet<String>,
+    error: Option<LessError>,
+}
+
+impl Scan {
+    fn check(&mut self, span: &Span, value: &Value) {
+        for piece in &value.pieces {
+            let message = match piece {
+                ValuePiece::IndirectVariableRef(name) => {
+                    format!("SCSS 中没有间接变量引用 @@{name} 的对应写法")
+                }
+                ValuePiece::VariableRef(name) if name == "arguments" => {
+                    "SCSS 中没有 @arguments 的对应写法".to_string()
+                }
+                ValuePiece::PropertyRef(name) => {
+                    format!("SCSS 中没有属性引用 ${name} 的对应写法")
+                }
+                ValuePiece::Lookup(lookup) => {
+                    format!("SCSS 中没有规则集查找 {lookup} 的对应写法")
+                }
+                _ => continue,
+            };
+            if self.error.is_none() {
+                self.error = Some(LessError::eval(message).at(span));
+            }
+        }
+    }
+
+    fn check_guard(&mut self, span: &Span, guard: &Guard) {
+        for condition in guard.alternatives.iter().flatten() {
+            self.check(span, &condition.lhs);
+            if let Some((_, rhs)) = &condition.comparison {
+                self.check(span, rhs);
+            }
+        }
+    }
+}
+
+impl Visitor for Scan {
+    fn visit_ruleset(&mut self, rule: &RuleSet) {
+        if let Some(guard) = &rule.guard {
+            self.check_guard(&rule.span, guard);
+        }
+        walk_body(self, &rule.body);
+    }
+
+    fn visit_at_rule(&mut self, at_rule: &AtRule) {
+        self.check(&at_rule.span, &at_rule.params);
+        walk_body(self, &at_rule.body);
+    }
+
+    fn visit_bodyless_at_rule(&mut self, at_rule: &BodylessAtRule) {
+        self.check(&at_rule.span, &at_rule.params);
+    }
+
+    fn visit_mixin_definition(&mut self, definition: &MixinDefinition) {
+        self.mixins.insert(mixin_name(&definition.name).to_string());
+        for default in definition
+            .params
+            .iter()
+            .filter_map(|param| param.default.as_ref())
+        {
+            self.check(&definition.span, default);
+        }
+        if let Some(guard) = &definition.guard {
+            self.check_guard(&definition.span, guard);
+        }
+        walk_body(self, &definition.body);
+    }
+
+    fn visit_mixin_call(&mut self, call: &MixinCall) {
+        for arg in &call.args {
+            match arg {
+                MixinArgument::Value(value) => self.check(&call.span, value),
+                MixinArgument::Ruleset(body) => walk_body(self, body),
+            }
+        }
+    }
+
+    fn visit_variable(&mut self, variable: &VariableDeclaration) {
+        match &variable.value {
+            VariableInit::Value(value) => self.check(&variable.span, value),
+            VariableInit::Ruleset(body) => {
+                self.rulesets.insert(variable.name.clone());
+                walk_body(self, body)
+            }
+        }
+    }
+
+    fn visit_declaration(&mut self, declaration: &Declaration) {
+        self.check(&declaration.span, &declaration.value);
+    }
+
+    fn visit_each(&mut self, call: &EachCall) {
+        self.check(&call.span, &call.list);
+        let locals = [(1, "key"), (2, "index")]
+            .map(|(index, default)| call.params.get(index).map_or(default, String::as_str));
+        match &call.ruleset {
+            MixinArgument::Ruleset(body)
+                if !locals.iter().any(|name| uses_variable(body, name)) =>
+            {
+                walk_body(self, body)
+            }
+            _ if self.error.is_none() => {
+                self.error = Some(
+                    LessError::eval("SCSS 中只有以 @value 遍历列表的 each() 对应写法 @each")
+                        .at(&call.span),
+                );
+            }
+            _ => {}
+        }
+    }
+}
+
+/// 规则体（含嵌套规则）中是否以 `@name` 或 `@{name}` 引用了变量。
+fn uses_variable(body: &[RuleBody], name: &str) -> bool {
+    struct Finder<'a> {
+        name: &'a str,
+        interpolation: String,
+        found: bool,
+    }
+    impl Finder<'_> {
+        fn value(&mut self, value: &Value) {
+            self.found |= value.pieces.iter().any(|piece| match piece {
+                ValuePiece::VariableRef(name) => name == self.name,
+                ValuePiece::Literal(text) => text.contains(&self.interpolation),
+                ValuePiece::IndirectVariableRef(_) | ValuePiece::PropertyRef(_) => false,
+                ValuePiece::Lookup(lookup) => {
+                    matches!(&lookup.target, LookupTarget::Variable(name) if name == self.name)
+                }
+            });
+        }
+    }
+    impl Visitor for Finder<'_> {
+        fn visit_ruleset(&mut self, rule: &RuleSet) {
+            self.found |= rule
+                .selectors
+                .iter()
+                .any(|selector| selector.value.contains(&self.interpolation));
+            walk_body(self, &rule.body);
+        }
+
+        fn visit_at_rule(&mut self, at_rule: &AtRule) {
+            self.value(&at_rule.params);
+            walk_body(self, &at_rule.body);
+        }
+
+        fn visit_variable(&mut self, variable: &VariableDeclaration) {
+            match &variable.value {
+                VariableInit::Value(value) => self.value(value),
+                VariableInit::Ruleset(body) => walk_body(self, body),
+            }
+        }
+
+        fn visit_declaration(&mut self, declaration: &Declaration) {
+            self.found |= declaration.name.contains(&self.interpolation);
+            self.value(&declaration.value);
+        }
+    }
+    let mut finder = Finder {
+        name,
+        interpolation: format!("@{{{name}}}"),
+        found: false,
+    };
+    walk_body(&mut finder, body);
+    finder.found
+}
@@ -0,0 +1,240 @@
+//! 父选择器 `&` 的展开。
+//!
+//! 选择器按真正的 `&` 拆成若干段；字符串、属性选择器 `[...]` 与转义字符 `\&` 中的 `&`
+//! 不是父选择器引用，原样保留。
+
+/// 按 `&` 拆分后的选择器：`&` 出现 n 次时 `parts` 有 n + 1 段。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ParentSelector {
+    parts: Vec<String>,
+}
+
+impl ParentSelector {
+    pub(crate) fn parse(selector: &str) -> Self {
+        let mut parts = Vec::new();
+        let mut current = String::new();
+        let mut quote: Option<char> = None;
+        let mut bracket_depth = 0usize;
+        let mut chars = selector.chars();
+        while let Some(ch) = chars.next() {
+            match ch {
+                '\\' => {
+                    current.push(ch);
+                    if let Some(escaped) = chars.next() {
+                        current.push(escaped);
+                    }
+                    continue;
+                }
+                '"' | '\'' if quote.is_none() => quote = Some(ch),
+                _ if quote == Some(ch) => quote = None,
+                '[' if quote.is_none() => bracket_depth += 1,
+                ']' if quote.is_none() => bracket_depth = bracket_depth.saturating_sub(1),
+                '&' if quote.is_none() && bracket_depth == 0 => {
+                    parts.push(std::mem::take(&mut current));
+                    continue;
+                }
+                _ => {}
+            }
+            current.push(ch);
+        }
+        parts.push(current);
+        Self { parts }
+    }
+
+    /// `&` 出现的次数。
+    pub(crate) fn references(&self) -> usize {
+        self.parts.len() - 1
+    }
+
+    /// 依次用 `parents` 中的选择器替换每个 `&`；`parents` 的长度须等于 [`Self::references`]。
+    fn resolve(&self, parents: &[&str]) -> String {
+        let mut result = String::new();
+        for (idx, part) in self.parts.iter().enumerate() {
+            if idx > 0 {
+                result.push_str(parents[idx - 1].trim());
+            }
+            result.push_str(part);
+        }
+        result.trim().to_string()
+    }
+
+    /// 与 `parents` 组合后生成的选择器数量；溢出时返回 `usize::MAX`。
+    pub(crate) fn combinations(&self, parents: usize) -> usize {
+        match self.references() {
+            0 => parents,
+            count => u32::try_from(count)
+                .ok()
+                .and_then(|count| parents.checked_pow(count))
+                .unwrap_or(usize::MAX),
+        }
+    }
+
+    /// 与父选择器列表组合：不含 `&` 时以后代关系拼接在每个父选择器之后；
+    /// 含多个 `&` 时每个 `&` 独立取遍全部父选择器（笛卡尔积，靠前的 `&` 变化最慢），与 lessc 一致。
+    pub(crate) fn combine(&self, parents: &[String], output: &mut Vec<String>) {
+        let count = self.references();
+        if count == 0 {
+            let child = self.parts[0].trim();
+            output.extend(
+                parents
+                    .iter()
+                    .map(|parent| format!("{} {child}", parent.trim())),
+            );
+            return;
+        }
+        if parents.is_empty() {
+            return;
+        }
+        let mut indices = vec![0usize; count];
+        loop {
+            let chosen: Vec<&str> = indices.iter().map(|&idx| parents[idx].as_str()).collect();
+            output.push(self.resolve(&chosen));
+            // 从最后一个 `&` 开始进位。
+            let Some(position) = indices.iter().rposition(|&idx| idx + 1 < parents.len()) else {
+                break;
+            };
+            indices[position] += 1;
+            indices[position + 1..].fill(0);
+        }
+    }
+}
+
+/// 用 `rename` 改写选择器中的类名（CSS Modules）；字符串、属性选择器与转义字符不变，
+/// `:global(...)` 去掉包裹后原样保留其中的选择器。
+pub(crate) fn rename_classes(selector: &str, rename: &mut impl FnMut(&str) -> String) -> String {
+    let mut output = String::with_capacity(selector.len());
+    let mut idx = 0;
+    while let Some(ch) = selector[idx..].chars().next() {
+        let rest = &selector[idx..];
+        match ch {
+            '"' | '\'' | '[' => {
+                let close = if ch == '[' { ']' } else { ch };
+                let end = rest[1..]
+                    .find(close)
+                    .map_or(selector.len(), |offset| idx + offset + 2);
+                output.push_str(&selector[idx..end]);
+                idx = end;
+            }
+            '\\' => {
+                let escaped = rest[1..].chars().next().map_or(0, char::len_utf8);
+                output.push_str(&rest[..1 + escaped]);
+                idx += 1 + escaped;
+            }
+            ':' if rest.starts_with(":global(") => {
+                let inner_start = idx + ":global(".len();
+                let mut depth = 1usize;
+                let mut end = selector.len();
+                for (offset, c) in selector[inner_start..].char_indices() {
+                    match c {
+                        '(' => depth += 1,
+                        ')' => {
+                            depth -= 1;
+                            if depth == 0 {
+                                end = inner_start + offset;
+                                break;
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                output.push_str(&selector[inner_start..end]);
+                idx = (end + 1).min(selector.len());
+            }
+            '.' if starts_identifier(&rest[1..]) => {
+                let name_len: usize = rest[1..]
+                    .chars()
+                    .take_while(|c| c.is_alphanumeric() || matches!(c, '-' | '_') || !c.is_ascii())
+                    .map(char::len_utf8)
+                    .sum();
+                output.push('.');
+                output.push_str(&rename(&rest[1..1 + name_len]));
+                idx += 1 + name_len;
+            }
+            _ => {
+                output.push(ch);
+                idx += ch.len_utf8();
+            }
+        }
+    }
+    output
+}
+
+/// 类名须以字母、`_`、非 ASCII 字符或 `-` 加字母开头；`12.5%` 之类的数值不是类名。
+fn starts_identifier(input: &str) -> bool {
+    let mut chars = input.chars();
+    match chars.next() {
+        Some('-') => chars
+            .next()
+            .is_some_and(|c| c.is_alphabetic() || matches!(c, '_' | '-')),
+        Some(c) => c.is_alphabetic() || c == '_' || !c.is_ascii(),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn combine(parents: &[&str], child: &str) -> Vec<String> {
+        let parents: Vec<String> = parents.iter().map(|parent| parent.to_string()).collect();
+        let mut output = Vec::new();
+        ParentSelector::parse(child).combine(&parents, &mut output);
+        output
+    }
+
+    #[test]
+    fn replaces_every_parent_reference() {
+        assert_eq!(combine(&[".a"], "& + &"), [".a + .a"]);
+        assert_eq!(combine(&[".a"], "&&"), [".a.a"]);
+        assert_eq!(combine(&[".a .b"], "&-item"), [".a .b-item"]);
+        assert_eq!(combine(&[".a"], ".c&"), [".c.a"]);
+        assert_eq!(combine(&[".a"], "&:hover &"), [".a:hover .a"]);
+        assert_eq!(combine(&[".a"], "> .d"), [".a > .d"]);
+    }
+
+    #[test]
+    fn ignores_ampersands_in_strings_attributes_and_escapes() {
+        assert_eq!(
+            combine(&[".a"], r#"[data-x="a&b"]"#),
+            [r#".a [data-x="a&b"]"#]
+        );
+        assert_eq!(combine(&[".a"], "&[rel=a&b]"), [".a[rel=a&b]"]);
+        assert_eq!(combine(&[".a"], r"&.x\&y"), [r".a.x\&y"]);
+        assert_eq!(
+            ParentSelector::parse(r#"&:after[content="&"]"#).references(),
+            1
+        );
+    }
+
+    #[test]
+    fn renames_class_names_only() {
+        let mut rename = |name: &str| format!("{name}_x");
+        assert_eq!(
+            rename_classes(".btn.primary:hover > a.link", &mut rename),
+            ".btn_x.primary_x:hover > a.link_x"
+        );
+        assert_eq!(
+            rename_classes(r#"[class=".a"] .b\.c 12.5%"#, &mut rename),
+            r#"[class=".a"] .b_x\.c 12.5%"#
+        );
+        assert_eq!(
+            rename_classes(":global(.app .theme) .card", &mut rename),
+            ".app .theme .card_x"
+        );
+    }
+
+    #[test]
+    fn distributes_parent_lists_over_each_reference() {
+        assert_eq!(
+            combine(&[".a", ".b"], "& + &"),
+            [".a + .a", ".a + .b", ".b + .a", ".b + .b"]
+        );
+        assert_eq!(combine(&[".a", ".b"], "&-x"), [".a-x", ".b-x"]);
+        assert_eq!(ParentSelector::parse("& &").combinations(3), 9);
+        assert_eq!(ParentSelector::parse(".x").combinations(3), 3);
+        assert_eq!(
+            ParentSelector::parse(&"&".repeat(80)).combinations(2),
+            usize::MAX
+        );
+    }
+}
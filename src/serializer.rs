@@ -1,61 +1,236 @@
 use crate::evaluator::{
     EvaluatedAtRule, EvaluatedDeclaration, EvaluatedNode, EvaluatedRule, EvaluatedStylesheet,
 };
+use crate::sourcemap::Mapping;
 use crate::utils::{collapse_whitespace, indent};
+use crate::OutputStyle;
+use std::borrow::Borrow;
+use std::convert::Infallible;
+use std::io::{self, Write};
+use std::iter::Peekable;
+use std::ops::{Deref, DerefMut};
+use std::path::Path;
+use std::sync::Arc;
+
+/// 把求值后的样式表转换为最终产物，由 [`crate::compile_with_emitter`] 调用。
+///
+/// 实现该 trait 即可从同一棵求值结果输出 CSS 以外的格式，如 CSS-in-JS 对象、JSON 规则列表或统计信息；
+/// 内置的 [`Serializer`] 按美化或压缩格式输出 CSS 文本。
+///
+/// ```
+/// use less_oxide::{CssEmitter, EvaluatedNode, EvaluatedStylesheet};
+///
+/// struct RuleCount;
+///
+/// impl CssEmitter for RuleCount {
+///     type Output = usize;
+///
+///     fn emit(&mut self, stylesheet: &EvaluatedStylesheet) -> usize {
+///         stylesheet.nodes.iter().filter(|node| matches!(node, EvaluatedNode::Rule(_))).count()
+///     }
+/// }
+///
+/// let count = less_oxide::compile_with_emitter(".a { .b { c: d; } }", Default::default(), &mut RuleCount);
+/// assert_eq!(count.unwrap(), 1);
+/// ```
+pub trait CssEmitter {
+    /// 输出产物的类型。
+    type Output;
+
+    fn emit(&mut self, stylesheet: &EvaluatedStylesheet) -> Self::Output;
+}
 
 /// 负责将扁平化的规则转换为最终 CSS 文本。
+#[derive(Debug, Clone)]
 pub struct Serializer {
     minify: bool,
+    keep_empty_rules: bool,
+    /// 显式指定的输出格式；`None` 时按 `minify` 选择压缩或默认美化格式。
+    style: Option<OutputStyle>,
+    /// 美化输出时在每条规则前写出 `/* line N, file */` 注释。
+    line_numbers: bool,
 }
 
 impl Serializer {
+    /// `minify` 为 `true` 时输出压缩格式，否则输出默认的美化格式。
     pub fn new(minify: bool) -> Self {
-        Self { minify }
+        Self {
+            minify,
+            keep_empty_rules: false,
+            style: None,
+            line_numbers: false,
+        }
+    }
+
+    /// 默认的美化格式，两空格缩进。
+    pub fn pretty() -> Self {
+        Self::new(false)
+    }
+
+    /// 压缩格式，去掉所有可省略的空白与分号。
+    pub fn minified() -> Self {
+        Self::new(true)
+    }
+
+    /// 按 [`OutputStyle`] 输出；`None` 时沿用构造时选择的格式。
+    pub fn output_style(mut self, style: Option<OutputStyle>) -> Self {
+        self.style = style;
+        self
     }
 
+    /// 输出没有声明的空规则，而不是将其省略。
+    pub fn keep_empty_rules(mut self, keep: bool) -> Self {
+        self.keep_empty_rules = keep;
+        self
+    }
+
+    /// 对应 lessc 的 `--line-numbers=comments`：美化输出（含 `expanded`）时在每条规则前写出
+    /// `/* line 12, src/button.less */` 注释，压缩与 `compact` 格式不受影响。
+    pub fn dump_line_numbers(mut self, enabled: bool) -> Self {
+        self.line_numbers = enabled;
+        self
+    }
+
+    /// 把整个样式表序列化为字符串。
     pub fn to_css(&self, stylesheet: &EvaluatedStylesheet) -> String {
-        if self.minify {
-            self.render_minified(stylesheet)
-        } else {
-            self.render_pretty(stylesheet)
+        self.to_css_mapped(stylesheet, false).0
+    }
+
+    /// 序列化并在 `record` 时记下每条规则选择器在输出中的位置，供生成 source map。
+    pub(crate) fn to_css_mapped(
+        &self,
+        stylesheet: &EvaluatedStylesheet,
+        record: bool,
+    ) -> (String, Vec<Mapping>) {
+        let mut css = String::new();
+        let Ok(mappings) = self.render(
+            stylesheet.header(),
+            stylesheet.nodes.iter(),
+            record,
+            |text| {
+                css.push_str(text);
+                Ok::<_, Infallible>(())
+            },
+        );
+        (css, mappings)
+    }
+
+    /// 逐个顶层节点序列化并写入 `writer`，写出后即释放该节点，不在内存中拼接完整输出。
+    pub fn write_css(
+        &self,
+        mut stylesheet: EvaluatedStylesheet,
+        writer: &mut impl Write,
+    ) -> io::Result<()> {
+        let nodes = std::mem::take(&mut stylesheet.nodes);
+        self.write_nodes(
+            stylesheet.charset.as_deref(),
+            &stylesheet.imports,
+            nodes.into_iter(),
+            writer,
+        )
+    }
+
+    /// 按 `nodes` 产出的顺序逐个序列化并写入 `writer`，节点可以边求值边产出；
+    /// `charset` 与 `imports` 必须事先确定，它们位于所有节点之前。
+    pub(crate) fn write_nodes(
+        &self,
+        charset: Option<&str>,
+        imports: &[String],
+        nodes: impl Iterator<Item = EvaluatedNode>,
+        writer: &mut impl Write,
+    ) -> io::Result<()> {
+        let header = Header { charset, imports };
+        self.render(header, nodes, false, |text| {
+            writer.write_all(text.as_bytes())
+        })?;
+        writer.flush()
+    }
+
+    fn render<N: Borrow<EvaluatedNode>, E>(
+        &self,
+        header: Header<'_>,
+        nodes: impl Iterator<Item = N>,
+        record: bool,
+        emit: impl FnMut(&str) -> Result<(), E>,
+    ) -> Result<Vec<Mapping>, E> {
+        let mut nodes = nodes.peekable();
+        let trim = match self.style {
+            Some(OutputStyle::Compressed) => Trim::Newlines,
+            Some(OutputStyle::Compact) => Trim::None,
+            Some(OutputStyle::Expanded) => Trim::Whitespace,
+            None if self.minify => Trim::Newlines,
+            None => Trim::Whitespace,
+        };
+        let mut output = Output::new(trim, record, emit);
+        match self.style {
+            Some(OutputStyle::Compressed) => self.render_minified(header, nodes, &mut output)?,
+            Some(OutputStyle::Compact) => self.render_compact(header, nodes, &mut output)?,
+            Some(OutputStyle::Expanded) => {
+                self.render_pretty(header, &mut nodes, &mut output)?;
+                output.write("\n")?;
+            }
+            None if self.minify => self.render_minified(header, nodes, &mut output)?,
+            None => self.render_pretty(header, &mut nodes, &mut output)?,
         }
+        Ok(output.mappings)
     }
 
-    fn render_pretty(&self, stylesheet: &EvaluatedStylesheet) -> String {
-        let mut output = String::new();
-        for import in &stylesheet.imports {
-            output.push_str(import.trim());
-            output.push('\n');
+    /// 选择器列表的分隔符：`expanded` 与 lessc 一致每个选择器单独一行。
+    fn selector_separator(&self, level: usize) -> String {
+        if self.style == Some(OutputStyle::Expanded) {
+            format!(",\n{}", indent(level))
+        } else {
+            ", ".to_string()
         }
-        if !stylesheet.imports.is_empty() && !stylesheet.nodes.is_empty() {
-            output.push('\n');
+    }
+
+    fn render_pretty<N: Borrow<EvaluatedNode>, E>(
+        &self,
+        header: Header<'_>,
+        nodes: &mut Peekable<impl Iterator<Item = N>>,
+        output: &mut Output<impl FnMut(&str) -> Result<(), E>>,
+    ) -> Result<(), E> {
+        header.render(&mut output.chunk);
+        if !header.is_empty() && nodes.peek().is_some() {
+            output.chunk.push('\n');
         }
-        for (idx, node) in stylesheet.nodes.iter().enumerate() {
-            self.render_node_pretty(node, 0, &mut output);
-            if idx + 1 < stylesheet.nodes.len() {
-                output.push('\n');
+        output.flush()?;
+        while let Some(node) = nodes.next() {
+            let node = node.borrow();
+            self.render_node_pretty(node, 0, &mut output.chunk);
+            // 注释紧贴其后的节点，不额外空行。
+            if nodes.peek().is_some() && !matches!(node, EvaluatedNode::Comment(_)) {
+                output.chunk.push('\n');
             }
+            output.flush()?;
         }
-        output.trim().to_string()
+        Ok(())
     }
 
-    fn render_minified(&self, stylesheet: &EvaluatedStylesheet) -> String {
-        let mut output = String::new();
-        for import in &stylesheet.imports {
-            output.push_str(import.trim());
-            output.push('\n');
+    fn render_minified<N: Borrow<EvaluatedNode>, E>(
+        &self,
+        header: Header<'_>,
+        nodes: impl Iterator<Item = N>,
+        output: &mut Output<impl FnMut(&str) -> Result<(), E>>,
+    ) -> Result<(), E> {
+        header.render(&mut output.chunk);
+        output.flush()?;
+        for node in nodes {
+            self.render_node_minified(node.borrow(), &mut output.chunk);
+            output.flush()?;
         }
-        for node in &stylesheet.nodes {
-            self.render_node_minified(node, &mut output);
-        }
-        while output.ends_with('\n') {
-            output.pop();
-        }
-        output
+        Ok(())
     }
 
     fn format_declaration(&self, decl: &EvaluatedDeclaration) -> String {
-        let mut result = format!("{}: {}", decl.name.trim(), decl.value.trim());
+        let name = decl.name.trim();
+        // 跨行书写的值（如 `grid-template-areas`）折叠为单行，字符串内容不变。
+        let value = if decl.value.contains('\n') && !name.starts_with("--") {
+            collapse_whitespace(&decl.value)
+        } else {
+            decl.value.trim().to_string()
+        };
+        let mut result = format!("{name}: {value}");
         if decl.important {
             result.push_str(" !important");
         }
@@ -64,37 +239,77 @@ impl Serializer {
     }
 
     fn format_declaration_minified(&self, decl: &EvaluatedDeclaration) -> String {
-        let mut result = format!("{}:{}", decl.name.trim(), collapse_whitespace(&decl.value));
+        let name = decl.name.trim();
+        // 自定义属性的值保持原文，不压缩内部空白。
+        let value = if name.starts_with("--") {
+            decl.value.trim().to_string()
+        } else {
+            collapse_whitespace(&decl.value)
+        };
+        let mut result = format!("{name}:{value}");
         if decl.important {
             result.push_str("!important");
         }
         result
     }
 
-    fn render_node_pretty(&self, node: &EvaluatedNode, level: usize, output: &mut String) {
+    fn render_node_pretty(&self, node: &EvaluatedNode, level: usize, output: &mut Chunk) {
         match node {
             EvaluatedNode::Rule(rule) => self.render_rule_pretty(rule, level, output),
             EvaluatedNode::AtRule(at_rule) => self.render_at_rule_pretty(at_rule, level, output),
+            EvaluatedNode::Raw(content) => {
+                output.push_str(&indent(level));
+                output.push_str(content.trim());
+                output.push('\n');
+            }
+            EvaluatedNode::Comment(text) => {
+                output.push_str(&indent(level));
+                output.push_str(text);
+                output.push('\n');
+            }
+        }
+    }
+
+    fn render_declarations_pretty(
+        &self,
+        declarations: &[EvaluatedDeclaration],
+        level: usize,
+        output: &mut Chunk,
+    ) {
+        for decl in declarations {
+            for comment in &decl.comments {
+                output.push_str(&indent(level));
+                output.push_str(comment);
+                output.push('\n');
+            }
+            output.push_str(&indent(level));
+            output.push_str(&self.format_declaration(decl));
+            output.push('\n');
         }
     }
 
-    fn render_rule_pretty(&self, rule: &EvaluatedRule, level: usize, output: &mut String) {
-        if rule.declarations.is_empty() {
+    fn render_rule_pretty(&self, rule: &EvaluatedRule, level: usize, output: &mut Chunk) {
+        if rule.declarations.is_empty() && !self.keep_empty_rules {
             return;
         }
+        if self.line_numbers {
+            output.push_str(&indent(level));
+            output.push_str(&format!("/* line {}", rule.line));
+            if let Some(source) = &rule.source {
+                output.push_str(&format!(", {}", source.display()));
+            }
+            output.push_str(" */\n");
+        }
         output.push_str(&indent(level));
-        output.push_str(&rule.selectors.join(", "));
+        output.mark(rule);
+        output.push_str(&rule.selectors.join(&self.selector_separator(level)));
         output.push_str(" {\n");
-        for decl in &rule.declarations {
-            output.push_str(&indent(level + 1));
-            output.push_str(&self.format_declaration(decl));
-            output.push('\n');
-        }
+        self.render_declarations_pretty(&rule.declarations, level + 1, output);
         output.push_str(&indent(level));
         output.push_str("}\n");
     }
 
-    fn render_at_rule_pretty(&self, at_rule: &EvaluatedAtRule, level: usize, output: &mut String) {
+    fn render_at_rule_pretty(&self, at_rule: &EvaluatedAtRule, level: usize, output: &mut Chunk) {
         output.push_str(&indent(level));
         output.push('@');
         output.push_str(&at_rule.name);
@@ -103,11 +318,7 @@ impl Serializer {
             output.push_str(at_rule.params.trim());
         }
         output.push_str(" {\n");
-        for decl in &at_rule.declarations {
-            output.push_str(&indent(level + 1));
-            output.push_str(&self.format_declaration(decl));
-            output.push('\n');
-        }
+        self.render_declarations_pretty(&at_rule.declarations, level + 1, output);
         for child in &at_rule.children {
             self.render_node_pretty(child, level + 1, output);
             if !output.ends_with('\n') {
@@ -118,29 +329,126 @@ impl Serializer {
         output.push_str("}\n");
     }
 
-    fn render_node_minified(&self, node: &EvaluatedNode, output: &mut String) {
+    fn render_compact<N: Borrow<EvaluatedNode>, E>(
+        &self,
+        header: Header<'_>,
+        nodes: impl Iterator<Item = N>,
+        output: &mut Output<impl FnMut(&str) -> Result<(), E>>,
+    ) -> Result<(), E> {
+        header.render(&mut output.chunk);
+        output.flush()?;
+        for node in nodes {
+            self.render_node_compact(node.borrow(), 0, &mut output.chunk);
+            output.flush()?;
+        }
+        Ok(())
+    }
+
+    fn render_node_compact(&self, node: &EvaluatedNode, level: usize, output: &mut Chunk) {
+        match node {
+            EvaluatedNode::Rule(rule) => {
+                if rule.declarations.is_empty() && !self.keep_empty_rules {
+                    return;
+                }
+                output.push_str(&indent(level));
+                output.mark(rule);
+                output.push_str(&rule.selectors.join(", "));
+                self.render_declarations_compact(&rule.declarations, output);
+                output.push('\n');
+            }
+            EvaluatedNode::AtRule(at_rule) => {
+                output.push_str(&indent(level));
+                output.push('@');
+                output.push_str(&at_rule.name);
+                if !at_rule.params.trim().is_empty() {
+                    output.push(' ');
+                    output.push_str(&collapse_whitespace(&at_rule.params));
+                }
+                if at_rule.children.is_empty() {
+                    self.render_declarations_compact(&at_rule.declarations, output);
+                    output.push('\n');
+                    return;
+                }
+                output.push_str(" {\n");
+                if !at_rule.declarations.is_empty() {
+                    output.push_str(&indent(level + 1));
+                    for (idx, decl) in at_rule.declarations.iter().enumerate() {
+                        if idx > 0 {
+                            output.push(' ');
+                        }
+                        output.push_str(&self.format_declaration(decl));
+                    }
+                    output.push('\n');
+                }
+                for child in &at_rule.children {
+                    self.render_node_compact(child, level + 1, output);
+                }
+                output.push_str(&indent(level));
+                output.push_str("}\n");
+            }
+            EvaluatedNode::Raw(content) | EvaluatedNode::Comment(content) => {
+                output.push_str(&indent(level));
+                output.push_str(content.trim());
+                output.push('\n');
+            }
+        }
+    }
+
+    /// 单行输出 `{ a: b; c: d; }`，声明前的注释留在行内。
+    fn render_declarations_compact(
+        &self,
+        declarations: &[EvaluatedDeclaration],
+        output: &mut Chunk,
+    ) {
+        output.push_str(" {");
+        for decl in declarations {
+            for comment in &decl.comments {
+                output.push(' ');
+                output.push_str(comment);
+            }
+            output.push(' ');
+            output.push_str(&self.format_declaration(decl));
+        }
+        output.push_str(" }");
+    }
+
+    fn render_node_minified(&self, node: &EvaluatedNode, output: &mut Chunk) {
         match node {
             EvaluatedNode::Rule(rule) => self.render_rule_minified(rule, output),
             EvaluatedNode::AtRule(at_rule) => self.render_at_rule_minified(at_rule, output),
+            EvaluatedNode::Raw(content) => output.push_str(content.trim()),
+            EvaluatedNode::Comment(text) => output.push_str(text),
         }
     }
 
-    fn render_rule_minified(&self, rule: &EvaluatedRule, output: &mut String) {
-        if rule.declarations.is_empty() {
-            return;
-        }
-        output.push_str(&rule.selectors.join(","));
-        output.push('{');
-        for (idx, decl) in rule.declarations.iter().enumerate() {
+    fn render_declarations_minified(
+        &self,
+        declarations: &[EvaluatedDeclaration],
+        output: &mut Chunk,
+    ) {
+        for (idx, decl) in declarations.iter().enumerate() {
             if idx > 0 {
                 output.push(';');
             }
+            for comment in &decl.comments {
+                output.push_str(comment);
+            }
             output.push_str(&self.format_declaration_minified(decl));
         }
+    }
+
+    fn render_rule_minified(&self, rule: &EvaluatedRule, output: &mut Chunk) {
+        if rule.declarations.is_empty() && !self.keep_empty_rules {
+            return;
+        }
+        output.mark(rule);
+        output.push_str(&rule.selectors.join(","));
+        output.push('{');
+        self.render_declarations_minified(&rule.declarations, output);
         output.push('}');
     }
 
-    fn render_at_rule_minified(&self, at_rule: &EvaluatedAtRule, output: &mut String) {
+    fn render_at_rule_minified(&self, at_rule: &EvaluatedAtRule, output: &mut Chunk) {
         output.push('@');
         output.push_str(&at_rule.name);
         if !at_rule.params.trim().is_empty() {
@@ -148,15 +456,199 @@ impl Serializer {
             output.push_str(&collapse_whitespace(&at_rule.params));
         }
         output.push('{');
-        for (idx, decl) in at_rule.declarations.iter().enumerate() {
-            if idx > 0 {
-                output.push(';');
-            }
-            output.push_str(&self.format_declaration_minified(decl));
-        }
+        self.render_declarations_minified(&at_rule.declarations, output);
         for child in &at_rule.children {
             self.render_node_minified(child, output);
         }
         output.push('}');
     }
 }
+
+impl CssEmitter for Serializer {
+    type Output = String;
+
+    fn emit(&mut self, stylesheet: &EvaluatedStylesheet) -> String {
+        self.to_css(stylesheet)
+    }
+}
+
+/// 位于所有规则之前的 `@charset` 与 `@import`。
+#[derive(Clone, Copy)]
+struct Header<'a> {
+    charset: Option<&'a str>,
+    imports: &'a [String],
+}
+
+impl Header<'_> {
+    fn is_empty(&self) -> bool {
+        self.charset.is_none() && self.imports.is_empty()
+    }
+
+    /// `@charset` 必须是输出的第一条语句，位于所有 `@import` 之前。
+    fn render(&self, output: &mut String) {
+        if let Some(charset) = self.charset {
+            output.push_str("@charset ");
+            output.push_str(charset);
+            output.push_str(";\n");
+        }
+        for import in self.imports {
+            output.push_str(import.trim());
+            output.push('\n');
+        }
+    }
+}
+
+impl EvaluatedStylesheet {
+    fn header(&self) -> Header<'_> {
+        Header {
+            charset: self.charset.as_deref(),
+            imports: &self.imports,
+        }
+    }
+}
+
+/// 输出首尾需要裁掉的空白。
+#[derive(Clone, Copy, PartialEq)]
+enum Trim {
+    None,
+    Newlines,
+    Whitespace,
+}
+
+impl Trim {
+    fn matches(self, ch: char) -> bool {
+        match self {
+            Trim::None => false,
+            Trim::Newlines => ch == '\n',
+            Trim::Whitespace => ch.is_whitespace(),
+        }
+    }
+}
+
+/// 一个顶层节点的渲染结果，连同其中各规则选择器的起点（相对本块的字节偏移）。
+struct Chunk {
+    text: String,
+    /// 为 `false` 时不记录位置。
+    record: bool,
+    marks: Vec<(usize, Mark)>,
+}
+
+/// 规则在源码中的位置。
+struct Mark {
+    source: Option<Arc<Path>>,
+    line: usize,
+    column: usize,
+}
+
+impl Chunk {
+    /// 记下 `rule` 的选择器从当前位置开始。
+    fn mark(&mut self, rule: &EvaluatedRule) {
+        if self.record {
+            self.marks.push((
+                self.text.len(),
+                Mark {
+                    source: rule.source.clone(),
+                    line: rule.line,
+                    column: rule.column,
+                },
+            ));
+        }
+    }
+}
+
+impl Deref for Chunk {
+    type Target = String;
+
+    fn deref(&self) -> &String {
+        &self.text
+    }
+}
+
+impl DerefMut for Chunk {
+    fn deref_mut(&mut self) -> &mut String {
+        &mut self.text
+    }
+}
+
+/// 逐块写出渲染结果。每块末尾的空白先暂存，之后还有内容时才写出，
+/// 从而在不保留完整输出的情况下裁掉整个输出末尾的空白。
+/// 记录位置时同时跟踪已写出内容的行列，把块中的标记换算为输出中的 [`Mapping`]。
+struct Output<F> {
+    emit: F,
+    trim: Trim,
+    chunk: Chunk,
+    pending: String,
+    started: bool,
+    /// 已写出内容末尾所在的行与列（UTF-16 码元，与浏览器的 source map 实现一致），从 0 开始。
+    line: usize,
+    column: usize,
+    mappings: Vec<Mapping>,
+}
+
+impl<E, F: FnMut(&str) -> Result<(), E>> Output<F> {
+    fn new(trim: Trim, record: bool, emit: F) -> Self {
+        Self {
+            emit,
+            trim,
+            chunk: Chunk {
+                text: String::new(),
+                record,
+                marks: Vec::new(),
+            },
+            pending: String::new(),
+            started: false,
+            line: 0,
+            column: 0,
+            mappings: Vec::new(),
+        }
+    }
+
+    fn write(&mut self, text: &str) -> Result<(), E> {
+        if self.chunk.record {
+            match text.rfind('\n') {
+                Some(last) => {
+                    self.line += memchr::memchr_iter(b'\n', text.as_bytes()).count();
+                    self.column = text[last + 1..].encode_utf16().count();
+                }
+                None => self.column += text.encode_utf16().count(),
+            }
+        }
+        (self.emit)(text)
+    }
+
+    fn flush(&mut self) -> Result<(), E> {
+        let mut chunk = std::mem::take(&mut self.chunk.text);
+        let marks = std::mem::take(&mut self.chunk.marks);
+        let end = chunk.trim_end_matches(|ch| self.trim.matches(ch)).len();
+        if end > 0 {
+            // 美化输出同样裁掉开头的空白。
+            let mut start = 0;
+            if self.started || self.trim != Trim::Whitespace {
+                let pending = std::mem::take(&mut self.pending);
+                self.write(&pending)?;
+                self.pending = pending;
+            } else {
+                start = end - chunk[..end].trim_start().len();
+            }
+            for (offset, mark) in marks {
+                let offset = offset.clamp(start, end);
+                self.write(&chunk[start..offset])?;
+                start = offset;
+                self.mappings.push(Mapping {
+                    generated_line: self.line,
+                    generated_column: self.column,
+                    source: mark.source,
+                    line: mark.line,
+                    column: mark.column,
+                });
+            }
+            self.write(&chunk[start..end])?;
+            self.started = true;
+            self.pending.clear();
+        }
+        self.pending.push_str(&chunk[end..]);
+        chunk.clear();
+        self.chunk.text = chunk;
+        Ok(())
+    }
+}
@@ -0,0 +1,195 @@
+//! Source map（v3）生成：序列化时在每条规则的选择器处记下输出位置与源码位置，
+//! 编译结束后编码为 JSON，并按 [`SourceMapOptions`] 决定是否嵌入 CSS 以及注释中的地址。
+
+use std::path::Path;
+use std::sync::Arc;
+
+/// [`CompileOptions::source_map`](crate::CompileOptions::source_map) 的配置，字段对应 lessc 的同名选项。
+#[derive(Debug, Clone, Default)]
+pub struct SourceMapOptions {
+    /// 以 base64 data URI 把映射嵌入 CSS 末尾的 `sourceMappingURL` 注释（lessc 的 `sourceMapFileInline`），
+    /// 此时 [`CompileOutput::source_map`](crate::CompileOutput::source_map) 为 `None`。
+    pub inline: bool,
+    /// 外部映射文件的地址，如 `app.css.map`，写入 `/*# sourceMappingURL=... */` 注释（lessc 的 `sourceMapURL`）；
+    /// 不内嵌且为 `None` 时不写注释，由调用方自行处理。
+    pub url: Option<String>,
+    /// 映射中的 `sourceRoot`，浏览器据此拼接 `sources` 中的路径（lessc 的 `sourceMapRootpath`）。
+    pub root: Option<String>,
+    /// 在 `sourcesContent` 中嵌入源文件内容，调试时无需再能访问源文件（lessc 的 `outputSourceFiles`）。
+    pub sources_content: bool,
+}
+
+/// 输出中一条规则的起点（从 0 开始的行列）与其在源码中的位置（从 1 开始的行列）。
+#[derive(Debug, Clone)]
+pub(crate) struct Mapping {
+    pub(crate) generated_line: usize,
+    pub(crate) generated_column: usize,
+    pub(crate) source: Option<Arc<Path>>,
+    pub(crate) line: usize,
+    pub(crate) column: usize,
+}
+
+/// 直接编译字符串时入口在 `sources` 中的名称，与 less.js 一致。
+const INPUT_NAME: &str = "input";
+
+/// 生成 source map JSON；`content` 返回源文件内容（`None` 为入口源码），仅在 `sources_content` 时调用。
+pub(crate) fn generate<'s>(
+    mappings: &[Mapping],
+    options: &SourceMapOptions,
+    content: impl Fn(Option<&Path>) -> Option<&'s str>,
+) -> String {
+    let mut sources: Vec<Option<&Arc<Path>>> = Vec::new();
+    let mut encoded = String::new();
+    let (mut line, mut column) = (0usize, 0i64);
+    let (mut previous_source, mut previous_line, mut previous_column) = (0i64, 0i64, 0i64);
+    // 行内各段以 `,` 分隔，换行以 `;` 表示；输出列在每行开头重新计数，其余字段相对整个映射的上一段。
+    for (idx, mapping) in mappings.iter().enumerate() {
+        if mapping.generated_line > line {
+            for _ in line..mapping.generated_line {
+                encoded.push(';');
+            }
+            line = mapping.generated_line;
+            column = 0;
+        } else if idx > 0 {
+            encoded.push(',');
+        }
+        let source = match sources
+            .iter()
+            .position(|known| *known == mapping.source.as_ref())
+        {
+            Some(index) => index,
+            None => {
+                sources.push(mapping.source.as_ref());
+                sources.len() - 1
+            }
+        } as i64;
+        let original_line = mapping.line.saturating_sub(1) as i64;
+        let original_column = mapping.column.saturating_sub(1) as i64;
+        encode_vlq(mapping.generated_column as i64 - column, &mut encoded);
+        encode_vlq(source - previous_source, &mut encoded);
+        encode_vlq(original_line - previous_line, &mut encoded);
+        encode_vlq(original_column - previous_column, &mut encoded);
+        column = mapping.generated_column as i64;
+        (previous_source, previous_line, previous_column) =
+            (source, original_line, original_column);
+    }
+
+    let mut json = String::from("{\"version\":3");
+    if let Some(root) = &options.root {
+        json.push_str(",\"sourceRoot\":");
+        push_json_string(&mut json, root);
+    }
+    json.push_str(",\"sources\":[");
+    for (idx, source) in sources.iter().enumerate() {
+        if idx > 0 {
+            json.push(',');
+        }
+        let name = source.map_or_else(
+            || INPUT_NAME.to_string(),
+            |path| path.display().to_string().replace('\\', "/"),
+        );
+        push_json_string(&mut json, &name);
+    }
+    json.push(']');
+    if options.sources_content {
+        json.push_str(",\"sourcesContent\":[");
+        for (idx, source) in sources.iter().enumerate() {
+            if idx > 0 {
+                json.push(',');
+            }
+            match content(source.map(|path| &**path)) {
+                Some(text) => push_json_string(&mut json, text),
+                None => json.push_str("null"),
+            }
+        }
+        json.push(']');
+    }
+    json.push_str(",\"names\":[],\"mappings\":");
+    push_json_string(&mut json, &encoded);
+    json.push('}');
+    json
+}
+
+/// 追加在 CSS 末尾的 `sourceMappingURL` 注释；内嵌时地址为 base64 data URI。
+pub(crate) fn url_comment(map: &str, options: &SourceMapOptions) -> Option<String> {
+    let url = if options.inline {
+        let mut url = String::from("data:application/json;base64,");
+        encode_base64(map.as_bytes(), &mut url);
+        url
+    } else {
+        options.url.clone()?
+    };
+    Some(format!("/*# sourceMappingURL={url} */"))
+}
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Base64 VLQ：最低位为符号位，每 5 位一组，除最后一组外带续位（32）。
+fn encode_vlq(value: i64, output: &mut String) {
+    let mut rest = if value < 0 {
+        ((-value as u64) << 1) | 1
+    } else {
+        (value as u64) << 1
+    };
+    loop {
+        let mut digit = (rest & 31) as usize;
+        rest >>= 5;
+        if rest > 0 {
+            digit |= 32;
+        }
+        output.push(BASE64[digit] as char);
+        if rest == 0 {
+            break;
+        }
+    }
+}
+
+fn encode_base64(bytes: &[u8], output: &mut String) {
+    for chunk in bytes.chunks(3) {
+        let triple = chunk.iter().enumerate().fold(0u32, |acc, (idx, &byte)| {
+            acc | u32::from(byte) << (16 - idx * 8)
+        });
+        for idx in 0..4 {
+            if idx <= chunk.len() {
+                output.push(BASE64[(triple >> (18 - idx * 6)) as usize & 63] as char);
+            } else {
+                output.push('=');
+            }
+        }
+    }
+}
+
+fn push_json_string(output: &mut String, text: &str) {
+    output.push('"');
+    for ch in text.chars() {
+        match ch {
+            '"' => output.push_str("\\\""),
+            '\\' => output.push_str("\\\\"),
+            '\n' => output.push_str("\\n"),
+            '\r' => output.push_str("\\r"),
+            '\t' => output.push_str("\\t"),
+            ch if u32::from(ch) < 0x20 => output.push_str(&format!("\\u{:04x}", u32::from(ch))),
+            ch => output.push(ch),
+        }
+    }
+    output.push('"');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_vlq_and_base64() {
+        let mut vlq = String::new();
+        for value in [0, 1, -1, 15, 16, -17, 1000] {
+            encode_vlq(value, &mut vlq);
+            vlq.push(' ');
+        }
+        assert_eq!(vlq, "A C D e gB jB w+B ");
+
+        let mut base64 = String::new();
+        encode_base64(b"{\"version\":3}", &mut base64);
+        assert_eq!(base64, "eyJ2ZXJzaW9uIjozfQ==");
+    }
+}
@@ -0,0 +1,111 @@
+//! 深层嵌套输入的栈空间保障。
+//!
+//! 解析、求值与格式化都按规则的嵌套层数递归，数千层嵌套的输入（模糊测试或机器生成的样式）
+//! 会耗尽默认的线程栈。[`grow`] 按源码的最大嵌套深度估算所需栈空间，超出当前线程的
+//! 安全范围时改在栈足够大的临时线程中执行，效果类似 `stacker::maybe_grow`。
+
+use crate::error::{LessError, LessResult};
+use std::cell::Cell;
+use std::panic;
+use std::thread;
+
+/// 不超过该深度时直接在当前线程执行，普通样式表不会因此多开线程。
+pub(crate) const INLINE_DEPTH: usize = 64;
+
+/// 每层嵌套预留的栈空间，按未优化构建的实际用量留出余量。
+const STACK_PER_LEVEL: usize = 64 * 1024;
+
+/// 临时线程在嵌套所需之外的基础栈空间。
+const BASE_STACK: usize = 8 << 20;
+
+/// 允许的最大嵌套深度，超过时直接报错而不是尝试分配巨大的栈。
+pub(crate) const MAX_NESTING_DEPTH: usize = 10_000;
+
+thread_local! {
+    /// 当前线程的栈空间可以容纳的嵌套深度；由 [`grow`] 创建的线程会记录自己的容量，
+    /// 避免在其中再次开线程。
+    static CAPACITY: Cell<usize> = const { Cell::new(INLINE_DEPTH) };
+}
+
+/// 源码中 `{}` 与 `()` 的最大嵌套深度；跳过字符串与块注释，结果只会偏大不会偏小。
+pub(crate) fn nesting_depth(source: &str) -> usize {
+    let bytes = source.as_bytes();
+    let (mut depth, mut max) = (0usize, 0usize);
+    let mut idx = 0;
+    while idx < bytes.len() {
+        match bytes[idx] {
+            b'{' | b'(' => {
+                depth += 1;
+                max = max.max(depth);
+            }
+            b'}' | b')' => depth = depth.saturating_sub(1),
+            quote @ (b'"' | b'\'') => {
+                idx += 1;
+                // 字符串不能跨行，遇到换行即视为结束，避免未闭合的引号吞掉后续的括号。
+                while idx < bytes.len() && bytes[idx] != quote && bytes[idx] != b'\n' {
+                    idx += usize::from(bytes[idx] == b'\\') + 1;
+                }
+            }
+            b'/' if bytes.get(idx + 1) == Some(&b'*') => {
+                idx = source[idx + 2..]
+                    .find("*/")
+                    .map_or(bytes.len(), |end| idx + 2 + end + 1);
+            }
+            _ => {}
+        }
+        idx += 1;
+    }
+    max
+}
+
+/// 保证执行 `f` 时有足够处理 `depth` 层嵌套的栈空间，超过 [`MAX_NESTING_DEPTH`] 时报错。
+pub(crate) fn grow<T: Send>(
+    depth: usize,
+    f: impl FnOnce() -> LessResult<T> + Send,
+) -> LessResult<T> {
+    if depth > MAX_NESTING_DEPTH {
+        return Err(LessError::eval(format!(
+            "嵌套层数 {depth} 超过上限 {MAX_NESTING_DEPTH}"
+        )));
+    }
+    if cfg!(target_arch = "wasm32") || depth <= CAPACITY.with(Cell::get) {
+        return f();
+    }
+    let mut task = Some(f);
+    let size = BASE_STACK + depth * STACK_PER_LEVEL;
+    let spawned = thread::scope(|scope| {
+        let task = &mut task;
+        let handle = thread::Builder::new()
+            .stack_size(size)
+            .spawn_scoped(scope, move || {
+                CAPACITY.with(|capacity| capacity.set(depth));
+                task.take().map(|f| f())
+            })
+            .ok()?;
+        handle
+            .join()
+            .unwrap_or_else(|payload| panic::resume_unwind(payload))
+    });
+    match (spawned, task) {
+        (Some(result), _) => result,
+        // 无法创建线程时退回当前线程执行。
+        (None, Some(f)) => f(),
+        (None, None) => unreachable!("任务已在临时线程中执行"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::nesting_depth;
+
+    #[test]
+    fn nesting_depth_skips_strings_and_comments() {
+        assert_eq!(nesting_depth(".a { .b { c: d; } }"), 2);
+        assert_eq!(nesting_depth(".a { content: \"{{{\"; b: f((1)); }"), 3);
+        assert_eq!(nesting_depth("/* { { { */ .a { }"), 1);
+        assert_eq!(
+            nesting_depth(".a { content: \"unterminated\n.b { .c { } } }"),
+            3
+        );
+    }
+}
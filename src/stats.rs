@@ -0,0 +1,98 @@
+//! `CompileOptions::collect_stats` 开启时收集的编译统计，供 CI 设置体积预算或排查缓慢的构建。
+//!
+//! 数量按后处理（`merge_rules`、`autoprefix`、压缩等）之后、序列化之前的求值结果统计；
+//! 嵌套深度按展开导入后的 AST 统计。只有开启该选项时才读取时钟。
+
+use crate::ast::{AtRule, MixinDefinition, RuleBody, RuleSet, Stylesheet};
+use crate::evaluator::{EvaluatedNode, EvaluatedStylesheet};
+use crate::visit::{walk_body, walk_stylesheet, Visitor};
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// 一次编译的统计信息，见 [`CompileOutput::stats`](crate::CompileOutput::stats)。
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CompileStats {
+    /// 输出的规则数，含 at-rule 内的规则。
+    pub rules: usize,
+    /// 输出的声明数，含 `@font-face` 等 at-rule 中直接书写的声明。
+    pub declarations: usize,
+    /// 全部规则的选择器数之和（`.a, .b` 计为 2）。
+    pub selectors: usize,
+    /// 源码（含导入文件）中规则集、at-rule 与 mixin 定义的最大嵌套层数，顶层规则为 1。
+    pub max_nesting_depth: usize,
+    /// 求值过程中展开 mixin 的总次数。
+    pub mixin_expansions: usize,
+    /// 入口与各导入文件的解析耗时，入口在前，其后与 `dependencies` 顺序一致。
+    pub files: Vec<FileStats>,
+    /// 求值及后处理的耗时；导入文件展开后与入口合为一棵 AST 一同求值，因此不按文件区分。
+    pub evaluate_time: Duration,
+}
+
+/// 单个文件的解析耗时。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileStats {
+    /// 文件路径；直接编译字符串时入口为 `None`。
+    pub path: Option<PathBuf>,
+    /// 解析耗时；命中 [`Compiler`](crate::Compiler) 解析缓存的文件只包含复制 AST 的时间。
+    pub parse_time: Duration,
+}
+
+impl CompileStats {
+    /// 统计求值结果中的规则、声明与选择器。
+    pub(crate) fn count_nodes(&mut self, stylesheet: &EvaluatedStylesheet) {
+        self.count(&stylesheet.nodes);
+    }
+
+    fn count(&mut self, nodes: &[EvaluatedNode]) {
+        for node in nodes {
+            match node {
+                EvaluatedNode::Rule(rule) => {
+                    self.rules += 1;
+                    self.selectors += rule.selectors.len();
+                    self.declarations += rule.declarations.len();
+                }
+                EvaluatedNode::AtRule(at_rule) => {
+                    self.declarations += at_rule.declarations.len();
+                    self.count(&at_rule.children);
+                }
+                EvaluatedNode::Raw(_) | EvaluatedNode::Comment(_) => {}
+            }
+        }
+    }
+}
+
+/// 规则集、at-rule 与 mixin 定义的最大嵌套层数。
+pub(crate) fn nesting_depth(stylesheet: &Stylesheet) -> usize {
+    let mut depth = NestingDepth::default();
+    walk_stylesheet(&mut depth, stylesheet);
+    depth.max
+}
+
+#[derive(Default)]
+struct NestingDepth {
+    current: usize,
+    max: usize,
+}
+
+impl NestingDepth {
+    fn enter(&mut self, body: &[RuleBody]) {
+        self.current += 1;
+        self.max = self.max.max(self.current);
+        walk_body(self, body);
+        self.current -= 1;
+    }
+}
+
+impl Visitor for NestingDepth {
+    fn visit_ruleset(&mut self, rule: &RuleSet) {
+        self.enter(&rule.body);
+    }
+
+    fn visit_at_rule(&mut self, at_rule: &AtRule) {
+        self.enter(&at_rule.body);
+    }
+
+    fn visit_mixin_definition(&mut self, definition: &MixinDefinition) {
+        self.enter(&definition.body);
+    }
+}
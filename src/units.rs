@@ -0,0 +1,140 @@
+//! 单位换算表：长度、时间与角度三组，同组单位之间可以互相换算。
+//! 每个单位记录换算到本组基准单位（m / s / turn）的倍率。
+//! 另提供 [`Unit`] 表示乘除运算产生的复合单位。
+
+use std::f64::consts::PI;
+use std::fmt;
+
+/// 复合单位：单位名 → 指数，按首次出现的顺序保存。
+/// `px` 记为 `[("px", 1)]`，`px*px` 为 `[("px", 2)]`，`px/s` 为 `[("px", 1), ("s", -1)]`。
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Unit(Vec<(String, i32)>);
+
+impl Unit {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// 输出到 CSS 的单位名：优先取第一个分子单位，其次第一个分母单位（与 lessc 非严格模式一致）。
+    pub fn name(&self) -> &str {
+        self.0
+            .iter()
+            .find(|(_, exp)| *exp > 0)
+            .or_else(|| self.0.first())
+            .map_or("", |(name, _)| name.as_str())
+    }
+
+    /// 只含一个指数为 1 的单位时返回该单位名。
+    pub fn simple(&self) -> Option<&str> {
+        match self.0.as_slice() {
+            [(name, 1)] => Some(name),
+            _ => None,
+        }
+    }
+
+    pub fn multiply(&self, other: &Unit) -> Unit {
+        self.combine(other, 1)
+    }
+
+    pub fn divide(&self, other: &Unit) -> Unit {
+        self.combine(other, -1)
+    }
+
+    fn combine(&self, other: &Unit, sign: i32) -> Unit {
+        let mut dims = self.0.clone();
+        for (name, exp) in &other.0 {
+            match dims.iter_mut().find(|(existing, _)| existing == name) {
+                Some((_, current)) => *current += sign * exp,
+                None => dims.push((name.clone(), sign * exp)),
+            }
+        }
+        dims.retain(|(_, exp)| *exp != 0);
+        Unit(dims)
+    }
+}
+
+impl From<&str> for Unit {
+    fn from(name: &str) -> Self {
+        if name.is_empty() {
+            Unit::default()
+        } else {
+            Unit(vec![(name.to_string(), 1)])
+        }
+    }
+}
+
+impl fmt::Display for Unit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// 单位所属的分组，只有同组单位才能互相换算。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum UnitGroup {
+    Length,
+    Duration,
+    Angle,
+}
+
+const UNITS: &[(&str, UnitGroup, f64)] = &[
+    ("m", UnitGroup::Length, 1.0),
+    ("cm", UnitGroup::Length, 0.01),
+    ("mm", UnitGroup::Length, 0.001),
+    ("in", UnitGroup::Length, 0.0254),
+    ("px", UnitGroup::Length, 0.0254 / 96.0),
+    ("pt", UnitGroup::Length, 0.0254 / 72.0),
+    ("pc", UnitGroup::Length, 0.0254 / 72.0 * 12.0),
+    ("s", UnitGroup::Duration, 1.0),
+    ("ms", UnitGroup::Duration, 0.001),
+    ("rad", UnitGroup::Angle, 1.0 / (2.0 * PI)),
+    ("deg", UnitGroup::Angle, 1.0 / 360.0),
+    ("grad", UnitGroup::Angle, 1.0 / 400.0),
+    ("turn", UnitGroup::Angle, 1.0),
+];
+
+fn lookup(unit: &str) -> Option<(UnitGroup, f64)> {
+    UNITS
+        .iter()
+        .find(|(name, _, _)| name.eq_ignore_ascii_case(unit))
+        .map(|&(_, group, factor)| (group, factor))
+}
+
+/// 将数值从 `from` 换算到 `to`；单位不在同一组时返回 `None`。
+pub fn convert(value: f64, from: &str, to: &str) -> Option<f64> {
+    if from.eq_ignore_ascii_case(to) {
+        return Some(value);
+    }
+    let (from_group, from_factor) = lookup(from)?;
+    let (to_group, to_factor) = lookup(to)?;
+    if from_group != to_group {
+        return None;
+    }
+    Some(value * from_factor / to_factor)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_within_group() {
+        assert_eq!(convert(9.0, "s", "ms"), Some(9000.0));
+        assert!((convert(14.0, "cm", "mm").unwrap() - 140.0).abs() < 1e-9);
+        assert!((convert(1.0, "in", "px").unwrap() - 96.0).abs() < 1e-9);
+        assert!((convert(PI, "rad", "deg").unwrap() - 180.0).abs() < 1e-9);
+        assert_eq!(convert(1.0, "px", "ms"), None);
+        assert_eq!(convert(1.0, "em", "px"), None);
+    }
+
+    #[test]
+    fn compound_units_cancel() {
+        let px = Unit::from("px");
+        let s = Unit::from("s");
+        assert_eq!(px.multiply(&px).name(), "px");
+        assert_eq!(px.multiply(&px).simple(), None);
+        assert!(px.divide(&px).is_empty());
+        assert_eq!(Unit::default().divide(&s).name(), "s");
+        assert_eq!(px.divide(&s).multiply(&s), px);
+    }
+}
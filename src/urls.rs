@@ -0,0 +1,285 @@
+//! `CompileOptions::rewrite_urls` 开启时的相对 URL 改写，对应 less.js 的 `rewriteUrls`。
+//!
+//! 在导入展开之后、求值之前处理 AST：每个节点的 `span.file` 记录了它来自的文件，
+//! `sub/dir/a.less` 中的 `url(img/x.png)` 改写为 `url(sub/dir/img/x.png)`，
+//! 因此变量与 mixin 中的 URL 也按其定义所在的文件计算。
+
+use crate::ast::{
+    EachCall, MixinArgument, MixinDefinition, RuleBody, Span, Statement, Stylesheet, Value,
+    ValuePiece, VariableInit,
+};
+use crate::RewriteUrls;
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+
+/// 以 `base`（入口文件所在目录）为基准改写全部相对 URL。
+pub(crate) fn rewrite_urls(stylesheet: &mut Stylesheet, mode: RewriteUrls, base: &Path) {
+    let mut rewriter = UrlRewriter {
+        mode,
+        base: absolute(base),
+        prefixes: HashMap::new(),
+    };
+    rewriter.statements(&mut stylesheet.statements);
+}
+
+struct UrlRewriter {
+    mode: RewriteUrls,
+    base: PathBuf,
+    /// 每个源文件相对入口目录的前缀，如 `sub/dir/`。
+    prefixes: HashMap<Arc<Path>, String>,
+}
+
+impl UrlRewriter {
+    fn statements(&mut self, statements: &mut [Statement]) {
+        for statement in statements {
+            match statement {
+                Statement::Variable(variable) => match &mut variable.value {
+                    VariableInit::Value(value) => self.value(value, &variable.span),
+                    VariableInit::Ruleset(body) => self.body(body),
+                },
+                Statement::RuleSet(rule) => self.body(&mut rule.body),
+                Statement::AtRule(at_rule) => {
+                    self.value(&mut at_rule.params, &at_rule.span);
+                    self.body(&mut at_rule.body);
+                }
+                Statement::BodylessAtRule(at_rule) => {
+                    self.value(&mut at_rule.params, &at_rule.span)
+                }
+                Statement::MixinDefinition(definition) => self.mixin_definition(definition),
+                Statement::MixinCall(call) => self.mixin_args(&mut call.args, &call.span),
+                Statement::Each(call) => self.each(call),
+                Statement::Reference(statements) => self.statements(statements),
+                Statement::Import(_)
+                | Statement::Plugin(_)
+                | Statement::Charset(_)
+                | Statement::DetachedCall(_)
+                | Statement::RawCss(_)
+                | Statement::Comment(_) => {}
+            }
+        }
+    }
+
+    fn body(&mut self, body: &mut [RuleBody]) {
+        for item in body {
+            match item {
+                RuleBody::Declaration(decl) => self.value(&mut decl.value, &decl.span),
+                RuleBody::NestedRule(rule) => self.body(&mut rule.body),
+                RuleBody::AtRule(at_rule) => {
+                    self.value(&mut at_rule.params, &at_rule.span);
+                    self.body(&mut at_rule.body);
+                }
+                RuleBody::BodylessAtRule(at_rule) => self.value(&mut at_rule.params, &at_rule.span),
+                RuleBody::Variable(variable) => match &mut variable.value {
+                    VariableInit::Value(value) => self.value(value, &variable.span),
+                    VariableInit::Ruleset(body) => self.body(body),
+                },
+                RuleBody::MixinDefinition(definition) => self.mixin_definition(definition),
+                RuleBody::MixinCall(call) => self.mixin_args(&mut call.args, &call.span),
+                RuleBody::Each(call) => self.each(call),
+                RuleBody::DetachedCall(_) | RuleBody::Import(_) | RuleBody::Comment(_) => {}
+            }
+        }
+    }
+
+    fn mixin_definition(&mut self, definition: &mut MixinDefinition) {
+        for param in &mut definition.params {
+            if let Some(default) = &mut param.default {
+                self.value(default, &definition.span);
+            }
+        }
+        self.body(&mut definition.body);
+    }
+
+    fn mixin_args(&mut self, args: &mut [MixinArgument], span: &Span) {
+        for arg in args {
+            match arg {
+                MixinArgument::Value(value) => self.value(value, span),
+                MixinArgument::Ruleset(body) => self.body(body),
+            }
+        }
+    }
+
+    fn each(&mut self, call: &mut EachCall) {
+        self.value(&mut call.list, &call.span);
+        self.mixin_args(std::slice::from_mut(&mut call.ruleset), &call.span);
+    }
+
+    fn value(&mut self, value: &mut Value, span: &Span) {
+        let mode = self.mode;
+        let mut prefix = None;
+        for piece in &mut value.pieces {
+            let ValuePiece::Literal(text) = piece else {
+                continue;
+            };
+            if !text.to_ascii_lowercase().contains("url(") {
+                continue;
+            }
+            // 直接编译字符串时没有文件信息，无从计算相对路径。
+            let Some(prefix) = prefix.get_or_insert_with(|| self.prefix(span)) else {
+                return;
+            };
+            *text = rewrite_text(text, prefix, mode);
+        }
+    }
+
+    fn prefix(&mut self, span: &Span) -> Option<String> {
+        let file = span.file.as_ref()?;
+        if let Some(prefix) = self.prefixes.get(file) {
+            return Some(prefix.clone());
+        }
+        let prefix = file_prefix(&self.base, file)?;
+        self.prefixes.insert(file.clone(), prefix.clone());
+        Some(prefix)
+    }
+}
+
+/// 源文件所在目录相对 `base` 的路径，带结尾的 `/`；远程文件直接使用其目录 URL。
+fn file_prefix(base: &Path, file: &Path) -> Option<String> {
+    let raw = file.to_str()?;
+    if raw.starts_with("http://") || raw.starts_with("https://") {
+        let end = raw.rfind('/')?;
+        return Some(raw[..=end].to_string());
+    }
+    let dir = absolute(file.parent()?);
+    let common = base
+        .components()
+        .zip(dir.components())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let mut prefix = "../".repeat(base.components().count() - common);
+    for component in dir.components().skip(common) {
+        prefix.push_str(component.as_os_str().to_str()?);
+        prefix.push('/');
+    }
+    Some(prefix)
+}
+
+/// 补全为绝对路径并按字面消去 `.` 与 `..`；相对路径以进程工作目录为基准。
+fn absolute(path: &Path) -> PathBuf {
+    let joined = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        std::env::current_dir().unwrap_or_default().join(path)
+    };
+    let mut normalized = PathBuf::new();
+    for component in joined.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
+/// 改写文本中每个完整的 `url(...)`；缺少右括号（URL 由变量拼出）时保持原样。
+fn rewrite_text(text: &str, prefix: &str, mode: RewriteUrls) -> String {
+    let lower = text.to_ascii_lowercase();
+    let mut output = String::with_capacity(text.len() + prefix.len());
+    let mut rest = 0;
+    while let Some(offset) = lower[rest..].find("url(") {
+        let open = rest + offset + 4;
+        let Some(close) = closing_paren(&text[open..]).map(|idx| open + idx) else {
+            break;
+        };
+        output.push_str(&text[rest..open]);
+        output.push_str(&rewrite_url(&text[open..close], prefix, mode));
+        rest = close;
+    }
+    output.push_str(&text[rest..]);
+    output
+}
+
+fn closing_paren(text: &str) -> Option<usize> {
+    let mut quote = None;
+    for (idx, ch) in text.char_indices() {
+        match (quote, ch) {
+            (Some(q), _) if ch == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(ch),
+            (None, ')') => return Some(idx),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// 改写 `url()` 括号内的内容，引号与两侧空白保持不变。
+fn rewrite_url(inner: &str, prefix: &str, mode: RewriteUrls) -> String {
+    let trimmed = inner.trim();
+    let quote = trimmed
+        .chars()
+        .next()
+        .filter(|ch| matches!(ch, '"' | '\'') && trimmed.len() > 1 && trimmed.ends_with(*ch));
+    let path = match quote {
+        Some(_) => &trimmed[1..trimmed.len() - 1],
+        None => trimmed,
+    };
+    let eligible = match mode {
+        RewriteUrls::Off => false,
+        RewriteUrls::Local => is_local_relative(path),
+        RewriteUrls::All => !path.is_empty() && is_relative(path),
+    };
+    if !eligible || path.contains("@{") {
+        return inner.to_string();
+    }
+    // 与 less.js 一致，未加引号的 URL 需要转义前缀中的括号、引号与空白。
+    let prefix = match quote {
+        Some(_) => prefix.to_string(),
+        None => escape_path(prefix),
+    };
+    let mut rewritten = normalize_path(&format!("{prefix}{path}"));
+    if is_local_relative(path) && is_relative(&prefix) && !is_local_relative(&rewritten) {
+        rewritten.insert_str(0, "./");
+    }
+    let start = inner.find(trimmed).unwrap_or(0);
+    let (leading, trailing) = (&inner[..start], &inner[start + trimmed.len()..]);
+    match quote {
+        Some(q) => format!("{leading}{q}{rewritten}{q}{trailing}"),
+        None => format!("{leading}{rewritten}{trailing}"),
+    }
+}
+
+fn is_local_relative(path: &str) -> bool {
+    path.starts_with("./") || path.starts_with("../")
+}
+
+/// 不以协议（`data:`、`https:` 等）、`/` 或 `#` 开头的路径。
+fn is_relative(path: &str) -> bool {
+    let has_scheme = path.find(':').is_some_and(|idx| {
+        idx > 0
+            && path[..idx]
+                .bytes()
+                .all(|b| b.is_ascii_alphabetic() || b == b'-')
+    });
+    !has_scheme && !path.starts_with('/') && !path.starts_with('#')
+}
+
+fn escape_path(path: &str) -> String {
+    let mut escaped = String::with_capacity(path.len());
+    for ch in path.chars() {
+        if matches!(ch, '(' | ')' | '\'' | '"') || ch.is_whitespace() {
+            escaped.push('\\');
+        }
+        escaped.push(ch);
+    }
+    escaped
+}
+
+/// 按 `/` 切分后消去 `.` 与可抵消的 `..`，与 less.js 的 `normalizePath` 一致。
+fn normalize_path(path: &str) -> String {
+    let mut segments: Vec<&str> = Vec::new();
+    for segment in path.split('/') {
+        match segment {
+            "." => {}
+            ".." if segments.last().is_some_and(|last| *last != "..") => {
+                segments.pop();
+            }
+            other => segments.push(other),
+        }
+    }
+    segments.join("/")
+}
@@ -1,9 +1,26 @@
-/// 压缩多余空白字符，主要用于输出压缩模式。
+use crate::error::{LessError, LessResult};
+use crate::units::Unit;
+
+/// 压缩多余空白字符；引号字符串内的内容（含转义）原样保留。
 pub fn collapse_whitespace(input: &str) -> String {
     let mut result = String::with_capacity(input.len());
     let mut last_was_space = false;
-    for ch in input.chars() {
-        if ch.is_whitespace() {
+    let mut chars = input.chars();
+    while let Some(ch) = chars.next() {
+        if ch == '"' || ch == '\'' {
+            result.push(ch);
+            while let Some(next) = chars.next() {
+                result.push(next);
+                if next == '\\' {
+                    if let Some(escaped) = chars.next() {
+                        result.push(escaped);
+                    }
+                } else if next == ch {
+                    break;
+                }
+            }
+            last_was_space = false;
+        } else if ch.is_whitespace() {
             if !last_was_space {
                 result.push(' ');
                 last_was_space = true;
@@ -21,3 +38,71 @@ pub fn indent(level: usize) -> String {
     const INDENT: &str = "  ";
     (0..level).map(|_| INDENT).collect()
 }
+
+/// 以最多 4 位小数输出数值并去除多余的尾零。
+pub fn format_number(value: f64) -> String {
+    let value = if value.abs() < 1e-9 { 0.0 } else { value };
+    let mut formatted = format!("{value:.4}");
+    while formatted.contains('.') && formatted.ends_with('0') {
+        formatted.pop();
+    }
+    if formatted.ends_with('.') {
+        formatted.pop();
+    }
+    if formatted == "-0" {
+        formatted = "0".to_string();
+    }
+    formatted
+}
+
+/// 带单位的数值，单位为空表示纯数字。
+#[derive(Debug, Clone, PartialEq)]
+pub struct Quantity {
+    pub value: f64,
+    pub unit: Unit,
+}
+
+/// 将 `12px`、`-0.5`、`50%` 之类的文本拆分为数值与单位。
+pub fn parse_quantity(token: &str) -> LessResult<Quantity> {
+    let trimmed = token.trim();
+    if trimmed.is_empty() {
+        return Err(LessError::eval("缺少数值内容".to_string()));
+    }
+
+    let mut value_part = String::new();
+    let mut unit_part = String::new();
+    for ch in trimmed.chars() {
+        if ch.is_ascii_digit() || ch == '.' || ((ch == '-' || ch == '+') && value_part.is_empty()) {
+            value_part.push(ch);
+        } else if ch.is_ascii_alphabetic() || ch == '%' {
+            unit_part.push(ch);
+        } else if ch.is_whitespace() {
+            continue;
+        } else {
+            return Err(LessError::eval(format!("无法解析数值片段: {token}")));
+        }
+    }
+
+    if value_part.is_empty() {
+        return Err(LessError::eval(format!("缺少数值部分: {token}")));
+    }
+
+    let value: f64 = value_part
+        .parse()
+        .map_err(|_| LessError::eval(format!("无法解析数值 {value_part}")))?;
+
+    Ok(Quantity {
+        value,
+        unit: Unit::from(unit_part.as_str()),
+    })
+}
+
+/// 按 [`format_number`] 的精度输出数值并拼接单位。
+pub fn format_quantity(quantity: &Quantity) -> String {
+    let formatted = format_number(quantity.value);
+    if quantity.unit.is_empty() {
+        formatted
+    } else {
+        format!("{formatted}{}", quantity.unit)
+    }
+}
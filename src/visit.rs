@@ -0,0 +1,135 @@
+//! 只读遍历 AST 的访问器，供 lint、格式化与 codemod 等工具使用。
+//!
+//! 实现 [`Visitor`] 时只需覆盖关心的节点；默认实现会继续遍历子节点，
+//! 覆盖后如需深入子节点，可调用同名的 `walk_*` 函数。
+//!
+//! ```
+//! use less_oxide::ast::Declaration;
+//! use less_oxide::visit::{walk_stylesheet, Visitor};
+//!
+//! struct Properties(Vec<String>);
+//!
+//! impl Visitor for Properties {
+//!     fn visit_declaration(&mut self, decl: &Declaration) {
+//!         self.0.push(decl.name.clone());
+//!     }
+//! }
+//!
+//! let stylesheet = less_oxide::parse(".a { color: red; .b { margin: 0; } }").unwrap();
+//! let mut properties = Properties(Vec::new());
+//! walk_stylesheet(&mut properties, &stylesheet);
+//! assert_eq!(properties.0, ["color", "margin"]);
+//! ```
+
+use crate::ast::{
+    AtRule, BodylessAtRule, Comment, Declaration, DetachedCall, EachCall, ImportStatement,
+    MixinArgument, MixinCall, MixinDefinition, PluginStatement, RuleBody, RuleSet, Statement,
+    Stylesheet, VariableDeclaration, VariableInit,
+};
+
+pub trait Visitor {
+    fn visit_statement(&mut self, statement: &Statement) {
+        walk_statement(self, statement);
+    }
+
+    fn visit_rule_body(&mut self, item: &RuleBody) {
+        walk_rule_body(self, item);
+    }
+
+    fn visit_ruleset(&mut self, rule: &RuleSet) {
+        walk_body(self, &rule.body);
+    }
+
+    fn visit_at_rule(&mut self, at_rule: &AtRule) {
+        walk_body(self, &at_rule.body);
+    }
+
+    fn visit_mixin_definition(&mut self, definition: &MixinDefinition) {
+        walk_body(self, &definition.body);
+    }
+
+    /// 默认会遍历以 `{ ... }` 形式传入的规则集参数。
+    fn visit_mixin_call(&mut self, call: &MixinCall) {
+        for arg in &call.args {
+            if let MixinArgument::Ruleset(body) = arg {
+                walk_body(self, body);
+            }
+        }
+    }
+
+    /// 默认会遍历以 `{ ... }` 形式传入的规则集。
+    fn visit_each(&mut self, call: &EachCall) {
+        if let MixinArgument::Ruleset(body) = &call.ruleset {
+            walk_body(self, body);
+        }
+    }
+
+    /// 默认会遍历 `@detached: { ... }` 的规则集内容。
+    fn visit_variable(&mut self, variable: &VariableDeclaration) {
+        if let VariableInit::Ruleset(body) = &variable.value {
+            walk_body(self, body);
+        }
+    }
+
+    fn visit_declaration(&mut self, _declaration: &Declaration) {}
+
+    fn visit_import(&mut self, _import: &ImportStatement) {}
+
+    fn visit_plugin(&mut self, _plugin: &PluginStatement) {}
+
+    fn visit_detached_call(&mut self, _call: &DetachedCall) {}
+
+    fn visit_comment(&mut self, _comment: &Comment) {}
+
+    fn visit_bodyless_at_rule(&mut self, _at_rule: &BodylessAtRule) {}
+}
+
+pub fn walk_stylesheet<V: Visitor + ?Sized>(visitor: &mut V, stylesheet: &Stylesheet) {
+    for statement in &stylesheet.statements {
+        visitor.visit_statement(statement);
+    }
+}
+
+pub fn walk_statement<V: Visitor + ?Sized>(visitor: &mut V, statement: &Statement) {
+    match statement {
+        Statement::Import(import) => visitor.visit_import(import),
+        Statement::Plugin(plugin) => visitor.visit_plugin(plugin),
+        Statement::AtRule(at_rule) => visitor.visit_at_rule(at_rule),
+        Statement::BodylessAtRule(at_rule) => visitor.visit_bodyless_at_rule(at_rule),
+        Statement::RuleSet(rule) => visitor.visit_ruleset(rule),
+        Statement::Variable(variable) => visitor.visit_variable(variable),
+        Statement::MixinDefinition(definition) => visitor.visit_mixin_definition(definition),
+        Statement::MixinCall(call) => visitor.visit_mixin_call(call),
+        Statement::DetachedCall(call) => visitor.visit_detached_call(call),
+        Statement::Each(call) => visitor.visit_each(call),
+        Statement::Reference(statements) => {
+            for statement in statements {
+                visitor.visit_statement(statement);
+            }
+        }
+        Statement::RawCss(_) | Statement::Charset(_) => {}
+        Statement::Comment(comment) => visitor.visit_comment(comment),
+    }
+}
+
+pub fn walk_rule_body<V: Visitor + ?Sized>(visitor: &mut V, item: &RuleBody) {
+    match item {
+        RuleBody::Declaration(declaration) => visitor.visit_declaration(declaration),
+        RuleBody::NestedRule(rule) => visitor.visit_ruleset(rule),
+        RuleBody::AtRule(at_rule) => visitor.visit_at_rule(at_rule),
+        RuleBody::BodylessAtRule(at_rule) => visitor.visit_bodyless_at_rule(at_rule),
+        RuleBody::DetachedCall(call) => visitor.visit_detached_call(call),
+        RuleBody::Each(call) => visitor.visit_each(call),
+        RuleBody::Variable(variable) => visitor.visit_variable(variable),
+        RuleBody::MixinDefinition(definition) => visitor.visit_mixin_definition(definition),
+        RuleBody::MixinCall(call) => visitor.visit_mixin_call(call),
+        RuleBody::Import(import) => visitor.visit_import(import),
+        RuleBody::Comment(comment) => visitor.visit_comment(comment),
+    }
+}
+
+pub fn walk_body<V: Visitor + ?Sized>(visitor: &mut V, body: &[RuleBody]) {
+    for item in body {
+        visitor.visit_rule_body(item);
+    }
+}
@@ -0,0 +1,131 @@
+//! WebAssembly 绑定（`wasm` feature），供浏览器或 Deno 等没有文件系统的环境使用。
+
+use crate::error::LessResult;
+use crate::{
+    compile_with_metadata, BrowserTarget, CompileOptions, ImportLoader, LessError, MathMode,
+    OutputStyle, RewriteUrls,
+};
+use js_sys::Function;
+use serde_json::Value as Json;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use wasm_bindgen::prelude::*;
+
+/// 编译 LESS 源码。
+///
+/// * `options_json` - JSON 字符串，支持 `minify`、`keepComments`、`keepEmptyRules`、`outputStyle`、`mergeRules`、`autoprefix`、`math`、`strictUnits`、`allowUndefinedVariables`、`rewriteUrls` 与 `includePaths`
+/// * `import_callback` - `(path) => string | undefined`，返回 `@import` 目标的源码，
+///   `undefined`/`null` 表示该候选路径不存在
+#[wasm_bindgen(js_name = compile)]
+pub fn compile_wasm(
+    source: &str,
+    options_json: Option<String>,
+    import_callback: Option<Function>,
+) -> Result<String, JsValue> {
+    let mut options = parse_options(options_json.as_deref()).map_err(to_js_error)?;
+    if let Some(callback) = import_callback {
+        options.import_loader = Some(Arc::new(JsImportLoader {
+            callback,
+            sources: Mutex::new(HashMap::new()),
+        }));
+    }
+    compile_with_metadata(source, options)
+        .map(|output| output.css)
+        .map_err(to_js_error)
+}
+
+fn parse_options(raw: Option<&str>) -> LessResult<CompileOptions> {
+    let mut options = CompileOptions::default();
+    let Some(raw) = raw.filter(|raw| !raw.trim().is_empty()) else {
+        return Ok(options);
+    };
+    let json: Json = serde_json::from_str(raw)
+        .map_err(|err| LessError::eval(format!("无法解析编译选项 JSON: {err}")))?;
+    if let Some(minify) = json.get("minify").and_then(Json::as_bool) {
+        options.minify = minify;
+    }
+    if let Some(keep) = json.get("keepComments").and_then(Json::as_bool) {
+        options.keep_comments = keep;
+    }
+    if let Some(keep) = json.get("keepEmptyRules").and_then(Json::as_bool) {
+        options.keep_empty_rules = keep;
+    }
+    if let Some(targets) = json.get("autoprefix").and_then(Json::as_array) {
+        options.autoprefix = targets
+            .iter()
+            .filter_map(Json::as_str)
+            .map(|target| {
+                BrowserTarget::parse(target)
+                    .ok_or_else(|| LessError::eval(format!("无法识别的浏览器目标: {target}")))
+            })
+            .collect::<LessResult<_>>()?;
+    }
+    if let Some(merge) = json.get("mergeRules").and_then(Json::as_bool) {
+        options.merge_rules = merge;
+    }
+    if let Some(name) = json.get("outputStyle").and_then(Json::as_str) {
+        options.output_style = Some(
+            OutputStyle::from_name(name)
+                .ok_or_else(|| LessError::eval(format!("未知的 outputStyle 选项: {name}")))?,
+        );
+    }
+    if let Some(name) = json.get("math").and_then(Json::as_str) {
+        options.math = MathMode::from_name(name)
+            .ok_or_else(|| LessError::eval(format!("未知的 math 选项: {name}")))?;
+    }
+    if let Some(strict) = json.get("strictUnits").and_then(Json::as_bool) {
+        options.strict_units = strict;
+    }
+    if let Some(allow) = json.get("allowUndefinedVariables").and_then(Json::as_bool) {
+        options.allow_undefined_variables = allow;
+    }
+    if let Some(name) = json.get("rewriteUrls").and_then(Json::as_str) {
+        options.rewrite_urls = RewriteUrls::from_name(name)
+            .ok_or_else(|| LessError::eval(format!("未知的 rewriteUrls 选项: {name}")))?;
+    }
+    if let Some(paths) = json.get("includePaths").and_then(Json::as_array) {
+        options.include_paths = paths
+            .iter()
+            .filter_map(Json::as_str)
+            .map(PathBuf::from)
+            .collect();
+    }
+    Ok(options)
+}
+
+fn to_js_error(err: LessError) -> JsValue {
+    JsError::new(&err.to_string()).into()
+}
+
+/// 通过 JS 回调读取导入文件；`resolve` 时调用回调并暂存源码，`load` 直接返回暂存内容。
+struct JsImportLoader {
+    callback: Function,
+    sources: Mutex<HashMap<PathBuf, String>>,
+}
+
+// SAFETY: wasm32 目标是单线程环境，回调只会在创建它的线程上被调用。
+unsafe impl Send for JsImportLoader {}
+unsafe impl Sync for JsImportLoader {}
+
+impl ImportLoader for JsImportLoader {
+    fn resolve(&self, candidate: &Path) -> Option<PathBuf> {
+        let key = candidate.to_string_lossy();
+        let result = self
+            .callback
+            .call1(&JsValue::NULL, &JsValue::from_str(&key))
+            .ok()?;
+        let content = result.as_string()?;
+        let mut sources = self.sources.lock().ok()?;
+        sources.insert(candidate.to_path_buf(), content);
+        Some(candidate.to_path_buf())
+    }
+
+    fn load(&self, path: &Path) -> LessResult<String> {
+        self.sources
+            .lock()
+            .ok()
+            .and_then(|sources| sources.get(path).cloned())
+            .ok_or_else(|| LessError::eval(format!("导入回调未提供 {}", path.display())))
+    }
+}
@@ -0,0 +1,113 @@
+//! 监听模式（`watch` feature）。
+//! 基于 `notify` 订阅入口文件及其全部 `@import` 依赖所在目录的文件系统事件，相关文件变化时重新编译。
+//! 监听目录而非文件本身，编辑器以“写临时文件再改名”方式保存时同样能收到事件。
+
+use crate::compiler::Compiler;
+use crate::error::{LessError, LessResult};
+use crate::CompileOutput;
+use notify::{EventKind, RecursiveMode, Watcher};
+use std::collections::HashSet;
+use std::ops::ControlFlow;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::Duration;
+
+/// 收到第一个变化事件后继续收集事件的时长，一次保存产生的多个事件合并为一次重新编译。
+const DEBOUNCE: Duration = Duration::from_millis(50);
+
+/// 一次（重新）编译的结果。
+#[derive(Debug)]
+pub struct WatchEvent {
+    /// 触发本次编译的文件（规范化后的绝对路径）；首次编译时为 `None`。
+    pub trigger: Option<PathBuf>,
+    pub result: LessResult<CompileOutput>,
+}
+
+impl Compiler {
+    /// 编译 `entry` 并持续监听，入口或任一传递导入的文件变化后重新编译。
+    ///
+    /// 每次编译（包括首次）都会调用 `callback`，返回 `ControlFlow::Break(())` 时停止监听并返回 `Ok(())`。
+    /// 编译失败时沿用上一次成功编译得到的依赖列表继续监听；无法建立监听时返回错误。
+    pub fn watch<P, F>(&mut self, entry: P, mut callback: F) -> LessResult<()>
+    where
+        P: AsRef<Path>,
+        F: FnMut(WatchEvent) -> ControlFlow<()>,
+    {
+        // 依赖路径由导入器规范化，入口也规范化后才能与它们及事件路径比对。
+        let entry = normalize_path(entry.as_ref());
+        let (sender, events) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(sender).map_err(watch_error)?;
+        let mut watched = HashSet::from([entry.clone()]);
+        let mut dirs = HashSet::new();
+        let mut trigger = None;
+        loop {
+            let result = self.compile_file(&entry);
+            if let Ok(output) = &result {
+                watched = std::iter::once(entry.clone())
+                    .chain(output.dependencies.iter().cloned())
+                    .collect();
+            }
+            let wanted: HashSet<PathBuf> = watched.iter().map(|path| parent_dir(path)).collect();
+            for dir in dirs.difference(&wanted) {
+                // 目录可能已被删除，取消失败无妨。
+                let _ = watcher.unwatch(dir);
+            }
+            for dir in wanted.difference(&dirs) {
+                watcher
+                    .watch(dir, RecursiveMode::NonRecursive)
+                    .map_err(watch_error)?;
+            }
+            dirs = wanted;
+            if callback(WatchEvent { trigger, result }).is_break() {
+                return Ok(());
+            }
+
+            let mut changed = None;
+            while changed.is_none() {
+                let event = events
+                    .recv()
+                    .map_err(|_| LessError::eval("文件监听已意外结束".to_string()))?;
+                changed = changed_path(event.map_err(watch_error)?, &watched);
+            }
+            // 合并紧随其后的事件，并清空通道，避免同一次保存触发多次编译。
+            while events.recv_timeout(DEBOUNCE).is_ok() {}
+            trigger = changed;
+        }
+    }
+}
+
+/// 事件涉及的第一个被监听文件；访问等不改变内容的事件忽略。
+fn changed_path(event: notify::Event, watched: &HashSet<PathBuf>) -> Option<PathBuf> {
+    if !matches!(
+        event.kind,
+        EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_) | EventKind::Any
+    ) {
+        return None;
+    }
+    event.paths.into_iter().find_map(|path| {
+        let path = normalize_path(&path);
+        watched.contains(&path).then_some(path)
+    })
+}
+
+/// 规范化为绝对路径；文件已被删除时规范化其所在目录，目录也不存在时原样返回。
+fn normalize_path(path: &Path) -> PathBuf {
+    if let Ok(path) = path.canonicalize() {
+        return path;
+    }
+    match (path.file_name(), parent_dir(path).canonicalize()) {
+        (Some(name), Ok(dir)) => dir.join(name),
+        _ => path.to_path_buf(),
+    }
+}
+
+fn parent_dir(path: &Path) -> PathBuf {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+        _ => PathBuf::from("."),
+    }
+}
+
+fn watch_error(err: notify::Error) -> LessError {
+    LessError::eval(format!("无法监听文件变化: {err}"))
+}
@@ -1,5 +1,16 @@
-use less_oxide::{compile, compile_file, CompileOptions};
-use std::path::Path;
+use less_oxide::ast::{MixinCall, RuleSet, Statement};
+use less_oxide::visit::{walk_body, walk_stylesheet, Visitor};
+use less_oxide::{
+    compile, compile_file, compile_file_to, compile_file_with_metadata, compile_files,
+    compile_stream, compile_with_diagnostics, compile_with_emitter, compile_with_metadata,
+    convert_to_scss, evaluate, format_source, lint, parse, parse_lenient, BrowserTarget,
+    CompileOptions, Compiler, CssEmitter, EvaluatedNode, EvaluatedStylesheet, FormatOptions,
+    FunctionRegistry, FunctionValue, ImportLoader, LessError, LessResult, OutputStyle, QuoteStyle,
+    RewriteUrls, Serializer, Severity, SourceMapOptions,
+};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 
 #[test]
 fn variable_and_nesting() {
@@ -75,8 +86,107 @@ fn mixin_default_and_override() {
         },
     )
     .unwrap();
-    assert!(css.contains(".dialog{box-shadow:0 2px 4px rgba(0, 0, 0, 0.4)}"));
-    assert!(css.contains(".dialog-elevated{box-shadow:0 8px 16px rgba(0, 0, 0, 0.4)}"));
+    assert!(css.contains(".dialog{box-shadow:0 2px 4px rgba(0, 0, 0, .4)}"));
+    assert!(css.contains(".dialog-elevated{box-shadow:0 8px 16px rgba(0, 0, 0, .4)}"));
+}
+
+#[test]
+fn mixin_arguments_variable_joins_all_values() {
+    let src = r".shadow(@x: 0, @y: 0, @blur: 1px, @color: #000) {
+  box-shadow: @arguments;
+}
+
+.a {
+  .shadow(2px, 5px);
+}
+
+.b {
+  .shadow(1px, 2px, 3px, red);
+}";
+    let css = compile(
+        src,
+        CompileOptions {
+            minify: true,
+            ..CompileOptions::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        css,
+        ".a{box-shadow:2px 5px 1px #000}.b{box-shadow:1px 2px 3px red}"
+    );
+}
+
+#[test]
+fn indirect_variable_references_resolve_twice() {
+    let src = r#"@primary: #336699;
+@name: "primary";
+.m(@which) {
+  border-color: @@which;
+}
+.a {
+  color: @@name;
+  .m("primary");
+}
+.b {
+  @name: "other";
+  @other: red;
+  color: @@name;
+}"#;
+    let css = compile(
+        src,
+        CompileOptions {
+            minify: true,
+            ..CompileOptions::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(css, ".a{color:#369;border-color:#369}.b{color:red}");
+}
+
+#[test]
+fn lazy_variables_resolve_in_the_referencing_scope() {
+    let src = r#".lazy-eval {
+  width: @var;
+  @a: 9%;
+}
+@var: @a;
+@a: 100%;
+.b {
+  @a: 1px;
+  height: @var;
+}
+.c {
+  height: @var;
+}"#;
+    let css = compile(
+        src,
+        CompileOptions {
+            minify: true,
+            ..CompileOptions::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(css, ".lazy-eval{width:9%}.b{height:1px}.c{height:100%}");
+}
+
+#[test]
+fn multi_line_values_keep_string_contents() {
+    let src = ".g {\n  grid-template-areas: \"a  a\"\n                       \"b   c\";\n  font-family: 'A  B',   serif;\n}";
+    let minified = compile(
+        src,
+        CompileOptions {
+            minify: true,
+            ..CompileOptions::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        minified,
+        ".g{grid-template-areas:\"a  a\" \"b   c\";font-family:\"A  B\", serif}"
+    );
+    let pretty = compile(src, CompileOptions::default()).unwrap();
+    assert!(pretty.contains("  grid-template-areas: \"a  a\" \"b   c\";\n"));
 }
 
 #[test]
@@ -96,6 +206,65 @@ fn arithmetic_multiple_segments_minified() {
     assert!(css.contains(".layout{padding:10px 20px 1px}"));
 }
 
+#[test]
+fn blend_mode_functions_match_lessc() {
+    let src = ".a {
+  multiply: multiply(#ff6600, #333333);
+  screen: screen(#ff6600, #333333);
+  overlay: overlay(#ff6600, #333333);
+  softlight: softlight(#ff6600, #333333);
+  hardlight: hardlight(#ff6600, #333333);
+  difference: difference(#ff6600, #333333);
+  exclusion: exclusion(#ff6600, #333333);
+  average: average(#ff6600, #333333);
+  negation: negation(#ff6600, #333333);
+  translucent: multiply(rgba(255, 0, 0, 0.5), rgba(0, 0, 255, 0.5));
+}";
+    let css = compile(src, CompileOptions::default()).unwrap();
+    assert_eq!(
+        css,
+        ".a {
+  multiply: #331400;
+  screen: #ff8533;
+  overlay: #ff2900;
+  softlight: #ff4100;
+  hardlight: #662900;
+  difference: #cc3333;
+  exclusion: #cc7033;
+  average: #994d1a;
+  negation: #cc9933;
+  translucent: rgba(85, 0, 85, 0.75);
+}"
+    );
+}
+
+#[test]
+fn color_arithmetic_operates_per_channel() {
+    let src = r"@base: #224488;
+.a {
+  sum: #111 + #222;
+  scaled: @base * 0.5;
+  halved: (@base / 2);
+  number: #888 - 10;
+  alpha: rgba(10, 20, 30, 0.5) + #010101;
+  clamped: #fff + #fff;
+  function: darken(#fff, 10%) + #111;
+  untouched: 0 -1px 0 #fff;
+}";
+    let css = compile(
+        src,
+        CompileOptions {
+            minify: true,
+            ..CompileOptions::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        css,
+        ".a{sum:#333;scaled:#124;halved:#124;number:#7e7e7e;alpha:#0b151f;clamped:#fff;function:#f7f7f7;untouched:0 -1px 0 #fff}"
+    );
+}
+
 #[test]
 fn import_statement_passthrough() {
     let src = r#"@import (css) "https://cdn.example.com/reset.css";
@@ -163,7 +332,7 @@ fn font_face_and_keyframes_blocks() {
     )
     .unwrap();
     assert!(css.contains(
-        "@font-face{font-family:'Open Sans';src:url('/fonts/open-sans.woff2') format('woff2')}"
+        r#"@font-face{font-family:"Open Sans";src:url('/fonts/open-sans.woff2') format("woff2")}"#
     ));
     assert!(css.contains("@keyframes fade-in{from{opacity:0}to{opacity:1}}"));
 }
@@ -183,3 +352,2675 @@ fn compile_styles_base_fixture() {
     assert!(css.contains(".page{min-height:100%"));
     assert!(css.contains(".weui-btn_primary"));
 }
+
+#[test]
+fn namespaced_mixin_calls() {
+    let src = r"#bundle() {
+  @accent: #f60;
+  .button() {
+    display: block;
+    color: @accent;
+  }
+  .tab {
+    border: 1px solid @accent;
+  }
+}
+
+.submit {
+  #bundle > .button();
+}
+
+.nav {
+  #bundle.tab;
+}";
+    let css = compile(
+        src,
+        CompileOptions {
+            minify: true,
+            ..CompileOptions::default()
+        },
+    )
+    .unwrap();
+    assert!(css.contains(".submit{display:block;color:#f60}"));
+    assert!(css.contains(".nav{border:1px solid #f60}"));
+    assert!(!css.contains("#bundle"));
+}
+
+#[test]
+fn nested_media_queries_are_merged() {
+    let src = r"@media screen {
+  .nav {
+    display: flex;
+    @media (min-width: 768px) {
+      display: block;
+    }
+  }
+}
+
+.card {
+  @media print, screen {
+    @media (max-width: 600px) {
+      width: 100%;
+    }
+  }
+}";
+    let css = compile(
+        src,
+        CompileOptions {
+            minify: true,
+            ..CompileOptions::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        css,
+        "@media screen{.nav{display:flex}}\
+@media screen and (min-width: 768px){.nav{display:block}}\
+@media print and (max-width: 600px), screen and (max-width: 600px){.card{width:100%}}"
+    );
+}
+
+#[test]
+fn delimiters_inside_strings_and_comments_do_not_split_statements() {
+    let src = r#".x[data-a="{"], .y[title='a;b'] { a: b; }
+.z {
+  --url: http://a.com/x;
+  color: red // 行注释
+  ;
+  content: "}" /* ; */;
+}"#;
+    let css = compile(
+        src,
+        CompileOptions {
+            minify: true,
+            ..CompileOptions::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        css,
+        r#".x[data-a="{"],.y[title="a;b"]{a:b}.z{--url:http://a.com/x;color:red;content:"}" /* ; */}"#
+    );
+}
+
+#[test]
+fn selector_interpolation() {
+    let src = r#"@prefix: ant;
+@name: "btn";
+@group: ~".a, .b";
+.@{prefix}-@{name} {
+  color: red;
+  .@{name}-icon {
+    width: 1em;
+  }
+}
+@{group} {
+  margin: 0;
+}"#;
+    let css = compile(
+        src,
+        CompileOptions {
+            minify: true,
+            ..CompileOptions::default()
+        },
+    )
+    .unwrap();
+    assert!(css.contains(".ant-btn{color:red}"));
+    assert!(css.contains(".ant-btn .btn-icon{width:1em}"));
+    assert!(css.contains(".a,.b{margin:0}"));
+}
+
+#[test]
+fn detached_rulesets() {
+    let src = r"@shadow: {
+  box-shadow: 0 1px 2px #000;
+};
+@print-rules: {
+  .no-print { display: none; }
+};
+.desktop(@rules) {
+  @media (min-width: 768px) {
+    @rules();
+  }
+}
+.card {
+  @shadow();
+}
+.panel {
+  .desktop(@shadow);
+}
+.banner {
+  .desktop({ width: 50%; });
+}
+@media print {
+  @print-rules();
+}
+@print-rules();";
+    let css = compile(
+        src,
+        CompileOptions {
+            minify: true,
+            ..CompileOptions::default()
+        },
+    )
+    .unwrap();
+    assert!(css.contains(".card{box-shadow:0 1px 2px #000}"));
+    assert!(css.contains("@media (min-width: 768px){.panel{box-shadow:0 1px 2px #000}}"));
+    assert!(css.contains("@media (min-width: 768px){.banner{width:50%}}"));
+    assert!(css.contains("@media print{.no-print{display:none}}"));
+    assert!(css.ends_with(".no-print{display:none}"));
+}
+
+#[test]
+fn detached_rulesets_keep_nested_at_rules() {
+    let src = r"@responsive: {
+  color: red;
+  @media (min-width: 768px) { color: blue; }
+  .child { x: y; @supports (display: grid) { display: grid; } }
+};
+.desktop(@rules) { @media screen { @rules(); } }
+.outer(@rules) { .desktop(@rules); }
+.a { .desktop(@responsive); }
+.b { .outer({ @media print { z: 1; } }); }
+@media tv { .c { @responsive(); } }";
+    let css = compile(
+        src,
+        CompileOptions {
+            minify: true,
+            ..CompileOptions::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        css,
+        concat!(
+            "@media screen{.a{color:red}.a .child{x:y}",
+            "@supports (display: grid){.a .child{display:grid}}}",
+            "@media screen and (min-width: 768px){.a{color:blue}}",
+            "@media screen and print{.b{z:1}}",
+            "@media tv{.c{color:red}.c .child{x:y}@supports (display: grid){.c .child{display:grid}}}",
+            "@media tv and (min-width: 768px){.c{color:blue}}"
+        )
+    );
+
+    let formatted = format_source(src, FormatOptions::default()).unwrap();
+    assert_eq!(
+        compile(&formatted, CompileOptions::default()).unwrap(),
+        compile(src, CompileOptions::default()).unwrap()
+    );
+    let scss = convert_to_scss(".m(@rules) { @media screen { @rules(); } }\n@r: { @media print { a: b; } }\n.x { .m(@r); }").unwrap();
+    assert_eq!(
+        scss,
+        "@mixin m {\n  @media screen {\n    @content;\n  }\n}\n@mixin r {\n  @media print {\n    a: b;\n  }\n}\n.x {\n  @include m {\n    @include r;\n  }\n}\n"
+    );
+}
+
+#[test]
+fn custom_functions_and_plugins() {
+    let mut plugin = FunctionRegistry::new();
+    plugin.register("double", |args| match &args[0] {
+        FunctionValue::Dimension { value, unit } => Ok(Some(FunctionValue::Dimension {
+            value: value * 2.0,
+            unit: unit.clone(),
+        })),
+        _ => Ok(None),
+    });
+    let mut functions = FunctionRegistry::new();
+    functions
+        .register("brand", |_| {
+            Ok(Some(FunctionValue::Color {
+                r: 0xe4,
+                g: 0x39,
+                b: 0x3c,
+                alpha: 1.0,
+            }))
+        })
+        .register("kinds", |args| {
+            let kinds: Vec<&str> = args
+                .iter()
+                .map(|arg| match arg {
+                    FunctionValue::Dimension { .. } => "dimension",
+                    FunctionValue::Color { .. } => "color",
+                    FunctionValue::Quoted { .. } => "quoted",
+                    FunctionValue::Keyword(_) => "keyword",
+                })
+                .collect();
+            Ok(Some(FunctionValue::Keyword(kinds.join("-"))))
+        })
+        .register("alpha-of", |args| match &args[0] {
+            FunctionValue::Color { alpha, .. } => Ok(Some(FunctionValue::Dimension {
+                value: *alpha,
+                unit: String::new(),
+            })),
+            _ => Ok(None),
+        })
+        .register("fail", |_| Err(LessError::eval("boom")))
+        .register_plugin("sizing", plugin);
+    let options = CompileOptions {
+        minify: true,
+        functions,
+        ..CompileOptions::default()
+    };
+
+    let src = r#"@plugin "sizing";
+.btn {
+  color: brand();
+  width: double(12px);
+}"#;
+    let css = compile(src, options.clone()).unwrap();
+    assert_eq!(css, ".btn{color:#e4393c;width:24px}");
+
+    let typed = compile(
+        r#".t { a: kinds(1px + 2, #fff, "s", red, ~"e"); b: alpha-of(rgba(0, 0, 0, 0.5)); c: double(auto); }"#,
+        options.clone(),
+    )
+    .unwrap();
+    assert_eq!(
+        typed,
+        ".t{a:dimension-color-quoted-keyword-keyword;b:.5;c:double(auto)}"
+    );
+
+    let without_plugin = compile(".btn { width: double(12px); }", options.clone()).unwrap();
+    assert_eq!(without_plugin, ".btn{width:double(12px)}");
+
+    let err = compile(".btn { width: fail(); }", options.clone()).unwrap_err();
+    assert!(err.to_string().contains("boom"));
+
+    let err = compile(r#"@plugin "missing";"#, options).unwrap_err();
+    assert!(err.to_string().contains("missing"));
+}
+
+#[test]
+fn property_merging() {
+    let src = r".shadow() {
+  box-shadow+: inset 0 0 10px #555;
+}
+.card {
+  .shadow();
+  box-shadow+: 0 0 20px black;
+  transform+_: scale(2);
+  color: red;
+  transform+_: rotate(15deg);
+}";
+    let css = compile(
+        src,
+        CompileOptions {
+            minify: true,
+            ..CompileOptions::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        css,
+        ".card{box-shadow:inset 0 0 10px #555, 0 0 20px black;transform:scale(2) rotate(15deg);color:red}"
+    );
+}
+
+#[test]
+fn important_mixin_call() {
+    let src = r".m() {
+  color: red;
+  .inner { width: 1px; }
+}
+.a {
+  .m() !important;
+  height: 2px;
+}";
+    let css = compile(
+        src,
+        CompileOptions {
+            minify: true,
+            ..CompileOptions::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        css,
+        ".a{color:red!important;height:2px}.a .inner{width:1px!important}"
+    );
+}
+
+#[test]
+fn rulesets_are_callable_as_mixins() {
+    let src = r".b { .a; .c { .x(); } }
+.a { color: red; }
+.x, .y { margin: 0; }
+#ns { .inner { .m; } .m { padding: 1px; } }
+.z { .y; }";
+    let css = compile(
+        src,
+        CompileOptions {
+            minify: true,
+            ..CompileOptions::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        css,
+        ".b{color:red}.b .c{margin:0}.a{color:red}.x,.y{margin:0}#ns .inner{padding:1px}#ns .m{padding:1px}.z{margin:0}"
+    );
+}
+
+#[test]
+fn compound_mixin_paths_match_like_lessc() {
+    let src = r".btn.primary { color: blue; }
+.btn { .secondary { color: gray; } }
+#ns { @pad: 2px; .a() { padding: @pad; } }
+#outer > .inner { margin: 0; }
+.u1 { .btn.primary(); }
+.u2 { .btn.secondary; }
+.u3 { #ns .a(); }
+.u4 { #outer.inner(); }";
+    let css = compile(
+        src,
+        CompileOptions {
+            minify: true,
+            ..CompileOptions::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        css,
+        ".btn.primary{color:blue}.btn .secondary{color:gray}#outer>.inner{margin:0}.u1{color:blue}.u2{color:gray}.u3{padding:2px}.u4{margin:0}"
+    );
+    assert!(lint(src, CompileOptions::default()).is_empty());
+
+    // 调用路径必须从选择器的第一个元素开始匹配。
+    let err = compile(
+        ".btn.primary { color: blue; }\n.a { .primary; }",
+        CompileOptions::default(),
+    )
+    .unwrap_err();
+    assert_eq!(err.message(), "未定义的 mixin .primary");
+}
+
+#[test]
+fn mixin_calls_closed_by_brace() {
+    let src = r".clearfix { zoom: 1; }
+#ns { .m(@c: red) { color: @c; } }
+.a { .clearfix }
+.b { width: 1px; #ns > .m(blue) !important }
+.c {
+  .clearfix
+}
+.d { #ns.m() }";
+    let css = compile(
+        src,
+        CompileOptions {
+            minify: true,
+            ..CompileOptions::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        css,
+        ".clearfix{zoom:1}.a{zoom:1}.b{width:1px;color:blue!important}.c{zoom:1}.d{color:red}"
+    );
+    let err = compile(".a { .missing }", CompileOptions::default()).unwrap_err();
+    assert_eq!(err.message(), "未定义的 mixin .missing");
+}
+
+#[test]
+fn ruleset_guards() {
+    let src = r"@dark: true;
+@size: 12px;
+.theme when (@dark) { color: white; }
+.light when not (@dark) { color: black; }
+.box when (@size > 10px) and (@size =< 1in) { width: @size; }
+.card {
+  color: red;
+  & when (@dark = false), (@size = 12px) { background: black; }
+}";
+    let css = compile(
+        src,
+        CompileOptions {
+            minify: true,
+            ..CompileOptions::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        css,
+        ".theme{color:white}.box{width:12px}.card{color:red}.card{background:black}"
+    );
+}
+
+#[test]
+fn conditional_functions_and_guard_operations() {
+    let src = r#"@bg: black;
+@bg-light: boolean(luma(@bg) > 50%);
+@w: 10px;
+.both(@a, @b) when ((@a > 1) and (@b < 2)) { both: yes; }
+.sum(@a) when (@a + 1 > 5) { sum: yes; }
+.a {
+  color: if(@bg-light, black, white);
+  b1: boolean(@w > 5px);
+  b2: boolean((@w = 10px) and not (@w < 1px));
+  i1: if((@w > 5px), @w * 2, 0);
+  i2: if(not (iscolor(@bg)), a, b);
+  i3: if((@w < 1px), 1px) 2px;
+  i4: if(@w + 1px >= 11px, ~"yes", "no");
+  .both(5, 1);
+  .both(1, 1);
+  .sum(5);
+}"#;
+    let css = compile(
+        src,
+        CompileOptions {
+            minify: true,
+            ..CompileOptions::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        css,
+        ".a{color:white;b1:true;b2:true;i1:20px;i2:b;i3:2px;i4:yes;both:yes;sum:yes}"
+    );
+
+    let err = compile(".a { b: if(true); }", CompileOptions::default()).unwrap_err();
+    assert_eq!(
+        err.message(),
+        "函数 if 参数数量不正确: 期望 2~3 个，实际 1 个"
+    );
+}
+
+#[test]
+fn each_iterates_lists_and_maps() {
+    let src = r"@selectors: blue, green;
+each(@selectors, {
+  .sel-@{value} { a: @index; }
+});
+@set: { one: 1px; two: 2px; }
+.set { each(@set, { @{key}-@{value}: @value; }) }
+@rules: { w-@{value}: @index; };
+.r { each(range(2), @rules); }
+.anon { each(a b, .(@v, @k) { .x-@{k} { c: @v; } }); }
+@media print { .p { each(1 2, { z@{value}: 1; }); } }";
+    let css = compile(
+        src,
+        CompileOptions {
+            minify: true,
+            ..CompileOptions::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        css,
+        ".sel-blue{a:1}.sel-green{a:2}.set{one-1px:1px;two-2px:2px}.r{w-1:1;w-2:2}\
+.anon .x-1{c:a}.anon .x-2{c:b}@media print{.p{z1:1;z2:1}}"
+    );
+    assert!(lint(src, CompileOptions::default()).is_empty());
+
+    let formatted = format_source(
+        ".a { each(@l, .(@v) { b: @v; }) }",
+        FormatOptions::default(),
+    );
+    assert_eq!(
+        formatted.unwrap(),
+        ".a {\n  each(@l, .(@v) {\n    b: @v;\n  });\n}\n"
+    );
+    let scss = convert_to_scss("@l: a, b;\n.a { each(@l, { .i-@{value} { c: @value; } }); }");
+    assert_eq!(
+        scss.unwrap(),
+        "$l: a, b;\n.a {\n  @each $value in $l {\n    .i-#{$value} {\n      c: $value;\n    }\n  }\n}\n"
+    );
+
+    let err = compile("@x: 1;\n.a { each(1 2, @x); }", CompileOptions::default()).unwrap_err();
+    assert_eq!(err.message(), "each() 的第二个参数必须是规则集");
+    assert_eq!(err.location().map(|location| location.line), Some(2));
+}
+
+#[test]
+fn ruleset_lookups_act_as_maps() {
+    let src = r"@config: {
+  enabled: true;
+  @colors: { primary: blue; @dark: navy; }
+}
+@name: primary;
+@key: dark;
+.average(@x, @y) { @result: ((@x + @y) / 2); }
+#ns { .sizes() { width: 10px; width: 20px; } }
+.a when (@config[enabled] = true) {
+  color: @config[@colors][primary];
+  b: @config[@colors][$@name] @config[@colors][@@key];
+  padding: .average(16px, 50px)[@result];
+  width: #ns.sizes()[width];
+  last: #ns.sizes[];
+}
+@sizes: { small: 1px; large: 2px; }
+@all: { @s: @sizes; }
+each(@all[@s], { .t-@{key} { c: @value; } });";
+    let css = compile(
+        src,
+        CompileOptions {
+            minify: true,
+            ..CompileOptions::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        css,
+        ".a{color:blue;b:blue navy;padding:33px;width:20px;last:20px}\
+.t-small{c:1px}.t-large{c:2px}"
+    );
+    assert!(lint(src, CompileOptions::default()).is_empty());
+
+    let formatted = format_source(".a { b: .m( 1px ,2px )[@r]; }", FormatOptions::default());
+    assert_eq!(formatted.unwrap(), ".a {\n  b: .m(1px, 2px)[@r];\n}\n");
+
+    let err = compile("@c: { a: 1; }\n.x { y: @c[b]; }", CompileOptions::default()).unwrap_err();
+    assert_eq!(err.message(), "规则集中未定义属性 b");
+    assert_eq!(err.location().map(|location| location.line), Some(2));
+    let err = compile("@c: red;\n.x { y: @c[b]; }", CompileOptions::default()).unwrap_err();
+    assert_eq!(err.message(), "@c[b] 中的 [b] 只能在规则集中查找");
+}
+
+#[test]
+fn property_accessors_reference_declarations() {
+    let src = r".block {
+  color: red;
+  .inner { background-color: $color; }
+  color: blue;
+}
+.widget {
+  color: #efefef;
+  border: 1px solid $color;
+  @media print { outline-color: $color; }
+}
+.m() { width: 10px; height: ($width * 2); }
+.x { .m(); }";
+    let css = compile(
+        src,
+        CompileOptions {
+            minify: true,
+            ..CompileOptions::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        css,
+        ".block{color:red;color:blue}.block .inner{background-color:blue}\
+.widget{color:#efefef;border:1px solid #efefef}@media print{.widget{outline-color:#efefef}}\
+.x{width:10px;height:20px}"
+    );
+
+    let err = compile(".a {\n  b: $c;\n}", CompileOptions::default()).unwrap_err();
+    assert_eq!(err.message(), "未定义的属性 $c");
+    assert_eq!(err.location().map(|location| location.line), Some(2));
+    let err = compile(".a { b: $b; }", CompileOptions::default()).unwrap_err();
+    assert_eq!(err.message(), "属性 $b 存在循环引用");
+    let err = convert_to_scss(".a { b: 1; c: $b; }").unwrap_err();
+    assert_eq!(err.message(), "SCSS 中没有属性引用 $b 的对应写法");
+}
+
+#[test]
+fn mixin_scopes_follow_less_closure_rules() {
+    let src = r"@a: global;
+.m() { v: @a; }
+.x { @a: caller; .m(); }
+.only() { w: @only; }
+.y { @only: from-caller; .only(); }
+.average(@x, @y) { @result: ((@x + @y) / 2); }
+div { .average(16px, 50px); padding: @result; }
+.outer { @scoped: lexical; .inner() { s: @scoped; } }
+.z { @scoped: caller; .outer > .inner(); }
+@detached: { d: @a; };
+.w { @a: caller; @detached(); }
+.unlock(@value) { .doSomething() { declaration: @value; } }
+#namespace { .unlock(5); .doSomething(); }
+.own { @result: mine; .average(1, 3); r: @result; }";
+    let css = compile(
+        src,
+        CompileOptions {
+            minify: true,
+            ..CompileOptions::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        css,
+        ".x{v:global}.y{w:from-caller}div{padding:33px}.z{s:lexical}.w{d:global}\
+#namespace{declaration:5}.own{r:mine}"
+    );
+}
+
+#[test]
+fn recursive_mixin_loops() {
+    let src = r".loop(@i) when (@i > 0) {
+  .w-@{i} { width: (@i * 10px); }
+  .loop(@i - 1);
+}
+.loop(3);
+.theme(@mode) when (@mode = dark) { color: white; }
+.card { .theme(dark); .theme(light); }";
+    let css = compile(
+        src,
+        CompileOptions {
+            minify: true,
+            ..CompileOptions::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        css,
+        ".w-3{width:30px}.w-2{width:20px}.w-1{width:10px}.card{color:white}"
+    );
+}
+
+#[test]
+fn mixin_recursion_limit() {
+    let src = ".loop(@i) when (@i > 0) { .loop(@i - 1); }\n.a { .loop(20); }";
+    assert!(compile(src, CompileOptions::default()).is_ok());
+
+    let err = compile(
+        src,
+        CompileOptions {
+            max_mixin_depth: Some(10),
+            ..CompileOptions::default()
+        },
+    )
+    .unwrap_err();
+    assert!(err.to_string().contains("递归展开超过 10 层"));
+}
+
+#[test]
+fn runaway_compiles_hit_configured_limits() {
+    let err = compile(
+        "@d: { a: b; @d(); };\n.x { @d(); }",
+        CompileOptions::default(),
+    )
+    .unwrap_err();
+    assert_eq!(err.message(), "分离规则集 @d 递归调用超过 128 层");
+
+    let mut loader = MemoryLoader::default();
+    for level in 1..=5 {
+        loader.files.insert(
+            PathBuf::from(format!("f{level}.less")),
+            format!("@import \"f{}\";\n.f{level} {{ a: b; }}", level + 1),
+        );
+    }
+    loader.files.insert(PathBuf::from("f6.less"), String::new());
+    let loader = Arc::new(loader);
+    let imports = |max_import_depth| {
+        compile(
+            "@import \"f1\";",
+            CompileOptions {
+                import_loader: Some(loader.clone()),
+                max_import_depth,
+                ..CompileOptions::default()
+            },
+        )
+    };
+    assert!(imports(None).is_ok());
+    let err = imports(Some(3)).unwrap_err();
+    assert_eq!(err.message(), "@import 嵌套超过 3 层: f4.less");
+    let location = err.location().unwrap();
+    assert_eq!(location.file.as_deref(), Some(Path::new("f3.less")));
+
+    let src = ".a() { width: 1234567890px; }\n.b() { .a; .a; .a; .a; }\n.c() { .b; .b; .b; .b; }\n.d { .c; .c; .c; .c; }";
+    let options = |max_output_size| CompileOptions {
+        max_output_size,
+        error_recovery: true,
+        ..CompileOptions::default()
+    };
+    assert!(compile(src, options(Some(10_000))).is_ok());
+    let output = compile_with_diagnostics(src, options(Some(500)));
+    let messages: Vec<_> = output.diagnostics.iter().map(|d| &d.message).collect();
+    assert_eq!(messages, ["编译输出超过 500 字节的上限"]);
+}
+
+#[test]
+fn deeply_nested_input_compiles() {
+    let nested = |depth: usize| {
+        let open: String = (0..depth).map(|i| format!(".n{i}{{")).collect();
+        format!("{open}color:red;{}", "}".repeat(depth))
+    };
+    let options = CompileOptions {
+        minify: true,
+        ..CompileOptions::default()
+    };
+
+    let css = compile(&nested(3000), options.clone()).unwrap();
+    assert!(css.starts_with(".n0 .n1 .n2 "));
+    assert!(css.ends_with(" .n2999{color:red}"));
+    let formatted = format_source(&nested(3000), FormatOptions::default()).unwrap();
+    assert!(formatted.contains(".n2999 {\n"));
+    assert!(lint(&nested(3000), CompileOptions::default()).is_empty());
+
+    let at_rules = format!(
+        "{}.a{{color:red}}{}",
+        "@supports (display:grid){".repeat(3000),
+        "}".repeat(3000)
+    );
+    assert!(compile(&at_rules, options.clone())
+        .unwrap()
+        .contains("(display:grid){.a{color:red}}}"));
+
+    let err = compile(&nested(10_001), options).unwrap_err();
+    assert_eq!(err.message(), "嵌套层数 10001 超过上限 10000");
+}
+
+#[test]
+fn reference_import_only_emits_used_rules() {
+    let css = compile_file(
+        Path::new("fixtures/imports/reference.less"),
+        CompileOptions {
+            minify: true,
+            ..CompileOptions::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        css,
+        ".card{color:#369;padding:4px;border-radius:5px;background:#369}"
+    );
+}
+
+#[test]
+fn import_options() {
+    let css = compile_file(
+        Path::new("fixtures/imports/options.less"),
+        CompileOptions {
+            minify: true,
+            ..CompileOptions::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        css,
+        ".plain { color: blue; }.plain{color:blue}.partial{width:10px}.partial{width:10px}"
+    );
+}
+
+#[test]
+fn imports_are_deduplicated_by_path() {
+    let path = Path::new("fixtures/imports/dedupe.less");
+    let css = compile_file(
+        path,
+        CompileOptions {
+            minify: true,
+            ..CompileOptions::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        css,
+        ".partial{width:10px}.header{height:40px}.footer{height:20px}"
+    );
+
+    let repeated = compile_file(
+        path,
+        CompileOptions {
+            minify: true,
+            multiple_imports: true,
+            ..CompileOptions::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(repeated.matches(".partial{").count(), 2);
+}
+
+#[derive(Default)]
+struct MemoryLoader {
+    files: HashMap<PathBuf, String>,
+}
+
+impl ImportLoader for MemoryLoader {
+    fn resolve(&self, candidate: &Path) -> Option<PathBuf> {
+        self.files
+            .contains_key(candidate)
+            .then(|| candidate.to_path_buf())
+    }
+
+    fn load(&self, path: &Path) -> LessResult<String> {
+        self.files
+            .get(path)
+            .cloned()
+            .ok_or_else(|| LessError::eval(format!("missing {}", path.display())))
+    }
+}
+
+#[test]
+fn custom_import_loader() {
+    let mut loader = MemoryLoader::default();
+    loader.files.insert(
+        PathBuf::from("theme/vars.less"),
+        "@import \"colors\";\n@gap: 4px;".to_string(),
+    );
+    loader.files.insert(
+        PathBuf::from("theme/colors.less"),
+        "@brand: red;".to_string(),
+    );
+    let src = "@import \"theme/vars\";\n.a { color: @brand; margin: @gap; }";
+    let css = compile(
+        src,
+        CompileOptions {
+            minify: true,
+            import_loader: Some(Arc::new(loader)),
+            ..CompileOptions::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(css, ".a{color:red;margin:4px}");
+}
+
+#[test]
+fn imported_definitions_are_visible_before_the_import() {
+    let mut plugin = FunctionRegistry::new();
+    plugin.register("half", |args| match &args[0] {
+        FunctionValue::Dimension { value, unit } => Ok(Some(FunctionValue::Dimension {
+            value: value / 2.0,
+            unit: unit.clone(),
+        })),
+        _ => Ok(None),
+    });
+    let mut functions = FunctionRegistry::new();
+    functions.register_plugin("sizing", plugin);
+    let mut loader = MemoryLoader::default();
+    loader.files.insert(
+        PathBuf::from("lib.less"),
+        "@plugin \"sizing\";\n@gap: 8px;\n.card() { padding: @gap; }\n.ns { .inner() { margin: half(@gap); } }"
+            .to_string(),
+    );
+    loader.files.insert(
+        PathBuf::from("mixins.less"),
+        ".hidden() { display: none; }".to_string(),
+    );
+    let options = CompileOptions {
+        minify: true,
+        functions,
+        import_loader: Some(Arc::new(loader)),
+        ..CompileOptions::default()
+    };
+    let src = r#".a { .card(); .ns.inner(); width: half(10px); }
+.b { .hidden(); @import "mixins"; }
+@import (reference) "lib";"#;
+    let css = compile(src, options.clone()).unwrap();
+    assert_eq!(css, ".a{padding:8px;margin:4px;width:5px}.b{display:none}");
+
+    let err = compile(
+        r#".a { b: 1; }
+@plugin "missing";"#,
+        options,
+    )
+    .unwrap_err();
+    assert_eq!(err.message(), "未注册的插件: missing");
+}
+
+#[test]
+fn rewrite_urls_relative_to_entry() {
+    let mut loader = MemoryLoader::default();
+    loader.files.insert(
+        PathBuf::from("theme/dark/skin.less"),
+        "@bg: url(\"bg.png\");\n.a { background: url(img/x.png), url(./y.png); mask: url(../z.svg); }\n.b { src: url(data:font/woff2;base64,AA) url(/abs.png) url(#id); }".to_string(),
+    );
+    let src = "@import \"theme/dark/skin\";\n.c { background: @bg; }";
+    let rewrite = |rewrite_urls| {
+        let options = CompileOptions {
+            minify: true,
+            import_loader: Some(Arc::new(MemoryLoader {
+                files: loader.files.clone(),
+            })),
+            rewrite_urls,
+            ..CompileOptions::default()
+        };
+        compile(src, options).unwrap()
+    };
+    let untouched = ".a{background:url(img/x.png), url(./y.png);mask:url(../z.svg)}.b{src:url(data:font/woff2;base64,AA) url(/abs.png) url(#id)}.c{background:url(\"bg.png\")}";
+    assert_eq!(rewrite(RewriteUrls::Off), untouched);
+    assert_eq!(
+        rewrite(RewriteUrls::Local),
+        ".a{background:url(img/x.png), url(./theme/dark/y.png);mask:url(./theme/z.svg)}.b{src:url(data:font/woff2;base64,AA) url(/abs.png) url(#id)}.c{background:url(\"bg.png\")}"
+    );
+    assert_eq!(
+        rewrite(RewriteUrls::All),
+        ".a{background:url(theme/dark/img/x.png), url(./theme/dark/y.png);mask:url(./theme/z.svg)}.b{src:url(data:font/woff2;base64,AA) url(/abs.png) url(#id)}.c{background:url(\"theme/dark/bg.png\")}"
+    );
+}
+
+#[test]
+fn circular_import_reports_chain_and_location() {
+    let mut loader = MemoryLoader::default();
+    loader.files.insert(
+        PathBuf::from("b.less"),
+        "@import (css) \"b.less\";\n@import (multiple) \"c\";".to_string(),
+    );
+    loader.files.insert(
+        PathBuf::from("c.less"),
+        ".c { d: e; }\n@import (multiple) \"b\";".to_string(),
+    );
+    loader.files.insert(
+        PathBuf::from("d.less"),
+        "@import (css) \"d.less\";\n.d { e: f; }".to_string(),
+    );
+    let loader = Arc::new(loader);
+    let options = || CompileOptions {
+        minify: true,
+        import_loader: Some(loader.clone()),
+        ..CompileOptions::default()
+    };
+    let err = compile("@import \"b\";", options()).unwrap_err();
+    let location = err.location().unwrap();
+    assert_eq!(err.message(), "检测到循环导入: b.less -> c.less -> b.less");
+    assert_eq!(location.file.as_deref(), Some(Path::new("c.less")));
+    assert_eq!((location.line, location.column), (2, 1));
+    assert_eq!(
+        location.line_text.as_deref(),
+        Some("@import (multiple) \"b\";")
+    );
+
+    // 原样输出的 CSS 导入不会被展开，指回自身也不算循环。
+    let css = compile("@import \"d\";", options()).unwrap();
+    assert_eq!(css, "@import \"d.less\";\n.d{e:f}");
+
+    let err = compile_file("fixtures/imports/cycle.less", CompileOptions::default()).unwrap_err();
+    let message = err.message();
+    let chain: Vec<_> = message
+        .trim_start_matches("检测到循环导入: ")
+        .split(" -> ")
+        .map(|path| Path::new(path).file_name().unwrap().to_str().unwrap())
+        .collect();
+    assert_eq!(chain, ["cycle.less", "cycle-partial.less", "cycle.less"]);
+    assert!(err
+        .location()
+        .and_then(|location| location.file.as_ref())
+        .is_some_and(|file| file.ends_with("fixtures/imports/cycle-partial.less")));
+}
+
+#[test]
+fn imports_nested_in_rulesets_are_scoped() {
+    let mut loader = MemoryLoader::default();
+    loader.files.insert(
+        PathBuf::from("buttons.less"),
+        "@import (css) \"reset.css\";\n@pad: 4px;\n.btn { padding: @pad; }\n.mix() { margin: 0; }\n@media (min-width: 10px) { .btn { padding: 8px; } }".to_string(),
+    );
+    loader.files.insert(
+        PathBuf::from("print.less"),
+        ".p { display: none; }".to_string(),
+    );
+    loader
+        .files
+        .insert(PathBuf::from("plugin.less"), "@plugin \"x\";".to_string());
+    let loader = Arc::new(loader);
+    let options = || CompileOptions {
+        minify: true,
+        import_loader: Some(loader.clone()),
+        ..CompileOptions::default()
+    };
+    let src = ".dark {\n  @import \"buttons\";\n  color: black;\n  .x { .mix(); }\n}\n@media print { @import \"print\"; }";
+    assert_eq!(
+        compile(src, options()).unwrap(),
+        "@import \"reset.css\";\n.dark{color:black}.dark .btn{padding:4px}@media (min-width: 10px){.dark .btn{padding:8px}}.dark .x{margin:0}@media print{.p{display:none}}"
+    );
+
+    // 导入的变量与 mixin 只在所在规则内可见。
+    let err = compile(
+        ".a { @import \"buttons\"; }\n.b { width: @pad; }",
+        options(),
+    )
+    .unwrap_err();
+    assert_eq!(err.message(), "未定义的变量 @pad");
+
+    let err = compile(".a {\n  @import (reference) \"print\";\n}", options()).unwrap_err();
+    assert_eq!(
+        err.message(),
+        "规则集内的 @import 不支持 (reference) 与 (inline) 选项"
+    );
+    assert_eq!(err.location().map(|location| location.line), Some(2));
+    let err = compile(".a { @import \"plugin\"; }", options()).unwrap_err();
+    assert_eq!(err.message(), "规则集内导入的文件不能使用 @plugin");
+}
+
+#[test]
+fn lint_reports_unused_and_undefined_names() {
+    let mut loader = MemoryLoader::default();
+    loader.files.insert(
+        PathBuf::from("lib.less"),
+        "@lib-width: 10px;\n@lib-unused: 1px;\n.lib-mixin() { x: y; }".to_string(),
+    );
+    let src = "@import \"lib\";\n@color: red;\n@unused: 1px;\n.btn() { color: @color; }\n.never(@a) when (@a > 0) { width: @a; }\n#ns { .inner() { margin: 0; } }\n.a { .btn(); #ns > .inner(); .ghost(); border: @missing @lib-width; .lib-mixin(); }";
+    let diagnostics = lint(
+        src,
+        CompileOptions {
+            import_loader: Some(Arc::new(loader)),
+            ..CompileOptions::default()
+        },
+    );
+    let reported: Vec<_> = diagnostics
+        .iter()
+        .map(|d| {
+            let span = d.span.as_ref().unwrap();
+            (d.severity, d.code, span.line, span.column)
+        })
+        .collect();
+    assert_eq!(
+        reported,
+        [
+            (Severity::Warning, "unused-variable", 3, 1),
+            (Severity::Warning, "unused-mixin", 5, 1),
+            (Severity::Warning, "undefined-mixin", 7, 30),
+            (Severity::Warning, "undefined-variable", 7, 40),
+        ]
+    );
+    assert_eq!(diagnostics[1].message, "mixin .never 已定义但从未调用");
+
+    // 导入未展开时无法判断名称是否定义，只报告未使用的定义。
+    let diagnostics = lint(src, CompileOptions::default());
+    assert!(diagnostics
+        .iter()
+        .all(|d| d.code == "unused-variable" || d.code == "unused-mixin"));
+    assert_eq!(
+        lint(".a {", CompileOptions::default())[0].code,
+        "parse-error"
+    );
+}
+
+#[test]
+fn css_imports_are_hoisted_and_deduplicated() {
+    let mut loader = MemoryLoader::default();
+    loader.files.insert(
+        PathBuf::from("parts.less"),
+        "@import url(\"fonts.css\");\n@import \"reset.css\";\n.b { color: blue; }".to_string(),
+    );
+    let src = "@import \"reset.css\";\n.a { color: red; }\n@import \"parts\";\n@import \"print.css\" print;\n@import \"reset.css\";";
+    let css = compile(
+        src,
+        CompileOptions {
+            minify: true,
+            import_loader: Some(Arc::new(loader)),
+            ..CompileOptions::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        css,
+        "@import \"reset.css\";\n@import url(\"fonts.css\");\n@import \"print.css\" print;\n.a{color:red}.b{color:blue}"
+    );
+}
+
+#[test]
+fn charset_leads_output_and_empty_rules_are_optional() {
+    let src = "@import \"a.css\";\n@charset \"UTF-8\";\n.a {}\n.b { color: red; }\n.c { .d { x: 1; } }\n@charset \"latin1\";";
+    let css = compile(
+        src,
+        CompileOptions {
+            minify: true,
+            ..CompileOptions::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        css,
+        "@charset \"UTF-8\";\n@import \"a.css\";\n.b{color:red}.c .d{x:1}"
+    );
+
+    let kept = compile(
+        src,
+        CompileOptions {
+            minify: true,
+            keep_empty_rules: true,
+            ..CompileOptions::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        kept,
+        "@charset \"UTF-8\";\n@import \"a.css\";\n.a{}.b{color:red}.c .d{x:1}"
+    );
+    let pretty = compile(
+        ".a {}",
+        CompileOptions {
+            keep_empty_rules: true,
+            ..CompileOptions::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(pretty, ".a {\n}");
+}
+
+#[test]
+fn bodyless_at_rules_pass_through_with_variables() {
+    let src = "@ns: svg;\n@namespace @ns url(http://www.w3.org/2000/svg);\n@layer base, components;\n.b {\n  color: blue;\n  @media print { @layer print-only; }\n}";
+    let css = compile(
+        src,
+        CompileOptions {
+            minify: true,
+            ..CompileOptions::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        css,
+        "@namespace svg url(http://www.w3.org/2000/svg);@layer base, components;.b{color:blue}@media print{@layer print-only;}"
+    );
+}
+
+#[test]
+fn modern_at_rules_resolve_variables_in_preludes() {
+    let src = r#"@bp: 600px;
+@name: card;
+@tablet: ~"(min-width: 768px)";
+.a {
+  @container @name (min-width: @bp) {
+    color: red;
+    @container (max-width: 900px) { color: blue; }
+  }
+  @media @tablet { x: 1; }
+  @scope (.card) to (.content) { img { z: 3; } }
+  @layer base { w: 4; }
+}"#;
+    let css = compile(
+        src,
+        CompileOptions {
+            minify: true,
+            ..CompileOptions::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        css,
+        "@container card (min-width: 600px){.a{color:red}}\
+@container card (min-width: 600px) and (max-width: 900px){.a{color:blue}}\
+@media (min-width: 768px){.a{x:1}}\
+@scope (.card) to (.content){.a img{z:3}}\
+@layer base{.a{w:4}}"
+    );
+}
+
+#[test]
+fn media_query_variables_as_whole_prelude() {
+    let src = r#"@w: 768px;
+@tablet: ~"(min-width: @{w})";
+@raw: ~"(min-width: @{w} + 100px)";
+@screen: screen;
+.respond(@q) { @media @q { color: red; } }
+@media @tablet, print { .a { x: 1; } }
+@media @raw { .b { x: 2; } }
+.c { @media @screen { @media @tablet { x: 3; } } }
+.d { .respond(~"(max-width: 400px)"); }"#;
+    let css = compile(
+        src,
+        CompileOptions {
+            minify: true,
+            ..CompileOptions::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        css,
+        "@media (min-width: 768px), print{.a{x:1}}\
+@media (min-width: 768px + 100px){.b{x:2}}\
+@media screen and (min-width: 768px){.c{x:3}}\
+@media (max-width: 400px){.d{color:red}}"
+    );
+}
+
+#[test]
+fn at_rule_params_evaluate_arithmetic() {
+    let src = "@bp: 600px;
+.a {
+  @media (min-width: (@bp + 1px)) and (max-width: @bp * 2) { a: 1; }
+  @media (min-aspect-ratio: 16/9) { b: 2; }
+  @supports (display: grid) and (not (display: inline-grid)) { c: 3; }
+}";
+    let css = compile(
+        src,
+        CompileOptions {
+            minify: true,
+            ..CompileOptions::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        css,
+        "@media (min-width: 601px) and (max-width: 1200px){.a{a:1}}\
+@media (min-aspect-ratio: 16/9){.a{b:2}}\
+@supports (display: grid) and (not (display: inline-grid)){.a{c:3}}"
+    );
+}
+
+#[test]
+fn strict_units_controls_incompatible_arithmetic() {
+    let src = "@gap: 2em;\n.a {\n  width: 10px + @gap;\n  height: 1cm + 10mm;\n}\n.b { margin: (1s - 200ms) (3px + 1%); }";
+    let output = compile_with_diagnostics(
+        src,
+        CompileOptions {
+            minify: true,
+            ..CompileOptions::default()
+        },
+    );
+    assert_eq!(output.css, ".a{width:12px;height:2cm}.b{margin:.8s 4px}");
+    let warnings: Vec<_> = output
+        .diagnostics
+        .iter()
+        .map(|diag| {
+            assert_eq!(
+                (diag.severity, diag.code),
+                (Severity::Warning, "unit-mismatch")
+            );
+            (
+                diag.message.as_str(),
+                diag.span.as_ref().map(|span| span.line),
+            )
+        })
+        .collect();
+    assert_eq!(
+        warnings,
+        [
+            ("不同单位相加/相减: 10px 与 2em，结果沿用 px", Some(3)),
+            ("不同单位相加/相减: 3px 与 1%，结果沿用 px", Some(6)),
+        ]
+    );
+
+    let err = compile(
+        src,
+        CompileOptions {
+            strict_units: true,
+            ..CompileOptions::default()
+        },
+    )
+    .unwrap_err();
+    assert_eq!(err.message(), "不同单位无法相加/相减: 10px 与 2em");
+    assert_eq!(err.location().map(|location| location.line), Some(3));
+}
+
+#[test]
+fn warnings_report_unknown_functions_and_deprecated_syntax() {
+    let src = ".a {\n  width: foo(1px);\n  transform: translate(2px) -webkit-linear-gradient(red, blue);\n  content: e(\"x\");\n}\n.b { height: foo(2px); }";
+    let output = compile_with_diagnostics(
+        src,
+        CompileOptions {
+            minify: true,
+            ..CompileOptions::default()
+        },
+    );
+    assert_eq!(
+        output.css,
+        ".a{width:foo(1px);transform:translate(2px) -webkit-linear-gradient(red, blue);content:x}.b{height:foo(2px)}"
+    );
+    let warnings: Vec<_> = output
+        .diagnostics
+        .iter()
+        .map(|diag| {
+            assert_eq!(diag.severity, Severity::Warning);
+            (diag.code, diag.span.as_ref().map(|span| span.line))
+        })
+        .collect();
+    assert_eq!(
+        warnings,
+        [("unknown-function", Some(2)), ("deprecated", Some(4))]
+    );
+}
+
+#[test]
+fn allow_undefined_variables_keeps_literal_text() {
+    let src = "@size: 2px;\n.a {\n  width: @missing;\n  margin: @size @gap;\n}\n.col-@{n} { color: red; }\n.b { top: @@y; }";
+    let options = || CompileOptions {
+        minify: true,
+        allow_undefined_variables: true,
+        ..CompileOptions::default()
+    };
+    let output = compile_with_diagnostics(src, options());
+    assert_eq!(
+        output.css,
+        ".a{width:@missing;margin:2px @gap}.col-@{n}{color:red}.b{top:@@y}"
+    );
+    let warnings: Vec<_> = output
+        .diagnostics
+        .iter()
+        .map(|diag| {
+            assert_eq!(
+                (diag.severity, diag.code),
+                (Severity::Warning, "undefined-variable")
+            );
+            (
+                diag.message.as_str(),
+                diag.span.as_ref().map(|span| span.line),
+            )
+        })
+        .collect();
+    assert_eq!(
+        warnings,
+        [
+            ("未定义的变量 @missing，按原文输出", Some(3)),
+            ("未定义的变量 @gap，按原文输出", Some(4)),
+            ("未定义的变量 @n，按原文输出", Some(6)),
+            ("未定义的变量 @y，按原文输出", Some(7)),
+        ]
+    );
+
+    let err = compile(src, CompileOptions::default()).unwrap_err();
+    assert_eq!(err.message(), "未定义的变量 @missing");
+}
+
+#[test]
+fn supports_preludes_keep_functions_and_keywords() {
+    let src = r#"@prop: display;
+@val: grid;
+@n: 2;
+@sel: ~":focus-visible";
+@supports (@{prop}: @val) and (not (display: inline-@{val})) { .a { x: 1; } }
+@supports selector(li:nth-child(@n)) and (not selector(a:has(+ b))) { .b { x: 2; } }
+@supports not selector(@sel) { .c { x: 3; } }
+@supports (--gap: 1 + 2) or ((width: @n * 3px) and (aspect-ratio: 16/9)) { .d { x: 4; } }"#;
+    let css = compile(
+        src,
+        CompileOptions {
+            minify: true,
+            ..CompileOptions::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        css,
+        "@supports (display: grid) and (not (display: inline-grid)){.a{x:1}}\
+@supports selector(li:nth-child(2)) and (not selector(a:has(+ b))){.b{x:2}}\
+@supports not selector(:focus-visible){.c{x:3}}\
+@supports (--gap: 1 + 2) or ((width: 6px) and (aspect-ratio: 16/9)){.d{x:4}}"
+    );
+}
+
+#[test]
+fn output_styles() {
+    let src = ".a, .b { color: red; background: blue; }\n@media print { .a { y: 2; } }";
+    let compile_with = |style| {
+        compile(
+            src,
+            CompileOptions {
+                output_style: Some(style),
+                ..CompileOptions::default()
+            },
+        )
+        .unwrap()
+    };
+    assert_eq!(
+        compile_with(OutputStyle::Expanded),
+        ".a,\n.b {\n  color: red;\n  background: blue;\n}\n\n@media print {\n  .a {\n    y: 2;\n  }\n}\n"
+    );
+    assert_eq!(
+        compile_with(OutputStyle::Compact),
+        ".a, .b { color: red; background: blue; }\n@media print {\n  .a { y: 2; }\n}\n"
+    );
+    assert_eq!(
+        compile_with(OutputStyle::Compressed),
+        ".a,.b{color:red;background:blue}@media print{.a{y:2}}"
+    );
+}
+
+#[test]
+fn evaluate_returns_rules_before_serialization() {
+    let src = "@w: 10px;\n.a {\n  width: @w * 2;\n  @media (min-width: 768px) { color: red !important; }\n}\n.b { width: 20px; }\n.b { height: 0; }";
+    let options = || CompileOptions {
+        merge_rules: true,
+        ..CompileOptions::default()
+    };
+    let stylesheet = evaluate(src, options()).unwrap();
+    let summary: Vec<_> = stylesheet
+        .nodes
+        .iter()
+        .map(|node| match node {
+            EvaluatedNode::Rule(rule) => (
+                rule.selectors.join(","),
+                rule.declarations
+                    .iter()
+                    .map(|decl| format!("{}={}", decl.name, decl.value))
+                    .collect::<Vec<_>>(),
+            ),
+            EvaluatedNode::AtRule(at_rule) => {
+                let EvaluatedNode::Rule(inner) = &at_rule.children[0] else {
+                    panic!("expected a rule inside @media");
+                };
+                assert!(inner.declarations[0].important);
+                (
+                    format!("@{} {}", at_rule.name, at_rule.params),
+                    vec![inner.selectors.join(",")],
+                )
+            }
+            other => panic!("unexpected node {other:?}"),
+        })
+        .collect();
+    assert_eq!(
+        summary,
+        [
+            (".a".to_string(), vec!["width=20px".to_string()]),
+            (
+                "@media (min-width: 768px)".to_string(),
+                vec![".a".to_string()]
+            ),
+            (
+                ".b".to_string(),
+                vec!["width=20px".to_string(), "height=0".to_string()]
+            ),
+        ]
+    );
+
+    let err = evaluate(".a { width: @missing; }", CompileOptions::default()).unwrap_err();
+    assert_eq!(err.message(), "未定义的变量 @missing");
+}
+
+#[test]
+fn compile_with_emitter_uses_custom_output() {
+    struct JsonRules;
+
+    impl CssEmitter for JsonRules {
+        type Output = String;
+
+        fn emit(&mut self, stylesheet: &EvaluatedStylesheet) -> String {
+            let rules: Vec<_> = stylesheet
+                .nodes
+                .iter()
+                .filter_map(|node| match node {
+                    EvaluatedNode::Rule(rule) => Some(format!(
+                        "{:?}:{{{}}}",
+                        rule.selectors.join(","),
+                        rule.declarations
+                            .iter()
+                            .map(|decl| format!("{:?}:{:?}", decl.name, decl.value))
+                            .collect::<Vec<_>>()
+                            .join(",")
+                    )),
+                    _ => None,
+                })
+                .collect();
+            format!("{{{}}}", rules.join(","))
+        }
+    }
+
+    let src = "@c: red;\n.a { color: @c; .b { margin: 0 auto; } }";
+    let json = compile_with_emitter(src, CompileOptions::default(), &mut JsonRules).unwrap();
+    assert_eq!(
+        json,
+        r#"{".a":{"color":"red"},".a .b":{"margin":"0 auto"}}"#
+    );
+
+    // 内置序列化器作为 emitter 时与 compile 输出一致。
+    for minify in [false, true] {
+        let options = || CompileOptions {
+            minify,
+            ..CompileOptions::default()
+        };
+        let mut emitter: Box<dyn CssEmitter<Output = String>> = if minify {
+            Box::new(Serializer::minified())
+        } else {
+            Box::new(Serializer::pretty())
+        };
+        assert_eq!(
+            compile_with_emitter(src, options(), emitter.as_mut()).unwrap(),
+            compile(src, options()).unwrap()
+        );
+    }
+
+    let err = compile_with_emitter(
+        ".a { width: @missing; }",
+        CompileOptions::default(),
+        &mut JsonRules,
+    )
+    .unwrap_err();
+    assert_eq!(err.message(), "未定义的变量 @missing");
+}
+
+#[test]
+fn dump_line_numbers_comments_before_rules() {
+    let mut loader = MemoryLoader::default();
+    loader.files.insert(
+        PathBuf::from("src/button.less"),
+        "\n.btn {\n  color: red;\n  .icon { width: 1px; }\n}".to_string(),
+    );
+    let src = "@import \"src/button\";\n@media print {\n  .a { color: blue; }\n}";
+    let options = |minify| CompileOptions {
+        import_loader: Some(Arc::new(MemoryLoader {
+            files: loader.files.clone(),
+        })),
+        dump_line_numbers: true,
+        minify,
+        ..CompileOptions::default()
+    };
+    assert_eq!(
+        compile(src, options(false)).unwrap(),
+        "/* line 2, src/button.less */\n.btn {\n  color: red;\n}\n\n/* line 4, src/button.less */\n.btn .icon {\n  width: 1px;\n}\n\n@media print {\n  /* line 3 */\n  .a {\n    color: blue;\n  }\n}"
+    );
+    assert_eq!(
+        compile(src, options(true)).unwrap(),
+        ".btn{color:red}.btn .icon{width:1px}@media print{.a{color:blue}}"
+    );
+}
+
+#[test]
+fn source_map_options_control_output() {
+    let mut loader = MemoryLoader::default();
+    loader.files.insert(
+        PathBuf::from("base.less"),
+        "\n.base { margin: 0; }".to_string(),
+    );
+    let src = "@import \"base\";\n.a {\n  .b { color: red; }\n}";
+    let options = |source_map| CompileOptions {
+        import_loader: Some(Arc::new(MemoryLoader {
+            files: loader.files.clone(),
+        })),
+        minify: true,
+        source_map: Some(source_map),
+        ..CompileOptions::default()
+    };
+
+    let output = compile_with_metadata(
+        src,
+        options(SourceMapOptions {
+            url: Some("app.css.map".to_string()),
+            root: Some("/styles/".to_string()),
+            sources_content: true,
+            ..SourceMapOptions::default()
+        }),
+    )
+    .unwrap();
+    assert_eq!(
+        output.css,
+        ".base{margin:0}.a .b{color:red}\n/*# sourceMappingURL=app.css.map */"
+    );
+    assert_eq!(
+        output.source_map.unwrap(),
+        r#"{"version":3,"sourceRoot":"/styles/","sources":["base.less","input"],"sourcesContent":["\n.base { margin: 0; }","@import \"base\";\n.a {\n  .b { color: red; }\n}"],"names":[],"mappings":"AACA,eCCE"}"#
+    );
+
+    // 不内嵌且没有地址时不写注释。
+    let output = compile_with_metadata(src, options(SourceMapOptions::default())).unwrap();
+    assert_eq!(output.css, ".base{margin:0}.a .b{color:red}");
+    assert!(output
+        .source_map
+        .unwrap()
+        .contains(r#""sources":["base.less","input"]"#));
+
+    let output = compile_with_metadata(
+        src,
+        options(SourceMapOptions {
+            inline: true,
+            ..SourceMapOptions::default()
+        }),
+    )
+    .unwrap();
+    assert!(output.source_map.is_none());
+    assert!(output
+        .css
+        .ends_with("\n/*# sourceMappingURL=data:application/json;base64,eyJ2ZXJzaW9uIjozLCJzb3VyY2VzIjpbImJhc2UubGVzcyIsImlucHV0Il0sIm5hbWVzIjpbXSwibWFwcGluZ3MiOiJBQUNBLGVDQ0UifQ== */"));
+}
+
+#[test]
+fn collect_stats_reports_counts_and_timings() {
+    let mut loader = MemoryLoader::default();
+    loader.files.insert(
+        PathBuf::from("mixins.less"),
+        ".pad(@p) { padding: @p; }\n.unused() when (false) { color: red; }".to_string(),
+    );
+    let src = "@import \"mixins\";\n.a, .b { .pad(1px); .c { .pad(2px); .unused(); @media print { color: red; } } }\n@font-face { font-family: x; }";
+    let options = |collect_stats| CompileOptions {
+        import_loader: Some(Arc::new(MemoryLoader {
+            files: loader.files.clone(),
+        })),
+        collect_stats,
+        ..CompileOptions::default()
+    };
+    let output = compile_with_metadata(src, options(true)).unwrap();
+    let stats = output.stats.unwrap();
+    assert_eq!(stats.rules, 3);
+    assert_eq!(stats.selectors, 6);
+    assert_eq!(stats.declarations, 4);
+    assert_eq!(stats.max_nesting_depth, 3);
+    assert_eq!(stats.mixin_expansions, 2);
+    let files: Vec<_> = stats.files.iter().map(|file| file.path.clone()).collect();
+    assert_eq!(files, [None, Some(PathBuf::from("mixins.less"))]);
+
+    assert!(compile_with_metadata(src, options(false))
+        .unwrap()
+        .stats
+        .is_none());
+}
+
+#[test]
+fn compile_stream_matches_compile_for_every_style() {
+    let src = "@charset \"utf-8\";\n@import url(\"base.css\");\n/* head */\n.a { color: red; }\n.empty {}\n@media print { .a { y: 2; } }\n.tail {}\n";
+    let styles = [
+        None,
+        Some(OutputStyle::Expanded),
+        Some(OutputStyle::Compact),
+        Some(OutputStyle::Compressed),
+    ];
+    for style in styles {
+        let options = CompileOptions {
+            output_style: style,
+            ..CompileOptions::default()
+        };
+        let mut css = Vec::new();
+        compile_stream(src.as_bytes(), &mut css, options.clone()).unwrap();
+        assert_eq!(
+            String::from_utf8(css).unwrap(),
+            compile(src, options).unwrap()
+        );
+    }
+
+    // 先使用后定义的变量与 mixin、前缀与压缩逐条处理；规则集中的 `@import` 需要整体求值后提到开头。
+    let late =
+        ".m(); .a { width: @w; display: flex; margin: 0px; }\n@w: 10px;\n.m() { .b { c: d; } }";
+    let nested = ".a { @import url(\"x.css\"); color: red; }\n@import url(\"y.css\");";
+    for src in [late, nested] {
+        for minify in [false, true] {
+            let options = CompileOptions {
+                minify,
+                autoprefix: vec![BrowserTarget::parse("safari 8").unwrap()],
+                ..CompileOptions::default()
+            };
+            let mut css = Vec::new();
+            compile_stream(src.as_bytes(), &mut css, options.clone()).unwrap();
+            assert_eq!(
+                String::from_utf8(css).unwrap(),
+                compile(src, options).unwrap()
+            );
+        }
+    }
+
+    let mut css = Vec::new();
+    let err = compile_stream(
+        ".a { color: @missing; }".as_bytes(),
+        &mut css,
+        Default::default(),
+    )
+    .unwrap_err();
+    assert!(err.message().contains("@missing"));
+    assert!(css.is_empty());
+}
+
+#[test]
+fn compile_stream_writes_each_rule_once_evaluated() {
+    struct Shared(Arc<Mutex<Vec<u8>>>);
+
+    impl std::io::Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    // `written()` 返回求值到该处时已写出的字节数。
+    let buffer = Arc::new(Mutex::new(Vec::new()));
+    let probe = Arc::clone(&buffer);
+    let mut functions = FunctionRegistry::new();
+    functions.register("written", move |_| {
+        Ok(Some(FunctionValue::Dimension {
+            value: probe.lock().unwrap().len() as f64,
+            unit: String::new(),
+        }))
+    });
+    let options = CompileOptions {
+        minify: true,
+        functions,
+        ..CompileOptions::default()
+    };
+    let src = ".a { color: red; }\n.b { n: written(); }";
+    let stream = |options: CompileOptions| {
+        buffer.lock().unwrap().clear();
+        compile_stream(src.as_bytes(), Shared(Arc::clone(&buffer)), options).unwrap();
+        String::from_utf8(buffer.lock().unwrap().clone()).unwrap()
+    };
+
+    assert_eq!(stream(options.clone()), ".a{color:red}.b{n:13}");
+    // `merge_rules` 需要看到整个样式表，求值完成后才写出。
+    assert_eq!(
+        stream(CompileOptions {
+            merge_rules: true,
+            ..options
+        }),
+        ".a{color:red}.b{n:0}"
+    );
+}
+
+#[test]
+fn minify_shortens_colors_zeros_and_combinators() {
+    let src = r##".list > li + li ~ .x {
+  color: #FFFFFF;
+  margin: 0px 0.5em;
+  width: calc(100% - 0px);
+  content: "#ffffff 0px";
+  --raw: 0px;
+}"##;
+    let css = compile(
+        src,
+        CompileOptions {
+            minify: true,
+            ..CompileOptions::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        css,
+        r##".list>li+li~.x{color:#FFF;margin:0 .5em;width:calc(100% - 0px);content:"#ffffff 0px";--raw:0px}"##
+    );
+}
+
+#[test]
+fn merge_rules_combines_adjacent_duplicates() {
+    let src = ".m() { color: red; margin: 0; }
+.a { .m(); }
+.a { .m(); padding: 1px; }
+.b { color: red; }
+@media print { .c { x: 1; } .c { x: 1; y: 2; } }";
+    let options = |merge_rules| CompileOptions {
+        minify: true,
+        merge_rules,
+        ..CompileOptions::default()
+    };
+    assert_eq!(
+        compile(src, options(true)).unwrap(),
+        ".a{color:red;margin:0;padding:1px}.b{color:red}@media print{.c{x:1;y:2}}"
+    );
+    assert_eq!(
+        compile(src, options(false)).unwrap(),
+        ".a{color:red;margin:0}.a{color:red;margin:0;padding:1px}.b{color:red}@media print{.c{x:1}.c{x:1;y:2}}"
+    );
+}
+
+#[test]
+fn autoprefix_inserts_vendor_prefixes_for_targets() {
+    let src = ".a { display: flex; user-select: none; -webkit-backdrop-filter: blur(2px); backdrop-filter: blur(2px); color: red; }";
+    let compile_for = |targets: &[&str]| {
+        compile(
+            src,
+            CompileOptions {
+                minify: true,
+                autoprefix: targets
+                    .iter()
+                    .map(|target| BrowserTarget::parse(target).unwrap())
+                    .collect(),
+                ..CompileOptions::default()
+            },
+        )
+        .unwrap()
+    };
+    assert_eq!(
+        compile_for(&["safari 8", "ie 10"]),
+        ".a{display:-webkit-flex;display:-ms-flexbox;display:flex;-webkit-user-select:none;-ms-user-select:none;user-select:none;-webkit-backdrop-filter:blur(2px);backdrop-filter:blur(2px);color:red}"
+    );
+    assert_eq!(
+        compile_for(&["chrome 120"]),
+        ".a{display:flex;user-select:none;-webkit-backdrop-filter:blur(2px);backdrop-filter:blur(2px);color:red}"
+    );
+}
+
+#[test]
+fn css_modules_hash_class_names_and_return_mapping() {
+    let src = ".btn { &.primary { color: red; } } :global(.app) .btn { margin: 0; }\n@keyframes fade { 50% { opacity: .5; } }";
+    let options = CompileOptions {
+        minify: true,
+        css_modules: true,
+        ..CompileOptions::default()
+    };
+    let output = compile_with_metadata(src, options.clone()).unwrap();
+    let btn = &output.class_names["btn"];
+    let primary = &output.class_names["primary"];
+    assert_eq!(output.class_names.len(), 2);
+    assert!(btn.starts_with("btn_") && btn.len() == "btn_".len() + 6);
+    assert_eq!(
+        output.css,
+        format!(".{btn}.{primary}{{color:red}}.app .{btn}{{margin:0}}@keyframes fade{{50%{{opacity:.5}}}}")
+    );
+    assert_eq!(
+        compile_with_metadata(src, options).unwrap().class_names,
+        output.class_names
+    );
+    assert!(compile_with_metadata(src, CompileOptions::default())
+        .unwrap()
+        .class_names
+        .is_empty());
+}
+
+#[test]
+fn css_modules_hash_relative_paths_and_salt() {
+    let base = std::env::temp_dir().join(format!("less_oxide_modules_{}", std::process::id()));
+    let class_name = |root: &Path, file: &str, source: &str, salt: &str| {
+        let path = root.join(file);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, source).unwrap();
+        let options = CompileOptions {
+            css_modules: true,
+            css_modules_root: Some(root.to_path_buf()),
+            css_modules_salt: salt.to_string(),
+            ..CompileOptions::default()
+        };
+        compile_file_with_metadata(&path, options)
+            .unwrap()
+            .class_names["btn"]
+            .clone()
+    };
+
+    let first = class_name(
+        &base.join("a"),
+        "src/button.less",
+        ".btn { color: red; }",
+        "",
+    );
+    // 同一相对路径在另一个根目录下、源码内容不同时类名不变。
+    let moved = class_name(
+        &base.join("b"),
+        "src/button.less",
+        ".btn { color: blue; }",
+        "",
+    );
+    assert_eq!(first, moved);
+    let other_file = class_name(&base.join("a"), "src/link.less", ".btn { color: red; }", "");
+    assert_ne!(first, other_file);
+    let salted = class_name(
+        &base.join("a"),
+        "src/button.less",
+        ".btn { color: red; }",
+        "v2",
+    );
+    assert_ne!(first, salted);
+
+    let from_source = |source: &str| {
+        let options = CompileOptions {
+            css_modules: true,
+            ..CompileOptions::default()
+        };
+        compile_with_metadata(source, options).unwrap().class_names["btn"].clone()
+    };
+    assert_eq!(from_source(".btn { a: b; }"), from_source(".btn { c: d; }"));
+
+    std::fs::remove_dir_all(&base).unwrap();
+}
+
+#[test]
+fn metadata_lists_top_level_variables_with_locations() {
+    let src = "@base: 8px;\n@gap: @base * 2;\n@rules: { color: red; };\n.a { @local: 1px; margin: @gap; }\n@base: 10px;\n@broken: @missing;";
+    let output = compile_with_metadata(src, CompileOptions::default()).unwrap();
+    let variables: Vec<_> = output
+        .variables
+        .iter()
+        .map(|var| {
+            (
+                var.name.as_str(),
+                var.value.as_str(),
+                var.location.line,
+                var.location.column,
+            )
+        })
+        .collect();
+    assert_eq!(variables, [("base", "10px", 5, 1), ("gap", "20px", 2, 1)]);
+}
+
+#[test]
+fn format_source_canonicalizes_layout_and_keeps_comments() {
+    let src = "@import (reference)   \"lib\";\n// theme\n@color:   red;   // brand\n\n\n/* buttons */\n.btn( @a , @b: 2px ) when (@a>0) { width:@a;height : @b }\n.a,.b  >  .c{color:@color;&:hover{color:\"blue\" !important}\n  .btn(1px);\n  .wrap({ margin: 0; });\n}\n@media (min-width: 768px) { .d { e: f } }\n";
+    let options = FormatOptions {
+        indent_width: 4,
+        quote_style: QuoteStyle::Single,
+        ..FormatOptions::default()
+    };
+    let formatted = format_source(src, options.clone()).unwrap();
+    assert_eq!(
+        formatted,
+        "@import (reference) 'lib';\n// theme\n@color: red; // brand\n\n/* buttons */\n.btn(@a, @b: 2px) when (@a > 0) {\n    width: @a;\n    height: @b;\n}\n.a,\n.b > .c {\n    color: @color;\n    &:hover {\n        color: 'blue' !important;\n    }\n    .btn(1px);\n    .wrap({\n        margin: 0;\n    });\n}\n@media (min-width: 768px) {\n    .d {\n        e: f;\n    }\n}\n"
+    );
+    assert_eq!(format_source(&formatted, options).unwrap(), formatted);
+
+    let src = "@w: 2px;\n.m(@x) { width: @x * 2; }\n.a { .m(@w); // note\n  b: c }";
+    let formatted = format_source(src, FormatOptions::default()).unwrap();
+    assert_eq!(
+        compile(&formatted, CompileOptions::default()).unwrap(),
+        compile(src, CompileOptions::default()).unwrap()
+    );
+}
+
+#[test]
+fn convert_to_scss_maps_variables_mixins_and_guards() {
+    let src = "@import (reference) \"mixins.less\";\n@w: 10px;\n.rounded { border-radius: 4px; }\n.m(@a: 1px) when (@a > 0) { width: @a; }\n.desktop(@rules) { @media (min-width: @w) { @rules(); } }\n.@{name}-x {\n  .m(@w);\n  .rounded;\n  .desktop({ padding: 0; });\n  width: ~\"calc(100% - @{w})\";\n}\n";
+    assert_eq!(
+        convert_to_scss(src).unwrap(),
+        "@import \"mixins.scss\";\n$w: 10px;\n.rounded {\n  border-radius: 4px;\n}\n@mixin m($a: 1px) {\n  @if $a > 0 {\n    width: $a;\n  }\n}\n@mixin desktop {\n  @media (min-width: #{$w}) {\n    @content;\n  }\n}\n.#{$name}-x {\n  @include m($w);\n  @extend .rounded;\n  @include desktop {\n    padding: 0;\n  }\n  width: unquote(\"calc(100% - #{$w})\");\n}\n"
+    );
+
+    let err = convert_to_scss("@a: b;\n.x { y: @@a; }").unwrap_err();
+    assert_eq!(err.message(), "SCSS 中没有间接变量引用 @@a 的对应写法");
+    assert_eq!(err.location().map(|location| location.line), Some(2));
+}
+
+#[test]
+fn global_and_modify_vars() {
+    let src = "@radius: 2px;\n@primary: red;\n.btn { color: @primary; border-radius: @radius; margin: @gap; }";
+    let css = compile(
+        src,
+        CompileOptions {
+            minify: true,
+            global_vars: HashMap::from([
+                ("@gap".to_string(), "4px".to_string()),
+                ("radius".to_string(), "8px".to_string()),
+            ]),
+            modify_vars: HashMap::from([("primary".to_string(), "#1890ff".to_string())]),
+            ..CompileOptions::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(css, ".btn{color:#1890ff;border-radius:2px;margin:4px}");
+}
+
+#[test]
+fn mixin_at_rules_attach_to_caller_selectors() {
+    let src = r"
+.responsive() { @media (min-width: 600px) { width: 50%; &:hover { color: red; } } }
+#ns { .grid() { @supports (display: grid) { display: grid; } } }
+.col { float: left; .responsive(); }
+.wrap { #ns > .grid() !important; }
+@media print { .print { .responsive(); } }
+";
+    let css = compile(
+        src,
+        CompileOptions {
+            minify: true,
+            ..CompileOptions::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        css,
+        concat!(
+            ".col{float:left}",
+            "@media (min-width: 600px){.col{width:50%}.col:hover{color:red}}",
+            "@supports (display: grid){.wrap{display:grid!important}}",
+            "@media print and (min-width: 600px){.print{width:50%}.print:hover{color:red}}"
+        )
+    );
+}
+
+#[test]
+fn keyframes_and_page_bodies_ignore_outer_selectors() {
+    let src = r"
+@step: 50%;
+@dur: 1s;
+.spinner {
+  color: red;
+  @-webkit-keyframes spin { from { opacity: 0; } @{step} { opacity: .5; } to { opacity: 1; } }
+  @page { size: A4; }
+}
+@keyframes grow { 0%, 100% { width: @dur; } }
+@page :first { margin: 1in; }
+";
+    let css = compile(
+        src,
+        CompileOptions {
+            minify: true,
+            ..CompileOptions::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        css,
+        concat!(
+            ".spinner{color:red}",
+            "@-webkit-keyframes spin{from{opacity:0}50%{opacity:.5}to{opacity:1}}",
+            "@page{size:A4}",
+            "@keyframes grow{0%,100%{width:1s}}",
+            "@page :first{margin:1in}"
+        )
+    );
+}
+
+#[test]
+fn custom_properties_keep_values_verbatim() {
+    let src = r#"@gap: 4px;
+:root {
+  --spacing:  calc( 1px + 2px ) ,  3px;
+  --sum: 1px + 2px;
+  --json: { "a": [1, 2]; };
+  --str: "a;b}";
+  --gap: @{gap} @gap;
+  --imp: red ! important;
+}"#;
+    let minified = CompileOptions {
+        minify: true,
+        ..CompileOptions::default()
+    };
+    let css = compile(src, minified.clone()).unwrap();
+    assert_eq!(
+        css,
+        r#":root{--spacing:calc( 1px + 2px ) ,  3px;--sum:1px + 2px;--json:{ "a": [1, 2]; };--str:"a;b}";--gap:@{gap} @gap;--imp:red!important}"#
+    );
+
+    let interpolated = compile(
+        ".a { --gap: @{gap} @gap; width: @gap; }",
+        CompileOptions {
+            custom_property_interpolation: true,
+            global_vars: HashMap::from([("gap".to_string(), "4px".to_string())]),
+            ..minified
+        },
+    )
+    .unwrap();
+    assert_eq!(interpolated, ".a{--gap:4px @gap;width:4px}");
+}
+
+#[test]
+fn escaped_values_skip_arithmetic_through_variables() {
+    let src = r#"@gap: 10px;
+@sum: ~"10px + 5px";
+@double: ~"@{gap} * 2";
+@brand: ~"red";
+@product: e("2px * 3");
+.pad(@value) { padding: @value; }
+.a {
+  width: ~"calc(100% - @{gap})";
+  height: e("calc(50% - 2px)");
+  left: e("1 + 2");
+  right: @product;
+  margin: @sum;
+  top: @double;
+  color: darken(@brand, 10%);
+  .pad(~"1px + 1px");
+}"#;
+    let css = compile(
+        src,
+        CompileOptions {
+            minify: true,
+            ..CompileOptions::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        css,
+        ".a{width:calc(100% - 10px);height:calc(50% - 2px);left:1 + 2;right:2px * 3;margin:10px + 5px;top:10px * 2;color:#c00;padding:1px + 1px}"
+    );
+}
+
+#[test]
+fn calc_substitutes_variables_and_nested_functions_only() {
+    let src = r"@gutter: 10px;
+@half: 50vh/2;
+.a {
+  width: calc(100% - (@gutter * 2));
+  height: calc(50% + (@half - 20px));
+  left: calc(100% - percentage(0.25));
+  top: calc(1px + max(2px, 3px * 2));
+}";
+    let css = compile(
+        src,
+        CompileOptions {
+            minify: true,
+            ..CompileOptions::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        css,
+        ".a{width:calc(100% - (10px * 2));height:calc(50% + (25vh - 20px));left:calc(100% - 25%);top:calc(1px + 6px)}"
+    );
+}
+
+#[test]
+fn parent_lists_distribute_over_every_ampersand() {
+    let src = ".a, .b {\n  & + & { margin: 0; }\n  .x, .y { color: red; }\n}";
+    let css = compile(
+        src,
+        CompileOptions {
+            minify: true,
+            ..CompileOptions::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        css,
+        ".a+.a,.a+.b,.b+.a,.b+.b{margin:0}.a .x,.b .x,.a .y,.b .y{color:red}"
+    );
+
+    let err = compile(
+        ".a, .b, .c { & & & { margin: 0; } }",
+        CompileOptions {
+            max_selectors: Some(8),
+            ..CompileOptions::default()
+        },
+    )
+    .unwrap_err();
+    assert!(err.message().contains("选择器组合数超过上限 8"));
+}
+
+#[test]
+fn comments_are_kept_on_request() {
+    let src = "/*! license */\n// dropped\n/* header */\n.a {\n  /* note */\n  color: red;\n  .b { margin: 0; }\n}";
+    let plain = compile(src, CompileOptions::default()).unwrap();
+    assert_eq!(
+        plain,
+        "/*! license */\n.a {\n  color: red;\n}\n\n.a .b {\n  margin: 0;\n}"
+    );
+
+    let kept = compile(
+        src,
+        CompileOptions {
+            keep_comments: true,
+            ..CompileOptions::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        kept,
+        "/*! license */\n/* header */\n.a {\n  /* note */\n  color: red;\n}\n\n.a .b {\n  margin: 0;\n}"
+    );
+
+    let minified = compile(
+        src,
+        CompileOptions {
+            minify: true,
+            keep_comments: true,
+            ..CompileOptions::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(minified, "/*! license */.a{color:red}.a .b{margin:0}");
+}
+
+struct AliasLoader {
+    inner: MemoryLoader,
+    seen: std::sync::Mutex<Vec<(String, Option<PathBuf>)>>,
+}
+
+impl ImportLoader for AliasLoader {
+    fn resolve(&self, candidate: &Path) -> Option<PathBuf> {
+        self.inner.resolve(candidate)
+    }
+
+    fn load(&self, path: &Path) -> LessResult<String> {
+        self.inner.load(path)
+    }
+
+    fn resolve_import(&self, target: &str, from: Option<&Path>) -> LessResult<Option<PathBuf>> {
+        self.seen
+            .lock()
+            .unwrap()
+            .push((target.to_string(), from.map(Path::to_path_buf)));
+        if target == "~broken" {
+            return Err(LessError::eval("别名 ~broken 无法解析"));
+        }
+        Ok(target
+            .strip_prefix('~')
+            .map(|rest| PathBuf::from("node_modules").join(format!("{rest}.less"))))
+    }
+}
+
+#[test]
+fn import_loader_resolves_aliases() {
+    let mut inner = MemoryLoader::default();
+    inner.files.insert(
+        PathBuf::from("node_modules/ui/style.less"),
+        "@import \"colors\";\n.ui { color: @brand; }".to_string(),
+    );
+    inner.files.insert(
+        PathBuf::from("node_modules/ui/colors.less"),
+        "@brand: blue;".to_string(),
+    );
+    let loader = Arc::new(AliasLoader {
+        inner,
+        seen: Default::default(),
+    });
+    let css = compile(
+        "@import \"~ui/style\";",
+        CompileOptions {
+            minify: true,
+            import_loader: Some(loader.clone()),
+            ..CompileOptions::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(css, ".ui{color:blue}");
+    let seen = loader.seen.lock().unwrap();
+    assert_eq!(
+        *seen,
+        [
+            ("~ui/style".to_string(), None),
+            (
+                "colors".to_string(),
+                Some(PathBuf::from("node_modules/ui/style.less"))
+            ),
+        ]
+    );
+}
+
+#[test]
+fn import_loader_errors_abort_compilation() {
+    let loader = Arc::new(AliasLoader {
+        inner: MemoryLoader::default(),
+        seen: Default::default(),
+    });
+    for source in ["@import \"~broken\";", "@import (optional) \"~broken\";"] {
+        let err = compile(
+            source,
+            CompileOptions {
+                import_loader: Some(loader.clone()),
+                ..CompileOptions::default()
+            },
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("别名 ~broken 无法解析"), "{err}");
+    }
+}
+
+#[test]
+fn errors_report_line_column_and_excerpt() {
+    let err = compile(".a {\n  color: @missing;\n}", CompileOptions::default()).unwrap_err();
+    let location = err.location().unwrap();
+    assert_eq!(
+        (location.file.as_ref(), location.line, location.column),
+        (None, 2, 3)
+    );
+    assert_eq!(
+        err.to_string(),
+        "语义求值失败: 未定义的变量 @missing\n --> <input>:2:3\n  |\n2 |   color: @missing;\n  |   ^"
+    );
+
+    let mut loader = MemoryLoader::default();
+    loader.files.insert(
+        PathBuf::from("theme/broken.less"),
+        "@gap: 4px;\n.b {\n  margin @gap;\n".to_string(),
+    );
+    let err = compile(
+        "@import \"theme/broken\";",
+        CompileOptions {
+            import_loader: Some(Arc::new(loader)),
+            ..CompileOptions::default()
+        },
+    )
+    .unwrap_err();
+    assert!(matches!(err, LessError::ParseError { .. }));
+    let location = err.location().unwrap();
+    assert_eq!(
+        location.file.as_deref(),
+        Some(Path::new("theme/broken.less"))
+    );
+    assert_eq!((location.line, location.column), (3, 15));
+}
+
+#[test]
+fn error_recovery_collects_diagnostics() {
+    let src = ".ok { color: red; }\n.bad { color red; width: 1px; }\n.eval { a: @nope; b: 2px; }\n@import \"missing\";\n.tail { z: 1; }";
+    let options = CompileOptions {
+        minify: true,
+        error_recovery: true,
+        import_loader: Some(Arc::new(MemoryLoader::default())),
+        ..CompileOptions::default()
+    };
+    let output = compile_with_diagnostics(src, options.clone());
+    assert_eq!(
+        output.css,
+        ".ok{color:red}.bad{width:1px}.eval{b:2px}.tail{z:1}"
+    );
+    let summary: Vec<_> = output
+        .diagnostics
+        .iter()
+        .map(|diag| {
+            assert_eq!(diag.severity, Severity::Error);
+            (diag.code, diag.span.as_ref().map(|span| span.line))
+        })
+        .collect();
+    assert_eq!(
+        summary,
+        [
+            ("parse-error", Some(2)),
+            ("eval-error", None),
+            ("eval-error", Some(3)),
+        ]
+    );
+
+    assert!(compile(src, options).is_err());
+    let strict = compile_with_diagnostics(src, CompileOptions::default());
+    assert!(strict.css.is_empty());
+    assert_eq!(strict.diagnostics.len(), 1);
+}
+
+#[test]
+fn malformed_input_is_rejected_without_panicking() {
+    let message = |src: &str| parse(src).unwrap_err().message().to_string();
+    assert_eq!(message(".a { b: c; }\n} .d { e: f; }"), "多余的 '}'");
+    assert_eq!(message(".a { b: c; }\n/* open"), "注释缺少结尾的 '*/'");
+    assert_eq!(message(".a { b: c /* open; }"), "注释缺少结尾的 '*/'");
+    assert_eq!(message(".a { content: \"open; }"), "字符串缺少结尾的引号");
+    assert_eq!(message("@x: 'open;"), "字符串缺少结尾的引号");
+
+    let (stylesheet, errors) = parse_lenient(".a { b: c; }\n} .d { e: f; }\n/* open");
+    let selectors: Vec<_> = stylesheet
+        .statements
+        .iter()
+        .filter_map(|statement| match statement {
+            Statement::RuleSet(rule) => Some(rule.selectors[0].value.as_str()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(selectors, [".a", ".d"]);
+    let errors: Vec<_> = errors
+        .iter()
+        .map(|err| (err.message(), err.location().map(|loc| loc.line)))
+        .collect();
+    assert_eq!(
+        errors,
+        [("多余的 '}'", Some(2)), ("注释缺少结尾的 '*/'", Some(3))]
+    );
+
+    // 截断到任意位置的真实样式表都只能报错，严格与容错解析对是否出错的判断一致。
+    for path in ["fixtures/mixins.less", "fixtures/at-rules.less"] {
+        let source = std::fs::read_to_string(path).unwrap();
+        for (end, _) in source.char_indices() {
+            let prefix = &source[..end];
+            let (_, errors) = parse_lenient(prefix);
+            assert_eq!(parse(prefix).is_err(), !errors.is_empty(), "{prefix:?}");
+        }
+    }
+}
+
+#[derive(Default)]
+struct RuleCollector {
+    selectors: Vec<(String, usize)>,
+    mixin_calls: usize,
+}
+
+impl Visitor for RuleCollector {
+    fn visit_ruleset(&mut self, rule: &RuleSet) {
+        let selectors: Vec<_> = rule
+            .selectors
+            .iter()
+            .map(|sel| sel.value.as_str())
+            .collect();
+        self.selectors.push((selectors.join(","), rule.span.line));
+        walk_body(self, &rule.body);
+    }
+
+    fn visit_mixin_call(&mut self, _call: &MixinCall) {
+        self.mixin_calls += 1;
+    }
+}
+
+#[test]
+fn parse_exposes_ast_to_visitors() {
+    let src = ".a, .b {\n  color: red;\n  .c { .m(); }\n}\n@media print {\n  .d { x: 1; }\n}";
+    let stylesheet = parse(src).unwrap();
+    let mut collector = RuleCollector::default();
+    walk_stylesheet(&mut collector, &stylesheet);
+    assert_eq!(
+        collector.selectors,
+        [
+            (".a,.b".to_string(), 1),
+            (".c".to_string(), 3),
+            (".d".to_string(), 6)
+        ]
+    );
+    assert_eq!(collector.mixin_calls, 1);
+    assert!(parse(".a { color red; }").is_err());
+}
+
+#[test]
+fn compile_reports_import_dependencies() {
+    let output =
+        compile_file_with_metadata("fixtures/imports/dedupe.less", CompileOptions::default())
+            .unwrap();
+    let names: Vec<_> = output
+        .dependencies
+        .iter()
+        .map(|path| path.file_name().unwrap().to_string_lossy().into_owned())
+        .collect();
+    assert_eq!(names, ["header.less", "partial.less", "footer.less"]);
+    assert!(output.css.contains(".footer"));
+}
+
+#[test]
+fn compiler_reuses_cache_and_picks_up_changes() {
+    let dir = std::env::temp_dir().join(format!("less_oxide_incremental_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let entry = dir.join("main.less");
+    let vars = dir.join("vars.less");
+    std::fs::write(&entry, "@import \"vars\";\n.a { color: @brand; }").unwrap();
+    std::fs::write(&vars, "@brand: red;").unwrap();
+
+    let mut compiler = Compiler::new(CompileOptions {
+        minify: true,
+        ..CompileOptions::default()
+    });
+    assert_eq!(compiler.compile_file(&entry).unwrap().css, ".a{color:red}");
+    assert_eq!(compiler.compile_file(&entry).unwrap().css, ".a{color:red}");
+
+    std::fs::write(&vars, "@brand: blue;").unwrap();
+    assert_eq!(compiler.compile_file(&entry).unwrap().css, ".a{color:blue}");
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn compile_file_to_writes_css_and_map() {
+    let dir = std::env::temp_dir().join(format!("less_oxide_render_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let entry = dir.join("main.less");
+    std::fs::write(dir.join("vars.less"), "@brand: red;").unwrap();
+    std::fs::write(&entry, "@import \"vars\";\n.a { color: @brand; }").unwrap();
+    let output = dir.join("dist/main.css");
+
+    let result = compile_file_to(
+        &entry,
+        &output,
+        CompileOptions {
+            minify: true,
+            source_map: Some(SourceMapOptions::default()),
+            ..CompileOptions::default()
+        },
+    )
+    .unwrap();
+    let css = std::fs::read_to_string(&output).unwrap();
+    assert_eq!(css, ".a{color:red}\n/*# sourceMappingURL=main.css.map */");
+    assert_eq!(css, result.css);
+    let map = std::fs::read_to_string(dir.join("dist/main.css.map")).unwrap();
+    assert_eq!(Some(map), result.source_map);
+    assert_eq!(result.dependencies.len(), 1);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn compile_files_keeps_entry_order_and_isolates_errors() {
+    let dir = std::env::temp_dir().join(format!("less_oxide_many_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("vars.less"), "@brand: red;").unwrap();
+    let mut entries = Vec::new();
+    for idx in 0..8 {
+        let entry = dir.join(format!("entry{idx}.less"));
+        let source = if idx == 3 {
+            ".a { color: @missing; }".to_string()
+        } else {
+            format!("@import \"vars\";\n.e{idx} {{ color: @brand; }}")
+        };
+        std::fs::write(&entry, source).unwrap();
+        let options = CompileOptions {
+            minify: idx % 2 == 0,
+            ..CompileOptions::default()
+        };
+        entries.push((entry, options));
+    }
+
+    let results = compile_files(entries);
+    assert_eq!(results.len(), 8);
+    for (idx, result) in results.iter().enumerate() {
+        match idx {
+            3 => assert!(result.is_err()),
+            _ if idx % 2 == 0 => {
+                assert_eq!(
+                    result.as_ref().unwrap().css,
+                    format!(".e{idx}{{color:red}}")
+                )
+            }
+            _ => {
+                let output = result.as_ref().unwrap();
+                assert_eq!(output.css, format!(".e{idx} {{\n  color: red;\n}}"));
+                assert_eq!(output.dependencies.len(), 1);
+            }
+        }
+    }
+    assert!(compile_files(Vec::<(PathBuf, CompileOptions)>::new()).is_empty());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(feature = "capi")]
+#[test]
+fn capi_compiles_and_frees_results() {
+    use less_oxide::capi::{
+        less_oxide_compile, less_oxide_compile_file, less_oxide_result_free, LessOxideOptions,
+    };
+    use std::ffi::{CStr, CString};
+
+    let dir = std::env::temp_dir().join(format!("less_oxide_capi_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("vars.less"), "@brand: red;").unwrap();
+    let entry = dir.join("main.less");
+    std::fs::write(&entry, "@import \"vars\";\n.a { color: @brand; }").unwrap();
+
+    let include = CString::new(dir.to_str().unwrap()).unwrap();
+    let include_paths = [include.as_ptr()];
+    let options = LessOxideOptions {
+        minify: true,
+        filename: std::ptr::null(),
+        include_paths: include_paths.as_ptr(),
+        include_paths_len: include_paths.len(),
+        output_style: std::ptr::null(),
+        source_map: true,
+    };
+    let source = CString::new("@import \"vars\";\n.b { color: @brand; }").unwrap();
+    unsafe {
+        let result = less_oxide_compile(source.as_ptr(), &options);
+        assert!((*result).error.is_null());
+        assert_eq!(
+            CStr::from_ptr((*result).css).to_str().unwrap(),
+            ".b{color:red}"
+        );
+        assert!(!(*result).map.is_null());
+        assert_eq!((*result).imports_len, 1);
+        let import = CStr::from_ptr(*(*result).imports).to_str().unwrap();
+        assert!(import.ends_with("vars.less"));
+        less_oxide_result_free(result);
+
+        let path = CString::new(entry.to_str().unwrap()).unwrap();
+        let result = less_oxide_compile_file(path.as_ptr(), std::ptr::null());
+        assert_eq!(
+            CStr::from_ptr((*result).css).to_str().unwrap(),
+            ".a {\n  color: red;\n}"
+        );
+        less_oxide_result_free(result);
+
+        let broken = CString::new(".a { color: @missing; }").unwrap();
+        let result = less_oxide_compile(broken.as_ptr(), std::ptr::null());
+        assert!((*result).css.is_null());
+        let error = CStr::from_ptr((*result).error).to_str().unwrap();
+        assert!(error.contains("missing"), "{error}");
+        less_oxide_result_free(result);
+
+        let result = less_oxide_compile(std::ptr::null(), std::ptr::null());
+        assert!(!(*result).error.is_null());
+        less_oxide_result_free(result);
+        less_oxide_result_free(std::ptr::null_mut());
+    }
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(feature = "capi")]
+#[test]
+fn capi_header_matches_generated() {
+    let generated_path = concat!(env!("OUT_DIR"), "/less_oxide.h");
+    let generated = std::fs::read_to_string(generated_path).unwrap();
+    let committed =
+        std::fs::read_to_string(concat!(env!("CARGO_MANIFEST_DIR"), "/include/less_oxide.h"))
+            .unwrap();
+    assert!(
+        generated == committed,
+        "include/less_oxide.h 已过期，请执行 cp {generated_path} include/less_oxide.h"
+    );
+}
+
+#[cfg(feature = "watch")]
+#[test]
+fn watch_recompiles_on_import_change() {
+    use std::ops::ControlFlow;
+
+    let dir = std::env::temp_dir().join(format!("less_oxide_watch_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let entry = dir.join("main.less");
+    let vars = dir.join("vars.less");
+    std::fs::write(&entry, "@import \"vars\";\n.a { color: @brand; }").unwrap();
+    std::fs::write(&vars, "@brand: red;").unwrap();
+
+    let mut compiler = Compiler::new(CompileOptions {
+        minify: true,
+        ..CompileOptions::default()
+    });
+    let mut events = Vec::new();
+    compiler
+        .watch(&entry, |event| {
+            let css = event.result.unwrap().css;
+            events.push((event.trigger.clone(), css));
+            if events.len() == 1 {
+                std::fs::write(&vars, "@brand: green;").unwrap();
+                ControlFlow::Continue(())
+            } else {
+                ControlFlow::Break(())
+            }
+        })
+        .unwrap();
+
+    assert_eq!(events[0], (None, ".a{color:red}".to_string()));
+    let (trigger, css) = &events[1];
+    assert_eq!(trigger.as_ref().unwrap().file_name().unwrap(), "vars.less");
+    assert_eq!(css, ".a{color:green}");
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(feature = "watch")]
+#[test]
+fn watch_accepts_relative_entry() {
+    use std::ops::ControlFlow;
+
+    // 测试的工作目录是包根目录，`./target/...` 是相对路径。
+    let dir = PathBuf::from(format!(
+        "./target/less_oxide_watch_rel_{}",
+        std::process::id()
+    ));
+    std::fs::create_dir_all(dir.join("styles")).unwrap();
+    let entry = dir.join("main.less");
+    let vars = dir.join("styles/vars.less");
+    std::fs::write(&entry, "@import \"styles/vars\";\n.a { color: @brand; }").unwrap();
+    std::fs::write(&vars, "@brand: red;").unwrap();
+
+    let mut compiler = Compiler::new(CompileOptions {
+        minify: true,
+        ..CompileOptions::default()
+    });
+    let mut events = Vec::new();
+    compiler
+        .watch(&entry, |event| {
+            let css = event.result.unwrap().css;
+            events.push((event.trigger.clone(), css));
+            match events.len() {
+                1 => std::fs::write(&entry, "@import \"styles/vars\";\n.b { color: @brand; }")
+                    .unwrap(),
+                2 => std::fs::write(&vars, "@brand: green;").unwrap(),
+                _ => return ControlFlow::Break(()),
+            }
+            ControlFlow::Continue(())
+        })
+        .unwrap();
+
+    assert_eq!(events[0], (None, ".a{color:red}".to_string()));
+    assert_eq!(
+        events[1],
+        (
+            Some(entry.canonicalize().unwrap()),
+            ".b{color:red}".to_string()
+        )
+    );
+    assert_eq!(
+        events[2],
+        (
+            Some(vars.canonicalize().unwrap()),
+            ".b{color:green}".to_string()
+        )
+    );
+    std::fs::remove_dir_all(&dir).unwrap();
+}